@@ -1,15 +1,24 @@
-use std::env;
-use std::path::PathBuf;
+// Without the `onelib` feature there is no C library to build, see Cargo.toml
+#[cfg(not(feature = "onelib"))]
+fn main() {}
 
+#[cfg(feature = "onelib")]
 fn main() {
+    use std::env;
+    use std::path::PathBuf;
+
     // Tell cargo to rerun this build script if these files change
     println!("cargo:rerun-if-changed=ONEcode/ONElib.c");
     println!("cargo:rerun-if-changed=ONEcode/ONElib.h");
+    println!("cargo:rerun-if-changed=csrc/onelib_ext.c");
+    println!("cargo:rerun-if-changed=csrc/onelib_ext.h");
 
-    // Compile the C library
+    // Compile the C library, with our additions to it: onelib_ext.c includes
+    // the unmodified ONEcode/ONElib.c
     cc::Build::new()
-        .file("ONEcode/ONElib.c")
+        .file("csrc/onelib_ext.c")
         .include("ONEcode")
+        .include("csrc")
         .flag("-fPIC")
         .flag("-fno-strict-aliasing")
         .flag("-DNDEBUG")
@@ -24,7 +33,7 @@ fn main() {
 
     // Generate bindings
     let bindings = bindgen::Builder::default()
-        .header("ONEcode/ONElib.h")
+        .header("csrc/onelib_ext.h")
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         // Types to whitelist
         .allowlist_type("OneFile")
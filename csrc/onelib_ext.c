@@ -0,0 +1,404 @@
+/*****************************************************************************************
+ *
+ *  File: onelib_ext.c
+ *    additions to ONElib used by the Rust bindings, declared in onelib_ext.h
+ *
+ *  ONEcode/ is vendored unchanged from upstream. This file includes ONElib.c, so that
+ *  the additions can use its static helpers, and is compiled in its place. Any change
+ *  the bindings need from the C library goes here, not into ONEcode/.
+ *
+ ****************************************************************************************/
+
+#include "ONElib.c"
+#include "onelib_ext.h"
+
+  //  Whether a compressor has a codec, from training or deserialization
+
+static bool vcIsCoded (OneCodec *vc)
+{ return ((_OneCodec *) vc)->state >= CODED_WITH ; }
+
+/***********************************************************************************
+ *
+ *   ONE_FILE_OPEN_APPEND:
+ *     Reopens a binary file for writing more lines after its data.  The header and
+ *     footer are read as by oneFileOpenRead(), counts and indexes carried over into
+ *     the write accumulators, and the footer cut off, to be written again on close.
+ *
+ **********************************************************************************/
+
+OneFile *oneFileOpenAppend (const char *path)
+{
+  OneFile *vf = oneFileOpenRead (path, 0, 0, 1) ;
+  if (!vf) return NULL ;
+
+#define APPEND_ERROR(x) \
+  { snprintf (errorString, 1024, "ONEcode file open error %s: %s\n", path, x) ; \
+    oneFileDestroy (vf) ; return NULL ; }
+
+  if (!vf->isBinary)
+    APPEND_ERROR("can only append to a binary file") ;
+  off_t dataStart = ftello (vf->f) ; // the reader stops at the start of the data
+  FILE *f = fopen (path, "r+") ;
+  if (!f)
+    APPEND_ERROR(strerror (errno)) ;
+  fclose (vf->f) ;
+  vf->f = f ;
+
+  // the footer offset ends the file, and the '\n' ending the data precedes the footer
+  off_t footOff ;
+  if (fseeko (f, -(off_t)sizeof(off_t), SEEK_END) != 0
+      || fread (&footOff, sizeof(off_t), 1, f) != 1)
+    APPEND_ERROR("failed to read footer offset") ;
+  off_t dataEnd = footOff - 1 ;
+  if (dataEnd < dataStart || fflush (f) != 0 || ftruncate (fileno (f), dataEnd) != 0
+      || fseeko (f, dataEnd, SEEK_SET) != 0)
+    APPEND_ERROR("failed to remove footer") ;
+
+  int i ;
+  for (i = 'A' ; i <= 'z' ; ++i) // object stats are made on reading '%' lines, which may be absent
+    if (vf->info[i] && vf->info[i]->isObject && !vf->info[i]->stats)
+      { initialiseStats (vf) ;
+	break ;
+      }
+  for (i = 0 ; i < 128 ; ++i)
+    { OneInfo *li = vf->info[i] ;
+      if (!li) continue ;
+      li->accum = li->given ;
+      if (li->isObject && !li->index) // no objects yet, so no index in the footer
+	{ allocateIndices (vf, i, 0x10000) ;
+	  li->index[0] = dataStart ;
+	}
+      if (li->listCodec && li->listCodec != DNAcodec && vcIsCoded (li->listCodec))
+	li->isUseListCodec = true ; // keep compressing with the codec in the footer
+    }
+
+  vf->isWrite          = true ;
+  vf->isHeaderOut      = true ;
+  vf->isLastLineBinary = true ;
+  vf->byte             = dataEnd ;
+  return vf ;
+}
+
+/***********************************************************************************
+ *
+ *   ONE_FILE_SET_LIST_COMPRESSION
+ *
+ **********************************************************************************/
+
+bool oneFileSetListCompression (OneFile *vf, char lineType, bool isCompress)
+{
+  OneInfo *li = vf->info[(int)lineType] ;
+  if (!vf->isWrite || vf->isHeaderOut || vf->share < 0 || !li || !li->listEltSize
+      || li->listCodec == DNAcodec || lineType == '/')
+    return false ;
+
+  int i, n = vf->share ? vf->share : 1 ;
+  for (i = 0 ; i < n ; ++i) // each thread collects its own training data
+    { OneInfo *lt = vf[i].info[(int)lineType] ;
+      if (isCompress && !lt->listCodec)
+	lt->listCodec = vcCreate () ;
+      else if (!isCompress && lt->listCodec)
+	{ vcDestroy (lt->listCodec) ;
+	  lt->listCodec = NULL ;
+	}
+    }
+  return true ;
+}
+
+/***********************************************************************************
+ *
+ *   ONE_FILE_CLOSE_CHECKED
+ *
+ **********************************************************************************/
+
+bool oneFileCloseChecked (OneFile *vf)
+{
+  bool isOK = true ;
+
+  assert (vf->share >= 0) ;
+
+  if (vf->isWrite)
+    { oneFinalize (vf) ;
+      if (fflush (vf->f) != 0 || ferror (vf->f))
+	{ snprintf (errorString, 1024, "ONEcode file write error %s: %s\n",
+		    vf->fileName ? vf->fileName : "-", strerror (errno)) ;
+	  isOK = false ;
+	}
+    }
+
+  oneFileDestroy (vf);
+  return isOK ;
+}
+
+/***********************************************************************************
+ *
+ *   ONE_WRITE_LINE_ASCII:
+ *     The ascii branch of oneWriteLine(), with the text after the line type made by
+ *     the caller, e.g. to print REAL values in its own format.
+ *
+ **********************************************************************************/
+
+void oneWriteLineAscii (OneFile *vf, char t, I64 listLen, void *listBuf,
+			const char *text, I64 textLen)
+{ OneInfo *li;
+
+  assert (vf->isWrite) ;
+  assert (!vf->isBinary) ;
+  assert (!vf->isFinal || !isalpha(t)) ;
+
+  li = vf->info[(int) t];
+  if (!li) die ("oneWriteLineAscii() attempting to write unkown linetype %c", t) ;
+
+  if (li->isFirst) closeObjects (vf, t) ;
+  while (vf->objectFrame && !(vf->openObjects[vf->objectFrame]->contains[(int)t]))
+    endObject (vf, vf->openObjects[vf->objectFrame]) ;
+  li->accum.count += 1;
+  if (li->isObject) startObject (vf, li) ;
+
+  if (li->listEltSize > 0)
+    { vf->field[li->listField].len = listLen ;
+      li->accum.total += listLen;
+      if (listLen > li->accum.max)
+	li->accum.max = listLen;
+      if (li->fieldType[li->listField] == oneSTRING_LIST) // as writeStringList() counts them
+	{ I64 j, totLen = 0 ;
+	  char *s = listBuf ? (char *) listBuf : (char *) li->buffer ;
+	  for (j = 0 ; j < listLen ; ++j)
+	    { I64 sLen = strlen (s) ;
+	      totLen += sLen ;
+	      s += sLen + 1 ;
+	    }
+	  li->accum.total += totLen;
+	  if (li->accum.max < totLen)
+	    li->accum.max = totLen;
+	}
+    }
+
+  if (!vf->isHeaderOut && !vf->isNoAsciiHeader && vf->share >= 0) writeHeader (vf) ;
+
+  if (!vf->isLastLineBinary)      // terminate previous ascii line
+    fputc ('\n', vf->f);
+
+  ++vf->line ;
+
+  fputc (t, vf->f);
+  fwrite (text, 1, textLen, vf->f) ; // errors show on closing, as in oneWriteLine()
+  vf->isLastLineBinary = false;
+}
+
+/***********************************************************************************
+ *
+ *   ONE_COPY_OBJECT_RAW:
+ *     Copies the lines of an object from a binary file being read to a binary file
+ *     being written without decoding them. A first pass reads just the line and list
+ *     headers, to check that the destination declares every line type with the same
+ *     layout and can take each list as it is stored; only then are the lines copied,
+ *     with the bookkeeping oneWriteLine() does for them, and the reader moved past them.
+ *
+ **********************************************************************************/
+
+typedef struct {
+  off_t pos ;          // of the line in the source
+  off_t size ;         // of the line in bytes
+  char  t ;
+  I64   listLen ;
+  off_t listPos ;      // of an uncompressed list, to train the destination's codec
+  I64   listSize ;
+} RawLine ;
+
+  //  Whether a compressed list of line type t can be copied as it is: DNA always can,
+  //    other lists only into a destination with the same codec, or with none trained yet
+  //    and so free to take the source's
+
+static bool rawCodecOK (OneFile *vf, OneFile *vfIn, int t, bool *isAdopt)
+{ OneInfo *li = vfIn->info[t], *lo = vf->info[t] ;
+
+  if (li->listCodec == DNAcodec) return lo->listCodec == DNAcodec ;
+  if (!li->listCodec || !vcIsCoded (li->listCodec) || !lo->listCodec) return false ;
+  if (!lo->isUseListCodec) { isAdopt[t] = true ; return true ; }
+
+  char *a = new (2*(vcMaxSerialSize()+1), char), *b = a + vcMaxSerialSize()+1 ;
+  int   n = vcSerialize (li->listCodec, a) ;
+  bool  isSame = vcSerialize (lo->listCodec, b) == n && !memcmp (a, b, n) ;
+  free (a) ;
+  return isSame ;
+}
+
+  //  Read the line at the source's position into *r, skipping its list, or return false
+  //    if it can't be copied raw
+
+static bool rawScanLine (OneFile *vf, OneFile *vfIn, RawLine *r, OneField *field,
+			 int *isChecked, bool *isAdopt)
+{ FILE    *f = vfIn->f ;
+  int      x = getc (f), i ;
+  OneInfo *li, *lo ;
+
+  r->pos = ftello (f) - 1 ;
+  if (x == EOF || !(x & 0x80)) return false ;
+  r->t = vfIn->binaryTypeUnpack[x] ;
+  li = vfIn->info[(int)r->t] ;
+  lo = vf->info[(int)r->t] ;
+  if (!li || !lo) return false ;
+
+  if (!isChecked[(int)r->t]) // the layouts match and the list is not read as ascii
+    { if (lo->nField != li->nField || lo->listEltSize != li->listEltSize
+	  || lo->listField != li->listField)
+	return false ;
+      for (i = 0 ; i < li->nField ; ++i)
+	if (lo->fieldType[i] != li->fieldType[i] || li->fieldType[i] == oneSTRING_LIST)
+	  return false ;
+      isChecked[(int)r->t] = 1 ;
+    }
+  if ((x & 0x1) && isChecked[(int)r->t] == 1) // and a compressed list can be taken as it is
+    { if (!rawCodecOK (vf, vfIn, r->t, isAdopt)) return false ;
+      isChecked[(int)r->t] = 2 ;
+    }
+
+  if (li->nField > 0) readCompressedFields (f, field, li) ;
+  r->listLen = li->listEltSize ? field[li->listField].i : 0 ;
+  r->listSize = 0 ;
+  if (r->listLen > 0)
+    { I64 n = r->listLen, eltSize = li->listEltSize ;
+      if (li->fieldType[li->listField] == oneINT_LIST)
+	{ ltfRead (f) ;
+	  if (n > 1) { eltSize = getc (f) ; --n ; }
+	  else n = 0 ;
+	}
+      if (x & 0x1)
+	{ I64 nBits = ltfRead (f) ;
+	  if (fseeko (f, (nBits+7) >> 3, SEEK_CUR) != 0) return false ;
+	}
+      else
+	{ r->listPos = ftello (f) ;
+	  r->listSize = n * eltSize ;
+	  if (fseeko (f, r->listSize, SEEK_CUR) != 0) return false ;
+	}
+    }
+  r->size = ftello (f) - r->pos ;
+  return !ferror (f) && !feof (f) ;
+}
+
+bool oneCopyObjectRaw (OneFile *vf, OneFile *vfIn, I64 start, I64 end)
+{
+  if (!vf->isWrite || !vf->isBinary || vf->share != 0 || vf->isFinal
+      || vfIn->isWrite || !vfIn->isBinary)
+    return false ;
+
+  FILE   *f = vfIn->f ;
+  off_t   readPos = ftello (f) ; // lines before here have been read and counted already
+  int     isChecked[128] = {0} ;
+  bool    isAdopt[128] = {false} ;
+  I64     n = 0, nMax = 256, i ;
+  RawLine *lines = new (nMax, RawLine) ;
+  int     nField = 1 ;
+  for (i = 0 ; i < 128 ; ++i)
+    if (vfIn->info[i] && vfIn->info[i]->nField > nField) nField = vfIn->info[i]->nField ;
+  OneField *field = new (nField, OneField) ;
+  bool    isOK = fseeko (f, start, SEEK_SET) == 0 ;
+
+  while (isOK)
+    { off_t pos = ftello (f) ;
+      if (end >= 0 && pos >= end) break ;
+      int c = getc (f) ;
+      if (c == '\n' || c == EOF) break ; // the end of the data
+      ungetc (c, f) ;
+      if (n == nMax) { resize (lines, nMax, 2*nMax, RawLine) ; nMax *= 2 ; }
+      isOK = rawScanLine (vf, vfIn, &lines[n++], field, isChecked, isAdopt) ;
+    }
+  free (field) ;
+  if (!isOK || n == 0)
+    { fseeko (f, readPos, SEEK_SET) ;
+      free (lines) ;
+      return false ;
+    }
+  off_t endPos = lines[n-1].pos + lines[n-1].size ;
+
+  for (i = 0 ; i < 128 ; ++i) // take up the source's codec where there is none yet
+    if (isAdopt[i])
+      { OneInfo *lo = vf->info[i] ;
+	char *buf = new (vcMaxSerialSize()+1, char) ;
+	vcSerialize (vfIn->info[i]->listCodec, buf) ;
+	vcDestroy (lo->listCodec) ;
+	lo->listCodec = vcDeserialize (buf) ;
+	lo->isUseListCodec = true ;
+	free (buf) ;
+      }
+
+  if (!vf->isHeaderOut)
+    { writeHeader (vf) ;
+      if (!vf->isLastLineBinary)
+	{ fputc ('\n', vf->f) ;
+	  vf->byte = ftello (vf->f) ;
+	}
+      for (i = 'A' ; i <= 'z' ; i++)
+	if (vf->info[i] && vf->info[i]->index)
+	  vf->info[i]->index[0] = vf->byte ;
+    }
+  else if (!vf->isLastLineBinary)
+    { fputc ('\n', vf->f) ;
+      vf->byte = ftello (vf->f) ;
+    }
+
+  I64   bufSize = 0 ;
+  char *buf = 0 ;
+  for (i = 0 ; i < n ; ++i)
+    { RawLine *r = &lines[i] ;
+      OneInfo *lo = vf->info[(int)r->t], *li = vfIn->info[(int)r->t] ;
+
+      // the counts oneWriteLine() keeps, and oneReadLine() for lines not read yet
+
+      if (lo->isFirst) closeObjects (vf, r->t) ;
+      while (vf->objectFrame && !(vf->openObjects[vf->objectFrame]->contains[(int)r->t]))
+	endObject (vf, vf->openObjects[vf->objectFrame]) ;
+      lo->accum.count += 1 ;
+      if (lo->isObject)
+	{ startObject (vf, lo) ;
+	  if (lo->accum.count >= lo->indexSize)
+	    { I64 oldSize = lo->indexSize ;
+	      lo->indexSize = (oldSize << 2) + 0x10000 ;
+	      resize (lo->index, oldSize, lo->indexSize, I64) ;
+	    }
+	  lo->index[lo->accum.count] = vf->byte ;
+	}
+      if (r->listLen > 0)
+	{ lo->accum.total += r->listLen ;
+	  if (r->listLen > lo->accum.max) lo->accum.max = r->listLen ;
+	}
+      if (r->pos >= readPos)
+	{ vfIn->line += 1 ;
+	  if (li->accum.count >= 0) li->accum.count += 1 ;
+	  if (r->listLen > 0)
+	    { li->accum.total += r->listLen ;
+	      if (r->listLen > li->accum.max) li->accum.max = r->listLen ;
+	    }
+	}
+
+      // then the bytes, with any uncompressed list added to the codec's training data
+
+      if (r->size > bufSize)
+	{ free (buf) ;
+	  bufSize = 2*r->size ;
+	  buf = new (bufSize, char) ;
+	}
+      if (fseeko (f, r->pos, SEEK_SET) != 0 || fread (buf, r->size, 1, f) != 1)
+	die ("ONE read error: failed to read line of %lld bytes", (long long) r->size) ;
+      fwrite (buf, r->size, 1, vf->f) ; // errors show on closing, as in oneWriteLine()
+      vf->byte += r->size ;
+      if (r->listSize > 0 && lo->listCodec && !lo->isUseListCodec)
+	{ vcAddToTable (lo->listCodec, r->listSize, buf + (r->listPos - r->pos)) ;
+	  lo->listTack += r->listSize ;
+	  if (lo->listTack > vf->codecTrainingSize)
+	    { vcCreateCodec (lo->listCodec, 1) ;
+	      lo->isUseListCodec = true ;
+	    }
+	}
+    }
+  vf->isLastLineBinary = true ;
+
+  fseeko (f, endPos, SEEK_SET) ;
+  free (buf) ;
+  free (lines) ;
+  return true ;
+}
+
+/******************* end of file **************/
@@ -0,0 +1,51 @@
+/*****************************************************************************************
+ *
+ *  File: onelib_ext.h
+ *    additions to ONElib used by the Rust bindings, see onelib_ext.c
+ *
+ *  ONEcode/ is vendored unchanged from upstream; these functions are built on its
+ *  internals without changing the OneFile struct or any upstream function.
+ *
+ ****************************************************************************************/
+
+#ifndef ONELIB_EXT_DEFINED
+#define ONELIB_EXT_DEFINED
+
+#include "ONElib.h"
+
+OneFile *oneFileOpenAppend (const char *path);
+
+  // Reopen the binary file at 'path' to write more lines after its data. Counts and
+  //   indexes continue from those in its footer, which is cut off the file and written
+  //   again on close. The header, including provenance, is left as it is.
+
+bool oneFileSetListCompression (OneFile *vf, char lineType, bool isCompress);
+
+  // Turn compression of the lists of lineType off, or back on, in a file opened for writing,
+  //   before the first line is written. Without a codec the lists are stored as they are.
+  //   DNA lists are always packed 2 bits per base, so can't be changed; returns false then,
+  //   and for a lineType with no list.
+
+bool oneFileCloseChecked (OneFile *vf);
+
+  // As oneFileClose(), but returns false if writing failed, e.g. because the disk is full,
+  //   with the reason in oneErrorString().
+
+void oneWriteLineAscii (OneFile *vf, char lineType, I64 listLen, void *listBuf,
+			const char *text, I64 textLen);
+
+  // As oneWriteLine() for a file written in ascii, but with the fields and list that follow
+  //   the line type given as text, formatted by the caller. listBuf is only used to count
+  //   the characters of a STRING_LIST.
+
+bool oneCopyObjectRaw (OneFile *vf, OneFile *vfIn, I64 start, I64 end);
+
+  // Copy the lines of vfIn from byte offset start up to end, or the end of the data if end
+  //   is negative, to vf without decoding them, and move vfIn past them. Both files must
+  //   be binary, and vf written by one thread. Returns false, having changed nothing, if
+  //   vf declares a line type differently, a list has a codec vf doesn't share, or a line
+  //   is a STRING_LIST, which is stored as ascii.
+
+#endif // ONELIB_EXT_DEFINED
+
+/******************* end of file **************/
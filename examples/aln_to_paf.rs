@@ -0,0 +1,23 @@
+//! Example: Convert FastGA alignments (.1aln) to PAF
+//!
+//! Usage: cargo run --example aln_to_paf -- [data/test.1aln]
+//!
+//! Writes PAF to stdout with `onecode::convert::aln_to_paf()`, which takes
+//! scaffold names and coordinates from the GDB skeletons embedded in the
+//! alignment file.
+
+use onecode::convert::aln_to_paf;
+use onecode::{OneFile, Result};
+use std::io::{BufWriter, Write};
+
+fn main() -> Result<()> {
+    let path = std::env::args().nth(1).unwrap_or_else(|| "data/test.1aln".to_string());
+    let mut file = OneFile::open_read(&path, None, Some("aln"), 1)?;
+
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    let count = aln_to_paf(&mut file, &mut out)?;
+    out.flush().map_err(|e| onecode::OneError::Other(e.to_string()))?;
+    eprintln!("{} alignments", count);
+
+    Ok(())
+}
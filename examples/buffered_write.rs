@@ -0,0 +1,47 @@
+//! Example: Compare direct and buffered writing of many small lines
+//!
+//! Usage: cargo run --release --example buffered_write -- [n_lines]
+
+use onecode::{BufferedWriter, OneFile, OneSchema, Result};
+use std::time::Instant;
+
+fn main() -> Result<()> {
+    let n: i64 = std::env::args()
+        .nth(1)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(5_000_000);
+    let schema = OneSchema::from_text("P 3 tst\nO P 2 3 INT 3 INT\nD V 1 4 REAL\n")?;
+    let path = std::env::temp_dir().join(format!("buffered_write_{}.1tst", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let start = Instant::now();
+    let mut file = OneFile::open_write_new(path, &schema, "tst", true, 1)?;
+    for i in 0..n {
+        file.set_int(0, i);
+        file.set_int(1, i % 1000);
+        file.write_line('P', 0, None);
+        file.set_real(0, i as f64 * 0.5);
+        file.write_line('V', 0, None);
+    }
+    file.close();
+    let direct = start.elapsed();
+
+    let start = Instant::now();
+    let file = OneFile::open_write_new(path, &schema, "tst", true, 1)?;
+    let mut writer = BufferedWriter::new(file);
+    for i in 0..n {
+        writer.set_int(0, i);
+        writer.set_int(1, i % 1000);
+        writer.write_line('P')?;
+        writer.set_real(0, i as f64 * 0.5);
+        writer.write_line('V')?;
+    }
+    writer.close()?;
+    let buffered = start.elapsed();
+
+    std::fs::remove_file(path).ok();
+    println!("{} object lines + {} data lines", n, n);
+    println!("  direct:   {:.2?}", direct);
+    println!("  buffered: {:.2?}", buffered);
+    Ok(())
+}
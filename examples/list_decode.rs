@@ -0,0 +1,73 @@
+//! Example: Time a sequential scan decoding DNA and integer lists
+//!
+//! Usage: cargo run --release --example list_decode [--features fastpath] -- [n_lines]
+//!
+//! Run it with and without the `fastpath` feature to compare the Rust and C
+//! list decoders.
+
+use onecode::{OneFile, OneSchema, Result};
+use std::time::Instant;
+
+fn main() -> Result<()> {
+    let n: usize = std::env::args()
+        .nth(1)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(200_000);
+    let schema = OneSchema::from_text("P 3 tst\nO S 1 3 DNA\nD L 1 8 INT_LIST\n")?;
+    let path = std::env::temp_dir().join(format!("list_decode_{}.1tst", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let mut state = 1u64;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        state >> 33
+    };
+    let mut file = OneFile::open_write_new(path, &schema, "tst", true, 1)?;
+    for _ in 0..n {
+        let seq: Vec<u8> = (0..1000).map(|_| b"acgt"[(next() % 4) as usize]).collect();
+        file.write_dna('S', &seq)?;
+        let mut pos = 0i64;
+        let list: Vec<i64> = (0..100)
+            .map(|_| {
+                pos += (next() % 200) as i64;
+                pos
+            })
+            .collect();
+        file.write_int_list('L', &list)?;
+    }
+    file.close();
+
+    let start = Instant::now();
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let (mut bases, mut sum) = (0usize, 0i64);
+    loop {
+        match file.read_line() {
+            '\0' => break,
+            'S' => {
+                bases += file
+                    .dna_char()
+                    .map_or(0, |s| s.iter().filter(|&&b| b == b'a').count())
+            }
+            'L' => sum = sum.wrapping_add(file.int_list().map_or(0, |l| l[l.len() - 1])),
+            _ => {}
+        }
+    }
+    let elapsed = start.elapsed();
+    std::fs::remove_file(path).ok();
+
+    println!(
+        "{} lines of each type in {:.2?} ({}; checksums {} {})",
+        n,
+        elapsed,
+        if cfg!(feature = "fastpath") {
+            "fastpath"
+        } else {
+            "C decoders"
+        },
+        bases,
+        sum
+    );
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Example: Merge .1aln chunks written by separate FastGA runs
+//!
+//! Usage: cargo run --example merge_chunks -- <out.1aln> <chunk.1aln>...
+//!
+//! The chunks must share their schema; `onecode::merge()` writes skeletons
+//! they share once and renumbers the contigs of those they do not, followed
+//! by the alignments of every chunk in order. Without arguments the bundled
+//! data/test.1aln is split into two chunks in the temp directory, which are
+//! then merged back and checked.
+
+use onecode::linetypes::aln;
+use onecode::{OneError, OneFile, Result};
+
+/// Copy the lines of `input` to `out`, returning the number of alignments copied
+///
+/// Lines before the first alignment (the skeletons) are copied only if
+/// `with_skeleton` is set; alignment `n` (1-indexed) and its lines only if `keep(n)`.
+fn copy_chunk(input: &str, out: &mut OneFile, with_skeleton: bool, keep: impl Fn(i64) -> bool) -> Result<i64> {
+    let mut file = OneFile::open_read(input, None, Some("aln"), 1)?;
+    let mut in_alignments = false;
+    let mut copied = 0;
+    loop {
+        let line_type = file.read_line();
+        if line_type == '\0' {
+            break;
+        }
+        in_alignments |= line_type == aln::ALIGNMENT || line_type == aln::CHAIN;
+        let copy = if in_alignments { keep(file.object(aln::ALIGNMENT)) } else { with_skeleton };
+        if copy {
+            file.copy_line_to(out)?;
+            if line_type == aln::ALIGNMENT {
+                copied += 1;
+            }
+        }
+    }
+    Ok(copied)
+}
+
+/// Merge `chunks` into `out_path`, returning the number of alignments
+fn merge(out_path: &str, chunks: &[String]) -> Result<i64> {
+    let inputs: Vec<&str> = chunks.iter().map(String::as_str).collect();
+    onecode::merge(&inputs, out_path)?;
+    OneFile::open_read(out_path, None, Some("aln"), 1)?.stats(aln::ALIGNMENT).map(|s| s.0)
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() >= 2 {
+        let total = merge(&args[0], &args[1..])?;
+        println!("Merged {} alignments from {} chunks into {}", total, args.len() - 1, args[0]);
+        return Ok(());
+    }
+
+    // Demonstration on the bundled data: split in two, merge, compare counts
+    let input = "data/test.1aln";
+    let source = OneFile::open_read(input, None, Some("aln"), 1)?;
+    let n = source.stats(aln::ALIGNMENT)?.0;
+    let tmp = |name: &str| {
+        let path = std::env::temp_dir().join(format!("merge_chunks_{}_{}", std::process::id(), name));
+        path.to_string_lossy().into_owned()
+    };
+    let chunks = vec![tmp("1.1aln"), tmp("2.1aln")];
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut out = OneFile::open_write_from(chunk, &source, true, 1)?;
+        let copied = copy_chunk(input, &mut out, true, |a| (a <= n / 2) == (i == 0))?;
+        out.close();
+        println!("Wrote {} alignments to {}", copied, chunk);
+    }
+
+    let merged = tmp("merged.1aln");
+    let total = merge(&merged, &chunks)?;
+    let check = OneFile::open_read(&merged, None, Some("aln"), 1)?;
+    println!(
+        "Merged {} alignments into {} (source has {}, merged file reports {})",
+        total,
+        merged,
+        n,
+        check.stats(aln::ALIGNMENT)?.0
+    );
+    for path in chunks.iter().chain([&merged]) {
+        std::fs::remove_file(path).ok();
+    }
+    if total != n {
+        return Err(OneError::Other("alignment count changed in the round trip".to_string()));
+    }
+    Ok(())
+}
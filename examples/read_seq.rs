@@ -1,6 +1,6 @@
-/// Example: Read a sequence file and print statistics
-///
-/// Usage: cargo run --example read_seq -- ONEcode/TEST/small.seq
+//! Example: Read a sequence file and print statistics
+//!
+//! Usage: cargo run --example read_seq -- ONEcode/TEST/small.seq
 
 use onecode::{OneFile, Result};
 
@@ -0,0 +1,68 @@
+//! Example: List the alignments whose target overlaps a scaffold region
+//!
+//! Usage: cargo run --example region_query -- [file.1aln] [name:start-end]
+//!
+//! Coordinates are 0-based and half-open, in scaffold space of the target
+//! genome. Without arguments the bundled data/test.1aln is queried for the
+//! middle of its first target scaffold.
+
+use onecode::linetypes::aln;
+use onecode::{GdbIndex, GdbReader, OneError, OneFile, RegionIndex, Result};
+
+/// Parse "name:start-end", where the name itself may contain ':'
+fn parse_region(region: &str) -> Result<(String, i64, i64)> {
+    let bad = || OneError::Other(format!("Region must be name:start-end, got {}", region));
+    let (name, range) = region.rsplit_once(':').ok_or_else(bad)?;
+    let (start, end) = range.split_once('-').ok_or_else(bad)?;
+    let start: i64 = start.parse().map_err(|_| bad())?;
+    let end: i64 = end.parse().map_err(|_| bad())?;
+    Ok((name.to_string(), start, end))
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| "data/test.1aln".to_string());
+    let mut file = OneFile::open_read(&path, None, Some("aln"), 1)?;
+
+    let group = if file.stats(aln::GDB_GROUP)?.0 > 1 {
+        2
+    } else {
+        1
+    };
+    let a_gdb = GdbIndex::new(&GdbReader::from_alignment(&mut file, 1)?);
+    let b_gdb = GdbReader::from_alignment(&mut file, group)?;
+    let (name, start, end) = match args.next() {
+        Some(region) => parse_region(&region)?,
+        None => {
+            let first = &b_gdb.scaffolds()[0];
+            (first.name.clone(), first.length / 4, first.length / 2)
+        }
+    };
+    let b_index = GdbIndex::new(&b_gdb);
+
+    let mut index = RegionIndex::new(file)?;
+    println!(
+        "Alignments overlapping {}:{}-{} ({} indexed)",
+        name,
+        start,
+        end,
+        index.len()
+    );
+    let hits = index.query(&name, start, end)?;
+    for aln in &hits {
+        let n = aln.normalized(&a_gdb, &b_index)?;
+        println!(
+            "  {} {}-{} {} {} {}-{}",
+            n.query_name,
+            n.query_start,
+            n.query_end,
+            if n.reverse { '-' } else { '+' },
+            n.target_name,
+            n.target_start,
+            n.target_end
+        );
+    }
+    println!("{} alignments", hits.len());
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+#ifndef ONECODE_RS_H
+#define ONECODE_RS_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stdbool.h>
+#include <stdint.h>
+#include <stddef.h>
+
+// An in-memory GDB skeleton with lookup by scaffold name
+//
+// The skeleton carries lengths and structure but no bases, so as a
+// `SequenceStore` it answers `len()` but fails on `fetch()`.
+typedef struct GdbReader GdbReader;
+
+// A `.1seq` reader with a name index for random access to sequences
+//
+// Opening the file performs a single pass to record the name and length of
+// every sequence. Binary files are then accessed through the object index;
+// ASCII files are re-scanned from the start whenever a fetch moves backwards.
+typedef struct SeqReader SeqReader;
+
+// Message of the last error on this thread, or NULL if none occurred
+//
+// The string stays valid until the next failing call on the same thread.
+const char *onecode_last_error(void);
+
+// Release a string returned by this library
+void onecode_string_free(char *s);
+
+// Release a buffer of `len` bytes returned by this library
+void onecode_bytes_free(uint8_t *data, size_t len);
+
+// Open a `.1seq` file and index its sequences, or return NULL on error
+struct SeqReader *onecode_seq_open(const char *path);
+
+// Close a sequence reader
+void onecode_seq_free(struct SeqReader *reader);
+
+// Number of sequences, or -1 on error
+int64_t onecode_seq_count(const struct SeqReader *reader);
+
+// Identifier of sequence `i` (0-indexed), or NULL if it has none
+char *onecode_seq_name(const struct SeqReader *reader, size_t i);
+
+// Length of sequence `i` (0-indexed), or -1 if out of range
+int64_t onecode_seq_length(const struct SeqReader *reader, size_t i);
+
+// Index of the sequence named `name`, or -1 if there is none
+int64_t onecode_seq_id(const struct SeqReader *reader, const char *name);
+
+// Read sequence `i` (0-indexed) as lower-case bases
+//
+// Stores the length in `*len` and returns a buffer to be released with
+// `onecode_bytes_free()`, or NULL on error.
+uint8_t *onecode_seq_fetch(struct SeqReader *reader, size_t i, size_t *len);
+
+// Open a standalone `.1gdb` skeleton, or return NULL on error
+struct GdbReader *onecode_gdb_open(const char *path);
+
+// Read the skeleton of 'g' group `group` (1-indexed) of a `.1aln` file, or return NULL on error
+struct GdbReader *onecode_gdb_from_alignment(const char *path, int64_t group);
+
+// Close a skeleton reader
+void onecode_gdb_free(struct GdbReader *gdb);
+
+// Number of scaffolds, or -1 on error
+int64_t onecode_gdb_num_scaffolds(const struct GdbReader *gdb);
+
+// Name of scaffold `i` (0-indexed), or NULL if out of range
+char *onecode_gdb_scaffold_name(const struct GdbReader *gdb, size_t i);
+
+// Length of scaffold `i` (0-indexed), or -1 if out of range
+int64_t onecode_gdb_scaffold_length(const struct GdbReader *gdb, size_t i);
+
+// Number of contigs of scaffold `i` (0-indexed), or -1 if out of range
+int64_t onecode_gdb_num_contigs(const struct GdbReader *gdb, size_t i);
+
+// Offset within its scaffold and length of contig `c` of scaffold `i`
+//
+// Returns false if either index is out of range.
+bool onecode_gdb_contig(const struct GdbReader *gdb, size_t i, size_t c, int64_t *offset, int64_t *len);
+
+// Import a FASTA or FASTQ file, optionally gzipped, into a new `.1seq` file with default options
+//
+// Returns the number of sequences written, or -1 on error.
+int64_t onecode_import_fasta(const char *fasta_path, const char *out_path, bool binary);
+
+// Export a `.1seq` file as FASTA (FASTQ for sequences with qualities)
+//
+// FASTA sequence lines are wrapped after `line_width` bases, or not at all
+// if it is 0. Returns the number of sequences written, or -1 on error.
+int64_t onecode_export_fasta(const char *seq_path, const char *out_path, size_t line_width);
+
+// Write a skeleton as AGP 2.1 to `out_path`, returning false on error
+bool onecode_gdb_to_agp(const struct GdbReader *gdb, const char *out_path);
+
+// Write the alignments of a binary `.1aln` file as PAF to `out_path`
+//
+// Returns the number of records written, or -1 on error.
+int64_t onecode_aln_to_paf(const char *aln_path, const char *out_path);
+
+#endif  /* ONECODE_RS_H */
@@ -0,0 +1,465 @@
+//! Reader for FastGA alignment files (.1aln)
+//!
+//! Each alignment is an 'A' object followed by optional data lines: 'R' when
+//! the b sequence is reverse-complemented, 'D' for the number of differences
+//! and 'T'/'X' for the trace points and per-interval differences. `AlnReader`
+//! gathers these into one `Alignment` per 'A' line:
+//!
+//! ```no_run
+//! use onecode::AlnReader;
+//!
+//! for aln in AlnReader::open("data.1aln")? {
+//!     println!("{} {}..{} -> {} {}..{}", aln.query_id, aln.query_start, aln.query_end,
+//!              aln.target_id, aln.target_start, aln.target_end);
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! On a binary file the trace lists are skipped without being decoded, as
+//! most uses need only the coordinates: `Alignment::load_trace()` fetches
+//! those of an alignment later through the object index, and
+//! `AlnReader::with_traces()` loads every alignment's as it is read.
+//!
+//! Query and target ids are contig ids of the first and second embedded
+//! skeleton; `GdbReader::from_alignment()` maps them to scaffolds, and
+//! `Alignment::normalized()` gives the coordinates in scaffold space.
+
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::gdb::GdbIndex;
+use crate::linetypes::aln;
+use crate::seq::complement;
+use std::borrow::Cow;
+
+/// One alignment with the data lines that follow its 'A' line
+///
+/// Coordinates are 0-based and half-open, in contig space, as in the file
+/// and as in PAF; see `coord` for converting to 1-based formats.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Alignment {
+    /// Contig id in the query (a) skeleton
+    pub query_id: i64,
+    pub query_start: i64,
+    pub query_end: i64,
+    /// Contig id in the target (b) skeleton
+    pub target_id: i64,
+    /// Target coordinates, on the reverse complement of the contig if `reverse`
+    pub target_start: i64,
+    pub target_end: i64,
+    /// Target is reverse-complemented ('R' line present)
+    pub reverse: bool,
+    /// Number of differences, 0 without a 'D' line
+    pub diffs: i64,
+    /// Target bases spanned by each trace interval, empty without a 'T' line
+    pub trace_points: Vec<i64>,
+    /// Differences per trace interval, empty without an 'X' line
+    pub trace_diffs: Vec<i64>,
+    /// Number of the 'A' object in its file, counting from 1, as for `OneFile::goto()`
+    pub object: i64,
+    /// Whether `trace_points` and `trace_diffs` have been read, rather than
+    /// deferred until `load_trace()`
+    pub trace_loaded: bool,
+}
+
+impl Alignment {
+    /// Coordinates on the forward strand of the query and target scaffolds
+    ///
+    /// `query` and `target` index the skeletons the contig ids refer to:
+    /// 'g' group 1 and group 2 (or group 1 again if there is only one), as
+    /// `OneFile::gdb_index()` and `OneFile::group_gdb_index()` give them.
+    /// The result is what FastGA's ALNtoPAF reports, and satisfies:
+    ///
+    /// - coordinates are 0-based and half-open, with
+    ///   `0 <= start <= end <= len` on both sides
+    /// - each span equals the span of the alignment in contig coordinates
+    /// - target coordinates are on the forward strand even if `reverse`;
+    ///   the query then aligns to the reverse complement of that interval
+    ///
+    /// Fails with `InvalidFormat` if a contig id is not in its skeleton or
+    /// the contig coordinates lie outside the contig.
+    pub fn normalized<'a>(
+        &self,
+        query: &'a GdbIndex,
+        target: &'a GdbIndex,
+    ) -> Result<NormalizedAlignment<'a>> {
+        let q = ContigPlace::find(query, "Query", self.query_id, self.query_start, self.query_end)?;
+        let (t_start, t_end) = (self.target_start, self.target_end);
+        let t = ContigPlace::find(target, "Target", self.target_id, t_start, t_end)?;
+        let (target_start, target_end) = if self.reverse {
+            (t.len - t_end, t.len - t_start)
+        } else {
+            (t_start, t_end)
+        };
+        Ok(NormalizedAlignment {
+            query_name: q.scaffold,
+            query_len: q.scaffold_len,
+            query_start: q.offset + self.query_start,
+            query_end: q.offset + self.query_end,
+            target_name: t.scaffold,
+            target_len: t.scaffold_len,
+            target_start: t.offset + target_start,
+            target_end: t.offset + target_end,
+            reverse: self.reverse,
+        })
+    }
+
+    /// Read the 'T' and 'X' lines of this alignment, deferred by `reader`
+    ///
+    /// Goes to the alignment through the object index of the binary file it
+    /// was read from, and leaves `reader` where it was, so alignments can be
+    /// loaded during iteration or after it. Does nothing if the trace is
+    /// already loaded.
+    pub fn load_trace(&mut self, reader: &mut AlnReader) -> Result<()> {
+        if self.trace_loaded {
+            return Ok(());
+        }
+        if self.object <= 0 {
+            return Err(OneError::Other(
+                "Cannot load the trace of an alignment not read from a file".to_string(),
+            ));
+        }
+        let file = &mut reader.file;
+        let projection = file.projection();
+        file.set_projection(None)?;
+        let loaded = file.goto(aln::ALIGNMENT, self.object).map(|()| {
+            file.read_line();
+            read_alignment(file).0
+        });
+        file.set_projection(projection.as_deref())?;
+
+        // Back to the line the reader has read but not consumed
+        let pending = reader.line_type;
+        if pending != '\0' {
+            let object = reader.file.object(pending);
+            reader.file.goto(pending, object)?;
+            reader.file.read_line();
+        }
+        let loaded = loaded?;
+        self.trace_points = loaded.trace_points;
+        self.trace_diffs = loaded.trace_diffs;
+        self.trace_loaded = true;
+        Ok(())
+    }
+
+    /// The trace intervals of the alignment, with their query and target spans
+    ///
+    /// FastGA places a trace point at every multiple of `spacing` in the
+    /// query, so the first and last intervals may be short. `spacing` is the
+    /// one the file was written with, `AlnReader::trace_spacing()`; FastGA's
+    /// default of 100 is not assumed. Fails with `InvalidFormat` if `spacing`
+    /// is not positive or the 'T' and 'X' lines do not hold one entry per
+    /// interval, e.g. because `spacing` is not the file's, or do not sum to
+    /// the target span.
+    pub fn trace_intervals(&self, spacing: i64) -> Result<Vec<TraceInterval>> {
+        if spacing <= 0 {
+            return Err(OneError::InvalidFormat(format!(
+                "Trace point spacing must be positive, not {} (no 't' line?)",
+                spacing
+            )));
+        }
+        let (start, end) = (self.query_start, self.query_end);
+        let n = if end > start { (end - 1) / spacing - start / spacing + 1 } else { 0 };
+        if self.trace_points.len() as i64 != n || self.trace_diffs.len() as i64 != n {
+            return Err(OneError::InvalidFormat(format!(
+                "Alignment over query {}..{} has {} trace points and {} trace diffs, \
+                 not the {} intervals of spacing {}",
+                start,
+                end,
+                self.trace_points.len(),
+                self.trace_diffs.len(),
+                n,
+                spacing
+            )));
+        }
+        let mut intervals = Vec::with_capacity(n as usize);
+        let (mut query, mut target) = (start, self.target_start);
+        for (&bases, &diffs) in self.trace_points.iter().zip(&self.trace_diffs) {
+            let query_end = ((query / spacing + 1) * spacing).min(end);
+            intervals.push(TraceInterval {
+                query_start: query,
+                query_end,
+                target_start: target,
+                target_end: target + bases,
+                diffs,
+            });
+            query = query_end;
+            target += bases;
+        }
+        if target != self.target_end {
+            return Err(OneError::InvalidFormat(format!(
+                "Trace points span target {}..{}, not the alignment's {}..{}",
+                self.target_start, target, self.target_start, self.target_end
+            )));
+        }
+        Ok(intervals)
+    }
+
+    /// Expand the alignment to a CIGAR string with `=`, `X`, `I` and `D` operations
+    ///
+    /// `query` and `target` are the contig sequences the alignment refers
+    /// to, the target on its forward strand, and `spacing` the file's trace
+    /// point spacing, as `trace_intervals()`. Each trace interval is aligned
+    /// with the fewest differences, and as in PAF an `I` is a query base
+    /// missing from the target and a `D` a target base missing from the
+    /// query. Bases compare without regard to case. Fails with
+    /// `InvalidFormat` if the trace does not fit `spacing` or the
+    /// alignment lies beyond the end of a sequence.
+    /// `convert::aln_to_paf_with_cigar()` does this for each alignment of a
+    /// file, fetching the sequences from `SequenceStore`s.
+    pub fn cigar(&self, spacing: i64, query: &[u8], target: &[u8]) -> Result<String> {
+        let intervals = self.trace_intervals(spacing)?;
+        if self.query_end > query.len() as i64 || self.target_end > target.len() as i64 {
+            return Err(OneError::InvalidFormat(format!(
+                "Alignment to {}..{} and {}..{} runs past sequences of length {} and {}",
+                self.query_start,
+                self.query_end,
+                self.target_start,
+                self.target_end,
+                query.len(),
+                target.len()
+            )));
+        }
+        let target: Cow<[u8]> = if self.reverse {
+            Cow::Owned(target.iter().rev().map(|&b| complement(b)).collect())
+        } else {
+            Cow::Borrowed(target)
+        };
+        let mut ops = Vec::new();
+        for interval in &intervals {
+            let q = &query[interval.query_start as usize..interval.query_end as usize];
+            let t = &target[interval.target_start as usize..interval.target_end as usize];
+            align_interval(q, t, &mut ops);
+        }
+
+        let mut cigar = String::new();
+        for run in ops.chunk_by(|a, b| a == b) {
+            cigar.push_str(&format!("{}{}", run.len(), run[0] as char));
+        }
+        Ok(cigar)
+    }
+
+}
+
+/// One trace interval of an alignment, from `Alignment::trace_intervals()`
+///
+/// Coordinates are 0-based and half-open, in contig space, target
+/// coordinates on the reverse complement of the contig if the alignment is
+/// reversed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceInterval {
+    pub query_start: i64,
+    pub query_end: i64,
+    pub target_start: i64,
+    pub target_end: i64,
+    /// Differences in the interval, from the 'X' line
+    pub diffs: i64,
+}
+
+/// An alignment in forward-strand scaffold coordinates, from `Alignment::normalized()`
+///
+/// Coordinates are 0-based and half-open, as in PAF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalizedAlignment<'a> {
+    pub query_name: &'a str,
+    /// Length of the query scaffold, gaps included
+    pub query_len: i64,
+    pub query_start: i64,
+    pub query_end: i64,
+    pub target_name: &'a str,
+    /// Length of the target scaffold, gaps included
+    pub target_len: i64,
+    pub target_start: i64,
+    pub target_end: i64,
+    /// The query aligns to the reverse complement of the target interval
+    pub reverse: bool,
+}
+
+/// Where a contig lies in its skeleton
+struct ContigPlace<'a> {
+    scaffold: &'a str,
+    scaffold_len: i64,
+    offset: i64,
+    len: i64,
+}
+
+impl<'a> ContigPlace<'a> {
+    /// Look up contig `id`, checking that `start..end` lies within it
+    fn find(index: &'a GdbIndex, which: &str, id: i64, start: i64, end: i64) -> Result<Self> {
+        let unknown = || {
+            let message = format!("{} contig {} is not in the embedded skeleton", which, id);
+            OneError::InvalidFormat(message)
+        };
+        let place = ContigPlace {
+            scaffold: index.name(id).ok_or_else(unknown)?,
+            scaffold_len: index.length(id).ok_or_else(unknown)?,
+            offset: index.offset(id).ok_or_else(unknown)?,
+            len: index.contig_length(id).ok_or_else(unknown)?,
+        };
+        if !(0 <= start && start <= end && end <= place.len) {
+            return Err(OneError::InvalidFormat(format!(
+                "{} interval {}..{} lies outside contig {} of length {}",
+                which, start, end, id, place.len
+            )));
+        }
+        Ok(place)
+    }
+}
+
+/// Iterator over the alignments of a `.1aln` file
+pub struct AlnReader {
+    file: OneFile,
+    trace_spacing: i64,
+    line_type: char, // read but not yet consumed
+    lazy: bool,      // 'T' and 'X' lines projected away
+}
+
+impl AlnReader {
+    /// Open a `.1aln` file
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Self::new(OneFile::open_read(path, None, Some("aln"), 1)?))
+    }
+
+    /// Read alignments from an open file, starting at its current position
+    ///
+    /// The trace point spacing is only seen if `file` is at the start.
+    /// Traces are deferred if `file` is binary and seekable, and read with
+    /// each alignment otherwise.
+    pub fn new(mut file: OneFile) -> Self {
+        let mut trace_spacing = 0;
+        let mut line_type = file.read_line();
+        while !matches!(line_type, '\0' | aln::GDB_GROUP | aln::SCAFFOLD | aln::CHAIN | aln::ALIGNMENT) {
+            if line_type == aln::TRACE_SPACING {
+                trace_spacing = file.int(0);
+            }
+            line_type = file.read_line();
+        }
+        let mut reader = AlnReader {
+            file,
+            trace_spacing,
+            line_type,
+            lazy: false,
+        };
+        if reader.file.is_binary() && reader.file.is_seekable() {
+            let declared = reader.file.all_stats().iter().map(|s| s.line_type).collect();
+            let kept: Vec<char> = reader
+                .file
+                .projection()
+                .unwrap_or(declared)
+                .into_iter()
+                .filter(|&t| t != aln::TRACE && t != aln::TRACE_DIFFS)
+                .collect();
+            reader.lazy = reader.file.set_projection(Some(&kept)).is_ok();
+        }
+        reader
+    }
+
+    /// Read the trace of each alignment with it, rather than deferring it
+    pub fn with_traces(mut self) -> Self {
+        if self.lazy {
+            // The pending line is not a trace line, so it is unaffected
+            let mut kept = self.file.projection().unwrap_or_default();
+            kept.extend([aln::TRACE, aln::TRACE_DIFFS]);
+            self.file.set_projection(Some(&kept)).ok();
+            self.lazy = false;
+        }
+        self
+    }
+
+    /// Spacing of trace points in the query, from the global 't' line (0 if absent)
+    ///
+    /// This is the spacing to pass to `Alignment::trace_intervals()` and
+    /// `Alignment::cigar()`: FastGA lets it be set per run, so it should be
+    /// read from the file rather than assumed to be the default of 100.
+    pub fn trace_spacing(&self) -> i64 {
+        self.trace_spacing
+    }
+
+    /// The underlying file, e.g. for reading header statistics
+    pub fn file(&self) -> &OneFile {
+        &self.file
+    }
+}
+
+impl Iterator for AlnReader {
+    type Item = Alignment;
+
+    fn next(&mut self) -> Option<Alignment> {
+        while self.line_type != aln::ALIGNMENT {
+            if self.line_type == '\0' {
+                return None;
+            }
+            self.line_type = self.file.read_line();
+        }
+
+        let (mut alignment, next) = read_alignment(&mut self.file);
+        alignment.trace_loaded = !self.lazy;
+        self.line_type = next;
+        Some(alignment)
+    }
+}
+
+/// Append the edit operations of a fewest-differences alignment of `q` to `t`
+fn align_interval(q: &[u8], t: &[u8], ops: &mut Vec<u8>) {
+    let (n, m) = (q.len(), t.len());
+    let same = |i: usize, j: usize| q[i] | 0x20 == t[j] | 0x20;
+    let mut cost = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in 0..=n {
+        for j in 0..=m {
+            cost[at(i, j)] = match (i, j) {
+                (0, _) => j as u32,
+                (_, 0) => i as u32,
+                _ => (cost[at(i - 1, j - 1)] + !same(i - 1, j - 1) as u32)
+                    .min(cost[at(i - 1, j)] + 1)
+                    .min(cost[at(i, j - 1)] + 1),
+            };
+        }
+    }
+    let mut interval = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && cost[at(i, j)] == cost[at(i - 1, j - 1)] + !same(i - 1, j - 1) as u32 {
+            interval.push(if same(i - 1, j - 1) { b'=' } else { b'X' });
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && cost[at(i, j)] == cost[at(i - 1, j)] + 1 {
+            interval.push(b'I');
+            i -= 1;
+        } else {
+            interval.push(b'D');
+            j -= 1;
+        }
+    }
+    ops.extend(interval.iter().rev());
+}
+
+/// Gather the current 'A' line of `file` and the data lines that follow it
+///
+/// Returns the alignment and the type of the first line after it, which has
+/// been read but not consumed.
+pub(crate) fn read_alignment(file: &mut OneFile) -> (Alignment, char) {
+    let mut alignment = Alignment {
+        query_id: file.int(0),
+        query_start: file.int(1),
+        query_end: file.int(2),
+        target_id: file.int(3),
+        target_start: file.int(4),
+        target_end: file.int(5),
+        object: file.object(aln::ALIGNMENT),
+        trace_loaded: true,
+        ..Alignment::default()
+    };
+    loop {
+        let line_type = file.read_line();
+        match line_type {
+            aln::REVERSE => alignment.reverse = true,
+            aln::DIFFERENCES => alignment.diffs = file.int(0),
+            aln::TRACE => alignment.trace_points = file.int_list().unwrap_or(&[]).to_vec(),
+            aln::TRACE_DIFFS => alignment.trace_diffs = file.int_list().unwrap_or(&[]).to_vec(),
+            '\0' | aln::ALIGNMENT | aln::CHAIN | aln::GDB_GROUP | aln::SCAFFOLD => {
+                return (alignment, line_type);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,543 @@
+//! Pure-Rust reader for ASCII ONE files
+//!
+//! Enabled with the `pure-rust` feature. `AsciiReader` parses the header
+//! (type, subtype, provenance, references, counts and the schema given by
+//! '~' lines) and decodes each data line into a `Record`, without calling
+//! into the C library, for platforms where ONElib is hard to build:
+//!
+//! ```no_run
+//! use onecode::ascii::AsciiReader;
+//! use onecode::{ListValue, Record};
+//!
+//! let mut reader = AsciiReader::open("reads.seq")?;
+//! println!("{} file", reader.header().file_type);
+//! for record in reader {
+//!     if let Record { line_type: 'S', list: Some(ListValue::Dna(dna)), .. } = record? {
+//!         println!("{} bp", dna.len());
+//!     }
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! Only reading is supported, and only ASCII files: binary files need
+//! `OneFile`.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::error::{OneError, Result};
+use crate::record::{FieldValue, ListValue, Record};
+use crate::schema::normalize_schema_text;
+use crate::types::{OneCounts, OneProvenance, OneReference, Warning};
+
+/// Most elements reserved up front for a list, whose length is read from the file
+const MAX_PREALLOC: usize = 1 << 16;
+
+/// Field types, as named in schema lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Int,
+    Real,
+    Char,
+    String,
+    Dna,
+    IntList,
+    RealList,
+    StringList,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        Some(match name {
+            "INT" => Field::Int,
+            "REAL" => Field::Real,
+            "CHAR" => Field::Char,
+            "STRING" => Field::String,
+            "DNA" => Field::Dna,
+            "INT_LIST" => Field::IntList,
+            "REAL_LIST" => Field::RealList,
+            "STRING_LIST" => Field::StringList,
+            _ => return None,
+        })
+    }
+}
+
+/// Fields of the header line types this reader understands
+fn header_fields(line_type: u8) -> Option<&'static [Field]> {
+    Some(match line_type {
+        b'1' => &[Field::String, Field::Int, Field::Int],
+        b'2' | b'>' => &[Field::String],
+        b'.' => &[],
+        b'~' => &[Field::Char, Field::Char, Field::StringList],
+        b'#' | b'@' | b'+' => &[Field::Char, Field::Int],
+        b'%' => &[Field::Char, Field::Char, Field::Char, Field::Int],
+        b'!' => &[Field::StringList],
+        b'<' => &[Field::String, Field::Int],
+        _ => return None,
+    })
+}
+
+/// Header of an ASCII ONE file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsciiHeader {
+    /// Primary file type, e.g. "seq"; empty for a file without a header
+    pub file_type: String,
+    pub major: i64,
+    pub minor: i64,
+    pub sub_type: Option<String>,
+    pub provenance: Vec<OneProvenance>,
+    pub references: Vec<OneReference>,
+    /// Line counts, longest list and total list length from the '#', '@'
+    /// and '+' lines
+    pub counts: HashMap<char, OneCounts>,
+    /// Free text of the '.' lines
+    pub text: Vec<String>,
+}
+
+/// Reader decoding the data lines of an ASCII ONE file into `Record`s
+///
+/// Data lines are checked against the schema as strictly as ONElib checks
+/// them: fields separated by single spaces, strings and lists prefixed by
+/// their length, and anything after the last field taken as a comment.
+pub struct AsciiReader<R> {
+    input: R,
+    header: AsciiHeader,
+    fields: HashMap<char, Vec<Field>>,
+    objects: Vec<char>,
+    line: i64,
+    comment: Option<String>,
+    done: bool,
+    lenient: bool,
+    warnings: Vec<Warning>,
+}
+
+impl AsciiReader<BufReader<File>> {
+    /// Open an ASCII ONE file whose header defines its schema
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|e| OneError::io(path, &e))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> AsciiReader<R> {
+    /// Read the header from `input`; the schema comes from its '~' lines
+    pub fn new(input: R) -> Result<Self> {
+        Self::with_schema(input, "")
+    }
+
+    /// Read the header from `input`, taking line types it does not define
+    /// from `schema_text`
+    ///
+    /// The schema text is in the form `OneSchema::from_text()` takes. Its
+    /// definitions for the file's primary type are used, or those of its
+    /// first primary type if the file has no header.
+    pub fn with_schema(input: R, schema_text: &str) -> Result<Self> {
+        let mut reader = AsciiReader {
+            input,
+            header: AsciiHeader::default(),
+            fields: HashMap::new(),
+            objects: Vec::new(),
+            line: 0,
+            comment: None,
+            done: false,
+            lenient: false,
+            warnings: Vec::new(),
+        };
+        reader.read_header()?;
+
+        let normalized = normalize_schema_text(schema_text);
+        let mut section = None;
+        for definition in normalized.lines() {
+            let mut tokens = definition.split(' ');
+            match tokens.next() {
+                Some("P") => {
+                    let name = tokens.next().unwrap_or("");
+                    let wanted = reader.header.file_type.is_empty() && section.is_none()
+                        || name == reader.header.file_type;
+                    section = Some(wanted);
+                }
+                Some(kind @ ("O" | "D")) if section == Some(true) => {
+                    let line_type = tokens.next().and_then(|t| t.chars().next());
+                    if let Some(line_type) = line_type {
+                        if !reader.fields.contains_key(&line_type) {
+                            let names: Vec<&str> = tokens.collect();
+                            reader.define(kind == "O", line_type, &names)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !reader.header.file_type.is_empty() && reader.fields.is_empty() {
+            return Err(OneError::SchemaError(format!(
+                "No schema for file type {}",
+                reader.header.file_type
+            )));
+        }
+        Ok(reader)
+    }
+
+    /// Skip data lines of types the schema does not declare, rather than
+    /// failing on them
+    ///
+    /// Each skipped line is noted as a `Warning::SkippedLine`, see
+    /// `warnings()`. Off by default, as in ONElib.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Lines skipped so far in lenient mode
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The file header
+    pub fn header(&self) -> &AsciiHeader {
+        &self.header
+    }
+
+    /// Line types defined as objects, in definition order
+    pub fn object_types(&self) -> &[char] {
+        &self.objects
+    }
+
+    /// Number of the line last read, counting from 1 at the top of the file
+    pub fn line_number(&self) -> i64 {
+        self.line
+    }
+
+    /// Comment following the fields of the last record read, if any
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Read the next data line, or None at the end of the file
+    ///
+    /// '.' lines between data lines are skipped, as are the '/' comment
+    /// lines of files converted from binary, and in lenient mode lines of
+    /// unknown types.
+    pub fn read_record(&mut self) -> Result<Option<Record>> {
+        if self.done {
+            return Ok(None);
+        }
+        let result = self.next_record();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        loop {
+            let line_type = match self.peek()? {
+                None | Some(b'\n') => return Ok(None), // a blank line ends the data
+                Some(c) => c,
+            };
+            self.input.consume(1);
+            self.line += 1;
+            if line_type == b'.' || line_type == b'/' {
+                self.skip_line()?;
+                continue;
+            }
+            let Some(fields) = self.fields.get(&(line_type as char)).cloned() else {
+                if self.lenient {
+                    let (line, line_type) = (self.line, line_type as char);
+                    self.warnings.push(Warning::SkippedLine { line, line_type });
+                    self.skip_line()?;
+                    continue;
+                }
+                return Err(self.error(format!("unknown line type '{}'", line_type as char)));
+            };
+            let (values, list) = self.read_fields(&fields)?;
+            self.comment = self.read_comment()?;
+            return Ok(Some(Record {
+                line_type: line_type as char,
+                fields: values,
+                list,
+            }));
+        }
+    }
+
+    fn read_header(&mut self) -> Result<()> {
+        let mut first = true;
+        while let Some(line_type) = self.peek()? {
+            if line_type.is_ascii_alphabetic() || line_type == b'\n' {
+                break;
+            }
+            if first && line_type != b'1' {
+                return Err(self.error("header must begin with a '1' line".to_string()));
+            }
+            first = false;
+            if line_type == b'$' {
+                return Err(OneError::InvalidFormat(
+                    "binary ONE file; the pure-Rust reader only reads ASCII".to_string(),
+                ));
+            }
+            let fields = header_fields(line_type).ok_or_else(|| {
+                self.error(format!("unknown header line '{}'", line_type as char))
+            })?;
+            self.input.consume(1);
+            self.line += 1;
+            let (values, list) = self.read_fields(fields)?;
+            let comment = self.read_comment()?;
+
+            let int = |i: usize| match values.get(i) {
+                Some(FieldValue::Int(x)) => *x,
+                _ => 0,
+            };
+            let char_at = |i: usize| match values.get(i) {
+                Some(FieldValue::Char(c)) => *c,
+                _ => '\0',
+            };
+            let string = match &list {
+                Some(ListValue::String(s)) => s.clone(),
+                _ => String::new(),
+            };
+            let strings = match list {
+                Some(ListValue::StringList(s)) => s,
+                _ => Vec::new(),
+            };
+            match line_type {
+                b'1' => {
+                    if self.line > 1 {
+                        return Err(self.error("'1' must be the first line".to_string()));
+                    }
+                    self.header.file_type = string;
+                    self.header.major = int(0);
+                    self.header.minor = int(1);
+                }
+                b'2' => self.header.sub_type = Some(string),
+                b'.' => self.header.text.extend(comment),
+                b'~' => {
+                    let line_type = char_at(1);
+                    if !line_type.is_ascii_alphabetic() {
+                        return Err(self.error(format!("type '{}' is not a letter", line_type)));
+                    }
+                    match char_at(0) {
+                        kind @ ('O' | 'D') => {
+                            let names: Vec<&str> = strings.iter().map(String::as_str).collect();
+                            self.define(kind == 'O', line_type, &names)?;
+                        }
+                        'G' => {}
+                        kind => {
+                            return Err(self.error(format!("unknown definition kind '{}'", kind)))
+                        }
+                    }
+                }
+                b'#' | b'@' | b'+' => {
+                    let counts = self.header.counts.entry(char_at(0)).or_default();
+                    match line_type {
+                        b'#' => counts.count = int(1),
+                        b'@' => counts.max = int(1),
+                        _ => counts.total = int(1),
+                    }
+                }
+                b'!' => {
+                    if strings.len() != 4 {
+                        return Err(self.error("provenance needs 4 strings".to_string()));
+                    }
+                    let mut strings = strings.into_iter();
+                    let mut next = || strings.next().unwrap_or_default();
+                    self.header.provenance.push(OneProvenance {
+                        program: next(),
+                        version: next(),
+                        command: next(),
+                        date: next(),
+                    });
+                }
+                b'<' => self.header.references.push(OneReference {
+                    filename: string,
+                    count: int(0),
+                }),
+                _ => {} // '%' group statistics and '>' deferred references
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the definition of `line_type` from its field type names
+    fn define(&mut self, is_object: bool, line_type: char, names: &[&str]) -> Result<()> {
+        let fields = names
+            .iter()
+            .map(|name| {
+                Field::from_name(name).ok_or_else(|| {
+                    OneError::SchemaError(format!("bad field type {} for '{}'", name, line_type))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let lists = fields
+            .iter()
+            .filter(|f| !matches!(f, Field::Int | Field::Real | Field::Char));
+        if lists.count() > 1 {
+            return Err(OneError::SchemaError(format!(
+                "more than one list field for '{}'",
+                line_type
+            )));
+        }
+        if is_object && !self.objects.contains(&line_type) {
+            self.objects.push(line_type);
+        }
+        self.fields.insert(line_type, fields);
+        Ok(())
+    }
+
+    fn read_fields(&mut self, fields: &[Field]) -> Result<(Vec<FieldValue>, Option<ListValue>)> {
+        let mut values = Vec::with_capacity(fields.len());
+        let mut list = None;
+        for field in fields {
+            match field {
+                Field::Int => values.push(FieldValue::Int(self.read_int()?)),
+                Field::Real => values.push(FieldValue::Real(self.read_real()?)),
+                Field::Char => {
+                    self.expect_space()?;
+                    let c = self
+                        .next_byte()?
+                        .ok_or_else(|| self.error("missing char".into()))?;
+                    values.push(FieldValue::Char(c as char));
+                }
+                Field::String => {
+                    let len = self.read_len()?;
+                    list = Some(ListValue::String(self.read_string(len)?));
+                }
+                Field::Dna => {
+                    let len = self.read_len()?;
+                    list = Some(ListValue::Dna(self.read_bytes(len)?));
+                }
+                Field::IntList => {
+                    let len = self.read_len()?;
+                    let ints = (0..len).map(|_| self.read_int()).collect::<Result<_>>()?;
+                    list = Some(ListValue::IntList(ints));
+                }
+                Field::RealList => {
+                    let len = self.read_len()?;
+                    let reals = (0..len).map(|_| self.read_real()).collect::<Result<_>>()?;
+                    list = Some(ListValue::RealList(reals));
+                }
+                Field::StringList => {
+                    let len = self.read_len()?;
+                    let mut strings = Vec::with_capacity(len.min(MAX_PREALLOC));
+                    for _ in 0..len {
+                        let n = self.read_len()?;
+                        strings.push(self.read_string(n)?);
+                    }
+                    list = Some(ListValue::StringList(strings));
+                }
+            }
+        }
+        Ok((values, list))
+    }
+
+    fn read_int(&mut self) -> Result<i64> {
+        let token = self.read_token()?;
+        token
+            .parse()
+            .map_err(|_| self.error(format!("bad int '{}'", token)))
+    }
+
+    fn read_real(&mut self) -> Result<f64> {
+        let token = self.read_token()?;
+        token
+            .parse()
+            .map_err(|_| self.error(format!("bad real '{}'", token)))
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let len = self.read_int()?;
+        usize::try_from(len).map_err(|_| self.error(format!("negative length {}", len)))
+    }
+
+    /// A space followed by the characters up to the next white space
+    fn read_token(&mut self) -> Result<String> {
+        self.expect_space()?;
+        let mut token = String::new();
+        while let Some(c) = self.peek()? {
+            if c.is_ascii_whitespace() {
+                break;
+            }
+            token.push(c as char);
+            self.input.consume(1);
+        }
+        Ok(token)
+    }
+
+    /// A space followed by exactly `len` bytes
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
+        self.expect_space()?;
+        let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOC));
+        while bytes.len() < len {
+            let buf = self.input.fill_buf().map_err(|_| OneError::ReadFailed)?;
+            if buf.is_empty() {
+                return Err(self.error("line too short".to_string()));
+            }
+            let n = buf.len().min(len - bytes.len());
+            bytes.extend_from_slice(&buf[..n]);
+            self.input.consume(n);
+        }
+        Ok(bytes)
+    }
+
+    fn read_string(&mut self, len: usize) -> Result<String> {
+        let bytes = self.read_bytes(len)?;
+        String::from_utf8(bytes).map_err(|e| OneError::InvalidUtf8(e.utf8_error()))
+    }
+
+    /// The rest of the line after the fields: nothing, or a space and a comment
+    fn read_comment(&mut self) -> Result<Option<String>> {
+        match self.next_byte()? {
+            None | Some(b'\n') => Ok(None),
+            Some(b' ') => {
+                let mut comment = Vec::new();
+                self.input
+                    .read_until(b'\n', &mut comment)
+                    .map_err(|_| OneError::ReadFailed)?;
+                if comment.last() == Some(&b'\n') {
+                    comment.pop();
+                }
+                Ok(Some(String::from_utf8_lossy(&comment).into_owned()))
+            }
+            Some(_) => Err(self.error("comment not separated by a space".to_string())),
+        }
+    }
+
+    fn skip_line(&mut self) -> Result<()> {
+        let mut rest = Vec::new();
+        self.input
+            .read_until(b'\n', &mut rest)
+            .map_err(|_| OneError::ReadFailed)?;
+        Ok(())
+    }
+
+    fn expect_space(&mut self) -> Result<()> {
+        match self.next_byte()? {
+            Some(b' ') => Ok(()),
+            _ => Err(self.error("expected a space before the next field".to_string())),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        let buf = self.input.fill_buf().map_err(|_| OneError::ReadFailed)?;
+        Ok(buf.first().copied())
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        let c = self.peek()?;
+        if c.is_some() {
+            self.input.consume(1);
+        }
+        Ok(c)
+    }
+
+    fn error(&self, msg: String) -> OneError {
+        OneError::InvalidFormat(format!("line {}: {}", self.line, msg))
+    }
+}
+
+impl<R: BufRead> Iterator for AsciiReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
@@ -0,0 +1,41 @@
+//! Describe the schema of a ONE file or schema file
+//!
+//! Usage: one-schema describe <file.1xxx | file.schema>
+//!
+//! Prints a table of the line types of each primary type, with their fields
+//! and comments, and the object types each group contains.
+
+use onecode::{sniff, FileKind, OneFile, OneSchema, Result};
+
+fn describe(path: &str) -> Result<String> {
+    let schema = match sniff(path) {
+        Some(FileKind::OneAscii { .. } | FileKind::OneBinary { .. }) => {
+            let file = OneFile::open_read(path, None, None, 1)?;
+            OneSchema::from_text(&file.schema_text()?)?
+        }
+        Some(FileKind::Other) => OneSchema::from_file(path)?,
+        Some(kind) => {
+            return Err(onecode::OneError::OpenFailed(format!("{}: {}", path, kind)));
+        }
+        None => return Err(onecode::OneError::OpenFailed(path.to_string())),
+    };
+    Ok(schema.describe())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path = match args.as_slice() {
+        [command, path] if command == "describe" => path,
+        _ => {
+            eprintln!("Usage: one-schema describe <file.1xxx | file.schema>");
+            std::process::exit(2);
+        }
+    };
+    match describe(path) {
+        Ok(text) => print!("{}", text),
+        Err(e) => {
+            eprintln!("one-schema: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! C ABI for the crate's high-level readers and converters
+//!
+//! Enabled with the `capi` feature. The functions here let Python, Julia or
+//! C programs reuse `SeqReader`, `GdbReader` and the converters instead of
+//! re-implementing them on top of ONElib. The header `include/onecode.h` is
+//! generated from this module with cbindgen:
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/onecode.h
+//! cargo rustc --release --features capi --crate-type cdylib
+//! ```
+//!
+//! Conventions:
+//!
+//! - Readers are opaque handles, created by `onecode_*_open()` and released
+//!   with the matching `onecode_*_free()`.
+//! - Functions that can fail return NULL, -1 or false, and leave a message
+//!   for `onecode_last_error()` on the calling thread.
+//! - Strings and buffers returned to the caller are owned by the caller and
+//!   must be released with `onecode_string_free()` or `onecode_bytes_free()`.
+//! - Pointer arguments must be NULL or valid for the access described; NULL
+//!   handles are reported as errors rather than dereferenced.
+
+#![allow(clippy::missing_safety_doc)]
+
+use crate::convert;
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::gdb::{self, GdbReader};
+use crate::seq::{self, ExportOptions, FastaOptions, SeqReader};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: OneError) {
+    let msg = CString::new(e.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+/// Unwrap a result, recording its error for `onecode_last_error()`
+fn check<T>(result: Result<T>) -> Option<T> {
+    result.map_err(set_error).ok()
+}
+
+unsafe fn str_arg<'a>(s: *const c_char, what: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(OneError::Other(format!("{} is NULL", what)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| OneError::Other(format!("{} is not valid UTF-8", what)))
+}
+
+unsafe fn handle<'a, T>(h: *const T, what: &str) -> Result<&'a T> {
+    h.as_ref().ok_or_else(|| OneError::Other(format!("{} handle is NULL", what)))
+}
+
+unsafe fn handle_mut<'a, T>(h: *mut T, what: &str) -> Result<&'a mut T> {
+    h.as_mut().ok_or_else(|| OneError::Other(format!("{} handle is NULL", what)))
+}
+
+fn to_c_string(s: &str) -> *mut c_char {
+    CString::new(s.replace('\0', " ")).unwrap_or_default().into_raw()
+}
+
+fn io_error(path: &str) -> impl Fn(std::io::Error) -> OneError + '_ {
+    move |e| OneError::Other(format!("{}: {}", path, e))
+}
+
+/// Message of the last error on this thread, or NULL if none occurred
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn onecode_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Release a string returned by this library
+#[no_mangle]
+pub unsafe extern "C" fn onecode_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Release a buffer of `len` bytes returned by this library
+#[no_mangle]
+pub unsafe extern "C" fn onecode_bytes_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Open a `.1seq` file and index its sequences, or return NULL on error
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_open(path: *const c_char) -> *mut SeqReader {
+    check(str_arg(path, "path").and_then(SeqReader::open))
+        .map_or(ptr::null_mut(), |r| Box::into_raw(Box::new(r)))
+}
+
+/// Close a sequence reader
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_free(reader: *mut SeqReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+/// Number of sequences, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_count(reader: *const SeqReader) -> i64 {
+    check(handle(reader, "SeqReader")).map_or(-1, |r| r.num_sequences() as i64)
+}
+
+/// Identifier of sequence `i` (0-indexed), or NULL if it has none
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_name(reader: *const SeqReader, i: usize) -> *mut c_char {
+    check(handle(reader, "SeqReader"))
+        .and_then(|r| r.name(i))
+        .map_or(ptr::null_mut(), to_c_string)
+}
+
+/// Length of sequence `i` (0-indexed), or -1 if out of range
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_length(reader: *const SeqReader, i: usize) -> i64 {
+    check(handle(reader, "SeqReader"))
+        .and_then(|r| r.length(i))
+        .unwrap_or(-1)
+}
+
+/// Index of the sequence named `name`, or -1 if there is none
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_id(reader: *const SeqReader, name: *const c_char) -> i64 {
+    let id = check(handle(reader, "SeqReader").and_then(|r| Ok((r, str_arg(name, "name")?))))
+        .and_then(|(r, name)| r.id(name));
+    id.map_or(-1, |i| i as i64)
+}
+
+/// Read sequence `i` (0-indexed) as lower-case bases
+///
+/// Stores the length in `*len` and returns a buffer to be released with
+/// `onecode_bytes_free()`, or NULL on error.
+#[no_mangle]
+pub unsafe extern "C" fn onecode_seq_fetch(reader: *mut SeqReader, i: usize, len: *mut usize) -> *mut u8 {
+    let Some(seq) = check(handle_mut(reader, "SeqReader").and_then(|r| r.sequence(i))) else {
+        return ptr::null_mut();
+    };
+    if !len.is_null() {
+        *len = seq.len();
+    }
+    Box::into_raw(seq.into_boxed_slice()) as *mut u8
+}
+
+/// Open a standalone `.1gdb` skeleton, or return NULL on error
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_open(path: *const c_char) -> *mut GdbReader {
+    check(str_arg(path, "path").and_then(GdbReader::open))
+        .map_or(ptr::null_mut(), |g| Box::into_raw(Box::new(g)))
+}
+
+/// Read the skeleton of 'g' group `group` (1-indexed) of a `.1aln` file, or return NULL on error
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_from_alignment(path: *const c_char, group: i64) -> *mut GdbReader {
+    let gdb = str_arg(path, "path").and_then(|path| {
+        let mut file = OneFile::open_read(path, None, Some("aln"), 1)?;
+        GdbReader::from_alignment(&mut file, group)
+    });
+    check(gdb).map_or(ptr::null_mut(), |g| Box::into_raw(Box::new(g)))
+}
+
+/// Close a skeleton reader
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_free(gdb: *mut GdbReader) {
+    if !gdb.is_null() {
+        drop(Box::from_raw(gdb));
+    }
+}
+
+/// Number of scaffolds, or -1 on error
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_num_scaffolds(gdb: *const GdbReader) -> i64 {
+    check(handle(gdb, "GdbReader")).map_or(-1, |g| g.scaffolds().len() as i64)
+}
+
+/// Name of scaffold `i` (0-indexed), or NULL if out of range
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_scaffold_name(gdb: *const GdbReader, i: usize) -> *mut c_char {
+    check(handle(gdb, "GdbReader"))
+        .and_then(|g| g.scaffolds().get(i))
+        .map_or(ptr::null_mut(), |s| to_c_string(&s.name))
+}
+
+/// Length of scaffold `i` (0-indexed), or -1 if out of range
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_scaffold_length(gdb: *const GdbReader, i: usize) -> i64 {
+    check(handle(gdb, "GdbReader"))
+        .and_then(|g| g.scaffolds().get(i))
+        .map_or(-1, |s| s.length)
+}
+
+/// Number of contigs of scaffold `i` (0-indexed), or -1 if out of range
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_num_contigs(gdb: *const GdbReader, i: usize) -> i64 {
+    check(handle(gdb, "GdbReader"))
+        .and_then(|g| g.scaffolds().get(i))
+        .map_or(-1, |s| s.contigs.len() as i64)
+}
+
+/// Offset within its scaffold and length of contig `c` of scaffold `i`
+///
+/// Returns false if either index is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_contig(
+    gdb: *const GdbReader,
+    i: usize,
+    c: usize,
+    offset: *mut i64,
+    len: *mut i64,
+) -> bool {
+    let contig = check(handle(gdb, "GdbReader"))
+        .and_then(|g| g.scaffolds().get(i))
+        .and_then(|s| s.contigs.get(c));
+    let Some(contig) = contig else { return false };
+    if !offset.is_null() {
+        *offset = contig.offset;
+    }
+    if !len.is_null() {
+        *len = contig.len;
+    }
+    true
+}
+
+/// Import a FASTA or FASTQ file, optionally gzipped, into a new `.1seq` file with default options
+///
+/// Returns the number of sequences written, or -1 on error.
+#[no_mangle]
+pub unsafe extern "C" fn onecode_import_fasta(
+    fasta_path: *const c_char,
+    out_path: *const c_char,
+    binary: bool,
+) -> i64 {
+    let count = str_arg(fasta_path, "fasta_path").and_then(|fasta_path| {
+        let out_path = str_arg(out_path, "out_path")?;
+        let input = File::open(fasta_path).map_err(io_error(fasta_path))?;
+        seq::import_fasta(BufReader::new(input), out_path, binary, &FastaOptions::default())
+    });
+    check(count).map_or(-1, |n| n as i64)
+}
+
+/// Export a `.1seq` file as FASTA (FASTQ for sequences with qualities)
+///
+/// FASTA sequence lines are wrapped after `line_width` bases, or not at all
+/// if it is 0. Returns the number of sequences written, or -1 on error.
+#[no_mangle]
+pub unsafe extern "C" fn onecode_export_fasta(
+    seq_path: *const c_char,
+    out_path: *const c_char,
+    line_width: usize,
+) -> i64 {
+    let count = str_arg(seq_path, "seq_path").and_then(|seq_path| {
+        let out_path = str_arg(out_path, "out_path")?;
+        seq::to_fasta(seq_path, out_path, &ExportOptions { line_width })
+    });
+    check(count).map_or(-1, |n| n as i64)
+}
+
+/// Write a skeleton as AGP 2.1 to `out_path`, returning false on error
+#[no_mangle]
+pub unsafe extern "C" fn onecode_gdb_to_agp(gdb: *const GdbReader, out_path: *const c_char) -> bool {
+    let written = handle(gdb, "GdbReader").and_then(|g| {
+        let out_path = str_arg(out_path, "out_path")?;
+        let mut out = BufWriter::new(File::create(out_path).map_err(io_error(out_path))?);
+        gdb::to_agp(g, &mut out)?;
+        out.flush().map_err(io_error(out_path))
+    });
+    check(written).is_some()
+}
+
+/// Write the alignments of a binary `.1aln` file as PAF to `out_path`
+///
+/// Returns the number of records written, or -1 on error.
+#[no_mangle]
+pub unsafe extern "C" fn onecode_aln_to_paf(aln_path: *const c_char, out_path: *const c_char) -> i64 {
+    let count = str_arg(aln_path, "aln_path").and_then(|aln_path| {
+        let out_path = str_arg(out_path, "out_path")?;
+        let mut file = OneFile::open_read(aln_path, None, Some("aln"), 1)?;
+        let mut out = BufWriter::new(File::create(out_path).map_err(io_error(out_path))?);
+        let count = convert::aln_to_paf(&mut file, &mut out)?;
+        out.flush().map_err(io_error(out_path))?;
+        Ok(count)
+    });
+    check(count).map_or(-1, |n| n as i64)
+}
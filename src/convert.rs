@@ -0,0 +1,249 @@
+//! Conversion of ONE files between binary and ASCII, and of alignment files
+//! to other formats
+
+use crate::aln::{read_alignment, Alignment, NormalizedAlignment};
+use crate::error::{OneError, Result};
+use crate::file::{OneFile, OneFileThread};
+use crate::gdb::{GdbIndex, GdbReader};
+use crate::linetypes::aln;
+use crate::store::SequenceStore;
+use std::io::Write;
+use std::ops::Range;
+
+/// Convert a ONE file to binary or ASCII, returning the number of lines copied
+///
+/// As ONEview: the output is opened with `open_write_from()`, inheriting the
+/// schema, provenance, references and header text of `input`, a provenance
+/// line for the conversion is added and every line is copied with its
+/// comment. Converting to ASCII keeps the object statistics of the input's
+/// header. Either path may be "-" for standard input or output.
+///
+/// With `nthreads` > 1 a binary input is copied in slices of its top-level
+/// objects, one per thread, which ONElib joins in order when the output is
+/// closed. Otherwise the copy runs on one thread.
+pub fn transcode(input: &str, output: &str, to_binary: bool, nthreads: usize) -> Result<i64> {
+    let threads = if input == "-" || output == "-" { 1 } else { nthreads.max(1) };
+    let mut source = OneFile::open_read(input, None, None, threads as i32)?;
+    let index_type = source.top_level_object_type().filter(|_| source.is_binary());
+    let threads = if index_type.is_some() { threads } else { 1 };
+    let mut dest = OneFile::open_write_from(output, &source, to_binary, threads as i32)?;
+    if !to_binary {
+        dest.inherit_object_stats(&source);
+    }
+    let command = format!("transcode {} {}", input, output);
+    dest.add_provenance("onecode", env!("CARGO_PKG_VERSION"), &command)?;
+
+    let copied = match index_type {
+        Some(index_type) if threads > 1 => {
+            let ranges = source.slice_ranges(index_type)?;
+            let readers = source.split_threads();
+            let writers = dest.split_threads();
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = readers
+                    .into_iter()
+                    .zip(writers)
+                    .zip(ranges)
+                    .map(|((mut from, mut to), range)| {
+                        scope.spawn(move || copy_slice(&mut from, &mut to, index_type, range))
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join().expect("transcode worker panicked"))
+                    .sum::<Result<i64>>()
+            })?
+        }
+        _ => {
+            let mut copied = 0;
+            while source.read_line() != '\0' {
+                source.copy_line_to(&mut dest)?;
+                copied += 1;
+            }
+            copied
+        }
+    };
+    dest.finalize()?;
+    Ok(copied)
+}
+
+/// Copy objects `range` of type `index_type` and the lines up to the next
+/// slice, the first slice also taking the lines before its first object
+fn copy_slice(
+    from: &mut OneFileThread<'_>,
+    to: &mut OneFileThread<'_>,
+    index_type: char,
+    range: Range<i64>,
+) -> Result<i64> {
+    if range.is_empty() && from.index() > 0 {
+        return Ok(0);
+    }
+    from.goto(index_type, if from.index() == 0 { 0 } else { range.start })?;
+    let mut copied = 0;
+    loop {
+        let line_type = from.read_line();
+        if line_type == '\0' || (line_type == index_type && from.object(index_type) >= range.end) {
+            return Ok(copied);
+        }
+        from.copy_line_to(to)?;
+        copied += 1;
+    }
+}
+
+/// Write the alignments of a `.1aln` file as PAF, returning the number of records
+///
+/// As FastGA's ALNtoPAF: contig ids are resolved to scaffold names with the
+/// embedded skeletons ('g' group 1 for the query, group 2 for the target if
+/// present, else group 1 again) and coordinates are shifted into scaffold
+/// space, with target coordinates of reverse alignments taken back onto the
+/// forward strand. Without the sequences the number of matching bases is
+/// estimated from the differences as `(query span + target span - diffs) / 2`,
+/// the block length being half the summed spans. Each record carries a
+/// `dv:f:` divergence and an `id:f:` identity tag.
+///
+/// Needs a binary file, since the skeletons are found via the object index.
+pub fn aln_to_paf<W: Write>(file: &mut OneFile, mut out: W) -> Result<usize> {
+    for_each_alignment(file, |alignment, a| {
+        let span_sum = (a.query_end - a.query_start) + (a.target_end - a.target_start);
+        let matches = (span_sum - alignment.diffs).max(0) / 2;
+        let divergence = if span_sum > 0 { alignment.diffs as f64 / span_sum as f64 } else { 0.0 };
+        write_paf_record(&mut out, a, matches, span_sum / 2, divergence, "")
+    })
+}
+
+/// Write the alignments of a `.1aln` file as PAF with exact CIGAR strings
+///
+/// As `aln_to_paf()`, but each alignment is expanded with
+/// `Alignment::cigar()` at the file's trace point spacing, against the
+/// bases `query` and `target` hold for its scaffolds, and recorded in a
+/// `cg:Z:` tag, as ALNtoPAF does with `-x`. The number of matches and the
+/// block length are then counted rather than estimated, and an `NM:i:` tag
+/// gives the mismatched, inserted and deleted bases. The stores are looked
+/// up by scaffold name; for a self-comparison, open the sequences twice.
+///
+/// Fails with `InvalidFormat` if the file has no 't' line or a trace does
+/// not fit its spacing, and with the store's error if a scaffold is
+/// missing or shorter than the skeleton says.
+pub fn aln_to_paf_with_cigar<W, Q, T>(
+    file: &mut OneFile,
+    query: &mut Q,
+    target: &mut T,
+    mut out: W,
+) -> Result<usize>
+where
+    W: Write,
+    Q: SequenceStore,
+    T: SequenceStore,
+{
+    let spacing = trace_spacing(file)?;
+    for_each_alignment(file, |alignment, a| {
+        // Only the aligned bases are fetched, the query from the trace
+        // point before it so that the intervals keep their boundaries
+        let lead = alignment.query_start.rem_euclid(spacing.max(1));
+        let query_range = (a.query_start - lead) as usize..a.query_end as usize;
+        let query_bases = query.fetch(a.query_name, query_range)?;
+        let target_range = a.target_start as usize..a.target_end as usize;
+        let target_bases = target.fetch(a.target_name, target_range)?;
+        let local = Alignment {
+            query_start: lead,
+            query_end: lead + alignment.query_end - alignment.query_start,
+            target_start: 0,
+            target_end: alignment.target_end - alignment.target_start,
+            ..alignment
+        };
+        let cigar = local.cigar(spacing, &query_bases, &target_bases)?;
+
+        let (mut matches, mut block) = (0, 0);
+        let mut len = 0;
+        for c in cigar.chars() {
+            match c.to_digit(10) {
+                Some(digit) => len = len * 10 + digit as i64,
+                None => {
+                    matches += if c == '=' { len } else { 0 };
+                    block += len;
+                    len = 0;
+                }
+            }
+        }
+        let divergence = if block > 0 { (block - matches) as f64 / block as f64 } else { 0.0 };
+        let tags = format!("\tNM:i:{}\tcg:Z:{}", block - matches, cigar);
+        write_paf_record(&mut out, a, matches, block, divergence, &tags)
+    })
+}
+
+/// Call `write` on each alignment of `file` with its scaffold coordinates,
+/// as resolved for `aln_to_paf()`, returning the number of alignments
+fn for_each_alignment<F>(file: &mut OneFile, mut write: F) -> Result<usize>
+where
+    F: FnMut(Alignment, &NormalizedAlignment) -> Result<()>,
+{
+    let query_gdb = GdbReader::from_alignment(file, 1)?;
+    let target_gdb = if file.stats(aln::GDB_GROUP)?.0 > 1 {
+        GdbReader::from_alignment(file, 2)?
+    } else {
+        GdbReader::from_alignment(file, 1)?
+    };
+    let (query, target) = (GdbIndex::new(&query_gdb), GdbIndex::new(&target_gdb));
+
+    let mut count = 0;
+    if file.stats(aln::ALIGNMENT)?.0 == 0 {
+        return Ok(count);
+    }
+    file.goto(aln::ALIGNMENT, 1)?;
+    let mut line_type = file.read_line();
+    while line_type != '\0' {
+        if line_type != aln::ALIGNMENT {
+            line_type = file.read_line();
+            continue;
+        }
+        let (alignment, next) = read_alignment(file);
+        line_type = next;
+        let normalized = alignment.normalized(&query, &target)?;
+        write(alignment, &normalized)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// The trace point spacing of the 't' line before the data, 0 if there is none
+fn trace_spacing(file: &mut OneFile) -> Result<i64> {
+    if !file.has_objects(aln::ALIGNMENT) {
+        return Ok(0);
+    }
+    file.goto(aln::ALIGNMENT, 0)?;
+    loop {
+        match file.read_line() {
+            aln::TRACE_SPACING => return Ok(file.int(0)),
+            '\0' | aln::GDB_GROUP | aln::SCAFFOLD | aln::CHAIN | aln::ALIGNMENT => return Ok(0),
+            _ => {}
+        }
+    }
+}
+
+fn write_paf_record<W: Write>(
+    out: &mut W,
+    a: &NormalizedAlignment,
+    matches: i64,
+    block: i64,
+    divergence: f64,
+    tags: &str,
+) -> Result<()> {
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t255\tdv:f:{:.4}\tid:f:{:.4}{}",
+        a.query_name,
+        a.query_len,
+        a.query_start,
+        a.query_end,
+        if a.reverse { '-' } else { '+' },
+        a.target_name,
+        a.target_len,
+        a.target_start,
+        a.target_end,
+        matches,
+        block,
+        divergence,
+        1.0 - divergence,
+        tags
+    )
+    .map_err(|e| OneError::Other(format!("Failed to write PAF: {}", e)))
+}
@@ -0,0 +1,241 @@
+//! The coordinate convention of the typed APIs, and conversions to others
+//!
+//! Every typed API of this crate uses 0-based, half-open coordinates: the
+//! contig and gap offsets and mask intervals of `GdbReader` and `GdbIndex`,
+//! the intervals of `Alignment`, `NormalizedAlignment` and `TraceInterval`,
+//! `RegionIndex` queries and `SequenceStore` ranges. Positions count from 0
+//! and an interval `start..end` holds `start` but not `end`, so its length is
+//! `end - start`. This is the convention of ONEcode and FastGA, of BED and
+//! PAF, and of Rust slices.
+//!
+//! SAM, GFF, VCF, AGP and samtools-style region strings count from 1 and
+//! include both ends. `Coord` and `Span` convert at that boundary, so the
+//! off-by-one is handled in one place:
+//!
+//! ```
+//! use onecode::{Coord, Span};
+//!
+//! // SAM POS 1 is the first base
+//! assert_eq!(Coord::from_one_based(1)?.to_zero_based(), 0);
+//!
+//! // chr1:101-200 in samtools notation is the 100 bases from 0-based 100
+//! let span = Span::from_one_based(101, 200)?;
+//! assert_eq!((span.start, span.end, span.len()), (100, 200, 100));
+//! assert_eq!(span.to_one_based(), (101, 200));
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! Object and group numbers, as `OneFile::goto()` takes them, are not
+//! coordinates but ordinals: they count from 1, with 0 for the start of the
+//! data.
+
+use crate::error::{OneError, Result};
+#[cfg(feature = "onelib")]
+use crate::gdb::GdbIndex;
+
+/// A position on a sequence, held 0-based
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Coord(i64);
+
+impl Coord {
+    /// The position `pos` counted from 0, as in this crate, BED and PAF
+    pub fn from_zero_based(pos: i64) -> Result<Self> {
+        if pos < 0 {
+            return Err(OneError::InvalidFormat(format!(
+                "0-based position {} is negative",
+                pos
+            )));
+        }
+        Ok(Coord(pos))
+    }
+
+    /// The position `pos` counted from 1, as in SAM, GFF and VCF
+    pub fn from_one_based(pos: i64) -> Result<Self> {
+        if pos < 1 {
+            return Err(OneError::InvalidFormat(format!(
+                "1-based position {} is less than 1",
+                pos
+            )));
+        }
+        Ok(Coord(pos - 1))
+    }
+
+    pub fn to_zero_based(self) -> i64 {
+        self.0
+    }
+
+    pub fn to_one_based(self) -> i64 {
+        self.0 + 1
+    }
+}
+
+/// An interval of a sequence, 0-based and half-open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    /// First position in the interval
+    pub start: i64,
+    /// First position after the interval
+    pub end: i64,
+}
+
+impl Span {
+    /// The interval `start..end` in this crate's convention
+    ///
+    /// Fails with `InvalidFormat` unless `0 <= start <= end`.
+    pub fn new(start: i64, end: i64) -> Result<Self> {
+        if start < 0 || start > end {
+            return Err(OneError::InvalidFormat(format!(
+                "{}..{} is not a 0-based half-open interval",
+                start, end
+            )));
+        }
+        Ok(Span { start, end })
+    }
+
+    /// The interval from `first` to `last` inclusive, counted from 1
+    ///
+    /// As in SAM, GFF, VCF, AGP and samtools regions. `last` may be one less
+    /// than `first` for an empty interval.
+    pub fn from_one_based(first: i64, last: i64) -> Result<Self> {
+        if first < 1 || last < first - 1 {
+            return Err(OneError::InvalidFormat(format!(
+                "{}-{} is not a 1-based closed interval",
+                first, last
+            )));
+        }
+        Ok(Span {
+            start: first - 1,
+            end: last,
+        })
+    }
+
+    /// The first and last positions, counted from 1
+    pub fn to_one_based(self) -> (i64, i64) {
+        (self.start + 1, self.end)
+    }
+
+    /// The start and end, as held
+    pub fn to_zero_based(self) -> (i64, i64) {
+        (self.start, self.end)
+    }
+
+    pub fn len(&self) -> i64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    /// Whether the two intervals share a position
+    pub fn overlaps(&self, other: &Span) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Conversions between contig and scaffold coordinates of one skeleton
+///
+/// Alignments give positions on contigs, by contig id; a scaffold strings
+/// its contigs together with gaps, each contig at an offset. A position on
+/// the forward strand of a contig lies `offset` further along the scaffold.
+/// On the reverse strand, as `Alignment` target coordinates are when
+/// `reverse`, a position `pos` of a contig of length `len` is the boundary
+/// `len - pos` of its forward strand. This is the arithmetic of FastGA's
+/// ALNtoPAF and of `Alignment::normalized()`.
+///
+/// ```no_run
+/// use onecode::{CoordinateMapper, OneFile, Span};
+///
+/// let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+/// let mapper = CoordinateMapper::new(file.gdb_index()?);
+/// let (name, span) = mapper.span_to_scaffold(3, Span::new(100, 250)?, true)?;
+/// println!("{}:{}-{}", name, span.start, span.end);
+/// # Ok::<(), onecode::OneError>(())
+/// ```
+#[cfg(feature = "onelib")]
+#[derive(Debug, Clone, Copy)]
+pub struct CoordinateMapper<'a> {
+    index: &'a GdbIndex,
+}
+
+#[cfg(feature = "onelib")]
+impl<'a> CoordinateMapper<'a> {
+    /// Map through the contigs of `index`
+    pub fn new(index: &'a GdbIndex) -> Self {
+        CoordinateMapper { index }
+    }
+
+    /// The scaffold and forward-strand scaffold position of position `pos`
+    /// of contig `contig_id`, counted on its reverse strand if `reverse`
+    ///
+    /// Positions are interval boundaries, so `pos` may be anything from 0
+    /// to the contig length. Fails with `InvalidFormat` if the contig is
+    /// not in the skeleton or `pos` lies outside it.
+    pub fn to_scaffold(&self, contig_id: i64, pos: i64, reverse: bool) -> Result<(&'a str, i64)> {
+        let (name, offset, len) = self.contig(contig_id)?;
+        if !(0..=len).contains(&pos) {
+            return Err(OneError::InvalidFormat(format!(
+                "Position {} lies outside contig {} of length {}",
+                pos, contig_id, len
+            )));
+        }
+        Ok((name, offset + if reverse { len - pos } else { pos }))
+    }
+
+    /// The scaffold and forward-strand scaffold interval of `span` of
+    /// contig `contig_id`, counted on its reverse strand if `reverse`
+    ///
+    /// As `to_scaffold()` for both ends, which swap places on the reverse
+    /// strand.
+    pub fn span_to_scaffold(
+        &self,
+        contig_id: i64,
+        span: Span,
+        reverse: bool,
+    ) -> Result<(&'a str, Span)> {
+        let (name, start) = self.to_scaffold(contig_id, span.start, reverse)?;
+        let (_, end) = self.to_scaffold(contig_id, span.end, reverse)?;
+        let span = if reverse { Span { start: end, end: start } } else { Span { start, end } };
+        Ok((name, span))
+    }
+
+    /// The contig id and forward-strand contig position of base `pos` of
+    /// scaffold `scaffold_name`, or None if the base lies in a gap
+    ///
+    /// Fails with `SequenceNotFound` if the skeleton has no such scaffold,
+    /// and with `InvalidFormat` unless `0 <= pos <` the scaffold length.
+    /// Of scaffolds sharing a name, the first is used.
+    pub fn to_contig(&self, scaffold_name: &str, pos: i64) -> Result<Option<(i64, i64)>> {
+        let ids = self.index.contig_ids(scaffold_name);
+        let Some(&first) = ids.first() else {
+            return Err(OneError::SequenceNotFound(scaffold_name.to_string()));
+        };
+        let scaffold_len = self.index.length(first).unwrap_or(0);
+        if !(0..scaffold_len).contains(&pos) {
+            return Err(OneError::InvalidFormat(format!(
+                "Position {} lies outside scaffold {} of length {}",
+                pos, scaffold_name, scaffold_len
+            )));
+        }
+        let scaffold = self.index.scaffold(first);
+        let end = ids.partition_point(|&id| self.index.scaffold(id) == scaffold);
+        let ids = &ids[..end];
+        // The last contig starting at or before pos, if pos is not in a leading gap
+        let i = ids.partition_point(|&id| self.index.offset(id).unwrap_or(0) <= pos);
+        let Some(&id) = i.checked_sub(1).and_then(|i| ids.get(i)) else {
+            return Ok(None);
+        };
+        let (_, offset, len) = self.contig(id)?;
+        Ok((pos < offset + len).then_some((id, pos - offset)))
+    }
+
+    /// Scaffold name, offset and length of contig `id`
+    fn contig(&self, id: i64) -> Result<(&'a str, i64, i64)> {
+        let index = self.index;
+        let found = index.name(id).zip(index.offset(id)).zip(index.contig_length(id));
+        let ((name, offset), len) = found.ok_or_else(|| {
+            OneError::InvalidFormat(format!("Contig {} is not in the skeleton", id))
+        })?;
+        Ok((name, offset, len))
+    }
+}
@@ -0,0 +1,156 @@
+//! Bounds and canary checks on the C library's list buffers
+//!
+//! Enabled with the `debug-ffi` feature, for development against files that
+//! may be corrupt. The list accessors build slices from the list length in
+//! the current line, which comes straight from the file, so a bad length
+//! reads past the end of ONElib's buffers, and a bad compressed list can make
+//! the decoder write past them. With the feature on:
+//!
+//! - after each `oneReadLine()`, the list of the line must fit the buffer
+//!   it was read into, a STRING_LIST must have all its terminators inside
+//!   it, and compressed data must fit the codec buffer;
+//! - before a list is decoded, the bytes after it in its buffer are filled
+//!   with a canary, which must be intact once the decoder has run.
+//!
+//! A failed check panics, naming the file and line. Lines read into user
+//! buffers, whose size the C library does not know, are not checked.
+
+use crate::ffi::{self, OneType};
+use std::ffi::CStr;
+
+const CANARY: [u8; 8] = [0xa5, 0x5a, 0xc3, 0x3c, 0xa5, 0x5a, 0xc3, 0x3c];
+
+/// The list buffer of the current line, when it can be checked
+struct ListBuffer {
+    data: *mut u8,
+    list_type: OneType,
+    len: usize,
+    end: usize,      // bytes the list takes, with string terminators
+    capacity: usize, // bytes allocated
+}
+
+/// Check the list of the line just read against its buffers
+///
+/// # Safety
+///
+/// `vf` must be a valid file handle positioned on a line.
+pub(crate) unsafe fn check_line(vf: *mut ffi::OneFile) {
+    let Some(buffer) = list_buffer(vf) else {
+        return;
+    };
+    let codec_bytes = ((*vf).nBits as usize).div_ceil(8);
+    if (*vf).nBits > 0 && codec_bytes > (*vf).codecBufSize as usize {
+        fail(
+            vf,
+            &format!(
+                "{} bytes of compressed list overrun the {}-byte codec buffer",
+                codec_bytes,
+                (*vf).codecBufSize
+            ),
+        );
+    }
+    if buffer.end > buffer.capacity {
+        fail(
+            vf,
+            &format!(
+                "a list of {} elements overruns its {}-byte buffer",
+                buffer.len, buffer.capacity
+            ),
+        );
+    }
+}
+
+/// Run `decode` on the list of the current line, checking it stays inside the list
+///
+/// # Safety
+///
+/// `vf` must be a valid file handle positioned on a line.
+pub(crate) unsafe fn guard_decode<T>(vf: *mut ffi::OneFile, decode: impl FnOnce() -> T) -> T {
+    check_line(vf);
+    let buffer = list_buffer(vf).filter(|_| (*vf).nBits > 0);
+    let canary = buffer
+        .as_ref()
+        .map_or(0, |b| CANARY.len().min(b.capacity - b.end));
+    if let Some(b) = &buffer {
+        std::ptr::copy_nonoverlapping(CANARY.as_ptr(), b.data.add(b.end), canary);
+    }
+    let result = decode();
+    if let Some(b) = &buffer {
+        if *std::slice::from_raw_parts(b.data.add(b.end), canary) != CANARY[..canary] {
+            fail(
+                vf,
+                &format!(
+                    "decoding a {:?} of {} elements wrote past its end",
+                    b.list_type, b.len
+                ),
+            );
+        }
+    }
+    result
+}
+
+/// Check the compressed list `_oneCompressedList()` just wrote
+///
+/// # Safety
+///
+/// `vf` must be a valid file handle positioned on a line.
+pub(crate) unsafe fn check_compressed(vf: *mut ffi::OneFile) {
+    let bytes = ((*vf).nBits as usize).div_ceil(8);
+    if bytes > (*vf).codecBufSize as usize {
+        fail(
+            vf,
+            &format!(
+                "compressing the list wrote {} bytes into the {}-byte codec buffer",
+                bytes,
+                (*vf).codecBufSize
+            ),
+        );
+    }
+}
+
+unsafe fn list_buffer(vf: *mut ffi::OneFile) -> Option<ListBuffer> {
+    let info = (*vf).info[(*vf).lineType as u8 as usize];
+    if info.is_null() || (*info).listEltSize <= 0 || (*info).isUserBuf || (*info).buffer.is_null() {
+        return None;
+    }
+    let field = &*(*vf).field.add((*info).listField as usize);
+    let len = (field.len & 0xffffffffffffff) as usize; // as oneLen()
+    let list_type = *(*info).fieldType.add((*info).listField as usize);
+    let data = (*info).buffer as *mut u8;
+    let capacity = (*info).bufSize.max(0) as usize * (*info).listEltSize as usize;
+    let end = match list_type {
+        OneType::oneSTRING | OneType::oneDNA => len.saturating_add(1),
+        OneType::oneSTRING_LIST if len > 0 => {
+            // The strings end at the len-th terminator, which must be in the buffer
+            let bytes = std::slice::from_raw_parts(data, capacity);
+            let mut terminators = bytes.iter().enumerate().filter(|&(_, &b)| b == 0);
+            terminators
+                .nth(len - 1)
+                .map_or(capacity + 1, |(i, _)| i + 1)
+        }
+        _ => len.saturating_mul((*info).listEltSize as usize),
+    };
+    Some(ListBuffer {
+        data,
+        list_type,
+        len,
+        end,
+        capacity,
+    })
+}
+
+fn fail(vf: *mut ffi::OneFile, message: &str) -> ! {
+    let (name, line, line_type) = unsafe {
+        let name = (*vf).fileName;
+        let name = if name.is_null() {
+            "<unnamed>".into()
+        } else {
+            CStr::from_ptr(name).to_string_lossy()
+        };
+        (name, (*vf).line, (*vf).lineType as u8 as char)
+    };
+    panic!(
+        "debug-ffi: {}, line {} ('{}'): {}",
+        name, line, line_type, message
+    );
+}
@@ -1,18 +1,47 @@
 //! Error handling for ONEcode operations
 
+use crate::sniff::FileKind;
 use std::fmt;
 
 /// Result type for ONEcode operations
 pub type Result<T> = std::result::Result<T, OneError>;
 
 /// Errors that can occur when working with ONE files
+///
+/// New variants may be added, so matches need a wildcard arm.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum OneError {
-    /// Failed to open file
+    /// Failed to open file, for reasons not covered by the variants below
     OpenFailed(String),
 
-    /// Failed to close file
-    CloseFailed,
+    /// The operating system failed to open, read or write a file
+    Io {
+        path: String,
+        kind: std::io::ErrorKind,
+        message: String,
+    },
+
+    /// A file that does not start with a ONE header line
+    /// ("1 <type name length> <type name> <major> <minor>")
+    BadMagic { path: String, message: String },
+
+    /// A file that ends early, e.g. one whose copy was cut short; `line` is
+    /// the line it ends in, if known
+    Truncated { path: String, line: Option<i64> },
+
+    /// A file of another type than the schema it was opened with describes
+    WrongFileType {
+        path: String,
+        expected: String,
+        found: String,
+    },
+
+    /// A file another writer holds the advisory lock on, see `WriteOptions::lock()`
+    Locked { path: String },
+
+    /// Failed to finish writing a file, e.g. on a full disk
+    CloseFailed(String),
 
     /// Failed to read from file
     ReadFailed,
@@ -26,6 +55,44 @@ pub enum OneError {
     /// Schema error
     SchemaError(String),
 
+    /// Line types a file defines differently from a schema; `path` is the
+    /// file when it was opened with the schema, and `None` from
+    /// `OneFile::check_schema()`
+    SchemaMismatch {
+        path: Option<String>,
+        mismatches: Vec<LineTypeMismatch>,
+    },
+
+    /// Named sequence not present in a sequence store
+    SequenceNotFound(String),
+
+    /// A name that must be unique occurs more than once
+    DuplicateName(String),
+
+    /// Dataset files do not match their manifest
+    ManifestMismatch(String),
+
+    /// A list is longer than an ASCII line may hold
+    ListTooLong(String),
+
+    /// A per-base list does not match the length of its sequence
+    LengthMismatch(String),
+
+    /// A list holds a character outside the alphabet set for its line type
+    InvalidCharacter(String),
+
+    /// A field read as a type the schema does not give it, from `OneFile::try_int()` etc.
+    FieldTypeMismatch(String),
+
+    /// A field beyond those of the current line, from `OneFile::try_int()` etc.
+    FieldOutOfRange(String),
+
+    /// A line type the file's schema does not declare, e.g. from `OneFile::goto()`
+    NoSuchLineType(String),
+
+    /// A declared line type of which the file holds no objects, see `OneFile::has_objects()`
+    NoObjects(String),
+
     /// Null pointer encountered
     NullPointer,
 
@@ -43,11 +110,48 @@ impl fmt::Display for OneError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             OneError::OpenFailed(path) => write!(f, "Failed to open file: {}", path),
-            OneError::CloseFailed => write!(f, "Failed to close file"),
+            OneError::Io { path, message, .. } => write!(f, "I/O error: {}: {}", path, message),
+            OneError::BadMagic { path, message } => {
+                write!(f, "Not a ONE file: {}: {}", path, message)
+            }
+            OneError::Truncated { path, line } => match line {
+                Some(line) => write!(f, "Truncated file: {} ends in line {}", path, line),
+                None => write!(f, "Truncated file: {} ends before its footer", path),
+            },
+            OneError::WrongFileType {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Wrong file type: {} is a '{}' file, expected '{}'",
+                path, found, expected
+            ),
+            OneError::Locked { path } => {
+                write!(f, "File locked: {} is being written by another process", path)
+            }
+            OneError::CloseFailed(msg) => write!(f, "Failed to close file: {}", msg),
             OneError::ReadFailed => write!(f, "Failed to read from file"),
             OneError::WriteFailed => write!(f, "Failed to write to file"),
             OneError::InvalidFormat(msg) => write!(f, "Invalid file format: {}", msg),
             OneError::SchemaError(msg) => write!(f, "Schema error: {}", msg),
+            OneError::SchemaMismatch { path, mismatches } => {
+                write!(f, "Schema mismatch")?;
+                if let Some(path) = path {
+                    write!(f, " in {}", path)?;
+                }
+                write_mismatches(f, mismatches)
+            }
+            OneError::SequenceNotFound(name) => write!(f, "Sequence not found: {}", name),
+            OneError::DuplicateName(name) => write!(f, "Duplicate name: {}", name),
+            OneError::ManifestMismatch(msg) => write!(f, "Manifest mismatch: {}", msg),
+            OneError::ListTooLong(msg) => write!(f, "List too long: {}", msg),
+            OneError::LengthMismatch(msg) => write!(f, "Length mismatch: {}", msg),
+            OneError::InvalidCharacter(msg) => write!(f, "Invalid character: {}", msg),
+            OneError::FieldTypeMismatch(msg) => write!(f, "Field type mismatch: {}", msg),
+            OneError::FieldOutOfRange(msg) => write!(f, "Field out of range: {}", msg),
+            OneError::NoSuchLineType(msg) => write!(f, "No such line type: {}", msg),
+            OneError::NoObjects(msg) => write!(f, "No objects: {}", msg),
             OneError::NullPointer => write!(f, "Unexpected null pointer"),
             OneError::InvalidUtf8(e) => write!(f, "Invalid UTF-8: {}", e),
             OneError::InvalidCString(e) => write!(f, "Invalid C string: {}", e),
@@ -56,6 +160,260 @@ impl fmt::Display for OneError {
     }
 }
 
+fn write_mismatches(f: &mut fmt::Formatter<'_>, mismatches: &[LineTypeMismatch]) -> fmt::Result {
+    for (i, m) in mismatches.iter().enumerate() {
+        let sep = if i == 0 { ":" } else { ";" };
+        write!(
+            f,
+            "{} line type '{}': expected `{}`, found `{}`",
+            sep, m.line_type, m.expected, m.found
+        )?;
+    }
+    Ok(())
+}
+
+/// A line type defined differently in a file and in a schema
+///
+/// Definitions are normalized as in "O S DNA", or "(undefined)" where one
+/// side lacks the line type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineTypeMismatch {
+    /// The line type
+    pub line_type: char,
+    /// Definition in the schema checked against
+    pub expected: String,
+    /// Definition in the file
+    pub found: String,
+}
+
+/// Multi-line diagnostic for an error, from `OneError::detailed_report()`
+///
+/// Displays as:
+///
+/// ```text
+/// error: Schema mismatch in reads.1seq: line type 'S': expected `O S DNA`, found `O S STRING`
+///   --> reads.1seq
+///   = expected: O S DNA
+///   = found:    O S STRING
+///   = help: the file was written with a different schema; ...
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorReport {
+    /// The error message, as `Display` gives it
+    pub message: String,
+    /// File the error concerns, if known
+    pub file: Option<String>,
+    /// Line number in that file, if known
+    pub line: Option<i64>,
+    /// Schema definition the code required (normalized, e.g. "O S DNA")
+    pub expected: Option<String>,
+    /// Definition found in the file instead
+    pub found: Option<String>,
+    /// Suggested fix
+    pub help: Option<String>,
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error: {}", self.message)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "\n  --> {}:{}", file, line)?,
+            (Some(file), None) => write!(f, "\n  --> {}", file)?,
+            (None, Some(line)) => write!(f, "\n  --> line {}", line)?,
+            (None, None) => {}
+        }
+        if let Some(expected) = &self.expected {
+            write!(f, "\n  = expected: {}", expected)?;
+        }
+        if let Some(found) = &self.found {
+            write!(f, "\n  = found:    {}", found)?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n  = help: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+impl OneError {
+    /// `Io` error for `path` from a `std::io::Error`
+    pub fn io(path: impl fmt::Display, error: &std::io::Error) -> Self {
+        OneError::Io {
+            path: path.to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+
+    /// File the error concerns, for the variants that record it
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            OneError::Io { path, .. }
+            | OneError::BadMagic { path, .. }
+            | OneError::Truncated { path, .. }
+            | OneError::WrongFileType { path, .. }
+            | OneError::SchemaMismatch { path: Some(path), .. }
+            | OneError::Locked { path } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Line number the error concerns, for the variants that record it
+    pub fn line(&self) -> Option<i64> {
+        match self {
+            OneError::Truncated { line, .. } => *line,
+            _ => None,
+        }
+    }
+
+    /// Diagnostic with the file, line number, schema mismatch and a
+    /// suggested fix, as far as they can be told from the error
+    ///
+    /// Meant for printing to users of command line tools:
+    /// `eprintln!("{}", err.detailed_report())`. For errors opening a file,
+    /// the file is inspected with `sniff()`, e.g. to point out gzip compression.
+    pub fn detailed_report(&self) -> ErrorReport {
+        let message = self.to_string();
+        let mut report = ErrorReport {
+            file: self.path().map(str::to_string),
+            line: self.line().or_else(|| line_number(&message)),
+            ..ErrorReport::default()
+        };
+        if let OneError::SchemaMismatch { mismatches, .. } = self {
+            if let Some(first) = mismatches.first() {
+                report.expected = Some(first.expected.clone());
+                report.found = Some(first.found.clone());
+            }
+        }
+        report.help = match self {
+            OneError::OpenFailed(msg) => {
+                let path = msg.split(": ").next().unwrap_or(msg);
+                report.file = Some(path.to_string());
+                open_failed_help(path, msg)
+            }
+            OneError::Io { path, message, .. } | OneError::BadMagic { path, message } => {
+                open_failed_help(path, message)
+            }
+            OneError::Truncated { .. } => Some(
+                "the file was cut short, e.g. by an interrupted write or copy; \
+                 write or copy it again"
+                    .to_string(),
+            ),
+            OneError::WrongFileType { .. } => Some(
+                "open the file with a schema for its type, \
+                 or without a schema to use the one in its header"
+                    .to_string(),
+            ),
+            OneError::SchemaError(_) => Some(
+                "schema lines are 'P <len> <name>', 'O|D <line type> <n fields> <types>' \
+                 or 'G <member type>'; see OneSchema::seq() for an example"
+                    .to_string(),
+            ),
+            OneError::SchemaMismatch { path: Some(_), .. } => Some(
+                "the file was written with a different schema; open it without a schema, \
+                 or with the schema it was written with (OneFile::schema_text() shows it)"
+                    .to_string(),
+            ),
+            OneError::SchemaMismatch { path: None, .. } => Some(
+                "the file was written with a different schema; \
+                 OneFile::schema_text() shows the schema it was written with"
+                    .to_string(),
+            ),
+            OneError::SequenceNotFound(_) => Some(
+                "identifiers are trimmed at the first whitespace; \
+                 list the names present with SeqReader::name()"
+                    .to_string(),
+            ),
+            OneError::DuplicateName(_) => Some(
+                "rename the duplicate, or import without FastaOptions::unique_ids".to_string(),
+            ),
+            OneError::ManifestMismatch(_) => Some(
+                "a file changed after the manifest was written; \
+                 rebuild the manifest with Manifest::add_file() if the change is intended"
+                    .to_string(),
+            ),
+            OneError::LengthMismatch(_) => Some(
+                "per-base lists need one value for each base of the sequence \
+                 last written with write_dna()"
+                    .to_string(),
+            ),
+            OneError::InvalidCharacter(_) => Some(
+                "the data is corrupted or of another kind, e.g. protein where DNA is expected; \
+                 OneFile::alphabet() shows the characters the line type accepts"
+                    .to_string(),
+            ),
+            OneError::FieldTypeMismatch(_) | OneError::FieldOutOfRange(_) => Some(
+                "OneFile::field_type() gives the type of each field of the current line; \
+                 schema.describe() lists the fields of every line type"
+                    .to_string(),
+            ),
+            OneError::NoSuchLineType(_) | OneError::NoObjects(_) => Some(
+                "OneFile::all_stats() lists the line types of the file with their counts; \
+                 check has_objects() before navigating to optional ones such as 'g' groups"
+                    .to_string(),
+            ),
+            OneError::Locked { .. } => Some(
+                "another task is writing to the same path; give each task its own output, \
+                 or wait for the other to finish"
+                    .to_string(),
+            ),
+            OneError::CloseFailed(_) => Some(
+                "the file is incomplete; check the space left on the device \
+                 and write the file again"
+                    .to_string(),
+            ),
+            OneError::ListTooLong(_) => Some(
+                "write a binary file, or set OpenOptions::binary_above() \
+                 to switch to binary when lists get long"
+                    .to_string(),
+            ),
+            _ => None,
+        };
+        report.message = message;
+        report
+    }
+}
+
+/// Suggested fix for a file that could not be opened
+fn open_failed_help(path: &str, msg: &str) -> Option<String> {
+    let kind = crate::sniff::sniff(path);
+    let help = if !std::path::Path::new(path).exists() {
+        "check the path: the file does not exist"
+    } else if kind == Some(FileKind::Gzip) {
+        "the file is gzip-compressed; decompress it first"
+    } else if msg.contains("bare oneFile") {
+        "files without a header need both a file type and a schema"
+    } else if msg.contains("major version") || msg.contains("minor version") {
+        "the file was written by a different ONEcode version; \
+         convert it to ASCII with that version's ONEview"
+    } else if kind == Some(FileKind::Other) {
+        "this is not a ONE file, whose first line reads \
+         '1 <type name length> <type name> <major> <minor>'"
+    } else {
+        return None;
+    };
+    Some(help.to_string())
+}
+
+/// Number following the first "line " in a message, as in "AGP line 3: ..."
+fn line_number(msg: &str) -> Option<i64> {
+    msg.match_indices("line ").find_map(|(i, word)| {
+        let digits: String = msg[i + word.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        digits.parse().ok()
+    })
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for OneError {
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = self.detailed_report().help?;
+        Some(Box::new(help))
+    }
+}
+
 impl std::error::Error for OneError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -0,0 +1,127 @@
+//! Rust versions of the hottest list decoding loops
+//!
+//! Enabled with the `fastpath` feature. ONElib decodes a binary line's list
+//! lazily, when it is first asked for, so the crate's list accessors can
+//! decode it here instead of in `_oneList()`:
+//!
+//! - DNA: unpacking the 2-bit codec into characters, via a lookup table
+//!   that writes four bases per packed byte.
+//! - INT_LIST: widening the compacted integers back to 64 bits and undoing
+//!   the differencing. The Huffman stage of trained codecs stays in C.
+//!
+//! Everything else, including the field unpacking done inside
+//! `oneReadLine()`, remains in the C library. Lists that are not handled
+//! here are left for `_oneList()`, so results never depend on the feature.
+
+use crate::ffi::{self, OneType};
+use crate::types::FieldSlot;
+use std::ffi::{c_char, c_int};
+
+extern "C" {
+    // Defined in ONElib.c but not declared in ONElib.h
+    fn vcDecode(vc: *mut ffi::OneCodec, ilen: c_int, ibytes: *mut c_char, obytes: *mut c_char) -> c_int;
+}
+
+const BASES: [u8; 4] = [b'a', b'c', b'g', b't'];
+
+/// Four bases for each packed byte, lowest bits first as in ONElib's DNA codec
+static UNPACK: [[u8; 4]; 256] = {
+    let mut table = [[0u8; 4]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut k = 0;
+        while k < 4 {
+            table[byte][k] = BASES[(byte >> (2 * k)) & 3];
+            k += 1;
+        }
+        byte += 1;
+    }
+    table
+};
+
+/// Unpack `out.len()` bases from 2-bit form into lower-case characters
+///
+/// `packed` must hold at least `out.len().div_ceil(4)` bytes.
+pub fn unpack_2bit(packed: &[u8], out: &mut [u8]) {
+    let full = out.len() / 4;
+    let mut chunks = out.chunks_exact_mut(4);
+    for (chunk, &byte) in (&mut chunks).zip(packed) {
+        chunk.copy_from_slice(&UNPACK[byte as usize]);
+    }
+    let tail = chunks.into_remainder();
+    if !tail.is_empty() {
+        let byte = packed[full];
+        tail.copy_from_slice(&UNPACK[byte as usize][..tail.len()]);
+    }
+}
+
+/// Expand a compacted integer list in place and undo its differencing
+///
+/// On entry `list[0]` holds the first value and the bytes from `list[1]`
+/// onwards hold `list.len() - 1` differences of `used_bytes` bytes each:
+/// the low-order bytes of each value in native byte order, as ONElib
+/// writes them.
+pub fn decompact_int_list(list: &mut [i64], used_bytes: usize) {
+    if list.len() > 1 && used_bytes < 8 {
+        let shift = 64 - 8 * used_bytes as u32;
+        let n = list.len() - 1;
+        // Expanding from the end never overwrites bytes not yet read
+        for i in (0..n).rev() {
+            let start = 8 + i * used_bytes;
+            let bytes: &[u8] = as_bytes(list);
+            let mut word = [0u8; 8];
+            let value = if cfg!(target_endian = "little") {
+                word[..used_bytes].copy_from_slice(&bytes[start..start + used_bytes]);
+                i64::from_le_bytes(word)
+            } else {
+                word[8 - used_bytes..].copy_from_slice(&bytes[start..start + used_bytes]);
+                i64::from_be_bytes(word)
+            };
+            list[i + 1] = (value << shift) >> shift; // sign-extend
+        }
+    }
+    for i in 1..list.len() {
+        list[i] = list[i].wrapping_add(list[i - 1]);
+    }
+}
+
+fn as_bytes(list: &[i64]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(list.as_ptr() as *const u8, std::mem::size_of_val(list)) }
+}
+
+/// Decode the pending compressed list of the current line, if this module handles it
+///
+/// Afterwards `_oneList()` finds nothing left to decode and returns the
+/// buffer as is. Only the list is decoded here: the fields, the list length
+/// among them, were already unpacked in C by `oneReadLine()`.
+pub(crate) unsafe fn decode_list(vf: *mut ffi::OneFile) {
+    if (*vf).nBits == 0 {
+        return;
+    }
+    let info = (*vf).info[(*vf).lineType as u8 as usize];
+    if info.is_null() || (*info).listEltSize == 0 {
+        return;
+    }
+    let list_field = (*info).listField as usize;
+    let field_type = *(*info).fieldType.add(list_field);
+    let slot = &*((*vf).field as *const FieldSlot).add(list_field);
+    let Some(len) = slot.list_len(field_type) else {
+        return;
+    };
+    let len = len as usize;
+    match field_type {
+        OneType::oneDNA if (*info).listCodec == ffi::DNAcodec => {
+            let packed = std::slice::from_raw_parts((*vf).codecBuf as *const u8, len.div_ceil(4));
+            let out = std::slice::from_raw_parts_mut((*info).buffer as *mut u8, len);
+            unpack_2bit(packed, out);
+        }
+        OneType::oneINT_LIST => {
+            let buffer = (*info).buffer as *mut i64;
+            vcDecode((*info).listCodec, (*vf).nBits as c_int, (*vf).codecBuf, buffer.add(1) as *mut c_char);
+            let list = std::slice::from_raw_parts_mut(buffer, len);
+            decompact_int_list(list, (*vf).intListBytes as usize);
+        }
+        _ => return,
+    }
+    (*vf).nBits = 0;
+}
@@ -1,37 +1,530 @@
 //! OneFile wrapper for reading and writing ONE files
 
-use crate::error::{OneError, Result};
+use crate::error::{LineTypeMismatch, OneError, Result};
 use crate::ffi;
-use crate::schema::OneSchema;
+use crate::gdb::{GdbGroup, GdbIndex, GdbReader};
+use crate::lines::{Groups, Line, Lines, List, ObjectLines, Objects, OneObject};
+use crate::record::{FieldValue, ListValue, Record};
+use crate::schema::{registered_schema, type_name, OneSchema, UNDEFINED};
+use crate::types::{
+    Alphabet, DnaEncoding, FieldSlot, LineStats, OneCounts, OneProvenance, OneReference, OneType,
+    RealFormat, Timestamp, Warning,
+};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::ops::{Deref, Range};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
 
 // Note: The C library's errorString is now _Thread_local (patched in ONEcode/ONElib.c)
 // so no mutex is needed for error handling
 
+/// Per-contig GDB metadata: (scaffold names, scaffold lengths, contig offsets),
+/// each keyed by contig ID
+pub type GdbMetadata = (HashMap<i64, String>, HashMap<i64, i64>, HashMap<i64, (i64, i64)>);
+
+/// Number of `get_sequence_name()` calls after which a slow-path warning is printed
+pub const SLOW_PATH_WARN_THRESHOLD: u64 = 64;
+
+/// Longest STRING or DNA list the C library can write on an ASCII line
+///
+/// Longer lists abort the process inside ONElib, so the typed writers return
+/// `OneError::ListTooLong` instead. ASCII lists are not split into chunks:
+/// the format puts each list on a single line, which ONElib writes and reads
+/// whole. Binary files have no such limit.
+pub const MAX_ASCII_LIST_LEN: i64 = i32::MAX as i64;
+
+// Where the files of open_write_null() go
+const NULL_DEVICE: &str = "/dev/null";
+
+/// Counters of expensive accesses to a `OneFile`, see `OneFile::access_stats()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessStats {
+    /// Calls to `get_sequence_name()`, each of which rescans the embedded GDB
+    pub sequence_name_lookups: u64,
+    /// Passes over the embedded GDB made by any of the sequence name, length
+    /// and offset lookups
+    pub gdb_scans: u64,
+}
+
+/// Size, modification time and descriptor of the file behind a `OneFile`,
+/// see `OneFile::file_metadata()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// Size in bytes; for a file being written, of what has been flushed
+    pub len: u64,
+    /// Last modification time, if the platform records one
+    pub modified: Option<SystemTime>,
+    /// Whether it is a regular file, rather than a pipe or terminal
+    pub is_file: bool,
+    /// The descriptor ONElib reads or writes through, owned by the `OneFile`
+    /// and valid only while it is open
+    #[cfg(unix)]
+    pub fd: std::os::unix::io::RawFd,
+}
+
+/// Options for opening a `OneFile`, created by `OneFile::options()`
+///
+/// Collects the settings that `open_read()` and `open_write_new()` take
+/// positionally, so new settings can be added without changing those
+/// signatures. Unset options have the same defaults as the positional
+/// forms: no schema or file type check, one thread, binary output.
+///
+/// ```no_run
+/// use onecode::{OneFile, OneSchema};
+///
+/// let reader = OneFile::options().nthreads(4).open_read("data.1aln")?;
+///
+/// let schema = OneSchema::seq()?;
+/// let writer = OneFile::options()
+///     .schema(&schema)
+///     .file_type("seq")
+///     .binary(false)
+///     .open_write("out.1seq")?;
+/// # Ok::<(), onecode::OneError>(())
+/// ```
+#[derive(Clone, Copy)]
+pub struct OpenOptions<'a> {
+    schema: Option<&'a OneSchema>,
+    file_type: Option<&'a str>,
+    nthreads: usize,
+    binary: bool,
+    binary_above: Option<i64>,
+    ascii_list_limit: i64,
+    real_format: RealFormat,
+    slow_path_warnings: bool,
+    alphabets: &'a [(char, Alphabet)],
+    projection: Option<&'a [char]>,
+    lock: bool,
+}
+
+impl Default for OpenOptions<'_> {
+    fn default() -> Self {
+        OpenOptions {
+            schema: None,
+            file_type: None,
+            nthreads: 1,
+            binary: true,
+            binary_above: None,
+            ascii_list_limit: MAX_ASCII_LIST_LEN,
+            real_format: RealFormat::Default,
+            slow_path_warnings: true,
+            alphabets: &[],
+            projection: None,
+            lock: false,
+        }
+    }
+}
+
+impl<'a> OpenOptions<'a> {
+    /// Schema to check a file against when reading, or to write with
+    pub fn schema(mut self, schema: &'a OneSchema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// File type to write, or to assume when reading a file without a header
+    pub fn file_type(mut self, file_type: &'a str) -> Self {
+        self.file_type = Some(file_type);
+        self
+    }
+
+    /// Number of threads for parallel reading or writing
+    pub fn nthreads(mut self, nthreads: usize) -> Self {
+        self.nthreads = nthreads.max(1);
+        self
+    }
+
+    /// Whether to write in binary (the default) or ASCII form
+    pub fn binary(mut self, binary: bool) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// Write binary after all when ASCII output would hold lists longer than `len`
+    ///
+    /// Only `open_write_from()` can tell in advance, from the list lengths
+    /// recorded in the header of a binary source file; the other ASCII write
+    /// constructors fail with `OneError::Other` if this is set.
+    pub fn binary_above(mut self, len: i64) -> Self {
+        self.binary_above = Some(len);
+        self
+    }
+
+    /// Longest list the typed writers accept on an ASCII line, see `OneFile::set_ascii_list_limit()`
+    pub fn ascii_list_limit(mut self, len: i64) -> Self {
+        self.ascii_list_limit = len;
+        self
+    }
+
+    /// Format of REAL values written to ASCII files, see `OneFile::set_real_format()`
+    pub fn real_format(mut self, format: RealFormat) -> Self {
+        self.real_format = format;
+        self
+    }
+
+    /// Enable or disable the one-time slow-path warning, see `OneFile::set_slow_path_warnings()`
+    pub fn slow_path_warnings(mut self, enabled: bool) -> Self {
+        self.slow_path_warnings = enabled;
+        self
+    }
+
+    /// Alphabets to check lists against, by line type, see `OneFile::set_alphabet()`
+    ///
+    /// ```no_run
+    /// use onecode::{Alphabet, OneFile};
+    ///
+    /// let reader = OneFile::options()
+    ///     .alphabets(&[('S', Alphabet::DNA), ('I', Alphabet::new("ACGT-"))])
+    ///     .open_read("data.1seq")?;
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    pub fn alphabets(mut self, alphabets: &'a [(char, Alphabet)]) -> Self {
+        self.alphabets = alphabets;
+        self
+    }
+
+    /// Read only lines of these types, see `OneFile::set_projection()`
+    ///
+    /// ```no_run
+    /// use onecode::OneFile;
+    ///
+    /// // Alignment coordinates only, without decoding traces or other lists
+    /// let mut reader = OneFile::options().project(&['A', 'I']).open_read("data.1aln")?;
+    /// while reader.read_line() != '\0' {
+    ///     // ... only 'A' and 'I' lines
+    /// }
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    pub fn project(mut self, line_types: &'a [char]) -> Self {
+        self.projection = Some(line_types);
+        self
+    }
+
+    /// Lock files opened for writing or appending, see `WriteOptions::lock()`
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    fn c_nthreads(&self) -> i32 {
+        self.nthreads.min(i32::MAX as usize) as i32
+    }
+
+    /// Fail if `binary_above()` is set for ASCII output without a source to
+    /// tell the list lengths from
+    fn check_binary_above(&self, target: &str) -> Result<()> {
+        if self.binary_above.is_some() && !self.binary {
+            return Err(OneError::Other(format!(
+                "Cannot tell list lengths in advance for {}: binary_above() needs \
+                 open_write_from() and a binary source",
+                target
+            )));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, mut file: OneFile) -> Result<OneFile> {
+        file.set_slow_path_warnings(self.slow_path_warnings);
+        file.set_ascii_list_limit(self.ascii_list_limit);
+        if self.real_format != RealFormat::Default {
+            file.set_real_format(self.real_format);
+        }
+        for &(line_type, alphabet) in self.alphabets {
+            file.set_alphabet(line_type, alphabet)?;
+        }
+        if let Some(line_types) = self.projection {
+            file.set_projection(Some(line_types))?;
+        }
+        Ok(file)
+    }
+
+    /// Open `path` for reading
+    pub fn open_read(&self, path: &str) -> Result<OneFile> {
+        let file = OneFile::open_read(path, self.schema, self.file_type, self.c_nthreads())?;
+        self.apply(file)
+    }
+
+    /// Create `path` for writing
+    ///
+    /// Requires both a schema and a file type.
+    pub fn open_write(&self, path: &str) -> Result<OneFile> {
+        let (Some(schema), Some(file_type)) = (self.schema, self.file_type) else {
+            return Err(OneError::SchemaError(format!(
+                "Writing {} needs both a schema and a file type",
+                path
+            )));
+        };
+        self.check_binary_above(path)?;
+        let options = WriteOptions::default()
+            .binary(self.binary)
+            .nthreads(self.nthreads)
+            .lock(self.lock);
+        let file = OneFile::create(path, schema, file_type, options)?;
+        self.apply(file)
+    }
+
+    /// Open ONE data held in memory for reading, see `OneFile::open_read_bytes()`
+    pub fn open_read_bytes(&self, bytes: &[u8]) -> Result<OneFile> {
+        let file = OneFile::open_read_bytes(bytes, self.schema, self.file_type, self.c_nthreads())?;
+        self.apply(file)
+    }
+
+    /// Create a ONE file in memory for writing, see `OneFile::open_write_vec()`
+    ///
+    /// Requires both a schema and a file type.
+    pub fn open_write_vec(&self) -> Result<OneFile> {
+        let (Some(schema), Some(file_type)) = (self.schema, self.file_type) else {
+            return Err(OneError::SchemaError(
+                "Writing in memory needs both a schema and a file type".to_string(),
+            ));
+        };
+        self.check_binary_above("a file in memory")?;
+        let file = OneFile::open_write_vec(schema, file_type, self.binary, self.c_nthreads())?;
+        self.apply(file)
+    }
+
+    /// Reopen the binary file `path` to write more lines, see `OneFile::open_append()`
+    pub fn open_append(&self, path: &str) -> Result<OneFile> {
+        let file = OneFile::append(path, WriteOptions::default().lock(self.lock))?;
+        self.apply(file)
+    }
+
+    /// Create `path` for writing with the schema and header of `source`
+    pub fn open_write_from(&self, path: &str, source: &OneFile) -> Result<OneFile> {
+        let long_lists = match (self.binary_above, source.longest_list()) {
+            (Some(limit), Some((_, max))) => max > limit,
+            _ => false,
+        };
+        let binary = self.binary || long_lists;
+        let file = with_lock(path, self.lock, || {
+            OneFile::open_write_from(path, source, binary, self.c_nthreads())
+        })?;
+        self.apply(file)
+    }
+}
+
+/// Options for creating a ONE file with `OneFile::create()`
+///
+/// Collects the settings of a new file that would otherwise be positional
+/// arguments of `open_write_new()`. The defaults are those of
+/// `open_write_new()`: binary output on one thread, every list compressed
+/// once ONElib has seen enough of its type to train a codec, and the file
+/// written in place with C's default buffering. The object index of a
+/// binary file is always written, as `goto()` and the parallel readers
+/// depend on it.
+///
+/// ```no_run
+/// use onecode::{OneFile, OneSchema, WriteOptions};
+///
+/// let schema = OneSchema::seq()?;
+/// let options = WriteOptions::default()
+///     .nthreads(4)
+///     .compress('I', false)
+///     .atomic(true)
+///     .buffer_size(1 << 20);
+/// let mut file = OneFile::create("reads.1seq", &schema, "seq", options)?;
+/// file.write_dna('S', b"acgt")?;
+/// file.finalize()?;
+/// # Ok::<(), onecode::OneError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    binary: bool,
+    nthreads: usize,
+    uncompressed: u128, // line types whose lists are written without a codec
+    bad_compress: Option<char>, // a non-ASCII line type given to compress()
+    atomic: bool,
+    buffer_size: Option<usize>,
+    lock: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            binary: true,
+            nthreads: 1,
+            uncompressed: 0,
+            bad_compress: None,
+            atomic: false,
+            buffer_size: None,
+            lock: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Whether to write in binary (the default) or ASCII form
+    pub fn binary(mut self, binary: bool) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// Number of threads for parallel writing
+    pub fn nthreads(mut self, nthreads: usize) -> Self {
+        self.nthreads = nthreads.max(1);
+        self
+    }
+
+    /// Whether to compress the lists of `line_type` in a binary file
+    ///
+    /// On by default. Uncompressed lists take more space but cost nothing to
+    /// encode or decode, which suits lists read far more often than written.
+    /// DNA lists are always packed two bits per base. Line types are ASCII:
+    /// any other character makes `OneFile::create()` fail.
+    pub fn compress(mut self, line_type: char, compress: bool) -> Self {
+        if !line_type.is_ascii() {
+            self.bad_compress = Some(line_type);
+            return self;
+        }
+        let bit = 1u128 << line_type as u32;
+        if compress {
+            self.uncompressed &= !bit;
+        } else {
+            self.uncompressed |= bit;
+        }
+        self
+    }
+
+    /// Write to a temporary file beside the target, renamed over it on close
+    ///
+    /// Readers then see either the old file or the complete new one, never a
+    /// partial file. If the file cannot be finished, or is dropped while
+    /// panicking, the temporary file is removed and the target left as it
+    /// was. Until then `file_name()` is the temporary file's.
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Size in bytes of the write buffer of each thread's file
+    ///
+    /// Larger buffers mean fewer system calls, which helps on network file
+    /// systems. Not applied to standard output.
+    pub fn buffer_size(mut self, bytes: usize) -> Self {
+        self.buffer_size = Some(bytes);
+        self
+    }
+
+    /// Hold an advisory `flock()` lock on the path while writing
+    ///
+    /// Taken before anything is written, so a second writer given the same
+    /// path, e.g. by a pipeline task run twice, fails with `OneError::Locked`
+    /// and leaves the file alone rather than interleaving its output with
+    /// the first's. The lock is released once the file is closed. Other
+    /// processes are only kept out if they lock too, and locks may not work
+    /// on network file systems. With `atomic()` the target path is locked,
+    /// so an empty file is created there if there was none. Not applied to
+    /// standard output.
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+}
+
 /// A ONE file handle for reading or writing
+///
+/// A `OneFile` can be moved to another thread, e.g. to hand readers to a
+/// pool of workers, but not used from two threads at once: it is `Send`
+/// but not `Sync`. Wrap it in a `Mutex` to share it.
+///
+/// A file opened with `nthreads` > 1 holds a C file handle per thread. All
+/// of them belong to this `OneFile` and are closed with it, by `close()` or
+/// on drop, including while unwinding from a panic in a worker: the handles
+/// given out by `split_threads()` and `par_objects()` only borrow them.
+///
+/// Strings and lists of the current line, such as `int_list()`, borrow the
+/// C library's line buffers through the `OneFile`, and every method that
+/// refills those buffers, from `read_line()` and `goto()` to `dna_2bit()`,
+/// borrows it mutably. A list therefore cannot outlive the line it came
+/// from; copy it, or read `Line`s with `lines()`, to keep it longer:
+///
+/// ```compile_fail
+/// use onecode::OneFile;
+///
+/// let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+/// file.read_line();
+/// let list = file.int_list().unwrap();
+/// file.read_line(); // error: `file` is still borrowed by `list`
+/// println!("{:?}", list);
+/// # Ok::<(), onecode::OneError>(())
+/// ```
+///
+/// A `OneFile` has the methods of both modes, whichever it was opened in;
+/// `OneReader` and `OneWriter` offer only those of one mode, so that calling
+/// a write method on a file being read, or the reverse, does not compile.
 pub struct OneFile {
     pub(crate) ptr: *mut ffi::OneFile,
     is_owned: bool, // true if we should close this on drop
+    access: AccessStats,
+    warn_slow_paths: bool, // cleared once the warning has been printed
+    ascii_list_limit: i64,
+    alphabets: HashMap<char, Alphabet>, // see set_alphabet()
+    projection: Option<u128>,            // line types read_line() returns, see set_projection()
+    gdb_indexes: HashMap<i64, GdbIndex>, // by 'g' group, see gdb_index()
+    last_dna_len: Option<usize>,         // list length of the last write_dna()
+    memory_path: Option<PathBuf>,        // backing file of an in-memory file, removed on drop
+    atomic_target: Option<(PathBuf, PathBuf)>, // temporary file and its target, see WriteOptions
+    warnings: Vec<Warning>,                    // see warnings()
+    write_lock: Option<std::fs::File>,         // held on the target, see WriteOptions::lock()
+    counts_checked: bool,                      // header counts compared at the end, see warnings()
+    real_format: RealFormat,                   // REAL values in ASCII output, see set_real_format()
 }
 
 impl OneFile {
+    /// Wrap a C file handle, which is closed on drop if `is_owned`
+    ///
+    /// All constructors go through here, so a new field needs its default
+    /// set in this one place.
+    fn from_raw(ptr: *mut ffi::OneFile, is_owned: bool) -> Self {
+        OneFile {
+            ptr,
+            is_owned,
+            access: AccessStats::default(),
+            warn_slow_paths: true,
+            ascii_list_limit: MAX_ASCII_LIST_LEN,
+            alphabets: HashMap::new(),
+            projection: None,
+            gdb_indexes: HashMap::new(),
+            last_dna_len: None,
+            memory_path: None,
+            atomic_target: None,
+            warnings: Vec::new(),
+            write_lock: None,
+            counts_checked: false,
+            real_format: RealFormat::Default,
+        }
+    }
+
     /// Trim sequence name at first whitespace character
     /// This removes FASTA header descriptions, keeping only the sequence ID
-    fn trim_sequence_name(name: &str) -> String {
+    pub(crate) fn trim_sequence_name(name: &str) -> String {
         name.split_whitespace()
             .next()
             .unwrap_or(name)
             .to_string()
     }
+    /// Options for opening a file, as an alternative to the positional constructors
+    pub fn options<'a>() -> OpenOptions<'a> {
+        OpenOptions::default()
+    }
+
     /// Open a ONE file for reading
     ///
+    /// See `options()` for setting the same parameters by name.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the file to open
     /// * `schema` - Optional schema to validate against
-    /// * `file_type` - Optional file type to match (primary or secondary)
+    /// * `file_type` - Optional file type to match (primary or secondary); with
+    ///   no `schema`, a schema registered for it with `register_schema()` is used
     /// * `nthreads` - Number of threads for parallel reading (1 for single-threaded)
     ///
     /// # Example
@@ -47,163 +540,1236 @@ impl OneFile {
         file_type: Option<&str>,
         nthreads: i32,
     ) -> Result<Self> {
+        if path == "-" && nthreads > 1 {
+            return Err(OneError::OpenFailed(
+                "-: parallel reading needs a file, not standard input".to_string(),
+            ));
+        }
         let c_path = CString::new(path)?;
+        // A file type registered with register_schema() brings its schema
+        let registered = match (schema, file_type) {
+            (None, Some(t)) => registered_schema(t)?,
+            _ => None,
+        };
+        let schema = schema.or(registered.as_ref());
         let schema_ptr = schema.map_or(ptr::null_mut(), |s| s.as_ptr());
         let c_type = file_type.map(CString::new).transpose()?;
         let type_ptr = c_type.as_ref().map_or(ptr::null(), |t| t.as_ptr());
 
+        if path != "-" {
+            check_footer(path)?;
+        }
+
         unsafe {
+            // ONElib leaves its message alone if the file cannot be opened,
+            // when errno tells why
+            clear_c_error();
             let ptr = ffi::oneFileOpenRead(c_path.as_ptr(), schema_ptr, type_ptr, nthreads);
             if ptr.is_null() {
-                let err_str = ffi::oneErrorString();
-                let err_msg = if !err_str.is_null() {
-                    CStr::from_ptr(err_str).to_string_lossy().into_owned()
-                } else {
-                    "Unknown error".to_string()
-                };
-                let msg = if err_msg.trim().is_empty() {
-                    path.to_string()
-                } else {
-                    format!("{}: {}", path, err_msg)
-                };
-                return Err(OneError::OpenFailed(msg));
+                let mut error = open_error(path, std::io::Error::last_os_error());
+                if let (Some(schema), OneError::OpenFailed(msg)) = (schema, &error) {
+                    if msg.ends_with("schema mismatch to code requirement") {
+                        error = schema_mismatch(path, &c_path, type_ptr, schema).unwrap_or(error);
+                    }
+                }
+                return Err(error);
             }
-            Ok(OneFile {
-                ptr,
-                is_owned: true,
-            })
+            Ok(OneFile::from_raw(ptr, true))
+        }
+    }
+
+    /// Open standard input for reading, as `open_read()` does for path "-"
+    ///
+    /// ASCII input can come through a pipe. Binary input must be a redirected
+    /// file: ONElib jumps to the footer when it reads the header, and exits
+    /// if it cannot seek. Input from a pipe is read front to back only, so
+    /// `goto()` and the helpers built on it fail; see `is_seekable()`.
+    pub fn open_read_stdin(schema: Option<&OneSchema>, file_type: Option<&str>) -> Result<Self> {
+        OneFile::open_read("-", schema, file_type, 1)
+    }
+
+    /// Open standard output for writing, as `open_write_new()` does for path "-"
+    ///
+    /// Binary output needs a seekable standard output, i.e. a redirected file,
+    /// since the footer records byte offsets; when writing to a pipe only ASCII
+    /// is accepted.
+    pub fn open_write_stdout(schema: &OneSchema, file_type: &str, is_binary: bool) -> Result<Self> {
+        if is_binary && !fd_is_seekable(libc::STDOUT_FILENO) {
+            return Err(OneError::OpenFailed(
+                "-: binary output needs a seekable standard output; write ASCII to a pipe"
+                    .to_string(),
+            ));
+        }
+        OneFile::open_write_new("-", schema, file_type, is_binary, 1)
+    }
+
+    /// Open ONE data held in memory for reading
+    ///
+    /// Takes the same arguments as `open_read()`. ONElib reads through file
+    /// handles it opens by name (one per thread), so the bytes are copied to
+    /// a temporary file, which is removed when the `OneFile` is dropped.
+    pub fn open_read_bytes(
+        bytes: &[u8],
+        schema: Option<&OneSchema>,
+        file_type: Option<&str>,
+        nthreads: i32,
+    ) -> Result<Self> {
+        let path = create_memory_file(bytes)?;
+        let name = path.to_string_lossy();
+        match OneFile::open_read(&name, schema, file_type, nthreads) {
+            Ok(mut file) => {
+                file.memory_path = Some(path);
+                Ok(file)
+            }
+            Err(e) => {
+                std::fs::remove_file(&path).ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Create a new ONE file for writing
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path where the file will be written
+    /// * `schema` - Schema defining the file format
+    /// * `file_type` - Primary file type
+    /// * `is_binary` - Whether to write in binary format (true) or ASCII (false)
+    /// * `nthreads` - Number of threads for parallel writing (1 for single-threaded)
+    ///
+    /// This is `create()` with only these two of its `WriteOptions`.
+    pub fn open_write_new(
+        path: &str,
+        schema: &OneSchema,
+        file_type: &str,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        let options = WriteOptions::default()
+            .binary(is_binary)
+            .nthreads(nthreads.max(1) as usize);
+        Self::create(path, schema, file_type, options)
+    }
+
+    /// Create a new ONE file for writing, with the settings of `options`
+    ///
+    /// Fails with `OneError::SchemaError`, creating no file, if compression
+    /// is set for a line type that is not ASCII, or turned off for one
+    /// without a list or with a DNA list.
+    pub fn create(
+        path: &str,
+        schema: &OneSchema,
+        file_type: &str,
+        options: WriteOptions,
+    ) -> Result<Self> {
+        if let Some(line_type) = options.bad_compress {
+            return Err(OneError::SchemaError(format!(
+                "Cannot set compression of '{}' lines: line types are ASCII characters",
+                line_type
+            )));
+        }
+        if path == "-" && options.atomic {
+            return Err(OneError::OpenFailed(
+                "-: atomic writing needs a file, not standard output".to_string(),
+            ));
+        }
+        let temp = options.atomic.then(|| atomic_temp_path(Path::new(path)));
+        let write_path = temp.as_ref().map_or(path.to_string(), |t| t.to_string_lossy().into());
+        let nthreads = options.nthreads.min(i32::MAX as usize) as i32;
+        let mut file = with_lock(path, options.lock, || {
+            Self::write_new(&write_path, schema, file_type, options.binary, nthreads)
+        })?;
+        for line_type in (0..128u8).filter(|&t| options.uncompressed & (1 << t) != 0) {
+            let set = unsafe { ffi::oneFileSetListCompression(file.ptr, line_type as i8, false) };
+            if !set {
+                drop(file);
+                std::fs::remove_file(&write_path).ok();
+                return Err(OneError::SchemaError(format!(
+                    "Cannot turn off compression of '{}' lines in a {} file, which have no \
+                     list or a DNA list",
+                    line_type as char, file_type
+                )));
+            }
+        }
+        if let Some(size) = options.buffer_size.filter(|_| path != "-") {
+            for i in 0..file.nthreads() {
+                unsafe {
+                    let f = (*file.ptr.add(i)).f as *mut libc::FILE;
+                    libc::setvbuf(f, ptr::null_mut(), libc::_IOFBF, size);
+                }
+            }
+        }
+        if let Some(temp) = temp {
+            file.atomic_target = Some((temp, PathBuf::from(path)));
+        }
+        Ok(file)
+    }
+
+    fn write_new(
+        path: &str,
+        schema: &OneSchema,
+        file_type: &str,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        if path == "-" && nthreads > 1 {
+            return Err(OneError::OpenFailed(
+                "-: parallel writing needs a file, not standard output".to_string(),
+            ));
+        }
+        // ONElib fails without a message on a file type the schema lacks
+        if !crate::schema::file_types(schema.text()).iter().any(|t| t == file_type) {
+            return Err(OneError::SchemaError(format!(
+                "{}: file type {} not found in schema",
+                path, file_type
+            )));
+        }
+        let c_path = CString::new(path)?;
+        let c_type = CString::new(file_type)?;
+
+        unsafe {
+            clear_c_error();
+            let ptr = ffi::oneFileOpenWriteNew(
+                c_path.as_ptr(),
+                schema.as_ptr(),
+                c_type.as_ptr(),
+                is_binary,
+                nthreads,
+            );
+            if ptr.is_null() {
+                return Err(open_error(path, std::io::Error::last_os_error()));
+            }
+            Ok(OneFile::from_raw(ptr, true))
+        }
+    }
+
+    /// Create a ONE file in memory for writing
+    ///
+    /// Takes the same arguments as `open_write_new()`; once all lines are
+    /// written, `into_vec()` closes the file and returns its bytes. The data
+    /// goes through a temporary file, as ONElib writes by name, which is
+    /// removed when the `OneFile` is dropped.
+    pub fn open_write_vec(
+        schema: &OneSchema,
+        file_type: &str,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        let path = create_memory_file(&[])?;
+        let name = path.to_string_lossy();
+        match OneFile::open_write_new(&name, schema, file_type, is_binary, nthreads) {
+            Ok(mut file) => {
+                file.memory_path = Some(path);
+                Ok(file)
+            }
+            Err(e) => {
+                std::fs::remove_file(&path).ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Create a ONE file for writing that discards what is written
+    ///
+    /// Takes the same arguments as `open_write_new()`, without threads: ONElib
+    /// writes the lines of other threads to temporary files next to the
+    /// output. Lines are checked and counted as for any file being written,
+    /// and `stats()` gives the counts, but the bytes go to `/dev/null`.
+    /// See `NullWriter`.
+    pub fn open_write_null(schema: &OneSchema, file_type: &str, is_binary: bool) -> Result<Self> {
+        Self::write_new(NULL_DEVICE, schema, file_type, is_binary, 1)
+    }
+
+    /// As `open_write_null()`, with the schema and header of `source`, see
+    /// `open_write_from()`
+    pub fn open_write_null_from(source: &OneFile, is_binary: bool) -> Result<Self> {
+        Self::open_write_from(NULL_DEVICE, source, is_binary, 1)
+    }
+
+    /// Create a new ONE file for writing based on an existing file
+    ///
+    /// Schema and header information is inherited from the source file.
+    pub fn open_write_from(
+        path: &str,
+        source: &OneFile,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        let c_path = CString::new(path)?;
+
+        unsafe {
+            let ptr =
+                ffi::oneFileOpenWriteFrom(c_path.as_ptr(), source.ptr, is_binary, nthreads);
+            if ptr.is_null() {
+                return Err(open_error(path, std::io::Error::last_os_error()));
+            }
+            Ok(OneFile::from_raw(ptr, true))
+        }
+    }
+
+    /// Reopen a binary ONE file to write more lines after its data
+    ///
+    /// The file is opened for update in place: its footer is cut off, lines
+    /// written are added after the existing data, and closing the file
+    /// writes the footer again, with counts and object indexes covering both
+    /// old and new lines. Lists are compressed with the codecs the file
+    /// already has. Until the file is closed it has no footer, so a writer
+    /// that dies first leaves a file that reads as truncated; use `append()`
+    /// with `WriteOptions::atomic()` to keep the file as it was instead.
+    ///
+    /// The header stays as it was, so provenance and references cannot be
+    /// added. Start with an object line: lines written before the first
+    /// object are counted as in no object.
+    ///
+    /// ```no_run
+    /// use onecode::OneFile;
+    ///
+    /// let mut file = OneFile::open_append("reads.1seq")?;
+    /// file.write_dna('S', b"acgt")?;
+    /// file.finalize()?;
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    pub fn open_append(path: &str) -> Result<Self> {
+        Self::append(path, WriteOptions::default())
+    }
+
+    /// Reopen a binary ONE file to write more lines, with the given options
+    ///
+    /// As `open_append()`, taking `atomic()` and `lock()` from `options`;
+    /// the others are fixed by the file. With `atomic()` the file is first
+    /// copied beside it, lines are added to the copy, and closing renames
+    /// the copy over the file, so until then, or if the process dies first,
+    /// readers see the file as it was. The copy costs a read and a write of
+    /// the whole file.
+    pub fn append(path: &str, options: WriteOptions) -> Result<Self> {
+        if path == "-" {
+            return Err(OneError::OpenFailed(
+                "-: appending needs a file, not standard output".to_string(),
+            ));
+        }
+        // Checked once locked, as another writer may not have finished the file
+        with_lock(path, options.lock, || {
+            if matches!(crate::sniff::sniff(path), Some(crate::sniff::FileKind::OneAscii { .. })) {
+                return Err(OneError::OpenFailed(format!(
+                    "{}: can only append to a binary file",
+                    path
+                )));
+            }
+            check_footer(path)?;
+            if options.atomic {
+                Self::append_to_copy(path)
+            } else {
+                std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(path)
+                    .map_err(|e| OneError::io(path, &e))?;
+                Self::append_at(path, path)
+            }
+        })
+    }
+
+    /// Append to a copy of `path`, renamed over it on close
+    fn append_to_copy(path: &str) -> Result<Self> {
+        let temp = atomic_temp_path(Path::new(path));
+        std::fs::copy(path, &temp).map_err(|e| OneError::io(path, &e))?;
+        match Self::append_at(&temp.to_string_lossy(), path) {
+            Ok(mut file) => {
+                file.atomic_target = Some((temp, PathBuf::from(path)));
+                Ok(file)
+            }
+            Err(e) => {
+                std::fs::remove_file(&temp).ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Reopen `write_path` for appending, naming `path` in errors
+    fn append_at(write_path: &str, path: &str) -> Result<Self> {
+        let c_path = CString::new(write_path)?;
+        unsafe {
+            clear_c_error();
+            let ptr = ffi::oneFileOpenAppend(c_path.as_ptr());
+            if ptr.is_null() {
+                let message = c_error_message().replace(write_path, path);
+                return Err(parse_open_error(path, &message, None));
+            }
+            Ok(OneFile::from_raw(ptr, true))
+        }
+    }
+
+    /// Read the next line from the file
+    ///
+    /// Returns the line type character, or 0 if at end of file. Lines of
+    /// types outside the projection, if one is set, are skipped.
+    pub fn read_line(&mut self) -> char {
+        loop {
+            let line_type = self.read_raw_line();
+            match self.projection {
+                Some(mask) if line_type != '\0' && mask & (1 << line_type as u32) == 0 => {}
+                _ => return line_type,
+            }
+        }
+    }
+
+    /// Read the next line whatever its type, ignoring the projection
+    fn read_raw_line(&mut self) -> char {
+        let line_type = unsafe { ffi::oneReadLine(self.ptr) as u8 as char };
+        #[cfg(feature = "debug-ffi")]
+        if line_type != '\0' {
+            unsafe { crate::debug_ffi::check_line(self.ptr) };
+        }
+        if line_type == '\0' && !self.counts_checked {
+            self.counts_checked = true;
+            self.check_header_counts();
+        }
+        line_type
+    }
+
+    /// Note the line types of an ASCII file read to its end whose '#' header
+    /// lines give other counts than were read
+    ///
+    /// Binary files are left alone: their footer counts are written from the
+    /// data, and `goto()` leaves the running counts of some types behind.
+    fn check_header_counts(&mut self) {
+        if self.is_binary() || unsafe { (*self.ptr).isWrite } {
+            return;
+        }
+        for t in 0..128u8 {
+            let info = unsafe { (*self.ptr).info[t as usize] };
+            if info.is_null() || !t.is_ascii_alphabetic() {
+                continue;
+            }
+            let (given, found) = unsafe { ((*info).given.count, (*info).accum.count) };
+            if given > 0 && given != found {
+                let line_type = t as char;
+                self.warnings.push(Warning::CountMismatch { line_type, given, found });
+            }
+        }
+    }
+
+    /// Non-fatal anomalies met while reading this file so far
+    ///
+    /// Collected rather than printed or raised, so a tool can finish its run
+    /// and print them as a QC summary. Noted are:
+    /// - scaffold names repeated within a skeleton, when `gdb_index()` or
+    ///   `group_gdb_index()` builds its index
+    /// - '#' header counts of an ASCII file that disagree with its lines,
+    ///   once `read_line()` reaches the end of the file
+    /// - the point at which `get_sequence_name()` has been called
+    ///   `SLOW_PATH_WARN_THRESHOLD` times
+    ///
+    /// ONElib itself rejects lines of unknown types; the pure-Rust
+    /// `AsciiReader` can skip them instead, see `AsciiReader::lenient()`.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Forget the warnings collected so far, see `warnings()`
+    pub fn clear_warnings(&mut self) {
+        self.warnings.clear();
+    }
+
+    /// Restrict `read_line()` to lines of the given types, or lift the restriction
+    ///
+    /// Lines of other types are skipped as opaque data: they are still read
+    /// past, and count towards `line_number()` and object numbering, but
+    /// `read_line()` and everything built on it (`lines()`, `read_record()`,
+    /// the sequence and alignment readers) never stop on them, and the lists
+    /// of skipped binary lines are never decoded. Each type must be declared
+    /// in the file's schema. Only for files being read.
+    pub fn set_projection(&mut self, line_types: Option<&[char]>) -> Result<()> {
+        let Some(line_types) = line_types else {
+            self.projection = None;
+            return Ok(());
+        };
+        if unsafe { (*self.ptr).isWrite } {
+            return Err(OneError::Other(
+                "A projection only applies to files being read".to_string(),
+            ));
+        }
+        let mut mask = 0u128;
+        for &line_type in line_types {
+            if !line_type.is_ascii() || self.stats(line_type).is_err() {
+                return Err(OneError::SchemaError(format!(
+                    "Cannot project onto line type '{}', which the schema does not declare",
+                    line_type
+                )));
+            }
+            mask |= 1 << line_type as u32;
+        }
+        self.projection = Some(mask);
+        Ok(())
+    }
+
+    /// The line types `read_line()` is restricted to, see `set_projection()`
+    pub fn projection(&self) -> Option<Vec<char>> {
+        let mask = self.projection?;
+        Some((0..128u8).filter(|&c| mask & (1 << c) != 0).map(char::from).collect())
+    }
+
+    /// Read the next line, checking its list against the alphabet set for its type
+    ///
+    /// As `read_line()`, but fails with `OneError::InvalidCharacter`, giving
+    /// the line number, if the list holds a character outside the alphabet
+    /// set with `set_alphabet()`. Lines of other types are not checked.
+    pub fn read_line_checked(&mut self) -> Result<char> {
+        let line_type = self.read_line();
+        let list_type = self.list_type(line_type);
+        if let Some(list_type) = list_type.filter(|_| self.alphabets.contains_key(&line_type)) {
+            let line = self.line_number();
+            self.check_alphabet(line_type, self.list_bytes(list_type))
+                .map_err(|e| match e {
+                    OneError::InvalidCharacter(msg) => {
+                        OneError::InvalidCharacter(format!("line {}: {}", line, msg))
+                    }
+                    e => e,
+                })?;
+        }
+        Ok(line_type)
+    }
+
+    /// Read the next line as a typed record, or None at the end of the file
+    ///
+    /// Fields are decoded according to the schema; see `Record`.
+    pub fn read_record(&mut self) -> Option<Record> {
+        if self.read_line() == '\0' {
+            return None;
+        }
+        Some(Line::from_current(self, false).into())
+    }
+
+    /// Iterate over the remaining lines of the file
+    ///
+    /// Each item is an owned copy of a line; see `Lines` for the filters that
+    /// can be applied before iterating.
+    pub fn lines(&mut self) -> Lines<'_> {
+        Lines::new(self)
+    }
+
+    /// Iterate over the objects of type `line_type` from the current position
+    ///
+    /// Each `OneObject` bundles an object line with the data lines that follow
+    /// it, up to the next object line of any type, e.g. a scaffold 'S' of a
+    /// GDB skeleton with its gaps and contigs. Works on ASCII and binary files.
+    pub fn objects(&mut self, line_type: char) -> Objects<'_> {
+        Objects::new(self, line_type)
+    }
+
+    /// Iterate over the groups of type `group_type` from the current position
+    ///
+    /// Each `Group` holds the group line and its member objects, e.g. for
+    /// `groups('g')` on a `.1aln` file one GDB skeleton per genome with its
+    /// scaffolds, contigs and gaps. Works on ASCII and binary files.
+    pub fn groups(&mut self, group_type: char) -> Groups<'_> {
+        Groups::new(self, group_type)
+    }
+
+    /// Copy the rest of this file to `output`, keeping only the objects of
+    /// type `line_type` that `keep` accepts
+    ///
+    /// Each object is read whole, as `objects()` gives it, and written with
+    /// all its lines if `keep` returns true, or dropped with all of them
+    /// otherwise. Everything else is copied unchanged: the lines before the
+    /// first object, such as the skeletons of a `.1aln`, and objects of other
+    /// types with their lines, so the groups they form are kept intact. For
+    /// a group type the members belong to the group, and are kept or
+    /// dropped with it. Comments are carried over, and `output` counts the
+    /// lines it is given, so its header and index match what was kept.
+    /// Returns the number of objects kept.
+    ///
+    /// ```no_run
+    /// use onecode::OneFile;
+    ///
+    /// let mut input = OneFile::open_read("all.1aln", None, None, 1)?;
+    /// let mut output = OneFile::open_write_from("long.1aln", &input, true, 1)?;
+    /// input.filter_objects(&mut output, 'A', |aln| {
+    ///     aln.line.int(2) - aln.line.int(1) >= 10_000 // query end - start
+    /// })?;
+    /// output.finalize()?;
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    ///
+    /// Fails with `OneError::SchemaError` if `line_type` is not an object
+    /// type of the schema, or `output` does not declare a line type to copy
+    /// with the same number of fields.
+    pub fn filter_objects<F>(
+        &mut self,
+        output: &mut OneFile,
+        line_type: char,
+        mut keep: F,
+    ) -> Result<i64>
+    where
+        F: FnMut(&OneObject) -> bool,
+    {
+        if !line_type.is_ascii() || !crate::lines::is_object(self, line_type) {
+            return Err(OneError::SchemaError(format!(
+                "Cannot filter on line type '{}', which the schema does not declare an object",
+                line_type
+            )));
+        }
+        let contains = unsafe { (*(*self.ptr).info[line_type as usize]).contains };
+        let mut kept = 0;
+        let mut current = self.read_raw_line();
+        while current != '\0' {
+            if current != line_type {
+                self.copy_line(output)?;
+                current = self.read_raw_line();
+                continue;
+            }
+            let number = self.object(line_type);
+            let line = Line::from_current(self, true);
+            let mut lines = Vec::new();
+            loop {
+                current = self.read_raw_line();
+                let is_member = current.is_ascii() && contains[current as usize];
+                if current == '\0' || crate::lines::is_object(self, current) && !is_member {
+                    break;
+                }
+                lines.push(Line::from_current(self, true));
+            }
+            let object = OneObject { number, line, lines };
+            if keep(&object) {
+                output.write_saved_line(&object.line)?;
+                for line in &object.lines {
+                    output.write_saved_line(line)?;
+                }
+                kept += 1;
+            }
+        }
+        Ok(kept)
+    }
+
+    /// Iterate over `n` objects of type `line_type` chosen uniformly at random
+    ///
+    /// The choice depends only on `seed` and the number of objects, so a
+    /// preview is reproducible. Objects are read in file order via the object
+    /// index, each as its object line followed by the data lines up to the
+    /// next object of any type; all objects are returned if there are at
+    /// most `n`. Needs a binary file.
+    pub fn sample_objects(
+        &mut self,
+        line_type: char,
+        n: usize,
+        seed: u64,
+    ) -> Result<ObjectLines<'_>> {
+        let count = self.object_index(line_type)?.len() as i64 - 1;
+        let objects = crate::lines::sample(count, n, seed);
+        Ok(ObjectLines::new(self, line_type, objects))
+    }
+
+    /// Iterate over the last `n` objects of type `line_type`
+    ///
+    /// Jumps straight to them via the object index, so the end of a large
+    /// file can be checked without reading the rest. Each object is its
+    /// object line followed by the data lines up to the next object of any
+    /// type. Needs a binary file.
+    pub fn last_objects(&mut self, line_type: char, n: usize) -> Result<ObjectLines<'_>> {
+        let count = self.object_index(line_type)?.len() as i64 - 1;
+        let first = count - (n as i64).min(count) + 1;
+        Ok(ObjectLines::new(self, line_type, (first..=count).collect()))
+    }
+
+    /// Read comment text from the current line
+    ///
+    /// Returns None if there is no comment.
+    pub fn read_comment(&self) -> Option<String> {
+        unsafe {
+            let comment_ptr = ffi::oneReadComment(self.ptr);
+            if comment_ptr.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(comment_ptr).to_string_lossy().into_owned())
+            }
+        }
+    }
+
+    /// Write a line to the file
+    ///
+    /// # Arguments
+    ///
+    /// * `line_type` - The line type character
+    /// * `list_len` - Length of the list (0 if no list)
+    /// * `list_buf` - Buffer containing list data (None to use internal buffer)
+    ///
+    /// For lines with list data prefer the typed writers `write_int_list()`,
+    /// `write_real_list()`, `write_string()`, `write_string_list()` and
+    /// `write_dna()`, which check the list type against the schema and the
+    /// list length against the ASCII limit (see `set_ascii_list_limit()`).
+    pub fn write_line(&mut self, line_type: char, list_len: i64, list_buf: Option<*mut std::ffi::c_void>) {
+        self.write_c_line(line_type, list_len, list_buf.unwrap_or(ptr::null_mut()));
+    }
+
+    /// Write a line whose list field is an INT_LIST
+    ///
+    /// Fields other than the list are taken from those set with `set_int()`
+    /// etc. Fails if the schema gives `line_type` a different list type.
+    pub fn write_int_list(&mut self, line_type: char, values: &[i64]) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneINT_LIST)?;
+        self.write_list(line_type, values.len(), values.as_ptr() as *const u8)
+    }
+
+    /// Write a line whose list field is a REAL_LIST
+    pub fn write_real_list(&mut self, line_type: char, values: &[f64]) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneREAL_LIST)?;
+        self.write_list(line_type, values.len(), values.as_ptr() as *const u8)
+    }
+
+    /// Write a line whose list field is a STRING
+    ///
+    /// Fails if `s` contains a NUL byte, which ONE strings cannot hold.
+    pub fn write_string(&mut self, line_type: char, s: &str) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneSTRING)?;
+        if s.contains('\0') {
+            return Err(OneError::Other(format!("String for line type '{}' contains a NUL byte", line_type)));
+        }
+        self.check_alphabet(line_type, s.as_bytes())?;
+        self.write_list(line_type, s.len(), s.as_ptr())
+    }
+
+    /// Write a line whose list field is a STRING_LIST
+    pub fn write_string_list(&mut self, line_type: char, strings: &[&str]) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneSTRING_LIST)?;
+        let mut buf = Vec::new();
+        for s in strings {
+            if s.contains('\0') {
+                return Err(OneError::Other(format!("String for line type '{}' contains a NUL byte", line_type)));
+            }
+            buf.extend_from_slice(s.as_bytes());
+            buf.push(0);
+        }
+        self.check_alphabet(line_type, &buf)?;
+        self.write_list(line_type, strings.len(), buf.as_ptr())
+    }
+
+    /// Write a line whose list field is DNA, given as characters
+    ///
+    /// Bases are stored in 2-bit form in binary files, so anything other
+    /// than acgt (either case) reads back as one of them.
+    pub fn write_dna(&mut self, line_type: char, bases: &[u8]) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneDNA)?;
+        self.check_alphabet(line_type, bases)?;
+        self.write_list(line_type, bases.len(), bases.as_ptr())?;
+        self.last_dna_len = Some(bases.len());
+        Ok(())
+    }
+
+    /// Write an INT_LIST with one value per base of the last sequence, e.g. coverage
+    ///
+    /// The sequence is the DNA list most recently written with `write_dna()`
+    /// in the current object; writing an object line forgets it.
+    /// Fails with `OneError::LengthMismatch`, writing nothing, unless
+    /// `values` is exactly as long as that sequence.
+    pub fn write_per_base_int_list(&mut self, line_type: char, values: &[i64]) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneINT_LIST)?;
+        self.check_per_base_len(line_type, values.len())?;
+        self.write_list(line_type, values.len(), values.as_ptr() as *const u8)
+    }
+
+    /// Write a REAL_LIST with one value per base of the last sequence, e.g. methylation levels
+    ///
+    /// See `write_per_base_int_list()`.
+    pub fn write_per_base_real_list(&mut self, line_type: char, values: &[f64]) -> Result<()> {
+        self.check_list_type(line_type, OneType::oneREAL_LIST)?;
+        self.check_per_base_len(line_type, values.len())?;
+        self.write_list(line_type, values.len(), values.as_ptr() as *const u8)
+    }
+
+    fn check_per_base_len(&self, line_type: char, len: usize) -> Result<()> {
+        match self.last_dna_len {
+            Some(bases) if bases == len => Ok(()),
+            Some(bases) => Err(OneError::LengthMismatch(format!(
+                "{} values on per-base '{}' line for a sequence of {} bases",
+                len, line_type, bases
+            ))),
+            None => Err(OneError::LengthMismatch(format!(
+                "Per-base '{}' line written before any sequence",
+                line_type
+            ))),
+        }
+    }
+
+    /// Limit the lists the typed writers accept in ASCII mode (at most `MAX_ASCII_LIST_LEN`)
+    ///
+    /// Very long ASCII lines are slow to write and read back; above the
+    /// limit the typed writers return `OneError::ListTooLong` and write
+    /// nothing. List lengths count elements, or strings for a STRING_LIST.
+    pub fn set_ascii_list_limit(&mut self, len: i64) {
+        self.ascii_list_limit = len.min(MAX_ASCII_LIST_LEN);
+    }
+
+    /// Restrict the characters of the STRING, STRING_LIST or DNA list of `line_type`
+    ///
+    /// The typed writers then return `OneError::InvalidCharacter`, writing
+    /// nothing, for a list with a character outside `alphabet`, and so does
+    /// `read_line_checked()` for such a line read. Fails if the schema gives
+    /// `line_type` no list of characters. Thread handles from
+    /// `split_threads()` inherit the alphabets set beforehand.
+    pub fn set_alphabet(&mut self, line_type: char, alphabet: Alphabet) -> Result<()> {
+        match self.list_type(line_type) {
+            Some(OneType::oneSTRING | OneType::oneSTRING_LIST | OneType::oneDNA) => {
+                self.alphabets.insert(line_type, alphabet);
+                Ok(())
+            }
+            list_type => Err(OneError::SchemaError(format!(
+                "Line type '{}' has list type {:?}, not a list of characters",
+                line_type, list_type
+            ))),
+        }
+    }
+
+    /// The alphabet set for `line_type` with `set_alphabet()`, if any
+    pub fn alphabet(&self, line_type: char) -> Option<Alphabet> {
+        self.alphabets.get(&line_type).copied()
+    }
+
+    fn check_alphabet(&self, line_type: char, bytes: &[u8]) -> Result<()> {
+        let Some(alphabet) = self.alphabets.get(&line_type) else {
+            return Ok(());
+        };
+        let mut start = 0;
+        for s in bytes.split(|&b| b == 0) {
+            if let Some((i, b)) = alphabet.first_invalid(s) {
+                return Err(OneError::InvalidCharacter(format!(
+                    "'{}' at position {} of a '{}' list",
+                    b.escape_ascii(),
+                    start + i,
+                    line_type
+                )));
+            }
+            start += s.len() + 1;
+        }
+        Ok(())
+    }
+
+    /// List type of `line_type` in the file schema, None if it has no list
+    fn list_type(&self, line_type: char) -> Option<OneType> {
+        unsafe {
+            let info = *(*self.ptr).info.get(line_type as usize)?;
+            if info.is_null() || (*info).listEltSize == 0 {
+                return None;
+            }
+            Some(*(*info).fieldType.add((*info).listField as usize))
+        }
+    }
+
+    /// Format of REAL and REAL_LIST values on lines written from now on
+    ///
+    /// Only affects ASCII output, e.g. `RealFormat::Fixed(4)` keeps identity
+    /// columns stable and short between pipeline versions. Applies to all
+    /// thread handles of a parallel writer.
+    pub fn set_real_format(&mut self, format: RealFormat) {
+        self.real_format = format;
+    }
+
+    /// The line type with the longest list recorded in the header, and that length
+    ///
+    /// ASCII files written by ONElib record no counts in their header.
+    fn longest_list(&self) -> Option<(char, i64)> {
+        (b'A'..=b'z')
+            .filter(u8::is_ascii_alphabetic)
+            .filter_map(|t| Some((t as char, self.stats(t as char).ok()?.1)))
+            .max_by_key(|&(_, max)| max)
+            .filter(|&(_, max)| max > 0)
+    }
+
+    fn check_ascii_list_len(&self, line_type: char, len: usize) -> Result<()> {
+        if !self.is_binary() && len as i64 > self.ascii_list_limit {
+            return Err(OneError::ListTooLong(format!(
+                "{} elements on an ASCII '{}' line, limit {}",
+                len, line_type, self.ascii_list_limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that this is a writable file whose schema gives `line_type` a list of type `list_type`
+    fn check_list_type(&self, line_type: char, list_type: OneType) -> Result<()> {
+        unsafe {
+            if !(*self.ptr).isWrite {
+                return Err(OneError::Other("File is not open for writing".to_string()));
+            }
+            let info = if line_type.is_ascii() {
+                (*self.ptr).info[line_type as usize]
+            } else {
+                ptr::null_mut()
+            };
+            if info.is_null() {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' is not defined in the file schema",
+                    line_type
+                )));
+            }
+            let actual = if (*info).listEltSize > 0 {
+                Some(*(*info).fieldType.add((*info).listField as usize))
+            } else {
+                None
+            };
+            if actual != Some(list_type) {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' has list type {:?}, not {:?}",
+                    line_type, actual, list_type
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a line with a list the C library only reads
+    ///
+    /// ONElib copies list data before modifying it (e.g. when compacting
+    /// integer lists), so passing a shared buffer is sound.
+    fn write_list(&mut self, line_type: char, len: usize, data: *const u8) -> Result<()> {
+        self.check_ascii_list_len(line_type, len)?;
+        let buf = if len == 0 { ptr::null_mut() } else { data as *mut std::ffi::c_void };
+        self.write_c_line(line_type, len as i64, buf);
+        Ok(())
+    }
+
+    /// Write a line whose fields are set, with its list in `list_buf` (null
+    /// for the line type's buffer)
+    ///
+    /// In an ASCII file with a `RealFormat` other than the default, lines
+    /// with REAL values are formatted here and ONElib only keeps the counts.
+    pub(crate) fn write_c_line(
+        &mut self,
+        line_type: char,
+        list_len: i64,
+        list_buf: *mut std::ffi::c_void,
+    ) {
+        self.start_line(line_type);
+        unsafe {
+            if self.real_format != RealFormat::Default && !self.is_binary() {
+                if let Some(text) = self.ascii_fields(line_type, list_len, list_buf) {
+                    let text_ptr = text.as_ptr() as *const c_char;
+                    let (t, len) = (line_type as c_char, text.len() as i64);
+                    ffi::oneWriteLineAscii(self.ptr, t, list_len, list_buf, text_ptr, len);
+                    return;
+                }
+            }
+            ffi::oneWriteLine(self.ptr, line_type as c_char, list_len, list_buf);
+        }
+    }
+
+    /// The text ONElib writes after the line type of an ASCII line, but with
+    /// REAL values in `self.real_format`; None if the line has no REAL values
+    unsafe fn ascii_fields(
+        &self,
+        line_type: char,
+        list_len: i64,
+        list_buf: *mut std::ffi::c_void,
+    ) -> Option<Vec<u8>> {
+        use std::io::Write;
+
+        let info = *(*self.ptr).info.get(line_type as usize)?;
+        if info.is_null() {
+            return None;
+        }
+        let types = std::slice::from_raw_parts((*info).fieldType, (*info).nField as usize);
+        if !types.iter().any(|t| matches!(t, OneType::oneREAL | OneType::oneREAL_LIST)) {
+            return None;
+        }
+        let list = if list_buf.is_null() { (*info).buffer } else { list_buf };
+        let len = list_len.max(0) as usize;
+        let mut text = Vec::new();
+        for (i, field_type) in types.iter().enumerate() {
+            let field = *(*self.ptr).field.add(i);
+            // Writes to a Vec cannot fail
+            let _ = match field_type {
+                OneType::oneINT => write!(text, " {}", field.i),
+                OneType::oneREAL => write!(text, " {}", self.real_format.format(field.r)),
+                OneType::oneCHAR => text.write_all(&[b' ', field.c as u8]),
+                // Even an empty string is preceded by a space
+                OneType::oneSTRING | OneType::oneDNA => write!(text, " {} ", list_len),
+                _ => write!(text, " {}", list_len),
+            };
+            // An empty list may have no buffer
+            if len == 0 {
+                continue;
+            }
+            match field_type {
+                OneType::oneSTRING | OneType::oneDNA => {
+                    // Printed with %.*s, so up to any NUL
+                    let bytes = std::slice::from_raw_parts(list as *const u8, len);
+                    let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+                    text.extend_from_slice(&bytes[..end]);
+                }
+                OneType::oneINT_LIST => {
+                    for value in std::slice::from_raw_parts(list as *const i64, len) {
+                        let _ = write!(text, " {}", value);
+                    }
+                }
+                OneType::oneREAL_LIST => {
+                    for &value in std::slice::from_raw_parts(list as *const f64, len) {
+                        let _ = write!(text, " {}", self.real_format.format(value));
+                    }
+                }
+                OneType::oneSTRING_LIST => {
+                    let mut s = list as *const c_char;
+                    for _ in 0..len {
+                        let bytes = CStr::from_ptr(s).to_bytes();
+                        let _ = write!(text, " {} ", bytes.len());
+                        text.extend_from_slice(bytes);
+                        s = s.add(bytes.len() + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(text)
+    }
+
+    /// Forget the last sequence when an object line starts a new object,
+    /// so per-base lists are never checked against another object's DNA
+    fn start_line(&mut self, line_type: char) {
+        if line_type.is_ascii() && crate::lines::is_object(self, line_type) {
+            self.last_dna_len = None;
+        }
+    }
+
+    /// Write a comment to the current line
+    pub fn write_comment(&mut self, comment: &str) -> Result<()> {
+        let c_comment = CString::new(comment)?;
+        unsafe {
+            // We need to use a format string for the variadic function
+            let format = CString::new("%s")?;
+            ffi::oneWriteComment(self.ptr, format.as_ptr() as *mut i8, c_comment.as_ptr());
         }
+        Ok(())
     }
 
-    /// Create a new ONE file for writing
+    /// Copy the current line to another file
     ///
-    /// # Arguments
+    /// Transfers the fields, list data and any comment of the line most recently
+    /// read into `writer`, like `oneWriteLineFrom()` in C. The line type need not
+    /// be known to the caller, so copy pipelines built on `open_write_from()`
+    /// pass through every line type declared in the source schema. Only a
+    /// shared borrow of the source is needed, so a filter can decide on and
+    /// copy a line from a `&OneFile`, such as a thread handle of `par_objects()`.
     ///
-    /// * `path` - Path where the file will be written
-    /// * `schema` - Schema defining the file format
-    /// * `file_type` - Primary file type
-    /// * `is_binary` - Whether to write in binary format (true) or ASCII (false)
-    /// * `nthreads` - Number of threads for parallel writing (1 for single-threaded)
-    pub fn open_write_new(
-        path: &str,
-        schema: &OneSchema,
-        file_type: &str,
-        is_binary: bool,
-        nthreads: i32,
-    ) -> Result<Self> {
-        let c_path = CString::new(path)?;
-        let c_type = CString::new(file_type)?;
+    /// Fails with `SchemaError` if `writer` does not declare the line type with
+    /// the same field types.
+    pub fn copy_line_to(&self, writer: &mut OneFile) -> Result<()> {
+        self.copy_line(writer)
+    }
 
-        unsafe {
-            let ptr = ffi::oneFileOpenWriteNew(
-                c_path.as_ptr(),
-                schema.as_ptr(),
-                c_type.as_ptr(),
-                is_binary,
-                nthreads,
-            );
-            if ptr.is_null() {
-                let err_str = ffi::oneErrorString();
-                let err_msg = if !err_str.is_null() {
-                    CStr::from_ptr(err_str).to_string_lossy().into_owned()
-                } else {
-                    "Unknown error".to_string()
-                };
-                let msg = if err_msg.trim().is_empty() {
-                    path.to_string()
-                } else {
-                    format!("{}: {}", path, err_msg)
-                };
-                return Err(OneError::OpenFailed(msg));
-            }
-            Ok(OneFile {
-                ptr,
-                is_owned: true,
-            })
-        }
+    /// Copy the current line to another file, see `copy_line_to()`
+    #[deprecated(note = "use copy_line_to(), which needs only a shared borrow of the source")]
+    pub fn copy_current_line(&self, dest: &mut OneFile) -> Result<()> {
+        self.copy_line(dest)
     }
 
-    /// Create a new ONE file for writing based on an existing file
+    /// Copy the current object, with the data lines that belong to it, to another file
     ///
-    /// Schema and header information is inherited from the source file.
-    pub fn open_write_from(
-        path: &str,
-        source: &OneFile,
-        is_binary: bool,
-        nthreads: i32,
-    ) -> Result<Self> {
-        let c_path = CString::new(path)?;
+    /// Must be called just after reading an object line from a binary file.
+    /// Copies that line and every following line belonging to the object,
+    /// whose extent is found from the object indexes, so a nested object type
+    /// such as the contigs of a scaffold ends the copy.
+    ///
+    /// Into a binary file written by one thread the lines are copied as
+    /// bytes, without decoding their lists, when `dest` declares each line
+    /// type as this file does and has the codecs of its compressed lists, or
+    /// none trained yet, in which case it takes this file's. Otherwise each
+    /// line is copied as by `copy_line_to()`, its lists decoded and encoded
+    /// again with the destination's codecs.
+    ///
+    /// After the call the next `read_line()` returns the first line after the object.
+    pub fn copy_raw_object_to(&mut self, dest: &mut OneFile) -> Result<()> {
+        if !self.is_binary() {
+            return Err(OneError::Other(
+                "Object copy needs a binary source file, whose index delimits objects".to_string(),
+            ));
+        }
 
-        unsafe {
-            let ptr =
-                ffi::oneFileOpenWriteFrom(c_path.as_ptr(), source.ptr, is_binary, nthreads);
-            if ptr.is_null() {
-                let err_str = ffi::oneErrorString();
-                let err_msg = if !err_str.is_null() {
-                    CStr::from_ptr(err_str).to_string_lossy().into_owned()
-                } else {
-                    "Unknown error".to_string()
-                };
-                let msg = if err_msg.trim().is_empty() {
-                    path.to_string()
-                } else {
-                    format!("{}: {}", path, err_msg)
-                };
-                return Err(OneError::OpenFailed(msg));
+        let (line_type, start, end) = unsafe {
+            let line_type = (*self.ptr).lineType as u8;
+            let info = (*self.ptr).info[line_type as usize];
+            if info.is_null() || !(*info).isObject || (*info).index.is_null() {
+                return Err(OneError::Other(
+                    "Current line is not an indexed object line".to_string(),
+                ));
             }
-            Ok(OneFile {
-                ptr,
-                is_owned: true,
-            })
+            let start = *(*info).index.add((*info).accum.count as usize);
+            (line_type as char, start, self.object_end(line_type, start))
+        };
+
+        if dest.is_binary() {
+            let end = end.unwrap_or(-1);
+            if unsafe { ffi::oneCopyObjectRaw(dest.ptr, self.ptr, start, end) } {
+                dest.start_line(line_type);
+                return Ok(());
+            }
+        }
+
+        self.copy_line(dest)?;
+        loop {
+            let position = self.get_current_byte_position();
+            if end.is_some_and(|end| position >= end) {
+                break;
+            }
+            if self.read_raw_line() == '\0' {
+                // Back onto the end-of-data marker, so that the next read
+                // meets it again rather than running into the footer
+                self.seek_to_byte_offset(position)?;
+                break;
+            }
+            self.copy_line(dest)?;
         }
+        Ok(())
     }
 
-    /// Read the next line from the file
-    ///
-    /// Returns the line type character, or 0 if at end of file.
-    pub fn read_line(&mut self) -> char {
-        unsafe { ffi::oneReadLine(self.ptr) as u8 as char }
+    /// The indexed object type with the most objects among those no other
+    /// object type contains, which splits the whole file into slices
+    pub(crate) fn top_level_object_type(&self) -> Option<char> {
+        let info = |t: usize| unsafe { (*self.ptr).info[t] };
+        let objects: Vec<usize> = (0..128)
+            .filter(|&t| !info(t).is_null() && unsafe { (*info(t)).isObject })
+            .collect();
+        objects
+            .iter()
+            .filter(|&&t| !unsafe { (*info(t)).index }.is_null())
+            .filter(|&&t| objects.iter().all(|&u| u == t || !unsafe { (*info(u)).contains[t] }))
+            .max_by_key(|&&t| unsafe { (*info(t)).given.count })
+            .map(|&t| t as u8 as char)
     }
 
-    /// Read comment text from the current line
+    /// Copy the object statistics of `source` into this file's header
     ///
-    /// Returns None if there is no comment.
-    pub fn read_comment(&mut self) -> Option<String> {
+    /// A binary file gathers them as it is written, but an ASCII file only
+    /// writes those it was given, as ONEview does when converting.
+    pub(crate) fn inherit_object_stats(&mut self, source: &OneFile) {
         unsafe {
-            let comment_ptr = ffi::oneReadComment(self.ptr);
-            if comment_ptr.is_null() {
-                None
-            } else {
-                Some(CStr::from_ptr(comment_ptr).to_string_lossy().into_owned())
+            for t in 0..128 {
+                let (from, to) = ((*source.ptr).info[t], (*self.ptr).info[t]);
+                if from.is_null() || to.is_null() || (*from).stats.is_null() {
+                    continue;
+                }
+                let mut n = 1;
+                while (*(*from).stats.add(n - 1)).type_ != 0 {
+                    n += 1;
+                }
+                let size = std::mem::size_of::<ffi::OneStat>();
+                let stats = libc::calloc(n, size) as *mut ffi::OneStat;
+                if stats.is_null() {
+                    continue;
+                }
+                ptr::copy_nonoverlapping((*from).stats, stats, n);
+                if !(*to).stats.is_null() {
+                    libc::free((*to).stats as *mut libc::c_void);
+                }
+                (*to).stats = stats;
             }
         }
     }
 
-    /// Write a line to the file
-    ///
-    /// # Arguments
+    /// Byte offset where the object of type `line_type` starting at `start` ends
     ///
-    /// * `line_type` - The line type character
-    /// * `list_len` - Length of the list (0 if no list)
-    /// * `list_buf` - Buffer containing list data (None to use internal buffer)
-    pub fn write_line(&mut self, line_type: char, list_len: i64, list_buf: Option<*mut std::ffi::c_void>) {
+    /// This is the first indexed object start after `start` among the object types
+    /// not contained in `line_type`, or None if the object runs to the end of the data.
+    fn object_end(&self, line_type: u8, start: i64) -> Option<i64> {
+        let mut end: Option<i64> = None;
         unsafe {
-            ffi::oneWriteLine(
-                self.ptr,
-                line_type as i8,
-                list_len,
-                list_buf.unwrap_or(ptr::null_mut()),
-            );
+            let info = (*self.ptr).info[line_type as usize];
+            for u in 0..128usize {
+                let ui = (*self.ptr).info[u];
+                if ui.is_null() || !(*ui).isObject || (*ui).index.is_null() {
+                    continue;
+                }
+                if u != line_type as usize && (*info).contains[u] {
+                    continue;
+                }
+                let index = std::slice::from_raw_parts((*ui).index, (*ui).given.count as usize + 1);
+                let next = index[1..].partition_point(|&b| b <= start) + 1;
+                if next < index.len() {
+                    end = Some(end.map_or(index[next], |e| e.min(index[next])));
+                }
+            }
         }
+        end
     }
 
-    /// Write a comment to the current line
-    pub fn write_comment(&mut self, comment: &str) -> Result<()> {
-        let c_comment = CString::new(comment)?;
+    /// Check that this file declares `line_type` with `field_types`, and lists
+    /// of `list_elt_size` bytes per element, before a line is copied into it
+    ///
+    /// ONElib reads a list by the element size of the line type it writes,
+    /// so a list copied into a line type with wider elements, e.g. a STRING
+    /// into an INT_LIST, would be read past its end.
+    fn check_copied_layout(
+        &self,
+        line_type: char,
+        field_types: &[OneType],
+        list_elt_size: i32,
+    ) -> Result<()> {
+        let compatible = line_type.is_ascii()
+            && unsafe {
+                let info = (*self.ptr).info[line_type as usize];
+                !info.is_null()
+                    && (*info).nField as usize == field_types.len()
+                    && (*info).listEltSize == list_elt_size
+                    && field_types.iter().enumerate().all(|(i, &t)| *(*info).fieldType.add(i) == t)
+            };
+        if !compatible {
+            return Err(OneError::SchemaError(format!(
+                "Line type '{}' is not declared compatibly in the destination file",
+                line_type
+            )));
+        }
+        Ok(())
+    }
+
+    /// Write the current line to `dest`
+    fn copy_line(&self, dest: &mut OneFile) -> Result<()> {
         unsafe {
-            // We need to use a format string for the variadic function
-            let format = CString::new("%s")?;
-            ffi::oneWriteComment(self.ptr, format.as_ptr() as *mut i8, c_comment.as_ptr());
+            let line_type = (*self.ptr).lineType;
+            let info = (*self.ptr).info[line_type as u8 as usize];
+            if line_type == 0 || info.is_null() {
+                return Err(OneError::Other("No current line to copy".to_string()));
+            }
+            let field_types: Vec<OneType> =
+                (0..(*info).nField as usize).map(|i| *(*info).fieldType.add(i)).collect();
+            dest.check_copied_layout(line_type as u8 as char, &field_types, (*info).listEltSize)?;
+
+            ptr::copy_nonoverlapping((*self.ptr).field, (*dest.ptr).field, (*info).nField as usize);
+
+            let list_len = if (*info).listEltSize == 0 { 0 } else { self.len() };
+            let list = if list_len == 0 { ptr::null_mut() } else { self.list_ptr() };
+            dest.write_c_line(line_type as u8 as char, list_len, list);
+        }
+
+        if let Some(comment) = self.read_comment() {
+            dest.write_comment(&comment)?;
+        }
+        Ok(())
+    }
+
+    /// Write a line snapshot, as `copy_line()` writes the current line
+    pub(crate) fn write_saved_line(&mut self, line: &Line) -> Result<()> {
+        self.check_copied_layout(line.line_type, &line.field_types, line.list.elt_size())?;
+        let list_type = unsafe {
+            let info = (*self.ptr).info[line.line_type as usize];
+            let fields = line.fields.as_ptr() as *const ffi::OneField;
+            ptr::copy_nonoverlapping(fields, (*self.ptr).field, line.fields.len());
+            if (*info).listEltSize == 0 {
+                None
+            } else {
+                Some(*(*info).fieldType.add((*info).listField as usize))
+            }
+        };
+        let (len, data) = match &line.list {
+            List::None => (0, ptr::null()),
+            List::Ints(values) => (values.len(), values.as_ptr() as *const u8),
+            List::Reals(values) => (values.len(), values.as_ptr() as *const u8),
+            // STRING_LIST lengths count the strings, each ending in a NUL
+            List::Bytes(bytes) if list_type == Some(OneType::oneSTRING_LIST) => {
+                (bytes.iter().filter(|&&b| b == 0).count(), bytes.as_ptr())
+            }
+            List::Bytes(bytes) => (bytes.len(), bytes.as_ptr()),
+        };
+        self.write_list(line.line_type, len, data)?;
+        if let Some(comment) = &line.comment {
+            self.write_comment(comment)?;
         }
         Ok(())
     }
@@ -251,12 +1817,147 @@ impl OneFile {
         unsafe { ffi::oneInheritReference(self.ptr, source.ptr) }
     }
 
+    /// Drop the provenance and reference lines that repeat an earlier one,
+    /// as when the headers of files with a common history are inherited
+    pub(crate) fn dedup_header(&mut self) {
+        unsafe {
+            let info = (*self.ptr).info['!' as usize];
+            if !info.is_null() && !(*self.ptr).provenance.is_null() {
+                let entries = (*self.ptr).provenance;
+                (*info).accum.count = dedup_entries(
+                    entries,
+                    (*info).accum.count as usize,
+                    |&p| OneProvenance::from(p),
+                    |p| {
+                        for s in [p.program, p.version, p.command, p.date] {
+                            libc::free(s as *mut libc::c_void);
+                        }
+                    },
+                ) as i64;
+            }
+            let info = (*self.ptr).info['<' as usize];
+            if !info.is_null() && !(*self.ptr).reference.is_null() {
+                let entries = (*self.ptr).reference;
+                (*info).accum.count = dedup_entries(
+                    entries,
+                    (*info).accum.count as usize,
+                    |&r| OneReference::from(r),
+                    |r| libc::free(r.filename as *mut libc::c_void),
+                ) as i64;
+            }
+        }
+    }
+
+    /// Add a line of free text to the file header, written as a '.' line
+    ///
+    /// Must be called before the first write_line(): fails once the header
+    /// has been written, or if `text` holds a newline. `open_write_from()`
+    /// copies the header text of its source.
+    pub fn add_header_text(&mut self, text: &str) -> Result<()> {
+        self.check_header_text(text)?;
+        let c_text = CString::new(text)?;
+        unsafe {
+            // ONElib frees the list with free() when the file is closed
+            let size = std::mem::size_of::<ffi::OneHeaderText>();
+            let t = libc::calloc(1, size) as *mut ffi::OneHeaderText;
+            if t.is_null() {
+                return Err(OneError::Other("Out of memory for header text".to_string()));
+            }
+            (*t).text = libc::strdup(c_text.as_ptr());
+            let mut next = &mut (*self.ptr).headerText;
+            while !next.is_null() {
+                next = &mut (**next).nxt;
+            }
+            *next = t;
+        }
+        Ok(())
+    }
+
+    /// The '.' header text lines of the file, in order
+    pub fn header_text(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        unsafe {
+            let mut t = (*self.ptr).headerText;
+            while !t.is_null() {
+                if !(*t).text.is_null() {
+                    lines.push(CStr::from_ptr((*t).text).to_string_lossy().into_owned());
+                }
+                t = (*t).nxt;
+            }
+        }
+        lines
+    }
+
+    /// Record `key=value` metadata in the file header, e.g. `sample=HG002`
+    ///
+    /// Metadata are header text lines of that form, so ONEview shows them
+    /// and `open_write_from()` carries them over. Setting a key again
+    /// replaces its value. Keys must be non-empty and hold neither '=' nor
+    /// whitespace; as for `add_header_text()`, this must be called before
+    /// the first write_line().
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<()> {
+        if key.is_empty() || key.contains(|c: char| c == '=' || c.is_whitespace()) {
+            return Err(OneError::Other(format!("Invalid metadata key '{}'", key)));
+        }
+        let text = format!("{}={}", key, value);
+        self.check_header_text(&text)?;
+        unsafe {
+            let mut t = (*self.ptr).headerText;
+            while !t.is_null() {
+                let line = CStr::from_ptr((*t).text).to_string_lossy();
+                if parse_metadata(&line).is_some_and(|(k, _)| k == key) {
+                    let c_text = CString::new(text)?;
+                    libc::free((*t).text as *mut libc::c_void);
+                    (*t).text = libc::strdup(c_text.as_ptr());
+                    return Ok(());
+                }
+                t = (*t).nxt;
+            }
+        }
+        self.add_header_text(&text)
+    }
+
+    /// The `key=value` metadata of the file header, in order
+    ///
+    /// Header text lines of any other form are left out; see `header_text()`.
+    /// For the size and descriptor of the file itself see `file_metadata()`.
+    pub fn metadata(&self) -> Vec<(String, String)> {
+        self.header_text()
+            .iter()
+            .filter_map(|line| parse_metadata(line))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// The value of metadata `key`, if the header has it
+    pub fn metadata_value(&self, key: &str) -> Option<String> {
+        self.metadata().into_iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn check_header_text(&self, text: &str) -> Result<()> {
+        unsafe {
+            if !(*self.ptr).isWrite || (*self.ptr).isHeaderOut {
+                return Err(OneError::Other(
+                    "Header text can only be added to a file being written, before its first line"
+                        .to_string(),
+                ));
+            }
+        }
+        if text.contains(['\n', '\r']) {
+            return Err(OneError::Other("Header text cannot hold a line break".to_string()));
+        }
+        Ok(())
+    }
+
     /// Get statistics for a line type
     ///
     /// Returns (count, max, total) where:
     /// - count: number of lines of this type
     /// - max: maximum list length
     /// - total: total list length
+    ///
+    /// Line types declared in the schema but absent from the data report zeros;
+    /// line types the schema does not declare are an error.
     pub fn stats(&self, line_type: char) -> Result<(i64, i64, i64)> {
         let mut count: i64 = 0;
         let mut max: i64 = 0;
@@ -281,13 +1982,60 @@ impl OneFile {
         Ok((count, max, total))
     }
 
+    /// Statistics of every line type the schema declares, in ASCII order
+    ///
+    /// Covers the types without any lines too, so a summary like ONEstat's
+    /// needs no knowledge of the schema. Header line types are left out.
+    pub fn all_stats(&self) -> Vec<LineStats> {
+        (b'A'..=b'z')
+            .filter(|t| t.is_ascii_alphabetic())
+            .filter_map(|t| {
+                let info = unsafe { (*self.ptr).info[t as usize] };
+                if info.is_null() {
+                    return None;
+                }
+                let (count, max, total) = self.stats(t as char).ok()?;
+                Some(LineStats {
+                    line_type: t as char,
+                    is_object: unsafe { (*info).isObject },
+                    is_list: unsafe { (*info).listEltSize } > 0,
+                    counts: OneCounts { count, max, total },
+                })
+            })
+            .collect()
+    }
+
     /// Navigate to a specific object in the file
     ///
     /// Only works on binary files with an index. The first object is numbered 1.
     /// Setting i == 0 goes to the start of the data.
+    ///
+    /// Fails with `OneError::NoSuchLineType` if the schema does not declare
+    /// `line_type`, and with `OneError::NoObjects` if the file has none of
+    /// its objects, e.g. 'g' groups in an alignment file without skeletons.
     pub fn goto(&mut self, line_type: char, index: i64) -> Result<()> {
+        let Ok((count, _, _)) = self.stats(line_type) else {
+            return Err(OneError::NoSuchLineType(format!(
+                "'{}' is not a line type of {}",
+                line_type,
+                self.file_name().unwrap_or_default()
+            )));
+        };
+        if self.is_binary() && count == 0 {
+            return Err(OneError::NoObjects(format!(
+                "{} has no '{}' objects",
+                self.file_name().unwrap_or_default(),
+                line_type
+            )));
+        }
         unsafe {
             let success = ffi::oneGoto(self.ptr, line_type as i8, index);
+            if !success && !self.is_seekable() {
+                return Err(OneError::Other(format!(
+                    "Failed to goto object {} of type '{}': input is not seekable",
+                    index, line_type
+                )));
+            }
             if !success {
                 return Err(OneError::Other(format!(
                     "Failed to goto object {} of type '{}'",
@@ -298,11 +2046,206 @@ impl OneFile {
         Ok(())
     }
 
+    /// Whether the file holds objects of `line_type`
+    ///
+    /// False for line types the schema does not declare. Read from the header
+    /// counts, so also false for ASCII files written without them; for files
+    /// being written, counts the objects written so far.
+    pub fn has_objects(&self, line_type: char) -> bool {
+        self.stats(line_type).is_ok_and(|(count, _, _)| count > 0)
+    }
+
     /// Get the current line type
     pub fn line_type(&self) -> char {
         unsafe { (*self.ptr).lineType as u8 as char }
     }
 
+    /// Check whether the file is in binary format
+    ///
+    /// Only binary files carry the object index needed by `goto()`.
+    pub fn is_binary(&self) -> bool {
+        unsafe { (*self.ptr).isBinary }
+    }
+
+    /// Check whether the file is open for writing rather than reading
+    pub fn is_write(&self) -> bool {
+        unsafe { (*self.ptr).isWrite }
+    }
+
+    /// Check whether the underlying file supports seeking
+    ///
+    /// False for pipes, e.g. standard input from another program, which can
+    /// only be read in order: `goto()` and the helpers built on it then fail.
+    pub fn is_seekable(&self) -> bool {
+        unsafe {
+            let f = (*self.ptr).f as *mut libc::FILE;
+            !f.is_null() && fd_is_seekable(libc::fileno(f))
+        }
+    }
+
+    /// Size, modification time and descriptor of the underlying file
+    ///
+    /// Taken from the open descriptor rather than the path, so it describes
+    /// the file actually being read even if the path has since been replaced:
+    /// the temporary file of an in-memory or atomically written file, or the
+    /// pipe of standard input. The descriptor is for advice and locking, e.g.
+    /// `posix_fadvise()` or `flock()`; reading, writing or seeking through it
+    /// would pull it from under ONElib's buffering. Files opened with several
+    /// threads share one descriptor per thread, of which this is the first.
+    pub fn file_metadata(&self) -> Result<FileMetadata> {
+        let path = self.file_name().unwrap_or_default();
+        let f = unsafe { (*self.ptr).f } as *mut libc::FILE;
+        if f.is_null() {
+            return Err(OneError::NullPointer);
+        }
+        #[cfg(unix)]
+        let (metadata, fd) = {
+            use std::os::unix::io::FromRawFd;
+            let fd = unsafe { libc::fileno(f) };
+            // Borrow the descriptor as a File without taking ownership of it
+            let file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+            (file.metadata(), fd)
+        };
+        #[cfg(not(unix))]
+        let metadata = std::fs::metadata(&path);
+        let metadata = metadata.map_err(|e| OneError::io(&path, &e))?;
+        Ok(FileMetadata {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            is_file: metadata.is_file(),
+            #[cfg(unix)]
+            fd,
+        })
+    }
+
+    /// Number of thread handles the file was opened with
+    ///
+    /// This is the `nthreads` passed to `open_read()` or one of the
+    /// `open_write_*()` constructors, or 1 if it was not greater than one.
+    pub fn nthreads(&self) -> usize {
+        unsafe { (*self.ptr).share.max(1) as usize }
+    }
+
+    /// Check whether the file was opened with more than one thread handle
+    pub fn is_threaded(&self) -> bool {
+        self.nthreads() > 1
+    }
+
+    /// Objects of type `line_type` assigned to thread slice `slice`
+    ///
+    /// Objects are divided into `nthreads()` contiguous runs of near-equal size,
+    /// the partitioning the ONEcode tools use for parallel reading. The range
+    /// holds object numbers as taken by `goto()`, starting at 1, so a worker
+    /// processes its slice with `goto(line_type, range.start)` followed by
+    /// `range.end - range.start` objects. Slices may be empty when there are
+    /// fewer objects than threads.
+    pub fn slice_range(&self, line_type: char, slice: usize) -> Result<Range<i64>> {
+        let nthreads = self.nthreads();
+        if slice >= nthreads {
+            return Err(OneError::Other(format!(
+                "Slice {} out of range ({} threads)",
+                slice, nthreads
+            )));
+        }
+        let (count, _, _) = self.stats(line_type)?;
+        let start = count * slice as i64 / nthreads as i64;
+        let end = count * (slice + 1) as i64 / nthreads as i64;
+        Ok(start + 1..end + 1)
+    }
+
+    /// Object ranges of type `line_type` for every thread slice, see `slice_range()`
+    pub fn slice_ranges(&self, line_type: char) -> Result<Vec<Range<i64>>> {
+        (0..self.nthreads())
+            .map(|slice| self.slice_range(line_type, slice))
+            .collect()
+    }
+
+    /// The per-thread handles of a file opened with more than one thread
+    ///
+    /// ONElib opens `nthreads()` handles, of which handle 0 is this file.
+    /// When reading, each has its own position, so worker `i` can `goto()`
+    /// the start of `slice_range(line_type, i)` and read its slice. When
+    /// writing, each writes to its own temporary file, and closing this file
+    /// appends them in handle order. The handles borrow this file, so use
+    /// them from scoped threads; a file opened with one thread gives one.
+    ///
+    /// ```no_run
+    /// use onecode::OneFile;
+    ///
+    /// let mut file = OneFile::open_read("data.1aln", None, None, 4)?;
+    /// let ranges = file.slice_ranges('A')?;
+    /// std::thread::scope(|scope| {
+    ///     for (mut handle, range) in file.split_threads().into_iter().zip(ranges) {
+    ///         scope.spawn(move || {
+    ///             handle.goto('A', range.start)?;
+    ///             for _ in range {
+    ///                 handle.read_line();
+    ///                 // ... and the data lines up to the next 'A'
+    ///             }
+    ///             Ok::<(), onecode::OneError>(())
+    ///         });
+    ///     }
+    /// });
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    pub fn split_threads(&mut self) -> Vec<OneFileThread<'_>> {
+        (0..self.nthreads())
+            .map(|i| {
+                let mut file = OneFile::from_raw(unsafe { self.ptr.add(i) }, false);
+                file.ascii_list_limit = self.ascii_list_limit;
+                file.alphabets = self.alphabets.clone();
+                file.projection = self.projection;
+                file.real_format = self.real_format;
+                OneFileThread {
+                    file,
+                    index: i,
+                    _master: PhantomData,
+                }
+            })
+            .collect()
+    }
+
+    /// Call `f` on every object of type `line_type`, in parallel over the
+    /// per-thread handles (`rayon` feature)
+    ///
+    /// Each handle processes its own `slice_range()` on a rayon task, so the
+    /// work is split `nthreads()` ways: open the file with as many threads as
+    /// there are cores to use. `f` receives the handle, positioned on the
+    /// object line, and the object number; it may read the object's data
+    /// lines, and reading on up to the next object line saves a seek. The
+    /// results are returned in object order; the first error stops the
+    /// remaining work. Needs a binary file.
+    ///
+    /// ```no_run
+    /// use onecode::OneFile;
+    ///
+    /// let mut file = OneFile::open_read("data.1aln", None, None, 8)?;
+    /// let lengths = file.par_objects('A', |handle, _| Ok(handle.int(2) - handle.int(1)))?;
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_objects<T, F>(&mut self, line_type: char, f: F) -> Result<Vec<T>>
+    where
+        T: Send,
+        F: Fn(&mut OneFileThread<'_>, i64) -> Result<T> + Sync,
+    {
+        use rayon::prelude::*;
+
+        if !self.is_binary() {
+            return Err(OneError::Other(
+                "Parallel object processing needs a binary file with an object index".to_string(),
+            ));
+        }
+        let ranges = self.slice_ranges(line_type)?;
+        let slices = self
+            .split_threads()
+            .into_par_iter()
+            .zip(ranges)
+            .map(|(mut handle, range)| handle.map_objects(line_type, range, &f))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(slices.into_iter().flatten().collect())
+    }
+
     /// Get the current line number
     pub fn line_number(&self) -> i64 {
         unsafe { (*self.ptr).line }
@@ -344,51 +2287,213 @@ impl OneFile {
         }
     }
 
+    /// Get the schema definition of this file as text
+    ///
+    /// This is the primary/sub type and the O/D/G definition lines in schema
+    /// file syntax, as written by `oneFileWriteSchema()`.
+    pub fn schema_text(&self) -> Result<String> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "onecode_schema_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let c_path = CString::new(path.to_string_lossy().as_ref())?;
+
+        let written = unsafe { ffi::oneFileWriteSchema(self.ptr, c_path.as_ptr() as *mut i8) };
+        if !written {
+            return Err(OneError::Other(format!(
+                "Failed to write schema to {}",
+                path.display()
+            )));
+        }
+        let text = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path).ok();
+        text.map_err(|e| OneError::Other(format!("Failed to read schema back: {}", e)))
+    }
+
+    /// Fingerprint of this file's schema, comparable with `OneSchema::fingerprint()`
+    pub fn schema_fingerprint(&self) -> Result<u64> {
+        Ok(crate::schema::fingerprint_text(&self.schema_text()?))
+    }
+
+    /// Check that this file conforms to `schema`, as `oneFileCheckSchema()`
+    ///
+    /// Line types the file lacks, or has no lines of, only count as
+    /// mismatches when `strict` is set; line types the schema lacks never do.
+    /// The error is a `OneError::SchemaMismatch` listing each line type
+    /// defined differently, or a `SchemaError` when the schema does not
+    /// cover the file's type at all.
+    pub fn check_schema(&self, schema: &OneSchema, strict: bool) -> Result<()> {
+        if unsafe { ffi::oneFileCheckSchema(self.ptr, schema.as_ptr(), strict) } {
+            return Ok(());
+        }
+        let c_error = unsafe {
+            let err_str = ffi::oneErrorString();
+            if err_str.is_null() {
+                "schema mismatch".to_string()
+            } else {
+                CStr::from_ptr(err_str).to_string_lossy().trim_end().to_string()
+            }
+        };
+        let file_type = self.file_type().unwrap_or_default();
+        let found = self.schema_text()?;
+        let mismatches: Vec<LineTypeMismatch> =
+            crate::schema::differences(schema.text(), &found, &file_type)
+                .into_iter()
+                .filter(|(t, expected, found)| {
+                    let in_file = if t.is_ascii() {
+                        unsafe { (*self.ptr).info[*t as usize] }
+                    } else {
+                        ptr::null_mut()
+                    };
+                    let has_lines = !in_file.is_null() && unsafe { (*in_file).given.count } > 0;
+                    expected != UNDEFINED && (strict || (found != UNDEFINED && has_lines))
+                })
+                .map(|(line_type, expected, found)| LineTypeMismatch {
+                    line_type,
+                    expected,
+                    found,
+                })
+                .collect();
+        if mismatches.is_empty() {
+            return Err(OneError::SchemaError(c_error));
+        }
+        Err(OneError::SchemaMismatch { path: None, mismatches })
+    }
+
     /// Get an integer field value
+    ///
+    /// Unchecked, as `oneInt()` in C: a field of another type is read as
+    /// if it were an INT, and a field beyond the file's widest line type
+    /// reads out of bounds. See `try_int()`, or build with the `checked`
+    /// feature to panic on such misuse instead.
     pub fn int(&self, field: usize) -> i64 {
-        unsafe {
-            let fields = (*self.ptr).field;
-            (*fields.add(field)).i
-        }
+        #[cfg(feature = "checked")]
+        self.expect_field(field, OneType::oneINT);
+        self.slot(field).as_int()
     }
 
-    /// Get a real field value
+    /// Get a real field value, unchecked as `int()`
     pub fn real(&self, field: usize) -> f64 {
-        unsafe {
-            let fields = (*self.ptr).field;
-            (*fields.add(field)).r
-        }
+        #[cfg(feature = "checked")]
+        self.expect_field(field, OneType::oneREAL);
+        self.slot(field).as_real()
     }
 
-    /// Get a character field value
+    /// Get a character field value, unchecked as `int()`
     pub fn char(&self, field: usize) -> char {
-        unsafe {
-            let fields = (*self.ptr).field;
-            (*fields.add(field)).c as u8 as char
+        #[cfg(feature = "checked")]
+        self.expect_field(field, OneType::oneCHAR);
+        self.slot(field).as_char()
+    }
+
+    /// Get an integer field value, checked against the current line's schema
+    ///
+    /// Fails with `OneError::FieldOutOfRange` if the current line has no
+    /// field `field`, and with `OneError::FieldTypeMismatch` if it is not an
+    /// INT.
+    pub fn try_int(&self, field: usize) -> Result<i64> {
+        Ok(self.checked_slot(field, OneType::oneINT)?.as_int())
+    }
+
+    /// Get a real field value, checked as `try_int()`
+    pub fn try_real(&self, field: usize) -> Result<f64> {
+        Ok(self.checked_slot(field, OneType::oneREAL)?.as_real())
+    }
+
+    /// Get a character field value, checked as `try_int()`
+    pub fn try_char(&self, field: usize) -> Result<char> {
+        Ok(self.checked_slot(field, OneType::oneCHAR)?.as_char())
+    }
+
+    fn checked_slot(&self, field: usize, expected: OneType) -> Result<&FieldSlot> {
+        let line_type = self.line_type();
+        let n_field = unsafe {
+            let info = (*self.ptr).info[line_type as u8 as usize];
+            if info.is_null() {
+                return Err(OneError::FieldOutOfRange(format!(
+                    "field {} read with no current line",
+                    field
+                )));
+            }
+            (*info).nField as usize
+        };
+        match self.field_type(field) {
+            Some(actual) if actual == expected => Ok(self.slot(field)),
+            Some(actual) => Err(OneError::FieldTypeMismatch(format!(
+                "field {} of a '{}' line is {}, not {}",
+                field,
+                line_type,
+                type_name(actual),
+                type_name(expected)
+            ))),
+            None => Err(OneError::FieldOutOfRange(format!(
+                "field {} of a '{}' line, which has {} fields",
+                field, line_type, n_field
+            ))),
+        }
+    }
+
+    #[cfg(feature = "checked")]
+    fn expect_field(&self, field: usize, expected: OneType) {
+        if let Err(err) = self.checked_slot(field, expected) {
+            panic!("{}", err);
         }
     }
 
     /// Set an integer field value
     pub fn set_int(&mut self, field: usize, value: i64) {
-        unsafe {
-            let fields = (*self.ptr).field;
-            (*fields.add(field)).i = value;
-        }
+        *self.slot_mut(field) = FieldSlot::from_int(value);
     }
 
     /// Set a real field value
     pub fn set_real(&mut self, field: usize, value: f64) {
-        unsafe {
-            let fields = (*self.ptr).field;
-            (*fields.add(field)).r = value;
-        }
+        *self.slot_mut(field) = FieldSlot::from_real(value);
     }
 
     /// Set a character field value
     pub fn set_char(&mut self, field: usize, value: char) {
+        *self.slot_mut(field) = FieldSlot::from_char(value);
+    }
+
+    /// Get a field of the current line without interpreting it
+    ///
+    /// Combine with `field_type()` to read it through the checked getters of
+    /// `FieldSlot`.
+    pub fn field(&self, field: usize) -> FieldSlot {
+        *self.slot(field)
+    }
+
+    /// All fields of the current line in schema order, decoded by type
+    ///
+    /// The list field, if any, is a `FieldValue::List` at its position, so
+    /// generic tools can print or pass on any line without knowing its
+    /// layout. Empty if there is no current line.
+    pub fn fields(&self) -> Vec<FieldValue> {
+        (0..)
+            .map_while(|i| {
+                Some(match self.field_type(i)? {
+                    OneType::oneINT => FieldValue::Int(self.slot(i).as_int()),
+                    OneType::oneREAL => FieldValue::Real(self.slot(i).as_real()),
+                    OneType::oneCHAR => FieldValue::Char(self.slot(i).as_char()),
+                    list_type => {
+                        let list = List::current(self, list_type);
+                        FieldValue::List(ListValue::from_list(list_type, list))
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Type of field `field` of the current line, or None if there is no such field
+    pub fn field_type(&self, field: usize) -> Option<OneType> {
         unsafe {
-            let fields = (*self.ptr).field;
-            (*fields.add(field)).c = value as i8;
+            let info = (*self.ptr).info[(*self.ptr).lineType as u8 as usize];
+            if info.is_null() || field >= (*info).nField as usize {
+                return None;
+            }
+            Some(*(*info).fieldType.add(field))
         }
     }
 
@@ -403,12 +2508,19 @@ impl OneFile {
             if info.is_null() {
                 return 0;
             }
-            let list_field = (*info).listField as usize;
-            let fields = (*self.ptr).field;
-            (*fields.add(list_field)).len & 0xffffffffffffffi64
+            self.slot((*info).listField as usize).as_list_len()
         }
     }
 
+    /// Field `field` of the file's field buffer, which holds `nFieldMax` fields
+    pub(crate) fn slot(&self, field: usize) -> &FieldSlot {
+        unsafe { &*((*self.ptr).field as *const FieldSlot).add(field) }
+    }
+
+    pub(crate) fn slot_mut(&mut self, field: usize) -> &mut FieldSlot {
+        unsafe { &mut *((*self.ptr).field as *mut FieldSlot).add(field) }
+    }
+
     /// Check if the list field is empty
     pub fn is_empty(&self) -> bool {
         self.len() == 0
@@ -416,25 +2528,59 @@ impl OneFile {
 
     /// Get a string from the current line
     ///
-    /// This corresponds to the `oneString()` macro in C.
-    /// Returns a reference to the string data.
-    pub fn string(&self) -> Option<&str> {
-        unsafe {
-            let ptr = ffi::_oneList(self.ptr) as *const i8;
-            if ptr.is_null() {
-                None
-            } else {
-                Some(CStr::from_ptr(ptr).to_str().unwrap_or(""))
+    /// This corresponds to the `oneString()` macro in C: the STRING list of
+    /// the line, or the first string of a STRING_LIST. Fails with
+    /// `OneError::FieldTypeMismatch` if the line has neither, and with
+    /// `OneError::InvalidUtf8` rather than losing data if the string is not
+    /// UTF-8; see `string_lossy()` and `bytes()`.
+    pub fn string(&self) -> Result<&str> {
+        Ok(std::str::from_utf8(self.string_bytes()?)?)
+    }
+
+    /// Get a string from the current line, with invalid UTF-8 replaced by U+FFFD
+    ///
+    /// As `string()`, but None if the line has no STRING or STRING_LIST.
+    pub fn string_lossy(&self) -> Option<Cow<'_, str>> {
+        self.string_bytes().ok().map(String::from_utf8_lossy)
+    }
+
+    /// Get the raw bytes of the current line's STRING, STRING_LIST or DNA list
+    ///
+    /// No UTF-8 is assumed, so names with arbitrary bytes survive. A
+    /// STRING_LIST comes as stored, each string followed by a NUL byte; DNA
+    /// as characters, see `dna_char()`. None for other lines.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self.list_type(self.line_type())? {
+            t @ (OneType::oneSTRING | OneType::oneSTRING_LIST | OneType::oneDNA) => {
+                Some(self.list_bytes(t))
+            }
+            _ => None,
+        }
+    }
+
+    fn string_bytes(&self) -> Result<&[u8]> {
+        let line_type = self.line_type();
+        match self.list_type(line_type) {
+            Some(OneType::oneSTRING) => Ok(self.list_bytes(OneType::oneSTRING)),
+            Some(OneType::oneSTRING_LIST) => {
+                let bytes = self.list_bytes(OneType::oneSTRING_LIST);
+                Ok(bytes.split(|&b| b == 0).next().unwrap_or_default())
             }
+            list_type => Err(OneError::FieldTypeMismatch(format!(
+                "a '{}' line has list type {}, not STRING",
+                line_type,
+                list_type.map_or("none", type_name)
+            ))),
         }
     }
 
     /// Get DNA sequence as characters from the current line
     ///
-    /// This corresponds to the `oneDNAchar()` macro in C.
+    /// This corresponds to the `oneDNAchar()` macro in C. Binary files store
+    /// DNA packed, so this unpacks it; see `dna_encoding()`.
     pub fn dna_char(&self) -> Option<&[u8]> {
         unsafe {
-            let ptr = ffi::_oneList(self.ptr) as *const u8;
+            let ptr = self.list_ptr() as *const u8;
             if ptr.is_null() {
                 None
             } else {
@@ -446,10 +2592,21 @@ impl OneFile {
 
     /// Get DNA sequence as 2-bit compressed data from the current line
     ///
-    /// This corresponds to the `oneDNA2bit()` macro in C.
-    pub fn dna_2bit(&self) -> Option<&[u8]> {
+    /// This corresponds to the `oneDNA2bit()` macro in C. ASCII files store
+    /// DNA as characters, so this packs it; see `dna_encoding()`. Packing
+    /// reuses the buffer the C library reads binary lists into, so this
+    /// borrows the file mutably: no list of the line can be held across it.
+    pub fn dna_2bit(&mut self) -> Option<&[u8]> {
         unsafe {
+            let decoded = (*self.ptr).nBits == 0;
             let ptr = ffi::_oneCompressedList(self.ptr) as *const u8;
+            #[cfg(feature = "debug-ffi")]
+            crate::debug_ffi::check_compressed(self.ptr);
+            if decoded {
+                // Packing marks the list as pending decoding, after which
+                // dna_char() would unpack it over the characters as read
+                (*self.ptr).nBits = 0;
+            }
             if ptr.is_null() {
                 None
             } else {
@@ -459,12 +2616,36 @@ impl OneFile {
         }
     }
 
+    /// How this file stores the DNA list of `line_type`
+    ///
+    /// Exporters can use this to pick `dna_2bit()` or `dna_char()`, whichever
+    /// needs no conversion. Returns None if `line_type` is not defined or has
+    /// no DNA list.
+    pub fn dna_encoding(&self, line_type: char) -> Option<DnaEncoding> {
+        unsafe {
+            let info = *(*self.ptr).info.get(line_type as usize)?;
+            if info.is_null()
+                || (*info).listEltSize == 0
+                || *(*info).fieldType.add((*info).listField as usize) != OneType::oneDNA
+            {
+                return None;
+            }
+            let packed =
+                self.is_binary() && (*info).isUseListCodec && (*info).listCodec == ffi::DNAcodec;
+            Some(if packed {
+                DnaEncoding::TwoBit
+            } else {
+                DnaEncoding::Chars
+            })
+        }
+    }
+
     /// Get an integer list from the current line
     ///
     /// This corresponds to the `oneIntList()` macro in C.
     pub fn int_list(&self) -> Option<&[i64]> {
         unsafe {
-            let ptr = ffi::_oneList(self.ptr) as *const i64;
+            let ptr = self.list_ptr() as *const i64;
             if ptr.is_null() {
                 None
             } else {
@@ -479,7 +2660,7 @@ impl OneFile {
     /// This corresponds to the `oneRealList()` macro in C.
     pub fn real_list(&self) -> Option<&[f64]> {
         unsafe {
-            let ptr = ffi::_oneList(self.ptr) as *const f64;
+            let ptr = self.list_ptr() as *const f64;
             if ptr.is_null() {
                 None
             } else {
@@ -489,6 +2670,44 @@ impl OneFile {
         }
     }
 
+    /// The bytes of the current line's STRING, DNA or STRING_LIST list,
+    /// the last with the terminators of its strings
+    pub(crate) fn list_bytes(&self, list_type: OneType) -> &[u8] {
+        let len = self.len() as usize;
+        let data = self.list_ptr() as *const u8;
+        if data.is_null() || len == 0 {
+            return &[];
+        }
+        unsafe {
+            if list_type == OneType::oneSTRING_LIST {
+                // len counts the strings; the data runs to the last terminator
+                let mut end = 0;
+                for _ in 0..len {
+                    while *data.add(end) != 0 {
+                        end += 1;
+                    }
+                    end += 1;
+                }
+                std::slice::from_raw_parts(data, end)
+            } else {
+                std::slice::from_raw_parts(data, len)
+            }
+        }
+    }
+
+    /// The decoded list buffer of the current line, as `_oneList()`
+    pub(crate) fn list_ptr(&self) -> *mut std::ffi::c_void {
+        let decode = || unsafe {
+            #[cfg(feature = "fastpath")]
+            crate::fastpath::decode_list(self.ptr);
+            ffi::_oneList(self.ptr)
+        };
+        #[cfg(feature = "debug-ffi")]
+        return unsafe { crate::debug_ffi::guard_decode(self.ptr, decode) };
+        #[cfg(not(feature = "debug-ffi"))]
+        decode()
+    }
+
     /// Get the next string in a string list
     ///
     /// This corresponds to the `oneNextString()` macro in C.
@@ -509,6 +2728,9 @@ impl OneFile {
     /// This corresponds to the `oneObject()` macro in C.
     /// Returns the count, or -1 if the line type doesn't exist.
     pub fn object(&self, line_type: char) -> i64 {
+        if !line_type.is_ascii() {
+            return -1;
+        }
         unsafe {
             let info = (*self.ptr).info[line_type as usize];
             if info.is_null() {
@@ -533,6 +2755,30 @@ impl OneFile {
         }
     }
 
+    /// Provenance records from the file header, oldest first
+    ///
+    /// Each `!` line records a program that produced or modified the file,
+    /// as added by `add_provenance()` or inherited with `inherit_provenance()`.
+    pub fn provenance(&self) -> Vec<OneProvenance> {
+        unsafe {
+            let info = (*self.ptr).info['!' as usize];
+            let prov = (*self.ptr).provenance;
+            if info.is_null() || prov.is_null() {
+                return Vec::new();
+            }
+            (0..(*info).accum.count as usize)
+                .map(|i| OneProvenance::from(*prov.add(i)))
+                .collect()
+        }
+    }
+
+    /// When the file was written: the date of its last provenance line
+    ///
+    /// None if the file has no provenance or the date does not parse.
+    pub fn created(&self) -> Option<Timestamp> {
+        self.provenance().last()?.timestamp()
+    }
+
     /// Get all references from the file header
     ///
     /// Returns a vector of (filename, count) tuples
@@ -567,6 +2813,80 @@ impl OneFile {
         references
     }
 
+    /// Counters of expensive lookups made on this file so far
+    ///
+    /// Useful for spotting accidental rescans of the embedded GDB, e.g. a
+    /// `sequence_name_lookups` count that grows with the number of alignments.
+    pub fn access_stats(&self) -> AccessStats {
+        self.access
+    }
+
+    /// Enable or disable the one-time slow-path warning (enabled by default)
+    pub fn set_slow_path_warnings(&mut self, enabled: bool) {
+        self.warn_slow_paths = enabled;
+    }
+
+    fn note_sequence_name_lookup(&mut self) {
+        self.access.sequence_name_lookups += 1;
+        if self.access.sequence_name_lookups == SLOW_PATH_WARN_THRESHOLD {
+            let lookups = self.access.sequence_name_lookups;
+            self.warnings.push(Warning::SlowPath { lookups });
+        }
+        if self.warn_slow_paths && self.access.sequence_name_lookups >= SLOW_PATH_WARN_THRESHOLD {
+            self.warn_slow_paths = false;
+            eprintln!(
+                "onecode: warning: get_sequence_name() called {} times on {}; each call rescans \
+                 the embedded GDB. Look names up with gdb_index() instead.",
+                self.access.sequence_name_lookups,
+                self.file_name().unwrap_or_default()
+            );
+        }
+    }
+
+    /// Contig id index of the skeleton in 'g' group 1 of an alignment file
+    ///
+    /// Resolves the contig ids of alignment records to scaffold names,
+    /// lengths and offsets in constant time. The index is built from a
+    /// second handle on the same file the first time it is asked for, so the
+    /// read position of this file is left alone, and cached for later calls.
+    /// Like `GdbReader::from_alignment()` this requires a binary file.
+    ///
+    /// ```no_run
+    /// use onecode::OneFile;
+    ///
+    /// let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+    /// while file.read_line() != '\0' {
+    ///     if file.line_type() == 'A' {
+    ///         let id = file.int(0);
+    ///         println!("{:?}", file.gdb_index()?.name(id));
+    ///     }
+    /// }
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    pub fn gdb_index(&mut self) -> Result<&GdbIndex> {
+        self.group_gdb_index(1)
+    }
+
+    /// Contig id index of the skeleton in 'g' group `group` (1-indexed)
+    ///
+    /// In a `.1aln` file group 2, if present, describes the target genome.
+    /// See `gdb_index()`.
+    pub fn group_gdb_index(&mut self, group: i64) -> Result<&GdbIndex> {
+        if !self.gdb_indexes.contains_key(&group) {
+            let path = self.file_name().ok_or(OneError::NullPointer)?;
+            let mut file = OneFile::open_read(&path, None, None, 1)?;
+            let gdb = GdbReader::from_alignment(&mut file, group)?;
+            for &i in gdb.duplicates() {
+                let name = gdb.scaffolds()[i].name.clone();
+                self.warnings.push(Warning::DuplicateName { group, name });
+            }
+            let index = GdbIndex::new(&gdb);
+            self.access.gdb_scans += 1;
+            self.gdb_indexes.insert(group, index);
+        }
+        Ok(&self.gdb_indexes[&group])
+    }
+
     /// Get the internal pointer (for advanced use with FFI)
     pub fn as_ptr(&self) -> *mut ffi::OneFile {
         self.ptr
@@ -577,20 +2897,30 @@ impl OneFile {
     /// This method maps a contig ID (as used in alignment records) to the name
     /// of the scaffold containing that contig.
     ///
+    /// Each call rescans the embedded GDB, so calling it for every alignment
+    /// is quadratic. After `SLOW_PATH_WARN_THRESHOLD` calls a one-time warning
+    /// is printed to stderr; use `gdb_index()` instead when looking up many
+    /// names.
+    ///
     /// # Arguments
     /// * `seq_id` - Contig ID from alignment record (0-indexed)
     ///
     /// # Returns
-    /// The scaffold name containing this contig, or None if not found
+    /// The scaffold name containing this contig, or None if not found or
+    /// not UTF-8
+    #[allow(clippy::collapsible_match)]
     pub fn get_sequence_name(&mut self, seq_id: i64) -> Option<String> {
+        self.note_sequence_name_lookup();
+
         // Save current position
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             // Navigate to the FIRST 'g' group object (objects are numbered starting at 1)
             if ffi::oneGoto(self.ptr, 'g' as i8, 1) {
                 let mut contig_id = 0i64;
-                let mut current_scaffold_name = String::new();
+                let mut current_scaffold_name = None;
                 let mut is_first_line = true;
                 
                 loop {
@@ -601,17 +2931,15 @@ impl OneFile {
                     
                     match line_type {
                         'S' => {
-                            // New scaffold - store its name
-                            if let Some(name) = self.string() {
-                                current_scaffold_name = name.to_string();
-                            }
+                            // New scaffold - store its name, if UTF-8
+                            current_scaffold_name = self.string().ok().map(str::to_string);
                         }
                         'C' => {
                             // Contig record - check if this is the one we're looking for
                             if contig_id == seq_id {
                                 // Restore position and return the scaffold name
                                 let _ = ffi::oneGoto(self.ptr, (*self.ptr).lineType, saved_line);
-                                return Some(current_scaffold_name);
+                                return current_scaffold_name;
                             }
                             contig_id += 1;
                         }
@@ -634,6 +2962,24 @@ impl OneFile {
         None
     }
 
+    /// Read the skeletons of all embedded GDB groups
+    ///
+    /// Returns one `GdbGroup` per 'g' group in file order: the scaffolds with
+    /// their names, lengths, contigs (ID, offset, length) and gaps. Like
+    /// `gdb_index()` this reads from a second handle on the file, leaving
+    /// the read position alone, and needs a binary file. Fails with
+    /// `OneError::InvalidUtf8` if a scaffold name is not valid UTF-8.
+    pub fn get_all_groups(&mut self) -> Result<Vec<GdbGroup>> {
+        self.access.gdb_scans += 1;
+        if !self.has_objects('g') {
+            return Ok(Vec::new());
+        }
+        let (count, _, _) = self.stats('g')?;
+        let path = self.file_name().ok_or(OneError::NullPointer)?;
+        let mut file = OneFile::open_read(&path, None, None, 1)?;
+        (1..=count).map(|group| GdbGroup::read(&mut file, group)).collect()
+    }
+
     /// Read all embedded GDB group metadata in a single pass
     ///
     /// Returns a vector of tuples, one per 'g' group, each containing:
@@ -642,9 +2988,13 @@ impl OneFile {
     ///
     /// # Returns
     /// A Vec of (names, lengths, offsets) tuples, one per 'g' group in order
-    pub fn get_all_groups_metadata(&mut self) -> Vec<(HashMap<i64, String>, HashMap<i64, i64>, HashMap<i64, (i64, i64)>)> {
+    /// (scaffold names that are not UTF-8 are left out of the names map)
+    #[deprecated(note = "use get_all_groups(), which returns structured GdbGroup values")]
+    #[allow(clippy::collapsible_match)]
+    pub fn get_all_groups_metadata(&mut self) -> Vec<GdbMetadata> {
         let mut groups = Vec::new();
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             if ffi::oneGoto(self.ptr, 'g' as i8, 1) {
@@ -653,7 +3003,7 @@ impl OneFile {
                 let mut current_group_lengths = HashMap::new();
                 let mut current_group_offsets = HashMap::new();
 
-                let mut current_scaffold_name = String::new();
+                let mut current_scaffold_name: Option<String> = None;
                 let mut current_scaffold_length = 0i64;
                 let mut scaffold_contigs = Vec::new();
                 let mut scaffold_pos = 0i64;
@@ -697,9 +3047,8 @@ impl OneFile {
                                 current_group_lengths.insert(*cid, current_scaffold_length);
                             }
                             // Start new scaffold
-                            if let Some(name) = self.string() {
-                                current_scaffold_name = Self::trim_sequence_name(name);
-                            }
+                            current_scaffold_name =
+                                self.string().ok().map(Self::trim_sequence_name);
                             scaffold_contigs.clear();
                             current_scaffold_length = 0;
                             scaffold_pos = 0;
@@ -713,7 +3062,9 @@ impl OneFile {
                         'C' => {
                             // Contig - use per-group contig ID
                             let clen = self.int(0);
-                            current_group_names.insert(group_contig_id, current_scaffold_name.clone());
+                            if let Some(name) = &current_scaffold_name {
+                                current_group_names.insert(group_contig_id, name.clone());
+                            }
                             current_group_offsets.insert(group_contig_id, (scaffold_pos, clen));
                             current_scaffold_length += clen;
                             scaffold_contigs.push(group_contig_id);
@@ -752,9 +3103,12 @@ impl OneFile {
     ///
     /// # Returns
     /// A HashMap mapping global contig IDs to their scaffold names
+    /// (contigs of scaffolds whose name is not UTF-8 are left out)
+    #[allow(clippy::collapsible_match)]
     pub fn get_group_sequence_names(&mut self, group_num: i64) -> HashMap<i64, String> {
         let mut names = HashMap::new();
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             // First, count contigs in all previous groups to get the starting contig_id
@@ -776,7 +3130,7 @@ impl OneFile {
             // Now read the target group with correct starting ID
             if ffi::oneGoto(self.ptr, 'g' as i8, group_num) {
                 let mut contig_id = starting_contig_id;
-                let mut current_scaffold_name = String::new();
+                let mut current_scaffold_name: Option<String> = None;
                 let mut is_first_line = true;
 
                 loop {
@@ -787,12 +3141,13 @@ impl OneFile {
 
                     match line_type {
                         'S' => {
-                            if let Some(name) = self.string() {
-                                current_scaffold_name = Self::trim_sequence_name(name);
-                            }
+                            current_scaffold_name =
+                                self.string().ok().map(Self::trim_sequence_name);
                         }
                         'C' => {
-                            names.insert(contig_id, current_scaffold_name.clone());
+                            if let Some(name) = &current_scaffold_name {
+                                names.insert(contig_id, name.clone());
+                            }
                             contig_id += 1;
                         }
                         'g' | 'A' | 'a' => {
@@ -818,15 +3173,18 @@ impl OneFile {
     ///
     /// # Returns
     /// A HashMap mapping contig IDs (0-indexed) to their scaffold names
+    /// (contigs of scaffolds whose name is not UTF-8 are left out)
+    #[allow(clippy::collapsible_match, unused_assignments)]
     pub fn get_all_sequence_names(&mut self) -> HashMap<i64, String> {
         let mut names = HashMap::new();
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             // Navigate to the first 'g' group object (GDB skeleton)
             if ffi::oneGoto(self.ptr, 'g' as i8, 1) {
                 let mut contig_id = 0i64;
-                let mut current_scaffold_name = String::new();
+                let mut current_scaffold_name: Option<String> = None;
                 let mut is_first_line = true;
 
                 loop {
@@ -838,13 +3196,14 @@ impl OneFile {
                     match line_type {
                         'S' => {
                             // New scaffold - store its name (trim at first whitespace)
-                            if let Some(name) = self.string() {
-                                current_scaffold_name = Self::trim_sequence_name(name);
-                            }
+                            current_scaffold_name =
+                                self.string().ok().map(Self::trim_sequence_name);
                         }
                         'C' => {
                             // Contig record - map this contig ID to current scaffold name
-                            names.insert(contig_id, current_scaffold_name.clone());
+                            if let Some(name) = &current_scaffold_name {
+                                names.insert(contig_id, name.clone());
+                            }
                             contig_id += 1;
                         }
                         'g' => {
@@ -873,6 +3232,33 @@ impl OneFile {
         names
     }
 
+    /// Get the contig IDs of all scaffolds, keyed by scaffold name
+    ///
+    /// The reverse of `get_all_sequence_names()`: each name (trimmed at the
+    /// first whitespace) maps to the IDs of its contigs in ascending order,
+    /// e.g. for selecting the alignments of one chromosome.
+    pub fn get_all_contig_ids_by_name(&mut self) -> HashMap<String, Vec<i64>> {
+        let mut ids: HashMap<String, Vec<i64>> = HashMap::new();
+        for (id, name) in self.get_all_sequence_names() {
+            ids.entry(name).or_default().push(id);
+        }
+        for contigs in ids.values_mut() {
+            contigs.sort_unstable();
+        }
+        ids
+    }
+
+    /// Get the IDs of the contigs in the scaffold called `name`
+    ///
+    /// Returns the IDs in ascending order, or an empty vector if no scaffold
+    /// has that name. Each call scans the embedded GDB; use
+    /// `get_all_contig_ids_by_name()` for many names.
+    pub fn get_contig_ids_by_name(&mut self, name: &str) -> Vec<i64> {
+        self.get_all_contig_ids_by_name()
+            .remove(name)
+            .unwrap_or_default()
+    }
+
     /// Get sequence lengths from a specific 'g' group with correct global contig IDs
     ///
     /// # Arguments
@@ -880,9 +3266,11 @@ impl OneFile {
     ///
     /// # Returns
     /// A HashMap mapping global contig IDs to their scaffold lengths
+    #[allow(clippy::collapsible_match)]
     pub fn get_group_sequence_lengths(&mut self, group_num: i64) -> HashMap<i64, i64> {
         let mut lengths = HashMap::new();
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             // Count contigs in previous groups
@@ -962,9 +3350,11 @@ impl OneFile {
     ///
     /// # Returns
     /// A HashMap mapping global contig IDs to (scaffold_offset, contig_length)
+    #[allow(clippy::collapsible_match)]
     pub fn get_group_contig_offsets(&mut self, group_num: i64) -> HashMap<i64, (i64, i64)> {
         let mut contigs = HashMap::new();
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             // Count contigs in previous groups
@@ -1031,9 +3421,11 @@ impl OneFile {
     ///
     /// # Returns
     /// A HashMap mapping contig IDs (0-indexed) to their scaffold's total length
+    #[allow(clippy::collapsible_match, unused_assignments)]
     pub fn get_all_sequence_lengths(&mut self) -> HashMap<i64, i64> {
         let mut lengths = HashMap::new();
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         unsafe {
             if ffi::oneGoto(self.ptr, 'g' as i8, 1) {
@@ -1120,11 +3512,13 @@ impl OneFile {
     ///
     /// # Returns
     /// A HashMap mapping contig IDs (0-indexed) to (scaffold_offset, contig_length)
+    #[allow(clippy::collapsible_match, unused_assignments)]
     pub fn get_all_contig_offsets(&mut self) -> HashMap<i64, (i64, i64)> {
         let mut contigs = HashMap::new();
 
         // Save current position
         let saved_line = self.line_number();
+        self.access.gdb_scans += 1;
 
         // Navigate to the FIRST 'g' group object (GDB skeleton)
         unsafe {
@@ -1193,64 +3587,38 @@ impl OneFile {
     ///
     /// # Returns
     /// A tuple of (seq_names, seq_lengths, contig_offsets) HashMaps
-    pub fn read_gdb_metadata(path: &str) -> Result<(HashMap<i64, String>, HashMap<i64, i64>, HashMap<i64, (i64, i64)>)> {
+    pub fn read_gdb_metadata(path: &str) -> Result<GdbMetadata> {
         let mut file = Self::open_read(path, None, Some("gdb"), 1)?;
 
         let mut seq_names = HashMap::new();
         let mut seq_lengths = HashMap::new();
         let mut contig_offsets = HashMap::new();
 
+        // Each S (scaffold) object is followed by its G (gap) and C (contig)
+        // records; others (M for masks, etc.) are skipped
         let mut contig_id = 0i64;
-        let mut spos = 0i64; // scaffold position accumulator
-        let mut current_scaffold_name = String::new();
-        let mut current_scaffold_length = 0i64;
-        let mut scaffold_contigs = Vec::new(); // Track contigs in current scaffold
-
-        // Read S (scaffold) and C (contig) records from top level
-        loop {
-            let line_type = file.read_line();
-            if line_type == '\0' {
-                // EOF - process final scaffold
-                for cid in scaffold_contigs.iter() {
-                    seq_lengths.insert(*cid, current_scaffold_length);
-                }
-                break;
-            }
-
-            match line_type {
-                'S' => {
-                    // Process previous scaffold's contigs
-                    for cid in scaffold_contigs.iter() {
-                        seq_lengths.insert(*cid, current_scaffold_length);
-                    }
-                    // Start new scaffold
-                    scaffold_contigs.clear();
-                    spos = 0;
-                    current_scaffold_length = 0;
-
-                    if let Some(name) = file.string() {
-                        current_scaffold_name = Self::trim_sequence_name(name);
+        for scaffold in file.objects('S') {
+            let name = match &scaffold.line.list {
+                List::Bytes(bytes) => Self::trim_sequence_name(std::str::from_utf8(bytes)?),
+                _ => String::new(),
+            };
+            let mut spos = 0i64; // scaffold position accumulator
+            let first_contig = contig_id;
+            for line in &scaffold.lines {
+                match line.line_type() {
+                    'G' => spos += line.int(0),
+                    'C' => {
+                        let clen = line.int(0);
+                        seq_names.insert(contig_id, name.clone());
+                        contig_offsets.insert(contig_id, (spos, clen));
+                        contig_id += 1;
+                        spos += clen;
                     }
+                    _ => {}
                 }
-                'G' => {
-                    // Gap record - advance scaffold position and add to scaffold length
-                    let gap_len = file.int(0);
-                    spos += gap_len;
-                    current_scaffold_length += gap_len;
-                }
-                'C' => {
-                    // Contig record - record metadata and advance position
-                    let clen = file.int(0);
-                    seq_names.insert(contig_id, current_scaffold_name.clone());
-                    contig_offsets.insert(contig_id, (spos, clen));
-                    scaffold_contigs.push(contig_id);
-                    contig_id += 1;
-                    spos += clen;
-                    current_scaffold_length += clen;
-                }
-                _ => {
-                    // Skip other records (M for masks, f for frequency, u for uppercase, etc.)
-                }
+            }
+            for cid in first_contig..contig_id {
+                seq_lengths.insert(cid, spos);
             }
         }
 
@@ -1283,6 +3651,75 @@ impl OneFile {
         }
     }
 
+    /// Size in bytes of object `i` of type `line_type` in a binary file
+    ///
+    /// Objects are numbered from 1, as for `goto()`. The size covers the
+    /// object line and the data lines (and contained objects) that follow it,
+    /// up to the start of the next object not contained in it, so a single
+    /// huge object, such as an alignment with a vast trace list, stands out.
+    pub fn object_byte_size(&self, line_type: char, i: i64) -> Result<i64> {
+        let index = self.object_index(line_type)?;
+        let count = index.len() as i64 - 1;
+        if i < 1 || i > count {
+            return Err(OneError::Other(format!(
+                "Object {} of type '{}' out of range (1..={})",
+                i, line_type, count
+            )));
+        }
+        let start = index[i as usize];
+        let end = match self.object_end(line_type as u8, start) {
+            Some(end) => end,
+            None => self.data_end()?,
+        };
+        Ok(end - start)
+    }
+
+    /// Sizes in bytes of all objects of type `line_type`, see `object_byte_size()`
+    ///
+    /// Lets a scheduler cut a file into chunks of similar size in bytes
+    /// rather than in objects.
+    pub fn object_byte_sizes(&self, line_type: char) -> Result<Vec<i64>> {
+        let count = self.object_index(line_type)?.len() as i64 - 1;
+        (1..=count).map(|i| self.object_byte_size(line_type, i)).collect()
+    }
+
+    /// The object index of `line_type`: the start of the data, then the start of each object
+    fn object_index(&self, line_type: char) -> Result<&[i64]> {
+        unsafe {
+            let info = if line_type.is_ascii() {
+                (*self.ptr).info[line_type as usize]
+            } else {
+                ptr::null_mut()
+            };
+            if !self.is_binary() || info.is_null() || !(*info).isObject || (*info).index.is_null() {
+                return Err(OneError::Other(format!(
+                    "Line type '{}' has no object index; only binary files are indexed",
+                    line_type
+                )));
+            }
+            Ok(std::slice::from_raw_parts((*info).index, (*info).given.count as usize + 1))
+        }
+    }
+
+    /// Byte offset of the end-of-data marker of a binary file
+    ///
+    /// The offset of the footer is stored in the last 8 bytes of the file,
+    /// after the marker.
+    fn data_end(&self) -> Result<i64> {
+        use std::io::{Read, Seek, SeekFrom};
+        let path = self.file_name().ok_or(OneError::NullPointer)?;
+        let read_footer_offset = || -> std::io::Result<i64> {
+            let mut f = std::fs::File::open(&path)?;
+            f.seek(SeekFrom::End(-8))?;
+            let mut bytes = [0u8; 8];
+            f.read_exact(&mut bytes)?;
+            Ok(i64::from_ne_bytes(bytes))
+        };
+        let footer = read_footer_offset()
+            .map_err(|e| OneError::Other(format!("Failed to read footer offset of {}: {}", path, e)))?;
+        Ok(footer - 1)
+    }
+
     /// Seek to a specific byte offset in the file
     pub fn seek_to_byte_offset(&mut self, byte_offset: i64) -> Result<()> {
         unsafe {
@@ -1317,26 +3754,474 @@ impl OneFile {
     /// Close the file explicitly
     ///
     /// This is called automatically on drop, but you can call it manually
-    /// to handle any cleanup earlier.
+    /// to handle any cleanup earlier. A file being written whose footer or
+    /// buffered lines cannot be written is reported on standard error; use
+    /// `finalize()` to handle that as an error.
     pub fn close(mut self) {
-        if self.is_owned && !self.ptr.is_null() {
-            unsafe {
-                ffi::oneFileClose(self.ptr);
+        if let Err(e) = self.close_c_file() {
+            report_close_error(&e);
+        }
+    }
+
+    /// Close a file being written, failing if any of it could not be written
+    ///
+    /// Closing writes the header counts, index and footer and flushes the
+    /// lines still buffered, so a full disk often shows only here; the
+    /// error is `OneError::CloseFailed` with the reason. For files being
+    /// read this is the same as `close()`.
+    pub fn finalize(mut self) -> Result<()> {
+        self.close_c_file()
+    }
+
+    /// Close the C file if owned, flushing C's buffered standard output
+    /// after writing to "-"
+    fn close_c_file(&mut self) -> Result<()> {
+        if !self.is_owned || self.ptr.is_null() {
+            return Ok(());
+        }
+        let to_stdout =
+            unsafe { (*self.ptr).isWrite } && self.file_name().as_deref() == Some("-");
+        let written = unsafe {
+            let written = ffi::oneFileCloseChecked(self.ptr);
+            if to_stdout {
+                libc::fflush(ptr::null_mut());
+            }
+            written
+        };
+        self.ptr = ptr::null_mut();
+        let result = if written {
+            Ok(())
+        } else {
+            let message = unsafe { CStr::from_ptr(ffi::oneErrorString()) };
+            Err(OneError::CloseFailed(message.to_string_lossy().trim_end().to_string()))
+        };
+        if let Some((temp, target)) = self.atomic_target.take() {
+            if result.is_ok() {
+                if let Err(e) = std::fs::rename(&temp, &target) {
+                    std::fs::remove_file(&temp).ok();
+                    return Err(OneError::io(target.display(), &e));
+                }
+            } else {
+                std::fs::remove_file(&temp).ok();
+            }
+        }
+        self.write_lock = None;
+        result
+    }
+
+    /// Close a file from `open_write_vec()` and return its contents
+    pub fn into_vec(mut self) -> Result<Vec<u8>> {
+        let Some(path) = self.memory_path.clone() else {
+            return Err(OneError::Other(
+                "into_vec() needs a file created with open_write_vec()".to_string(),
+            ));
+        };
+        if !unsafe { (*self.ptr).isWrite } {
+            return Err(OneError::Other("into_vec() needs a file open for writing".to_string()));
+        }
+        self.close_c_file()?;
+        std::fs::read(&path).map_err(|_| OneError::ReadFailed)
+    }
+}
+
+/// One of the per-thread handles of a file, from `OneFile::split_threads()`
+///
+/// Read-only methods are available through `Deref`; reading and writing go
+/// through the methods below, which behave as those of `OneFile`.
+pub struct OneFileThread<'a> {
+    file: OneFile, // not owned, closed with the master
+    index: usize,
+    _master: PhantomData<&'a mut OneFile>,
+}
+
+impl OneFileThread<'_> {
+    /// Position of this handle among the file's thread handles
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// See `OneFile::read_line()`
+    pub fn read_line(&mut self) -> char {
+        self.file.read_line()
+    }
+
+    /// See `OneFile::read_line_checked()`
+    pub fn read_line_checked(&mut self) -> Result<char> {
+        self.file.read_line_checked()
+    }
+
+    /// See `OneFile::read_record()`
+    pub fn read_record(&mut self) -> Option<Record> {
+        self.file.read_record()
+    }
+
+    /// See `OneFile::dna_2bit()`
+    pub fn dna_2bit(&mut self) -> Option<&[u8]> {
+        self.file.dna_2bit()
+    }
+
+    /// See `OneFile::goto()`
+    pub fn goto(&mut self, line_type: char, index: i64) -> Result<()> {
+        self.file.goto(line_type, index)
+    }
+
+    /// Call `f` on objects `range` of type `line_type`, positioned on each
+    /// object line in turn, seeking only where `f` did not leave the handle
+    /// on or before the next one
+    #[cfg(feature = "rayon")]
+    fn map_objects<T, F>(&mut self, line_type: char, range: Range<i64>, f: &F) -> Result<Vec<T>>
+    where
+        F: Fn(&mut OneFileThread<'_>, i64) -> Result<T>,
+    {
+        let mut results = Vec::with_capacity((range.end - range.start).max(0) as usize);
+        let start = range.start;
+        for object in range {
+            // On the object line, the count of objects read is `object`
+            let count = self.object(line_type);
+            let past = count > object || (count == object && self.line_type() != line_type);
+            if object == start || past {
+                self.goto(line_type, object)?;
+            }
+            while self.line_type() != line_type || self.object(line_type) < object {
+                if self.read_line() == '\0' {
+                    return Err(OneError::Other(format!(
+                        "Object {} of type '{}' not found before end of data",
+                        object, line_type
+                    )));
+                }
+            }
+            results.push(f(self, object)?);
+        }
+        Ok(results)
+    }
+
+    /// See `OneFile::set_int()`
+    pub fn set_int(&mut self, field: usize, value: i64) {
+        self.file.set_int(field, value)
+    }
+
+    /// See `OneFile::set_real()`
+    pub fn set_real(&mut self, field: usize, value: f64) {
+        self.file.set_real(field, value)
+    }
+
+    /// See `OneFile::set_char()`
+    pub fn set_char(&mut self, field: usize, value: char) {
+        self.file.set_char(field, value)
+    }
+
+    /// See `OneFile::write_line()`
+    pub fn write_line(&mut self, line_type: char, list_len: i64, list_buf: Option<*mut std::ffi::c_void>) {
+        self.file.write_line(line_type, list_len, list_buf)
+    }
+
+    /// See `OneFile::write_int_list()`
+    pub fn write_int_list(&mut self, line_type: char, values: &[i64]) -> Result<()> {
+        self.file.write_int_list(line_type, values)
+    }
+
+    /// See `OneFile::write_real_list()`
+    pub fn write_real_list(&mut self, line_type: char, values: &[f64]) -> Result<()> {
+        self.file.write_real_list(line_type, values)
+    }
+
+    /// See `OneFile::write_string()`
+    pub fn write_string(&mut self, line_type: char, s: &str) -> Result<()> {
+        self.file.write_string(line_type, s)
+    }
+
+    /// See `OneFile::write_string_list()`
+    pub fn write_string_list(&mut self, line_type: char, strings: &[&str]) -> Result<()> {
+        self.file.write_string_list(line_type, strings)
+    }
+
+    /// See `OneFile::write_dna()`
+    pub fn write_dna(&mut self, line_type: char, bases: &[u8]) -> Result<()> {
+        self.file.write_dna(line_type, bases)
+    }
+
+    /// See `OneFile::write_comment()`
+    pub fn write_comment(&mut self, comment: &str) -> Result<()> {
+        self.file.write_comment(comment)
+    }
+
+    /// See `OneFile::copy_line_to()`; `dest` is a handle of the file being
+    /// written, so threads can copy their slices side by side
+    pub fn copy_line_to(&self, dest: &mut OneFileThread<'_>) -> Result<()> {
+        self.file.copy_line(&mut dest.file)
+    }
+
+    /// See `copy_line_to()`
+    #[deprecated(note = "use copy_line_to(), which needs only a shared borrow of the source")]
+    pub fn copy_current_line(&self, dest: &mut OneFileThread<'_>) -> Result<()> {
+        self.file.copy_line(&mut dest.file)
+    }
+}
+
+// No DerefMut: swapping the inner OneFile out would let it outlive the master
+impl Deref for OneFileThread<'_> {
+    type Target = OneFile;
+
+    fn deref(&self) -> &OneFile {
+        &self.file
+    }
+}
+
+/// Move the first of each set of equal entries to the front, in order,
+/// freeing the rest, and return how many are kept
+///
+/// # Safety
+///
+/// `entries` must point to `n` initialized entries owning what `free` frees.
+unsafe fn dedup_entries<T: Copy, K: PartialEq>(
+    entries: *mut T,
+    n: usize,
+    key: impl Fn(&T) -> K,
+    free: impl Fn(&T),
+) -> usize {
+    let entries = std::slice::from_raw_parts_mut(entries, n);
+    let mut kept: Vec<K> = Vec::new();
+    for i in 0..n {
+        let entry = entries[i];
+        let k = key(&entry);
+        if kept.contains(&k) {
+            free(&entry);
+        } else {
+            entries[kept.len()] = entry;
+            kept.push(k);
+        }
+    }
+    kept.len()
+}
+
+/// Key and value of a `key=value` header text line
+fn parse_metadata(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once('=')?;
+    (!key.is_empty() && !key.contains(char::is_whitespace)).then_some((key, value))
+}
+
+/// Why a file did not open with `schema`, which it is reopened without to
+/// tell a file of another type from one with differing line types
+fn schema_mismatch(
+    path: &str,
+    c_path: &CStr,
+    type_ptr: *const std::os::raw::c_char,
+    schema: &OneSchema,
+) -> Option<OneError> {
+    let ptr = unsafe { ffi::oneFileOpenRead(c_path.as_ptr(), ptr::null_mut(), type_ptr, 1) };
+    if ptr.is_null() {
+        return None;
+    }
+    let file = OneFile::from_raw(ptr, true);
+    let found = file.file_type()?;
+    let expected = crate::schema::primary_types(schema.text());
+    if !expected.is_empty() && !expected.contains(&found) {
+        return Some(OneError::WrongFileType {
+            path: path.to_string(),
+            expected: expected.join(" or "),
+            found,
+        });
+    }
+    match file.check_schema(schema, false) {
+        Err(OneError::SchemaMismatch { mismatches, .. }) => Some(OneError::SchemaMismatch {
+            path: Some(path.to_string()),
+            mismatches,
+        }),
+        _ => None,
+    }
+}
+
+/// The error for a file ONElib did not open, told apart by its message
+///
+/// ONElib returns without a message when the file itself cannot be opened,
+/// leaving the reason in errno, which `os_error` holds.
+fn open_error(path: &str, os_error: std::io::Error) -> OneError {
+    parse_open_error(path, &c_error_message(), Some(os_error))
+}
+
+/// ONElib's message for the last error on this thread
+fn c_error_message() -> String {
+    unsafe {
+        let err_str = ffi::oneErrorString();
+        if err_str.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(err_str).to_string_lossy().trim_end().to_string()
+        }
+    }
+}
+
+/// Clear ONElib's error message, so a failure after this has its own
+fn clear_c_error() {
+    unsafe {
+        let err_str = ffi::oneErrorString();
+        if !err_str.is_null() {
+            *err_str = 0;
+        }
+    }
+}
+
+/// The error for ONElib's open error `message`, or for `os_error` without one
+fn parse_open_error(path: &str, message: &str, os_error: Option<std::io::Error>) -> OneError {
+    // Messages read "ONEcode file open error <path>: <detail>", where ONElib
+    // may have added the file type to the path as an extension
+    let detail = message
+        .strip_prefix("ONEcode file open error ")
+        .and_then(|rest| rest.strip_prefix(path))
+        .and_then(|rest| rest.split_once(": "))
+        .map_or(message, |(_, detail)| detail);
+    match (detail, os_error) {
+        ("", Some(os_error)) if os_error.raw_os_error().is_some_and(|errno| errno != 0) => {
+            OneError::io(path, &os_error)
+        }
+        ("", _) => OneError::OpenFailed(path.to_string()),
+        ("end of file before end of line 1", _) => OneError::Truncated {
+            path: path.to_string(),
+            line: Some(1),
+        },
+        _ if detail == "file is empty"
+            || detail.starts_with("line 1: ")
+            || detail.contains("bare oneFile")
+            || detail.contains("must begin with '1' line") =>
+        {
+            OneError::BadMagic {
+                path: path.to_string(),
+                message: detail.to_string(),
             }
-            self.ptr = ptr::null_mut();
         }
+        _ if detail.contains("not found in schema") => {
+            OneError::SchemaError(format!("{}: {}", path, detail))
+        }
+        _ => OneError::OpenFailed(format!("{}: {}", path, detail)),
+    }
+}
+
+/// Fail with `Truncated` if `path` is a binary ONE file that was cut short
+///
+/// A binary file ends with a footer and its offset; ONElib exits the
+/// process when it cannot find the footer, so it is looked for first.
+fn check_footer(path: &str) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    if !matches!(crate::sniff::sniff(path), Some(crate::sniff::FileKind::OneBinary { .. })) {
+        return Ok(());
+    }
+    let mut file = std::fs::File::open(path).map_err(|e| OneError::io(path, &e))?;
+    let len = file.metadata().map_err(|e| OneError::io(path, &e))?.len();
+    // "^\n" ends the footer, followed by the offset at which it starts
+    let mut tail = [0u8; 10];
+    let intact = len >= 10
+        && file.seek(SeekFrom::End(-10)).is_ok()
+        && file.read_exact(&mut tail).is_ok()
+        && tail.starts_with(b"^\n")
+        && (i64::from_ne_bytes(tail[2..].try_into().unwrap()) as u64) <= len - 10;
+    if intact {
+        Ok(())
+    } else {
+        Err(OneError::Truncated {
+            path: path.to_string(),
+            line: None,
+        })
     }
 }
 
 impl Drop for OneFile {
     fn drop(&mut self) {
-        if self.is_owned && !self.ptr.is_null() {
-            unsafe {
-                ffi::oneFileClose(self.ptr);
+        if std::thread::panicking() {
+            // An atomic file is left unfinished rather than put in place
+            if let Some((temp, _)) = self.atomic_target.take() {
+                self.close_c_file().ok();
+                std::fs::remove_file(temp).ok();
             }
         }
+        if let Err(e) = self.close_c_file() {
+            report_close_error(&e);
+        }
+        if let Some(path) = self.memory_path.take() {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+/// Report a failure to finish writing a file closed without `finalize()`
+fn report_close_error(error: &OneError) {
+    eprintln!("onecode: warning: {} (call finalize() to handle this as an error)", error);
+}
+
+/// Open a file for writing with `open`, holding the advisory lock on `path`
+/// if `lock` is set, see `WriteOptions::lock()`
+fn with_lock(path: &str, lock: bool, open: impl FnOnce() -> Result<OneFile>) -> Result<OneFile> {
+    if !lock || path == "-" {
+        return open();
+    }
+    // Opened without truncating, so a file someone else is writing is left alone
+    let lock_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| OneError::io(path, &e))?;
+    let locked = unsafe { libc::flock(lock_file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if locked != 0 {
+        let error = std::io::Error::last_os_error();
+        return Err(match error.kind() {
+            std::io::ErrorKind::WouldBlock => OneError::Locked { path: path.to_string() },
+            _ => OneError::io(path, &error),
+        });
+    }
+    let mut file = open()?;
+    file.write_lock = Some(lock_file);
+    Ok(file)
+}
+
+/// Whether `fd` supports seeking, which pipes and terminals do not
+fn fd_is_seekable(fd: i32) -> bool {
+    unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) >= 0 }
+}
+
+/// A path for writing `target` atomically, hidden in the same directory
+fn atomic_temp_path(target: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(
+        ".{}.{}_{}.tmp",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Create a uniquely named temporary file holding `bytes`, for the
+/// in-memory constructors
+fn create_memory_file(bytes: &[u8]) -> Result<PathBuf> {
+    use std::io::Write;
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "onecode_memory_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| OneError::io(path.display(), &e))?;
+    if file.write_all(bytes).is_err() {
+        std::fs::remove_file(&path).ok();
+        return Err(OneError::WriteFailed);
     }
+    Ok(path)
 }
 
-// OneFile is not thread-safe by default
-// The user needs to manage thread-safety if using nthreads > 1
+// SAFETY: a OneFile exclusively owns its C handle (and, for nthreads > 1,
+// the array of thread handles behind it), so moving it to another thread
+// moves everything it touches:
+// - the schema information is deep-copied when the file is opened, so the
+//   handle holds no pointers into a OneSchema or another OneFile
+// - ONElib keeps no per-thread state for open files; its error string is
+//   thread-local, but is read right after the failing call on the same thread
+// - the Rust-side fields are plain owned data
+//
+// OneFile is not Sync: even the &self accessors such as dna_char() decode
+// lists lazily into buffers of the C handle. Share one between threads
+// behind a Mutex, which needs only Send.
+unsafe impl Send for OneFile {}
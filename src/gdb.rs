@@ -0,0 +1,495 @@
+//! Reader for GDB (genome database) skeletons
+//!
+//! A GDB skeleton describes each scaffold as a name ('S') followed by a run of
+//! gap ('G') and contig ('C') lengths, each contig optionally followed by its
+//! mask intervals ('M'). It is stored either as a standalone
+//! `.1gdb` file or embedded in `.1aln` files as one 'g' group per genome.
+
+use crate::coord::Span;
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::linetypes::{aln, gdb};
+use crate::names::{index_names, NameNormalizer};
+use crate::store::{check_range, SequenceStore};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+
+/// A contig within a scaffold
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contig {
+    /// Contig ID, 0-indexed within its skeleton (as used by alignment records)
+    pub id: i64,
+    /// 0-based offset of the contig start within its scaffold
+    pub offset: i64,
+    /// Contig length
+    pub len: i64,
+    /// Masked intervals as 0-based half-open (start, end) pairs, as stored
+    /// in the 'M' lines
+    pub masks: Vec<(i64, i64)>,
+}
+
+/// A run of unknown bases between contigs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    /// 0-based offset of the gap start within its scaffold
+    pub offset: i64,
+    /// Gap length
+    pub len: i64,
+}
+
+/// A piece of a scaffold, see `Scaffold::segments()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment<'a> {
+    Gap(&'a Gap),
+    Contig(&'a Contig),
+}
+
+/// A scaffold: a named sequence of contigs separated by gaps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scaffold {
+    /// Scaffold name, trimmed at the first whitespace character
+    pub name: String,
+    /// Total scaffold length including gaps
+    pub length: i64,
+    /// Contigs in positional order
+    pub contigs: Vec<Contig>,
+    /// Gaps in positional order
+    pub gaps: Vec<Gap>,
+}
+
+impl Scaffold {
+    /// Gaps and contigs interleaved in positional order
+    ///
+    /// This is the full layout of the scaffold as recorded in the skeleton,
+    /// e.g. for regenerating AGP.
+    pub fn segments(&self) -> Vec<Segment<'_>> {
+        let mut segments: Vec<Segment> = self.gaps.iter().map(Segment::Gap).collect();
+        segments.extend(self.contigs.iter().map(Segment::Contig));
+        segments.sort_by_key(|s| match s {
+            Segment::Gap(g) => g.offset,
+            Segment::Contig(c) => c.offset,
+        });
+        segments
+    }
+}
+
+/// One skeleton embedded in an alignment file, see `OneFile::get_all_groups()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GdbGroup {
+    /// Scaffolds in file order, with contig IDs counted from 0 within the group
+    pub scaffolds: Vec<Scaffold>,
+}
+
+impl GdbGroup {
+    /// Read the skeleton in 'g' group `group` (1-indexed) of a binary alignment file
+    pub fn read(file: &mut OneFile, group: i64) -> Result<Self> {
+        file.goto(aln::GDB_GROUP, group)?;
+        Ok(GdbGroup {
+            scaffolds: read_skeleton(file, true)?,
+        })
+    }
+
+    /// Number of contigs, i.e. one more than the largest contig ID
+    pub fn num_contigs(&self) -> usize {
+        self.scaffolds.iter().map(|s| s.contigs.len()).sum()
+    }
+}
+
+/// How to handle scaffolds sharing a name within one skeleton
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Fail with `OneError::DuplicateName`
+    Error,
+    /// Rename later occurrences by appending ".2", ".3", ... to the name
+    Suffix,
+    /// Keep all scaffolds but resolve the name to the first one
+    #[default]
+    KeepFirst,
+}
+
+/// An in-memory GDB skeleton with lookup by scaffold name
+///
+/// The skeleton carries lengths and structure but no bases, so as a
+/// `SequenceStore` it answers `len()` but fails on `fetch()`.
+pub struct GdbReader {
+    scaffolds: Vec<Scaffold>,
+    index: HashMap<String, usize>,
+    duplicates: Vec<usize>,
+    normalizer: Option<Box<dyn NameNormalizer>>,
+}
+
+impl GdbReader {
+    /// Read the skeleton of a standalone GDB file (.1gdb)
+    ///
+    /// Duplicate scaffold names resolve to the first scaffold; see `open_with()`.
+    pub fn open(path: &str) -> Result<Self> {
+        Self::open_with(path, DuplicatePolicy::default())
+    }
+
+    /// Read the skeleton of a standalone GDB file, handling duplicate names per `policy`
+    pub fn open_with(path: &str, policy: DuplicatePolicy) -> Result<Self> {
+        let mut file = OneFile::open_read(path, None, Some("gdb"), 1)?;
+        Self::from_scaffolds(read_skeleton(&mut file, false)?, policy)
+    }
+
+    /// Read the skeleton embedded as 'g' group `group` (1-indexed) of an alignment file
+    ///
+    /// In a `.1aln` file the first group describes the query genome and the
+    /// second (if present) the target genome. This moves the read position of
+    /// `file`; requires a binary file since it navigates via the object index.
+    pub fn from_alignment(file: &mut OneFile, group: i64) -> Result<Self> {
+        Self::from_alignment_with(file, group, DuplicatePolicy::default())
+    }
+
+    /// Read an embedded skeleton, handling duplicate names per `policy`
+    pub fn from_alignment_with(file: &mut OneFile, group: i64, policy: DuplicatePolicy) -> Result<Self> {
+        Self::from_scaffolds(GdbGroup::read(file, group)?.scaffolds, policy)
+    }
+
+    fn from_scaffolds(mut scaffolds: Vec<Scaffold>, policy: DuplicatePolicy) -> Result<Self> {
+        let mut index = HashMap::new();
+        let mut duplicates = Vec::new();
+        for (i, scaffold) in scaffolds.iter_mut().enumerate() {
+            if !index.contains_key(&scaffold.name) {
+                index.insert(scaffold.name.clone(), i);
+                continue;
+            }
+            duplicates.push(i);
+            match policy {
+                DuplicatePolicy::Error => {
+                    return Err(OneError::DuplicateName(scaffold.name.clone()));
+                }
+                DuplicatePolicy::Suffix => {
+                    let mut k = 2;
+                    let renamed = loop {
+                        let candidate = format!("{}.{}", scaffold.name, k);
+                        if !index.contains_key(&candidate) {
+                            break candidate;
+                        }
+                        k += 1;
+                    };
+                    scaffold.name = renamed.clone();
+                    index.insert(renamed, i);
+                }
+                DuplicatePolicy::KeepFirst => {}
+            }
+        }
+        Ok(GdbReader {
+            scaffolds,
+            index,
+            duplicates,
+            normalizer: None,
+        })
+    }
+
+    /// Indices of scaffolds whose name repeated an earlier scaffold's when read
+    ///
+    /// Under `DuplicatePolicy::Suffix` these are the renamed scaffolds.
+    pub fn duplicates(&self) -> &[usize] {
+        &self.duplicates
+    }
+
+    /// All scaffolds in file order
+    pub fn scaffolds(&self) -> &[Scaffold] {
+        &self.scaffolds
+    }
+
+    /// Look up a scaffold by name
+    pub fn scaffold(&self, name: &str) -> Option<&Scaffold> {
+        let i = match &self.normalizer {
+            Some(normalizer) => self.index.get(normalizer.normalize(name).as_ref()),
+            None => self.index.get(name),
+        };
+        i.map(|&i| &self.scaffolds[i])
+    }
+
+    /// Look scaffolds up by their names as `normalizer` maps them
+    ///
+    /// `scaffold()` and the `SequenceStore` methods then find a scaffold by
+    /// any name that normalizes to the same key as its own, and if several
+    /// scaffolds do, the first. Scaffold names stay as read. See `names`.
+    pub fn with_normalizer(mut self, normalizer: impl NameNormalizer + 'static) -> Self {
+        let names = self.scaffolds.iter().map(|s| s.name.as_str());
+        self.index = index_names(names.enumerate(), &normalizer);
+        self.normalizer = Some(Box::new(normalizer));
+        self
+    }
+}
+
+impl SequenceStore for GdbReader {
+    fn len(&self, name: &str) -> Result<usize> {
+        self.scaffold(name)
+            .map(|s| s.length as usize)
+            .ok_or_else(|| OneError::SequenceNotFound(name.to_string()))
+    }
+
+    fn fetch(&mut self, name: &str, range: Range<usize>) -> Result<Vec<u8>> {
+        let len = self.len(name)?;
+        check_range(name, &range, len)?;
+        Err(OneError::Other(format!(
+            "GDB skeleton holds no bases for {}",
+            name
+        )))
+    }
+}
+
+/// Constant-time lookups by contig id into a skeleton
+///
+/// Alignment records refer to contigs by id; this resolves an id to its
+/// scaffold without scanning. `OneFile::gdb_index()` builds and caches one
+/// for the skeletons embedded in a `.1aln` file.
+#[derive(Debug, Clone, Default)]
+pub struct GdbIndex {
+    scaffold_names: Vec<String>,
+    scaffold_lengths: Vec<i64>,
+    contigs: Vec<(usize, i64, i64)>, // (scaffold, offset, length) by contig id
+    ids_by_name: HashMap<String, Vec<i64>>,
+}
+
+impl GdbIndex {
+    /// Index the contigs of `gdb`
+    pub fn new(gdb: &GdbReader) -> Self {
+        let mut index = GdbIndex::default();
+        for (i, scaffold) in gdb.scaffolds().iter().enumerate() {
+            index.scaffold_names.push(scaffold.name.clone());
+            index.scaffold_lengths.push(scaffold.length);
+            for contig in &scaffold.contigs {
+                let id = contig.id as usize;
+                if index.contigs.len() <= id {
+                    index.contigs.resize(id + 1, (usize::MAX, 0, 0));
+                }
+                index.contigs[id] = (i, contig.offset, contig.len);
+                index
+                    .ids_by_name
+                    .entry(scaffold.name.clone())
+                    .or_default()
+                    .push(contig.id);
+            }
+        }
+        index
+    }
+
+    /// Number of contig ids covered
+    pub fn num_contigs(&self) -> usize {
+        self.contigs.len()
+    }
+
+    /// Name of the scaffold containing contig `id`
+    pub fn name(&self, id: i64) -> Option<&str> {
+        self.scaffold(id).map(|s| self.scaffold_names[s].as_str())
+    }
+
+    /// Total length of the scaffold containing contig `id`, gaps included
+    pub fn length(&self, id: i64) -> Option<i64> {
+        self.scaffold(id).map(|s| self.scaffold_lengths[s])
+    }
+
+    /// 0-based offset of contig `id` within its scaffold
+    pub fn offset(&self, id: i64) -> Option<i64> {
+        self.contig(id).map(|&(_, offset, _)| offset)
+    }
+
+    /// Length of contig `id`
+    pub fn contig_length(&self, id: i64) -> Option<i64> {
+        self.contig(id).map(|&(_, _, len)| len)
+    }
+
+    /// IDs of the contigs in the scaffold(s) called `name`, in skeleton order
+    pub fn contig_ids(&self, name: &str) -> &[i64] {
+        self.ids_by_name.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    fn contig(&self, id: i64) -> Option<&(usize, i64, i64)> {
+        let contig = self.contigs.get(usize::try_from(id).ok()?)?;
+        (contig.0 != usize::MAX).then_some(contig)
+    }
+
+    /// Position of the scaffold containing contig `id` in the skeleton
+    pub(crate) fn scaffold(&self, id: i64) -> Option<usize> {
+        self.contig(id).map(|&(scaffold, _, _)| scaffold)
+    }
+}
+
+/// Write the scaffolding structure of `gdb` as AGP 2.1
+///
+/// Each contig becomes a 'W' component named `<scaffold>_<n>` (n counting
+/// contigs within the scaffold from 1) in '+' orientation, and each gap an
+/// 'N' line of type scaffold with unspecified linkage evidence. Masks have no
+/// AGP representation and are not written.
+pub fn to_agp<W: Write>(gdb: &GdbReader, mut writer: W) -> Result<()> {
+    let io_error = |e: std::io::Error| OneError::Other(format!("Failed to write AGP: {}", e));
+    writeln!(writer, "##agp-version\t2.1").map_err(io_error)?;
+    for scaffold in gdb.scaffolds() {
+        let mut n_contig = 0;
+        for (part, segment) in scaffold.segments().iter().enumerate() {
+            match segment {
+                Segment::Contig(c) => {
+                    n_contig += 1;
+                    let (beg, end) = Span::new(c.offset, c.offset + c.len)?.to_one_based();
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}\tW\t{}_{}\t1\t{}\t+",
+                        scaffold.name,
+                        beg,
+                        end,
+                        part + 1,
+                        scaffold.name,
+                        n_contig,
+                        c.len
+                    )
+                }
+                Segment::Gap(g) => {
+                    let (beg, end) = Span::new(g.offset, g.offset + g.len)?.to_one_based();
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}\tN\t{}\tscaffold\tyes\tunspecified",
+                        scaffold.name,
+                        beg,
+                        end,
+                        part + 1,
+                        g.len
+                    )
+                }
+            }
+            .map_err(io_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the scaffolding structure in an AGP file into a skeleton
+///
+/// Component lines ('W' and the other sequence types) become contigs of
+/// length `component_end - component_beg + 1` and gap lines ('N', 'U') gaps;
+/// component names and orientations are not kept. Lines must be in
+/// positional order within each object.
+pub fn from_agp<R: BufRead>(reader: R) -> Result<GdbReader> {
+    let mut scaffolds: Vec<Scaffold> = Vec::new();
+    let mut contig_id = 0i64;
+    for (n, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| OneError::Other(format!("Failed to read AGP: {}", e)))?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let bad_line = || OneError::InvalidFormat(format!("AGP line {}: {}", n + 1, line));
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() < 8 {
+            return Err(bad_line());
+        }
+        let int = |i: usize| cols[i].trim().parse::<i64>().map_err(|_| bad_line());
+        let span = Span::from_one_based(int(1)?, int(2)?).map_err(|_| bad_line())?;
+
+        if scaffolds.last().is_none_or(|s| s.name != cols[0]) {
+            scaffolds.push(Scaffold {
+                name: cols[0].to_string(),
+                length: 0,
+                contigs: Vec::new(),
+                gaps: Vec::new(),
+            });
+        }
+        let scaffold = scaffolds.last_mut().unwrap();
+        if span.start != scaffold.length || span.is_empty() {
+            return Err(bad_line());
+        }
+        let offset = scaffold.length;
+        if cols[4] == "N" || cols[4] == "U" {
+            let len = int(5)?;
+            scaffold.gaps.push(Gap { offset, len });
+            scaffold.length += len;
+        } else {
+            let len = int(7)? - int(6)? + 1;
+            scaffold.contigs.push(Contig {
+                id: contig_id,
+                offset,
+                len,
+                masks: Vec::new(),
+            });
+            scaffold.length += len;
+            contig_id += 1;
+        }
+        if scaffold.length != span.end {
+            return Err(bad_line());
+        }
+    }
+    GdbReader::from_scaffolds(scaffolds, DuplicatePolicy::default())
+}
+
+/// Scaffold names and lengths in skeleton order, as needed for ideograms
+pub fn karyotype(gdb: &GdbReader) -> Vec<(String, i64)> {
+    gdb.scaffolds()
+        .iter()
+        .map(|s| (s.name.clone(), s.length))
+        .collect()
+}
+
+/// Write the karyotype of `gdb` in circos karyotype format
+///
+/// One `chr - ID LABEL 0 LENGTH COLOR` line per scaffold, using the scaffold
+/// name as both ID and label and `color` for every band.
+pub fn to_circos_karyotype<W: Write>(gdb: &GdbReader, mut writer: W, color: &str) -> Result<()> {
+    for (name, length) in karyotype(gdb) {
+        writeln!(writer, "chr - {} {} 0 {} {}", name, name, length, color)
+            .map_err(|e| OneError::Other(format!("Failed to write karyotype: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Read S/G/C records into scaffolds
+///
+/// For an embedded skeleton the handle is positioned just before the 'g' line
+/// and reading stops at the next group or at the alignments. A scaffold name
+/// that is not UTF-8 fails with `OneError::InvalidUtf8`.
+fn read_skeleton(file: &mut OneFile, embedded: bool) -> Result<Vec<Scaffold>> {
+    let mut scaffolds: Vec<Scaffold> = Vec::new();
+    let mut contig_id = 0i64;
+    let mut is_first_line = true;
+
+    loop {
+        let line_type = file.read_line();
+        match line_type {
+            '\0' => break,
+            aln::GDB_GROUP | aln::ALIGNMENT | aln::CHAIN if embedded && !is_first_line => break,
+            gdb::SCAFFOLD => scaffolds.push(Scaffold {
+                name: OneFile::trim_sequence_name(file.string()?),
+                length: 0,
+                contigs: Vec::new(),
+                gaps: Vec::new(),
+            }),
+            gdb::GAP => {
+                if let Some(scaffold) = scaffolds.last_mut() {
+                    let len = file.int(0);
+                    scaffold.gaps.push(Gap {
+                        offset: scaffold.length,
+                        len,
+                    });
+                    scaffold.length += len;
+                }
+            }
+            gdb::CONTIG => {
+                if let Some(scaffold) = scaffolds.last_mut() {
+                    let len = file.int(0);
+                    scaffold.contigs.push(Contig {
+                        id: contig_id,
+                        offset: scaffold.length,
+                        len,
+                        masks: Vec::new(),
+                    });
+                    scaffold.length += len;
+                    contig_id += 1;
+                }
+            }
+            gdb::MASK => {
+                let contig = scaffolds.last_mut().and_then(|s| s.contigs.last_mut());
+                if let (Some(contig), Some(pairs)) = (contig, file.int_list()) {
+                    contig.masks.extend(pairs.chunks_exact(2).map(|p| (p[0], p[1])));
+                }
+            }
+            _ => {
+                // Skip other records (f for frequency, etc.)
+            }
+        }
+        is_first_line = false;
+    }
+    Ok(scaffolds)
+}
@@ -0,0 +1,152 @@
+//! Inventory of the ONE files under a directory
+//!
+//! `scan_dir()` finds the ONE files in a directory tree by content (see
+//! `sniff()`) and describes each from its header alone: opening a file reads
+//! only the header, so even large datasets are inventoried quickly.
+//! `line_type_usage()` then sums the counts per file type and line type:
+//!
+//! ```no_run
+//! use onecode::{line_type_usage, scan_dir};
+//!
+//! let files = scan_dir("dataset")?;
+//! for usage in line_type_usage(&files) {
+//!     println!(
+//!         "{}\t{}\t{} files\t{} lines",
+//!         usage.file_type, usage.line_type, usage.files, usage.counts.count
+//!     );
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::sniff::{sniff, FileKind};
+use crate::types::{LineStats, OneCounts};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Header summary of one ONE file, from `scan_dir()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub path: PathBuf,
+    /// `FileKind::OneAscii` or `FileKind::OneBinary`, with the primary type
+    pub kind: FileKind,
+    pub sub_type: Option<String>,
+    /// File size in bytes
+    pub size: u64,
+    /// Schema fingerprint, see `OneSchema::fingerprint()`
+    pub fingerprint: Option<u64>,
+    /// Counts of every declared line type, see `OneFile::all_stats()`;
+    /// zero throughout for ASCII files written without header counts
+    pub stats: Vec<LineStats>,
+    /// Why the file looked like a ONE file but could not be opened
+    pub error: Option<String>,
+}
+
+impl FileInfo {
+    /// Primary file type, e.g. "seq"
+    pub fn file_type(&self) -> &str {
+        self.kind.file_type().unwrap_or_default()
+    }
+
+    /// Number of `line_type` lines, or 0 if the schema does not declare it
+    pub fn count(&self, line_type: char) -> i64 {
+        self.stats
+            .iter()
+            .find(|s| s.line_type == line_type)
+            .map_or(0, |s| s.counts.count)
+    }
+}
+
+/// Line counts of one line type summed over the files of one type
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineTypeUsage {
+    pub file_type: String,
+    pub line_type: char,
+    /// Number of files with at least one such line
+    pub files: usize,
+    /// Summed `count` and `total`, and the largest `max`
+    pub counts: OneCounts,
+}
+
+/// Describe the ONE files under `dir`, recursively, in path order
+///
+/// Files are recognised by content, so other files are skipped whatever
+/// their names; symbolic links to directories are not followed. A file that
+/// looks like a ONE file but cannot be opened is listed with its `error`.
+/// Fails only if `dir` itself cannot be read.
+pub fn scan_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<FileInfo>> {
+    let dir = dir.as_ref();
+    std::fs::read_dir(dir).map_err(|e| OneError::io(dir.display(), &e))?;
+    let mut paths = Vec::new();
+    collect_files(dir, &mut paths);
+    paths.sort();
+    Ok(paths.iter().filter_map(|path| describe(path)).collect())
+}
+
+/// Sum the line counts of `files` per file type and line type
+///
+/// Line types no file has lines of are left out, so this reports what a
+/// dataset uses rather than what its schemas declare.
+pub fn line_type_usage(files: &[FileInfo]) -> Vec<LineTypeUsage> {
+    let mut usage = BTreeMap::new();
+    for file in files {
+        for stats in file.stats.iter().filter(|s| s.counts.count > 0) {
+            let entry = usage
+                .entry((file.file_type().to_string(), stats.line_type))
+                .or_insert((0, OneCounts::default()));
+            entry.0 += 1;
+            entry.1.count += stats.counts.count;
+            entry.1.max = entry.1.max.max(stats.counts.max);
+            entry.1.total += stats.counts.total;
+        }
+    }
+    usage
+        .into_iter()
+        .map(|((file_type, line_type), (files, counts))| LineTypeUsage {
+            file_type,
+            line_type,
+            files,
+            counts,
+        })
+        .collect()
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            collect_files(&entry.path(), paths);
+        } else if file_type.is_file() || entry.path().is_file() {
+            paths.push(entry.path());
+        }
+    }
+}
+
+fn describe(path: &Path) -> Option<FileInfo> {
+    let kind = sniff(path)?;
+    kind.file_type()?;
+    let mut info = FileInfo {
+        path: path.to_path_buf(),
+        kind,
+        sub_type: None,
+        size: std::fs::metadata(path).map_or(0, |m| m.len()),
+        fingerprint: None,
+        stats: Vec::new(),
+        error: None,
+    };
+    match OneFile::open_read(&path.to_string_lossy(), None, None, 1) {
+        Ok(file) => {
+            info.sub_type = file.sub_type();
+            info.fingerprint = file.schema_fingerprint().ok();
+            info.stats = file.all_stats();
+        }
+        Err(e) => info.error = Some(e.to_string()),
+    }
+    Some(info)
+}
@@ -11,19 +11,109 @@
 //! // Open a ONE file for reading
 //! let file = OneFile::open_read("data.1seq", None, None, 1).unwrap();
 //! ```
+//!
+//! Everything built on ONElib needs the default `onelib` feature. Without it,
+//! and with `pure-rust`, only the C-free parts are built: `ascii::AsciiReader`
+//! and the types it returns.
 
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+#[cfg(feature = "onelib")]
 pub mod ffi;
 pub mod error;
 pub mod types;
+#[cfg(feature = "onelib")]
 pub mod file;
 pub mod schema;
+#[cfg(feature = "onelib")]
+pub mod lines;
+#[cfg(feature = "onelib")]
+pub mod stats;
+pub mod record;
+pub mod linetypes;
+pub mod store;
+pub mod names;
+#[cfg(feature = "onelib")]
+pub mod seq;
+#[cfg(feature = "onelib")]
+pub mod gdb;
+#[cfg(feature = "onelib")]
+pub mod aln;
+pub mod coord;
+#[cfg(feature = "onelib")]
+pub mod protein;
+#[cfg(feature = "onelib")]
+pub mod convert;
+#[cfg(feature = "onelib")]
+pub mod merge;
+#[cfg(feature = "onelib")]
+pub mod region;
+#[cfg(feature = "onelib")]
+pub mod manifest;
+#[cfg(feature = "onelib")]
+pub mod lineage;
+#[cfg(feature = "onelib")]
+pub mod writer;
+#[cfg(feature = "onelib")]
+pub mod mode;
+pub mod sniff;
+#[cfg(feature = "onelib")]
+pub mod inventory;
+#[cfg(feature = "fastpath")]
+pub mod fastpath;
+#[cfg(feature = "debug-ffi")]
+mod debug_ffi;
+#[cfg(feature = "pure-rust")]
+pub mod ascii;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "python")]
+mod python;
 
 // Re-export main types
-pub use error::{OneError, Result};
-pub use file::OneFile;
-pub use schema::OneSchema;
-pub use types::{OneType, OneProvenance, OneReference};
+pub use error::{ErrorReport, LineTypeMismatch, OneError, Result};
+#[cfg(feature = "onelib")]
+pub use file::{AccessStats, FileMetadata, OneFile, OneFileThread, OpenOptions, WriteOptions};
+#[cfg(feature = "onelib")]
+pub use mode::{NullWriter, OneReader, OneWriter};
+#[cfg(feature = "onelib")]
+pub use schema::{register_schema, OneSchema, SchemaBuilder};
+#[cfg(feature = "onelib")]
+pub use lines::{Group, Groups, Line, Lines, ObjectLines, Objects, OneObject};
+pub use record::{FieldValue, ListValue, Record};
+pub use types::{
+    Alphabet, DnaEncoding, LineStats, OneCounts, OneProvenance, OneReference, RealFormat,
+    Timestamp, Warning,
+};
+#[cfg(feature = "onelib")]
+pub use types::{FieldSlot, OneType};
+pub use store::{CachedSequenceStore, SequenceStore};
+pub use names::NameNormalizer;
+#[cfg(feature = "onelib")]
+pub use seq::{DeflinePolicy, ExportOptions, FastaOptions, Minimizers, SeqReader, SequenceChunks};
+#[cfg(feature = "onelib")]
+pub use gdb::{DuplicatePolicy, GdbGroup, GdbIndex, GdbReader};
+#[cfg(feature = "onelib")]
+pub use aln::{Alignment, AlnReader, NormalizedAlignment, TraceInterval};
+pub use coord::{Coord, Span};
+#[cfg(feature = "onelib")]
+pub use coord::CoordinateMapper;
+#[cfg(feature = "onelib")]
+pub use protein::{Protein, ProteinReader, ProteinWriter};
+#[cfg(feature = "onelib")]
+pub use merge::{merge, split, split_by_objects};
+#[cfg(feature = "onelib")]
+pub use region::RegionIndex;
+#[cfg(feature = "onelib")]
+pub use manifest::{Manifest, ManifestEntry};
+#[cfg(feature = "onelib")]
+pub use lineage::{FileNode, ProvenanceGraph};
+#[cfg(feature = "onelib")]
+pub use writer::{BufferedWriter, ObjectOrder};
+pub use sniff::{sniff, FileKind};
+#[cfg(feature = "onelib")]
+pub use inventory::{line_type_usage, scan_dir, FileInfo, LineTypeUsage};
+#[cfg(feature = "onelib")]
+pub use stats::StatsAccumulator;
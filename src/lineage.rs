@@ -0,0 +1,201 @@
+//! Creation times and the chain of upstream files of a ONE file
+//!
+//! Each provenance ('!') line records a program run with its date, and the
+//! last one is the run that wrote the file; reference ('<') lines name the
+//! files it was built from. `ProvenanceGraph::from_file()` follows the
+//! references recursively, as far as the referenced files can be found, so
+//! workflow systems can export the lineage, e.g. as W3C PROV:
+//!
+//! ```no_run
+//! use onecode::ProvenanceGraph;
+//!
+//! let graph = ProvenanceGraph::from_file("alignments.1aln")?;
+//! for (derived, source) in &graph.derived_from {
+//!     println!("{} <- {}", graph.files[*derived].path, graph.files[*source].path);
+//! }
+//! std::fs::write("alignments.provn", graph.to_prov_n())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::error::Result;
+use crate::file::OneFile;
+use crate::types::{OneProvenance, Timestamp};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A file of a `ProvenanceGraph`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileNode {
+    /// Path as opened, or as named in the reference if it was not found
+    pub path: String,
+    /// Whether the file was found and its header read
+    pub resolved: bool,
+    pub file_type: Option<String>,
+    /// Provenance lines, oldest first; the last is the run that wrote the file
+    pub provenance: Vec<OneProvenance>,
+}
+
+impl FileNode {
+    fn new(path: &str) -> Self {
+        FileNode {
+            path: path.to_string(),
+            resolved: false,
+            file_type: None,
+            provenance: Vec::new(),
+        }
+    }
+
+    /// The run that wrote the file, i.e. its last provenance line
+    pub fn generated_by(&self) -> Option<&OneProvenance> {
+        self.provenance.last()
+    }
+
+    /// When the file was written, see `OneFile::created()`
+    pub fn created(&self) -> Option<Timestamp> {
+        self.generated_by()?.timestamp()
+    }
+}
+
+/// The files a ONE file was derived from, gathered through reference lines
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProvenanceGraph {
+    /// The file the graph was built from first, then its upstream files
+    pub files: Vec<FileNode>,
+    /// (derived, source) pairs of indexes into `files`, one per reference
+    pub derived_from: Vec<(usize, usize)>,
+}
+
+impl ProvenanceGraph {
+    /// Build the graph of `path` and, recursively, the files it references
+    ///
+    /// Relative references are looked up next to the referencing file, then
+    /// as given. Files that cannot be found or opened are kept as
+    /// unresolved leaves; each file appears once however often it is
+    /// referenced, so reference cycles end. Fails only if `path` itself
+    /// cannot be opened.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let file = OneFile::open_read(path, None, None, 1)?;
+        let mut graph = ProvenanceGraph::default();
+        let mut seen = HashMap::new();
+        let mut pending = vec![(0, file_key(Path::new(path)), Some(file))];
+        graph.files.push(FileNode::new(path));
+        seen.insert(pending[0].1.clone(), 0);
+
+        while let Some((index, key, file)) = pending.pop() {
+            let Some(file) = file else { continue };
+            let node = &mut graph.files[index];
+            node.resolved = true;
+            node.file_type = file.file_type();
+            node.provenance = file.provenance();
+
+            for (name, _) in file.get_references() {
+                let found = resolve(&key, &name);
+                let source_key = found
+                    .as_deref()
+                    .map_or_else(|| PathBuf::from(&name), file_key);
+                let source = match seen.get(&source_key) {
+                    Some(&source) => source,
+                    None => {
+                        let source = graph.files.len();
+                        let opened = found
+                            .as_ref()
+                            .and_then(|p| OneFile::open_read(p.to_str()?, None, None, 1).ok());
+                        let shown = found
+                            .as_ref()
+                            .map_or(name.clone(), |p| p.display().to_string());
+                        graph.files.push(FileNode::new(&shown));
+                        seen.insert(source_key.clone(), source);
+                        pending.push((source, source_key, opened));
+                        source
+                    }
+                };
+                graph.derived_from.push((index, source));
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Indexes of the files `file` was directly derived from
+    pub fn sources(&self, file: usize) -> impl Iterator<Item = usize> + '_ {
+        self.derived_from
+            .iter()
+            .filter(move |&&(derived, _)| derived == file)
+            .map(|&(_, source)| source)
+    }
+
+    /// The graph in PROV-N, the W3C PROV notation
+    ///
+    /// Files are entities `one:file<i>` and the run that wrote each file an
+    /// activity `one:run<i>`, with the program, version and command as
+    /// attributes and the date as its end time. References become
+    /// `wasDerivedFrom` and `used` relations. Older provenance lines, which
+    /// the writing program inherited from its inputs, are left to the
+    /// files they came from.
+    pub fn to_prov_n(&self) -> String {
+        let mut out = String::from("document\n  prefix one <urn:onecode:>\n");
+        for (i, node) in self.files.iter().enumerate() {
+            let mut attributes = vec![format!("prov:label=\"{}\"", escape(&node.path))];
+            if let Some(file_type) = &node.file_type {
+                attributes.push(format!("one:fileType=\"{}\"", escape(file_type)));
+            }
+            out.push_str(&format!(
+                "  entity(one:file{}, [{}])\n",
+                i,
+                attributes.join(", ")
+            ));
+            if let Some(run) = node.generated_by() {
+                let end = run.timestamp().map_or("-".to_string(), |t| t.to_string());
+                out.push_str(&format!(
+                    concat!(
+                        "  activity(one:run{}, -, {}, [prov:label=\"{}\", ",
+                        "one:version=\"{}\", one:command=\"{}\"])\n"
+                    ),
+                    i,
+                    end,
+                    escape(&run.program),
+                    escape(&run.version),
+                    escape(&run.command)
+                ));
+                out.push_str(&format!(
+                    "  wasGeneratedBy(one:file{}, one:run{}, {})\n",
+                    i, i, end
+                ));
+            }
+        }
+        for &(derived, source) in &self.derived_from {
+            out.push_str(&format!(
+                "  wasDerivedFrom(one:file{}, one:file{})\n",
+                derived, source
+            ));
+            if self.files[derived].generated_by().is_some() {
+                out.push_str(&format!(
+                    "  used(one:run{}, one:file{}, -)\n",
+                    derived, source
+                ));
+            }
+        }
+        out.push_str("endDocument\n");
+        out
+    }
+}
+
+/// Identity of a file for spotting repeat references
+fn file_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Where a file referenced as `name` from `referrer` is, if it exists
+fn resolve(referrer: &Path, name: &str) -> Option<PathBuf> {
+    let beside = referrer.parent().map(|dir| dir.join(name));
+    beside
+        .into_iter()
+        .chain(std::iter::once(PathBuf::from(name)))
+        .find(|p| p.is_file())
+}
+
+/// A PROV-N string literal body
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
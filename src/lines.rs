@@ -0,0 +1,585 @@
+//! Iterator over the lines of a ONE file
+//!
+//! `OneFile::lines()` returns a `Lines` iterator yielding an owned `Line` per
+//! data line. Filters set on the iterator before it is consumed decide which
+//! lines are copied out at all, so filtered scans read declaratively:
+//!
+//! ```no_run
+//! use onecode::OneFile;
+//!
+//! let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+//! for line in file.lines().of_type('S').in_group('g', 1) {
+//!     println!("{}", line.string().unwrap_or(""));
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! `&mut OneFile` is itself iterable, and the usual iterator adaptors apply:
+//!
+//! ```no_run
+//! use onecode::OneFile;
+//!
+//! let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+//! let skeleton_lines = file.lines().take_while(|l| l.line_type() != 'A').count();
+//! println!("{} lines before the first alignment", skeleton_lines);
+//!
+//! let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+//! for line in &mut file {
+//!     if let Some(n) = line.get_int(0) {
+//!         println!("{} {}", line.line_type(), n);
+//!     }
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use crate::error::Result;
+use crate::ffi::OneType;
+use crate::file::OneFile;
+use crate::types::FieldSlot;
+use std::collections::{BTreeSet, HashSet};
+
+/// List data copied out of a line
+#[derive(Debug, Clone)]
+pub(crate) enum List {
+    None,
+    Bytes(Vec<u8>), // STRING, STRING_LIST and DNA (as characters)
+    Ints(Vec<i64>),
+    Reals(Vec<f64>),
+}
+
+impl List {
+    /// Bytes per element, as ONElib's `listEltSize` for the list's type
+    pub(crate) fn elt_size(&self) -> i32 {
+        match self {
+            List::None => 0,
+            List::Ints(_) | List::Reals(_) => 8,
+            List::Bytes(_) => 1,
+        }
+    }
+
+    /// Copy of the list of `file`'s current line, whose list type is `list_type`
+    pub(crate) fn current(file: &OneFile, list_type: OneType) -> Self {
+        match list_type {
+            OneType::oneINT_LIST => List::Ints(file.int_list().unwrap_or(&[]).to_vec()),
+            OneType::oneREAL_LIST => List::Reals(file.real_list().unwrap_or(&[]).to_vec()),
+            _ => List::Bytes(file.list_bytes(list_type).to_vec()),
+        }
+    }
+}
+
+/// A line read from a ONE file, with its fields and list copied out
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub(crate) line_type: char,
+    pub(crate) fields: Vec<FieldSlot>,
+    pub(crate) field_types: Vec<OneType>,
+    pub(crate) list: List,
+    pub(crate) comment: Option<String>,
+}
+
+impl Line {
+    /// Snapshot the current line of `file`
+    pub(crate) fn from_current(file: &mut OneFile, with_comment: bool) -> Self {
+        let line_type = file.line_type();
+        let (fields, field_types, list) = unsafe {
+            let ptr = file.ptr;
+            let info = (*ptr).info[line_type as usize];
+            let n_field = (*info).nField as usize;
+            let fields = (0..n_field).map(|i| file.field(i)).collect();
+            let field_types = (0..n_field).map(|i| *(*info).fieldType.add(i)).collect();
+            let list = if (*info).listEltSize == 0 {
+                List::None
+            } else {
+                List::current(file, *(*info).fieldType.add((*info).listField as usize))
+            };
+            (fields, field_types, list)
+        };
+        let comment = if with_comment { file.read_comment() } else { None };
+        Line {
+            line_type,
+            fields,
+            field_types,
+            list,
+            comment,
+        }
+    }
+
+    /// The line type character
+    pub fn line_type(&self) -> char {
+        self.line_type
+    }
+
+    /// Number of fields of the line, including the list field if any
+    pub fn num_fields(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Schema type of field `field`, or None if the line has no such field
+    pub fn field_type(&self, field: usize) -> Option<OneType> {
+        self.field_types.get(field).copied()
+    }
+
+    /// Get field `field` if it exists and is an INT
+    pub fn get_int(&self, field: usize) -> Option<i64> {
+        self.fields.get(field)?.int(self.field_types[field])
+    }
+
+    /// Get field `field` if it exists and is a REAL
+    pub fn get_real(&self, field: usize) -> Option<f64> {
+        self.fields.get(field)?.real(self.field_types[field])
+    }
+
+    /// Get field `field` if it exists and is a CHAR
+    pub fn get_char(&self, field: usize) -> Option<char> {
+        self.fields.get(field)?.char(self.field_types[field])
+    }
+
+    /// Get an integer field value
+    ///
+    /// Like `OneFile::int()` this does not check the field's type; see
+    /// `get_int()` for a checked version.
+    pub fn int(&self, field: usize) -> i64 {
+        self.fields[field].as_int()
+    }
+
+    /// Get a real field value
+    pub fn real(&self, field: usize) -> f64 {
+        self.fields[field].as_real()
+    }
+
+    /// Get a character field value
+    pub fn char(&self, field: usize) -> char {
+        self.fields[field].as_char()
+    }
+
+    /// Get the string of a line whose list field is a STRING
+    pub fn string(&self) -> Option<&str> {
+        match &self.list {
+            List::Bytes(b) => std::str::from_utf8(b).ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the DNA of a line whose list field is DNA, as lower-case characters
+    pub fn dna(&self) -> Option<&[u8]> {
+        match &self.list {
+            List::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Get the integer list of the line
+    pub fn int_list(&self) -> Option<&[i64]> {
+        match &self.list {
+            List::Ints(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Get the real list of the line
+    pub fn real_list(&self) -> Option<&[f64]> {
+        match &self.list {
+            List::Reals(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Get the comment attached to the line
+    ///
+    /// Only filled in when the iterator was created with `with_comments()`.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+}
+
+/// Iterator over the lines of a `OneFile`, created by `OneFile::lines()`
+///
+/// Reading starts at the file's current position. The filter methods may be
+/// chained; a line is yielded only if it passes all of them.
+pub struct Lines<'a> {
+    file: &'a mut OneFile,
+    types: Option<HashSet<char>>,
+    group: Option<(char, i64)>,
+    comments: bool,
+    done: bool,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(file: &'a mut OneFile) -> Self {
+        Lines {
+            file,
+            types: None,
+            group: None,
+            comments: false,
+            done: false,
+        }
+    }
+
+    /// Only yield lines of type `line_type`
+    ///
+    /// Calling this repeatedly accepts each of the given types.
+    pub fn of_type(mut self, line_type: char) -> Self {
+        self.types.get_or_insert_with(HashSet::new).insert(line_type);
+        self
+    }
+
+    /// Only yield lines inside group object `n` (1-indexed) of type `group_type`
+    ///
+    /// This includes the group line itself and every line the schema places
+    /// within that group, e.g. `in_group('g', 2)` in a `.1aln` file yields the
+    /// second GDB skeleton. Iteration stops once the group has been passed,
+    /// and at once if `group_type` is not an ASCII line type.
+    pub fn in_group(mut self, group_type: char, n: i64) -> Self {
+        self.group = Some((group_type, n));
+        self
+    }
+
+    /// Attach the comment of each yielded line, see `Line::comment()`
+    pub fn with_comments(mut self) -> Self {
+        self.comments = true;
+        self
+    }
+
+    /// Check the group filter: Some(true) to keep, Some(false) to skip, None to stop
+    fn group_filter(&self, line_type: char) -> Option<bool> {
+        let Some((group_type, n)) = self.group else {
+            return Some(true);
+        };
+        if !group_type.is_ascii() {
+            return None;
+        }
+        let count = self.file.object(group_type);
+        if count > n {
+            return None;
+        }
+        let contained = unsafe {
+            let info = (*self.file.ptr).info[group_type as usize];
+            !info.is_null() && (*info).contains[line_type as usize]
+        };
+        Some(count == n && (line_type == group_type || contained))
+    }
+}
+
+impl Iterator for Lines<'_> {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        while !self.done {
+            let line_type = self.file.read_line();
+            if line_type == '\0' {
+                self.done = true;
+                break;
+            }
+            match self.group_filter(line_type) {
+                None => self.done = true,
+                Some(false) => {}
+                Some(true) => {
+                    if self.types.as_ref().is_none_or(|t| t.contains(&line_type)) {
+                        return Some(Line::from_current(self.file, self.comments));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a> IntoIterator for &'a mut OneFile {
+    type Item = Line;
+    type IntoIter = Lines<'a>;
+
+    fn into_iter(self) -> Lines<'a> {
+        self.lines()
+    }
+}
+
+/// Iterator over chosen objects with their lines, created by
+/// `OneFile::sample_objects()` and `OneFile::last_objects()`
+///
+/// Yields each object's number (1-indexed, as for `OneFile::goto()`) with
+/// its lines, in increasing order of object number.
+pub struct ObjectLines<'a> {
+    file: &'a mut OneFile,
+    line_type: char,
+    objects: std::vec::IntoIter<i64>,
+}
+
+impl<'a> ObjectLines<'a> {
+    /// Read `objects`, which must be in increasing order
+    pub(crate) fn new(file: &'a mut OneFile, line_type: char, objects: Vec<i64>) -> Self {
+        ObjectLines {
+            file,
+            line_type,
+            objects: objects.into_iter(),
+        }
+    }
+
+    /// Number of objects left to yield
+    pub fn remaining(&self) -> usize {
+        self.objects.len()
+    }
+
+}
+
+/// Whether the schema of `file` declares `line_type` an object type
+pub(crate) fn is_object(file: &OneFile, line_type: char) -> bool {
+    unsafe {
+        let info = (*file.ptr).info[line_type as usize];
+        !info.is_null() && (*info).isObject
+    }
+}
+
+impl Iterator for ObjectLines<'_> {
+    type Item = Result<(i64, Vec<Line>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let object = self.objects.next()?;
+        if let Err(e) = self.file.goto(self.line_type, object) {
+            self.objects = Vec::new().into_iter();
+            return Some(Err(e));
+        }
+        self.file.read_line();
+        let mut lines = vec![Line::from_current(self.file, false)];
+        loop {
+            let line_type = self.file.read_line();
+            if line_type == '\0' || is_object(self.file, line_type) {
+                break;
+            }
+            lines.push(Line::from_current(self.file, false));
+        }
+        Some(Ok((object, lines)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.objects.size_hint()
+    }
+}
+
+/// An object line with the data lines that follow it, from `OneFile::objects()`
+#[derive(Debug, Clone)]
+pub struct OneObject {
+    /// Object number, 1-indexed as for `OneFile::goto()`
+    pub number: i64,
+    /// The object line itself
+    pub line: Line,
+    /// The lines after it up to the next object line of any type, in file order
+    pub lines: Vec<Line>,
+}
+
+impl OneObject {
+    /// The object's data lines of type `line_type`
+    pub fn lines_of_type(&self, line_type: char) -> impl Iterator<Item = &Line> {
+        self.lines.iter().filter(move |l| l.line_type == line_type)
+    }
+}
+
+/// Iterator over the objects of one type, created by `OneFile::objects()`
+///
+/// Reading starts at the file's current position. Lines before the first
+/// object of the type, and objects of other types with their lines, are
+/// skipped.
+pub struct Objects<'a> {
+    file: &'a mut OneFile,
+    line_type: char,
+    pending: bool, // the current line is an object line not yet considered
+    done: bool,
+}
+
+impl<'a> Objects<'a> {
+    pub(crate) fn new(file: &'a mut OneFile, line_type: char) -> Self {
+        Objects {
+            file,
+            line_type,
+            pending: false,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Objects<'_> {
+    type Item = OneObject;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Reading on after the end of the data is an error in ONElib
+        if self.done {
+            return None;
+        }
+        loop {
+            let line_type = if std::mem::take(&mut self.pending) {
+                self.file.line_type()
+            } else {
+                self.file.read_line()
+            };
+            if line_type == '\0' {
+                self.done = true;
+                return None;
+            }
+            if line_type == self.line_type {
+                break;
+            }
+        }
+
+        let number = self.file.object(self.line_type);
+        let line = Line::from_current(self.file, false);
+        let mut lines = Vec::new();
+        loop {
+            let line_type = self.file.read_line();
+            if line_type == '\0' {
+                self.done = true;
+                break;
+            }
+            if is_object(self.file, line_type) {
+                self.pending = true;
+                break;
+            }
+            lines.push(Line::from_current(self.file, false));
+        }
+        Some(OneObject {
+            number,
+            line,
+            lines,
+        })
+    }
+}
+
+/// A group object with its members, from `OneFile::groups()`
+///
+/// A group line, e.g. a 'g' GDB skeleton of a `.1aln` file, is followed by
+/// the objects the schema places in the group ('S' scaffolds for 'g'), each
+/// with its data lines.
+#[derive(Debug, Clone)]
+pub struct Group {
+    /// Group number, 1-indexed as for `OneFile::goto()`
+    pub number: i64,
+    /// The group line itself, holding any group-level fields
+    pub line: Line,
+    /// Data lines between the group line and its first member
+    pub lines: Vec<Line>,
+    /// Member objects in file order
+    pub objects: Vec<OneObject>,
+}
+
+impl Group {
+    /// The member objects of type `line_type`
+    pub fn objects_of_type(&self, line_type: char) -> impl Iterator<Item = &OneObject> {
+        self.objects.iter().filter(move |o| o.line.line_type == line_type)
+    }
+}
+
+/// Iterator over the groups of one type, created by `OneFile::groups()`
+///
+/// Reading starts at the file's current position. A group ends at the next
+/// group line of its type, or at the first object line the schema does not
+/// place in it, e.g. the alignments after the last skeleton.
+pub struct Groups<'a> {
+    file: &'a mut OneFile,
+    group_type: char,
+    pending: bool, // the current line is an object line not yet considered
+    done: bool,
+}
+
+impl<'a> Groups<'a> {
+    pub(crate) fn new(file: &'a mut OneFile, group_type: char) -> Self {
+        Groups {
+            file,
+            group_type,
+            pending: false,
+            done: false,
+        }
+    }
+
+    fn contains(&self, line_type: char) -> bool {
+        unsafe {
+            let info = (*self.file.ptr).info[self.group_type as usize];
+            !info.is_null() && (*info).contains[line_type as usize]
+        }
+    }
+}
+
+impl Iterator for Groups<'_> {
+    type Item = Group;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let line_type = if std::mem::take(&mut self.pending) {
+                self.file.line_type()
+            } else {
+                self.file.read_line()
+            };
+            if line_type == '\0' {
+                self.done = true;
+                return None;
+            }
+            if line_type == self.group_type {
+                break;
+            }
+        }
+
+        let mut group = Group {
+            number: self.file.object(self.group_type),
+            line: Line::from_current(self.file, false),
+            lines: Vec::new(),
+            objects: Vec::new(),
+        };
+        loop {
+            let line_type = self.file.read_line();
+            if line_type == '\0' {
+                self.done = true;
+                break;
+            }
+            if !is_object(self.file, line_type) {
+                let line = Line::from_current(self.file, false);
+                match group.objects.last_mut() {
+                    Some(object) => object.lines.push(line),
+                    None => group.lines.push(line),
+                }
+            } else if line_type != self.group_type && self.contains(line_type) {
+                group.objects.push(OneObject {
+                    number: self.file.object(line_type),
+                    line: Line::from_current(self.file, false),
+                    lines: Vec::new(),
+                });
+            } else {
+                self.pending = true;
+                break;
+            }
+        }
+        Some(group)
+    }
+}
+
+/// Choose `n` of objects 1..=`count` uniformly with Floyd's algorithm, in
+/// increasing order
+pub(crate) fn sample(count: i64, n: usize, seed: u64) -> Vec<i64> {
+    let mut rng = SplitMix64(seed);
+    let mut chosen = BTreeSet::new();
+    let n = (n as i64).min(count);
+    for j in count - n + 1..=count {
+        let pick = 1 + rng.below(j as u64) as i64;
+        if !chosen.insert(pick) {
+            chosen.insert(j);
+        }
+    }
+    chosen.into_iter().collect()
+}
+
+/// Small seeded generator for reproducible sampling
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0..n, by multiply-shift
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next() as u128 * n as u128) >> 64) as u64
+    }
+}
@@ -0,0 +1,98 @@
+//! Line type characters of the standard schemas
+//!
+//! Named constants for the line types defined by `SEQ_SCHEMA_TEXT`,
+//! `GDB_SCHEMA_TEXT`, `ALN_SCHEMA_TEXT` and `PROTEIN_SCHEMA_TEXT`, so that code reading these files
+//! says what it means and can be found with a search when a schema changes.
+//! The constants work in `match` patterns:
+//!
+//! ```no_run
+//! use onecode::linetypes::seq;
+//! use onecode::OneFile;
+//!
+//! let mut file = OneFile::open_read("data.1seq", None, Some("seq"), 1)?;
+//! loop {
+//!     match file.read_line() {
+//!         '\0' => break,
+//!         seq::SEQUENCE => println!("{} bp", file.len()),
+//!         seq::IDENTIFIER => println!("{}", file.string()?),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+/// Line types of `.1seq` sequence files
+pub mod seq {
+    /// Object: the DNA of one sequence
+    pub const SEQUENCE: char = 'S';
+    /// Optional sequence identifier
+    pub const IDENTIFIER: char = 'I';
+    /// Quality values, as an ASCII string of q+33
+    pub const QUALITY: char = 'Q';
+    /// Non-ACGT run: position, base, count
+    pub const NON_ACGT: char = 'N';
+    /// Full FASTA defline, only with the `seq::DESCRIPTION_SCHEMA_TEXT` extension
+    pub const DESCRIPTION: char = 'D';
+}
+
+/// Line types of `.1gdb` GDB skeleton files
+pub mod gdb {
+    /// Global base frequency vector
+    pub const FREQUENCY: char = 'f';
+    /// Object: a scaffold, carrying its name
+    pub const SCAFFOLD: char = 'S';
+    /// Gap of given length
+    pub const GAP: char = 'G';
+    /// Contig of given length
+    pub const CONTIG: char = 'C';
+    /// Mask interval list for a contig
+    pub const MASK: char = 'M';
+}
+
+/// Line types of `.1aln` alignment files
+pub mod aln {
+    /// Trace point spacing, global
+    pub const TRACE_SPACING: char = 't';
+    /// Group object: an embedded GDB skeleton, one per genome
+    pub const GDB_GROUP: char = 'g';
+    /// Object: a scaffold of an embedded skeleton
+    pub const SCAFFOLD: char = 'S';
+    /// Gap of given length in an embedded skeleton
+    pub const GAP: char = 'G';
+    /// Contig of given length in an embedded skeleton
+    pub const CONTIG: char = 'C';
+    /// Mask interval list for a contig in an embedded skeleton
+    pub const MASK: char = 'M';
+    /// Group object: a colinear chain of alignments
+    pub const CHAIN: char = 'a';
+    /// Spacing in a and b between the previous alignment of a chain and the next
+    pub const CHAIN_SPACING: char = 'p';
+    /// Object: an alignment, a_id a_start a_end b_id b_start b_end
+    pub const ALIGNMENT: char = 'A';
+    /// Lengths of sequences a and b
+    pub const LENGTHS: char = 'L';
+    /// Flag: b is reverse-complemented
+    pub const REVERSE: char = 'R';
+    /// Number of differences
+    pub const DIFFERENCES: char = 'D';
+    /// Trace points in b
+    pub const TRACE: char = 'T';
+    /// Differences per trace interval
+    pub const TRACE_DIFFS: char = 'X';
+    /// Alignment confidence in phred units
+    pub const QUALITY: char = 'Q';
+    /// Number of equal bases
+    pub const MATCHES: char = 'E';
+    /// CIGAR string
+    pub const CIGAR: char = 'Z';
+}
+
+/// Line types of `.1pro` protein files
+pub mod pro {
+    /// Object: the amino acids of one protein
+    pub const SEQUENCE: char = 'S';
+    /// Optional sequence identifier
+    pub const IDENTIFIER: char = 'I';
+    /// Source sequence and reading frame of a translation
+    pub const FRAME: char = 'F';
+}
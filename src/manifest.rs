@@ -0,0 +1,266 @@
+//! Manifests describing multi-file datasets
+//!
+//! A manifest is a small ASCII ONE file listing the component files of a
+//! dataset (for example the `.1seq`, `.1gdb` and `.1aln` shards of a project)
+//! together with their file type, schema fingerprint, object counts, size and
+//! checksum. `Manifest::verify()` re-examines the files and reports anything
+//! that no longer matches, so a bundle can be integrity-checked before use.
+
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::schema::{fnv1a, OneSchema, FNV_OFFSET};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Schema for manifest files
+pub const MANIFEST_SCHEMA_TEXT: &str = "\
+P 8 manifest              DATASET MANIFEST
+O F 1 6 STRING            component file path, relative to the manifest
+D T 1 6 STRING            primary file type
+D H 1 3 INT               schema fingerprint
+D N 2 4 CHAR 3 INT        object line type, number of objects
+D B 1 3 INT               file size in bytes
+D K 1 3 INT               checksum: 64-bit FNV-1a of the file contents
+";
+
+/// Description of one component file of a dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// Path of the file as listed in the manifest
+    pub path: String,
+    /// Primary file type, e.g. "seq" or "aln"
+    pub file_type: String,
+    /// Schema fingerprint, see `OneSchema::fingerprint()`
+    pub fingerprint: u64,
+    /// Number of objects of each object line type
+    pub counts: Vec<(char, i64)>,
+    /// File size in bytes
+    pub size: u64,
+    /// 64-bit FNV-1a checksum of the file contents
+    pub checksum: u64,
+}
+
+impl ManifestEntry {
+    /// Describe the ONE file at `path`, listing it under `name`
+    fn describe(path: &Path, name: &str) -> Result<Self> {
+        let path_str = path.to_string_lossy();
+        let file = OneFile::open_read(&path_str, None, None, 1)?;
+        let mut counts = Vec::new();
+        for line_type in object_types(&file) {
+            counts.push((line_type, file.stats(line_type)?.0));
+        }
+        let (size, checksum) = checksum_file(path)?;
+        Ok(ManifestEntry {
+            path: name.to_string(),
+            file_type: file.file_type().unwrap_or_default(),
+            fingerprint: file.schema_fingerprint()?,
+            counts,
+            size,
+            checksum,
+        })
+    }
+}
+
+/// A list of dataset component files with integrity information
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    entries: Vec<ManifestEntry>,
+    dir: PathBuf, // directory relative paths are resolved against
+}
+
+impl Manifest {
+    /// Create an empty manifest whose relative paths resolve against the current directory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty manifest whose relative paths resolve against `dir`
+    ///
+    /// Use the directory the manifest will be written to, so that the listed
+    /// paths stay valid when the dataset is moved as a whole.
+    pub fn with_dir(dir: &str) -> Self {
+        Manifest {
+            entries: Vec::new(),
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    /// Component files in the order they were added
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// Look up a component file by its listed path
+    pub fn entry(&self, path: &str) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    /// Add a ONE file to the manifest, recording its current state
+    ///
+    /// Relative paths are taken relative to the manifest's directory, which is
+    /// the current directory for `Manifest::new()`.
+    pub fn add_file(&mut self, path: &str) -> Result<&ManifestEntry> {
+        if self.entry(path).is_some() {
+            return Err(OneError::Other(format!(
+                "File already listed in manifest: {}",
+                path
+            )));
+        }
+        let entry = ManifestEntry::describe(&self.resolve(path), path)?;
+        self.entries.push(entry);
+        Ok(self.entries.last().unwrap())
+    }
+
+    /// Write the manifest as an ASCII ONE file
+    pub fn write(&self, path: &str) -> Result<()> {
+        let schema = OneSchema::from_text(MANIFEST_SCHEMA_TEXT)?;
+        let mut file = OneFile::open_write_new(path, &schema, "manifest", false, 1)?;
+        for entry in &self.entries {
+            file.write_string('F', &entry.path)?;
+            file.write_string('T', &entry.file_type)?;
+            file.set_int(0, entry.fingerprint as i64);
+            file.write_line('H', 0, None);
+            for &(line_type, count) in &entry.counts {
+                file.set_char(0, line_type);
+                file.set_int(1, count);
+                file.write_line('N', 0, None);
+            }
+            file.set_int(0, entry.size as i64);
+            file.write_line('B', 0, None);
+            file.set_int(0, entry.checksum as i64);
+            file.write_line('K', 0, None);
+        }
+        file.finalize()?;
+        Ok(())
+    }
+
+    /// Read a manifest file
+    ///
+    /// Relative component paths are resolved against the manifest's directory.
+    pub fn read(path: &str) -> Result<Self> {
+        let mut file = OneFile::open_read(path, None, Some("manifest"), 1)?;
+        let mut entries: Vec<ManifestEntry> = Vec::new();
+        loop {
+            let line_type = file.read_line();
+            if line_type == '\0' {
+                break;
+            }
+            if line_type == 'F' {
+                entries.push(ManifestEntry {
+                    path: file.string()?.to_string(),
+                    file_type: String::new(),
+                    fingerprint: 0,
+                    counts: Vec::new(),
+                    size: 0,
+                    checksum: 0,
+                });
+                continue;
+            }
+            let entry = entries.last_mut().ok_or_else(|| {
+                OneError::InvalidFormat(format!("{}: '{}' line before first 'F' line", path, line_type))
+            })?;
+            match line_type {
+                'T' => entry.file_type = file.string()?.to_string(),
+                'H' => entry.fingerprint = file.int(0) as u64,
+                'N' => entry.counts.push((file.char(0), file.int(1))),
+                'B' => entry.size = file.int(0) as u64,
+                'K' => entry.checksum = file.int(0) as u64,
+                _ => {}
+            }
+        }
+        let dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(Manifest { entries, dir })
+    }
+
+    /// Check that every listed file still matches its recorded description
+    ///
+    /// All files are examined; the error lists every mismatch found.
+    pub fn verify(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        for entry in &self.entries {
+            match ManifestEntry::describe(&self.resolve(&entry.path), &entry.path) {
+                Ok(actual) if actual == *entry => {}
+                Ok(actual) => problems.push(describe_mismatch(entry, &actual)),
+                Err(e) => problems.push(format!("{}: {}", entry.path, e)),
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(OneError::ManifestMismatch(problems.join("; ")))
+        }
+    }
+
+    /// Check that all files of each file type were written with identical schemas
+    ///
+    /// Uses only the recorded fingerprints, so it is cheap enough to run before
+    /// every merge of shards.
+    pub fn check_schemas(&self) -> Result<()> {
+        for (i, entry) in self.entries.iter().enumerate() {
+            let first = self.entries[..i]
+                .iter()
+                .find(|e| e.file_type == entry.file_type);
+            if let Some(first) = first {
+                if first.fingerprint != entry.fingerprint {
+                    return Err(OneError::ManifestMismatch(format!(
+                        "{} and {} have different {} schemas",
+                        first.path, entry.path, entry.file_type
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.dir.join(path)
+    }
+}
+
+/// Object line types defined in the schema of `file`
+fn object_types(file: &OneFile) -> Vec<char> {
+    let mut types = Vec::new();
+    unsafe {
+        for u in 1..128u8 {
+            let info = (*file.ptr).info[u as usize];
+            if !info.is_null() && (*info).isObject && u.is_ascii_alphabetic() {
+                types.push(u as char);
+            }
+        }
+    }
+    types
+}
+
+/// Size and FNV-1a checksum of the file at `path`
+fn checksum_file(path: &Path) -> Result<(u64, u64)> {
+    let io_error = |e: std::io::Error| OneError::Other(format!("{}: {}", path.display(), e));
+    let mut file = File::open(path).map_err(io_error)?;
+    let mut buf = vec![0u8; 1 << 16];
+    let mut hash = FNV_OFFSET;
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf).map_err(io_error)?;
+        if n == 0 {
+            break;
+        }
+        hash = fnv1a(hash, &buf[..n]);
+        size += n as u64;
+    }
+    Ok((size, hash))
+}
+
+fn describe_mismatch(expected: &ManifestEntry, actual: &ManifestEntry) -> String {
+    let what = if expected.file_type != actual.file_type {
+        "file type"
+    } else if expected.fingerprint != actual.fingerprint {
+        "schema"
+    } else if expected.counts != actual.counts {
+        "object counts"
+    } else if expected.size != actual.size {
+        "size"
+    } else {
+        "checksum"
+    };
+    format!("{}: {} differs from manifest", expected.path, what)
+}
@@ -0,0 +1,409 @@
+//! Concatenation of ONE files of one type into a single file, and splitting
+//! of one file into parts
+//!
+//! `merge()` joins the shards of a split run, e.g. the `.1aln` files of
+//! FastGA runs over parts of a genome, into the file one run would have
+//! written. `split()` and `split_by_objects()` go the other way, e.g. to
+//! spread a large `.1aln` over cluster jobs:
+//!
+//! ```no_run
+//! let lines = onecode::merge(&["part1.1aln", "part2.1aln"], "all.1aln")?;
+//! let parts = onecode::split("all.1aln", 8)?; // all.1.1aln ... all.8.1aln
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use crate::error::{LineTypeMismatch, OneError, Result};
+use crate::file::OneFile;
+use crate::gdb::GdbGroup;
+use crate::lines::is_object;
+use crate::linetypes::aln;
+use crate::record::FieldValue;
+use std::ops::Range;
+
+/// Line types of the skeletons embedded in an alignment file
+const SKELETON_TYPES: [char; 5] = [
+    aln::GDB_GROUP,
+    aln::SCAFFOLD,
+    aln::GAP,
+    aln::CONTIG,
+    aln::MASK,
+];
+
+/// Concatenate the objects of `inputs` into a new binary file `output`,
+/// returning the number of data lines written
+///
+/// The inputs must share their primary type and schema, and the lines
+/// before their first object, such as the trace spacing of an alignment
+/// file, which are written once. The header takes the provenance,
+/// references and header text of every input, each distinct line once,
+/// plus a provenance line for the merge.
+///
+/// The embedded skeletons of `.1aln` files are written once when the
+/// inputs share them. Otherwise the distinct skeletons of each 'g' group
+/// are joined into one, in input order, and the contig ids of the 'A'
+/// lines of later inputs are shifted past the contigs before theirs, so
+/// every alignment still names its contigs. This needs binary inputs with
+/// the same number of skeletons. Files of other types are concatenated as
+/// they are, their group numbers following on from those of the inputs
+/// before.
+pub fn merge(inputs: &[&str], output: &str) -> Result<i64> {
+    let Some(first_path) = inputs.first() else {
+        return Err(OneError::Other("No files to merge".to_string()));
+    };
+    let first = OneFile::open_read(first_path, None, None, 1)?;
+    let file_type = first.file_type().unwrap_or_default();
+    let schema = first.schema_text()?;
+    let with_skeletons = file_type == "aln" && first.stats(aln::GDB_GROUP)?.0 > 0;
+
+    let mut preamble = None;
+    let mut skeletons = Vec::new();
+    for path in inputs {
+        let mut file = OneFile::open_read(path, None, None, 1)?;
+        check_schema(&file, path, &file_type, &schema)?;
+        let lines = read_preamble(&mut file);
+        match &preamble {
+            None => preamble = Some(lines),
+            Some(first_lines) if *first_lines != lines => {
+                return Err(OneError::Other(format!(
+                    "{}: the lines before the first object differ from those of {}",
+                    path, first_path
+                )));
+            }
+            _ => {}
+        }
+        if with_skeletons {
+            let groups = file.stats(aln::GDB_GROUP)?.0;
+            skeletons.push(
+                (1..=groups)
+                    .map(|g| GdbGroup::read(&mut file, g))
+                    .collect::<Result<_>>()?,
+            );
+        }
+    }
+    let layout = SkeletonLayout::new(inputs, &skeletons)?;
+
+    let mut out = OneFile::open_write_from(output, &first, true, 1)?;
+    for path in &inputs[1..] {
+        let file = OneFile::open_read(path, None, None, 1)?;
+        out.inherit_provenance(&file);
+        out.inherit_reference(&file);
+        for text in file.header_text() {
+            if !out.header_text().contains(&text) {
+                out.add_header_text(&text)?;
+            }
+        }
+    }
+    out.dedup_header();
+    let command = format!("merge {} {}", output, inputs.join(" "));
+    out.add_provenance("onecode", env!("CARGO_PKG_VERSION"), &command)?;
+    drop(first);
+
+    let mut written = copy_preamble(first_path, &mut out)?;
+    for (group, members) in layout.groups.iter().enumerate() {
+        for (n, &input) in members.iter().enumerate() {
+            written += copy_skeleton(inputs[input], group as i64 + 1, n == 0, &mut out)?;
+        }
+    }
+    for (input, path) in inputs.iter().enumerate() {
+        let mut file = OneFile::open_read(path, None, None, 1)?;
+        let mut in_preamble = true;
+        loop {
+            let line_type = file.read_line();
+            if line_type == '\0' {
+                break;
+            }
+            in_preamble &= !is_object(&file, line_type);
+            if in_preamble || (with_skeletons && SKELETON_TYPES.contains(&line_type)) {
+                continue;
+            }
+            if line_type == aln::ALIGNMENT && layout.shifts(input) {
+                write_shifted(&file, &mut out, layout.offsets(input))?;
+            } else {
+                file.copy_line_to(&mut out)?;
+            }
+            written += 1;
+        }
+    }
+    out.finalize()?;
+    Ok(written)
+}
+
+/// Split the binary file `input` into `n_parts` files of about as many
+/// objects each, returning their paths
+///
+/// The parts are named after `input` with their number, counting from 1,
+/// before the extension: "all.1aln" splits into "all.1.1aln", "all.2.1aln"
+/// and so on. They are split on the most numerous object type that does
+/// not lie inside another object type the file has objects of: the 'A'
+/// alignments of a `.1aln` file, or its 'a' chains if it has them, and the
+/// 'S' sequences of a `.1seq` file. See `split_by_objects()` for what each
+/// part holds. There are fewer parts if the file has fewer objects.
+pub fn split(input: &str, n_parts: usize) -> Result<Vec<String>> {
+    if n_parts == 0 {
+        return Err(OneError::Other("Cannot split into 0 parts".to_string()));
+    }
+    let file = OneFile::open_read(input, None, None, 1)?;
+    let Some(line_type) = split_type(&file) else {
+        return Err(OneError::NoObjects(format!(
+            "{} has no object types",
+            input
+        )));
+    };
+    let count = file.stats(line_type)?.0;
+    if count == 0 {
+        return Err(OneError::NoObjects(format!("{} has no objects to split", input)));
+    }
+    let n = (n_parts as i64).min(count);
+    let ranges: Vec<Range<i64>> = (0..n)
+        .map(|k| 1 + k * count / n..1 + (k + 1) * count / n)
+        .collect();
+    split_by_objects(input, line_type, &ranges)
+}
+
+/// Write objects `ranges` of type `line_type` of the binary file `input`
+/// to a file each, returning their paths
+///
+/// Ranges count objects from 1, as `OneFile::goto()`, and are half-open:
+/// `1..101` is the first hundred. Each part holds what comes before the
+/// first `line_type` object, such as the embedded skeletons of a `.1aln`
+/// file, followed by the objects of its range and the lines up to the next
+/// `line_type` object; a range ending past the last object runs to the end
+/// of the file. The header is that of `input` with a provenance line for
+/// the split, and ONElib writes each part's counts and index. Parts are
+/// named as by `split()`.
+pub fn split_by_objects(
+    input: &str,
+    line_type: char,
+    ranges: &[Range<i64>],
+) -> Result<Vec<String>> {
+    let mut source = OneFile::open_read(input, None, None, 1)?;
+    if !source.is_binary() {
+        return Err(OneError::Other(format!(
+            "Splitting needs a binary file with an object index, which {} is not",
+            input
+        )));
+    }
+    let count = source.stats(line_type)?.0;
+    for range in ranges {
+        if range.start < 1 || range.start > range.end || range.end > count + 1 {
+            return Err(OneError::InvalidFormat(format!(
+                "Objects {}..{} are not within the {} '{}' objects of {}",
+                range.start, range.end, count, line_type, input
+            )));
+        }
+    }
+
+    let mut paths = Vec::with_capacity(ranges.len());
+    for (k, range) in ranges.iter().enumerate() {
+        let path = part_path(input, k + 1, ranges.len());
+        let mut out = OneFile::open_write_from(&path, &source, true, 1)?;
+        let command = format!(
+            "split {} {} {}..{}",
+            input, line_type, range.start, range.end
+        );
+        out.add_provenance("onecode", env!("CARGO_PKG_VERSION"), &command)?;
+
+        source.goto(line_type, 0)?;
+        loop {
+            let t = source.read_line();
+            if t == '\0' || t == line_type {
+                break;
+            }
+            source.copy_line_to(&mut out)?;
+        }
+        if !range.is_empty() {
+            source.goto(line_type, range.start)?;
+            loop {
+                let t = source.read_line();
+                if t == '\0' || (t == line_type && source.object(line_type) >= range.end) {
+                    break;
+                }
+                source.copy_line_to(&mut out)?;
+            }
+        }
+        out.finalize()?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// The object type `split()` splits `file` on
+fn split_type(file: &OneFile) -> Option<char> {
+    let info = |t: usize| unsafe { (*file.ptr).info[t] };
+    let count = |t: usize| unsafe { (*info(t)).given.count };
+    let objects: Vec<usize> = (0..128)
+        .filter(|&t| is_object(file, t as u8 as char))
+        .collect();
+    objects
+        .iter()
+        .filter(|&&t| {
+            objects
+                .iter()
+                .all(|&u| u == t || count(u) == 0 || !unsafe { (*info(u)).contains[t] })
+        })
+        .max_by_key(|&&t| count(t))
+        .map(|&t| t as u8 as char)
+}
+
+/// Path of part `k` of `n` of `input`, numbered before the extension
+fn part_path(input: &str, k: usize, n: usize) -> String {
+    let number = format!("{:0width$}", k, width = n.to_string().len());
+    let name_start = input.rfind('/').map_or(0, |i| i + 1);
+    match input[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = name_start + dot;
+            format!("{}.{}{}", &input[..dot], number, &input[dot..])
+        }
+        _ => format!("{}.{}", input, number),
+    }
+}
+
+/// Where the skeletons of each input went in the merged file
+struct SkeletonLayout {
+    /// Per group, the inputs whose skeleton is written, in order
+    groups: Vec<Vec<usize>>,
+    /// Per input, the contig id shift of each group
+    offsets: Vec<Vec<i64>>,
+}
+
+impl SkeletonLayout {
+    fn new(inputs: &[&str], skeletons: &[Vec<GdbGroup>]) -> Result<Self> {
+        let groups = skeletons.first().map_or(0, Vec::len);
+        let mut layout = SkeletonLayout {
+            groups: vec![Vec::new(); groups],
+            offsets: Vec::new(),
+        };
+        for (input, groups_of_input) in skeletons.iter().enumerate() {
+            if groups_of_input.len() != groups {
+                return Err(OneError::Other(format!(
+                    "{} has {} skeletons where {} has {}",
+                    inputs[input],
+                    groups_of_input.len(),
+                    inputs[0],
+                    groups
+                )));
+            }
+            let mut offsets = Vec::with_capacity(groups);
+            for (group, skeleton) in groups_of_input.iter().enumerate() {
+                let members = &mut layout.groups[group];
+                let mut offset = 0;
+                let same = members.iter().find_map(|&j| {
+                    let found = skeletons[j][group] == *skeleton;
+                    let at = offset;
+                    offset += skeletons[j][group].num_contigs() as i64;
+                    found.then_some(at)
+                });
+                offsets.push(same.unwrap_or_else(|| {
+                    members.push(input);
+                    offset
+                }));
+            }
+            layout.offsets.push(offsets);
+        }
+        Ok(layout)
+    }
+
+    /// Whether the contig ids of `input` change
+    fn shifts(&self, input: usize) -> bool {
+        self.offsets
+            .get(input)
+            .is_some_and(|o| o.iter().any(|&x| x != 0))
+    }
+
+    /// Contig id shifts of the query and target skeletons of `input`
+    fn offsets(&self, input: usize) -> (i64, i64) {
+        let offsets = &self.offsets[input];
+        (offsets[0], *offsets.get(1).unwrap_or(&offsets[0]))
+    }
+}
+
+/// Fail unless `file` has the primary type and schema of the first input
+fn check_schema(file: &OneFile, path: &str, file_type: &str, schema: &str) -> Result<()> {
+    let found = file.file_type().unwrap_or_default();
+    if found != file_type {
+        return Err(OneError::WrongFileType {
+            path: path.to_string(),
+            expected: file_type.to_string(),
+            found,
+        });
+    }
+    let mismatches: Vec<LineTypeMismatch> =
+        crate::schema::differences(schema, &file.schema_text()?, file_type)
+            .into_iter()
+            .map(|(line_type, expected, found)| LineTypeMismatch {
+                line_type,
+                expected,
+                found,
+            })
+            .collect();
+    if !mismatches.is_empty() {
+        return Err(OneError::SchemaMismatch {
+            path: Some(path.to_string()),
+            mismatches,
+        });
+    }
+    Ok(())
+}
+
+/// The lines before the first object, with their fields
+fn read_preamble(file: &mut OneFile) -> Vec<(char, Vec<FieldValue>)> {
+    let mut lines = Vec::new();
+    loop {
+        let line_type = file.read_line();
+        if line_type == '\0' || is_object(file, line_type) {
+            return lines;
+        }
+        lines.push((line_type, file.fields()));
+    }
+}
+
+fn copy_preamble(path: &str, out: &mut OneFile) -> Result<i64> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let mut copied = 0;
+    loop {
+        let line_type = file.read_line();
+        if line_type == '\0' || is_object(&file, line_type) {
+            return Ok(copied);
+        }
+        file.copy_line_to(out)?;
+        copied += 1;
+    }
+}
+
+/// Copy skeleton `group` of the alignment file `path`, with its 'g' line
+/// if `with_group_line`
+fn copy_skeleton(path: &str, group: i64, with_group_line: bool, out: &mut OneFile) -> Result<i64> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    file.goto(aln::GDB_GROUP, group)?;
+    file.read_line();
+    let mut copied = 0;
+    if with_group_line {
+        file.copy_line_to(out)?;
+        copied += 1;
+    }
+    loop {
+        let line_type = file.read_line();
+        if line_type == '\0' || !SKELETON_TYPES[1..].contains(&line_type) {
+            return Ok(copied);
+        }
+        file.copy_line_to(out)?;
+        copied += 1;
+    }
+}
+
+/// Write the 'A' line of `file` with its contig ids shifted by `offsets`
+fn write_shifted(file: &OneFile, out: &mut OneFile, (query, target): (i64, i64)) -> Result<()> {
+    for i in 0..6 {
+        let shift = match i {
+            0 => query,
+            3 => target,
+            _ => 0,
+        };
+        out.set_int(i, file.int(i) + shift);
+    }
+    out.write_line(aln::ALIGNMENT, 0, None);
+    if let Some(comment) = file.read_comment() {
+        out.write_comment(&comment)?;
+    }
+    Ok(())
+}
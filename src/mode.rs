@@ -0,0 +1,382 @@
+//! Handles restricted to reading or to writing a ONE file
+//!
+//! A `OneFile` offers the read and the write methods of the C library
+//! whatever the mode it was opened in, and calling one of the wrong mode
+//! corrupts the C library's state or aborts. `OneReader` and `OneWriter`
+//! wrap a `OneFile` opened in one mode and offer only that mode's methods,
+//! so such misuse does not compile:
+//!
+//! ```compile_fail
+//! use onecode::OneReader;
+//!
+//! let mut reader = OneReader::open("data.1aln", None, None, 1)?;
+//! reader.write_line('A', 0, None); // error: no method `write_line`
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! Methods that only inspect the file or the current line, such as `int()`,
+//! `stats()` or `provenance()`, are available on both through `Deref`.
+//! `into_inner()` gives the `OneFile` back, e.g. for the readers of this
+//! crate, which still take a `OneFile`; `OneFile` stays as the untyped
+//! handle during the transition.
+//!
+//! `NullWriter` is a `OneWriter` whose bytes are thrown away, to try out a
+//! conversion, with all the checks of a real one, before paying for its I/O.
+
+use crate::error::{OneError, Result};
+use crate::file::{OneFile, OneFileThread, WriteOptions};
+use crate::gdb::{GdbGroup, GdbIndex};
+use crate::lines::{Groups, Lines, ObjectLines, Objects, OneObject};
+use crate::record::Record;
+use crate::schema::OneSchema;
+use crate::types::{Alphabet, LineStats, RealFormat, Warning};
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::ops::{Deref, DerefMut};
+
+/// Methods of the wrapped `OneFile`, each passed straight through
+///
+/// Written as `fn name(receiver, args) -> ret;`, one per method, each
+/// documented as a pointer to the `OneFile` method of the same name.
+macro_rules! forward {
+    () => {};
+    (fn $name:ident(&self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        #[doc = concat!("See `OneFile::", stringify!($name), "()`")]
+        pub fn $name(&self $(, $arg: $ty)*) $(-> $ret)? {
+            self.0.$name($($arg),*)
+        }
+        forward!($($rest)*);
+    };
+    (fn $name:ident(&mut self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        #[doc = concat!("See `OneFile::", stringify!($name), "()`")]
+        pub fn $name(&mut self $(, $arg: $ty)*) $(-> $ret)? {
+            self.0.$name($($arg),*)
+        }
+        forward!($($rest)*);
+    };
+    (fn $name:ident(self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        #[doc = concat!("See `OneFile::", stringify!($name), "()`")]
+        pub fn $name(self $(, $arg: $ty)*) $(-> $ret)? {
+            self.0.$name($($arg),*)
+        }
+        forward!($($rest)*);
+    };
+}
+
+/// A ONE file open for reading
+pub struct OneReader(OneFile);
+
+impl OneReader {
+    /// Open a ONE file for reading, see `OneFile::open_read()`
+    pub fn open(
+        path: &str,
+        schema: Option<&OneSchema>,
+        file_type: Option<&str>,
+        nthreads: i32,
+    ) -> Result<Self> {
+        Ok(OneReader(OneFile::open_read(
+            path, schema, file_type, nthreads,
+        )?))
+    }
+
+    /// Open ONE data held in memory, see `OneFile::open_read_bytes()`
+    pub fn open_bytes(
+        bytes: &[u8],
+        schema: Option<&OneSchema>,
+        file_type: Option<&str>,
+        nthreads: i32,
+    ) -> Result<Self> {
+        Ok(OneReader(OneFile::open_read_bytes(
+            bytes, schema, file_type, nthreads,
+        )?))
+    }
+
+    /// Read a ONE file from standard input, see `OneFile::open_read_stdin()`
+    pub fn stdin(schema: Option<&OneSchema>, file_type: Option<&str>) -> Result<Self> {
+        Ok(OneReader(OneFile::open_read_stdin(schema, file_type)?))
+    }
+
+    /// The untyped handle, with the methods of both modes
+    pub fn into_inner(self) -> OneFile {
+        self.0
+    }
+
+    forward! {
+        fn read_line(&mut self) -> char;
+        fn read_line_checked(&mut self) -> Result<char>;
+        fn read_record(&mut self) -> Option<Record>;
+        fn read_comment(&self) -> Option<String>;
+        fn lines(&mut self) -> Lines<'_>;
+        fn objects(&mut self, line_type: char) -> Objects<'_>;
+        fn groups(&mut self, group_type: char) -> Groups<'_>;
+        fn last_objects(&mut self, line_type: char, n: usize) -> Result<ObjectLines<'_>>;
+        fn goto(&mut self, line_type: char, index: i64) -> Result<()>;
+        fn seek_to_byte_offset(&mut self, byte_offset: i64) -> Result<()>;
+        fn seek_and_read_line(&mut self, byte_offset: i64) -> Result<char>;
+        fn dna_2bit(&mut self) -> Option<&[u8]>;
+        fn set_projection(&mut self, line_types: Option<&[char]>) -> Result<()>;
+        fn set_alphabet(&mut self, line_type: char, alphabet: Alphabet) -> Result<()>;
+        fn set_slow_path_warnings(&mut self, enabled: bool);
+        fn warnings(&self) -> &[Warning];
+        fn clear_warnings(&mut self);
+        fn split_threads(&mut self) -> Vec<OneFileThread<'_>>;
+    }
+
+    /// See `OneFile::filter_objects()`
+    pub fn filter_objects<F>(
+        &mut self,
+        output: &mut OneWriter,
+        line_type: char,
+        keep: F,
+    ) -> Result<i64>
+    where
+        F: FnMut(&OneObject) -> bool,
+    {
+        self.0.filter_objects(&mut output.0, line_type, keep)
+    }
+
+    /// See `OneFile::par_objects()`
+    #[cfg(feature = "rayon")]
+    pub fn par_objects<T, F>(&mut self, line_type: char, f: F) -> Result<Vec<T>>
+    where
+        T: Send,
+        F: Fn(&mut OneFileThread<'_>, i64) -> Result<T> + Sync,
+    {
+        self.0.par_objects(line_type, f)
+    }
+
+    /// See `OneFile::copy_line_to()`
+    pub fn copy_line_to(&self, writer: &mut OneWriter) -> Result<()> {
+        self.0.copy_line_to(&mut writer.0)
+    }
+
+    /// See `OneFile::copy_line_to()`
+    #[deprecated(note = "use copy_line_to(), which needs only a shared borrow of the source")]
+    pub fn copy_current_line(&self, dest: &mut OneWriter) -> Result<()> {
+        self.0.copy_line_to(&mut dest.0)
+    }
+
+    /// See `OneFile::copy_raw_object_to()`
+    pub fn copy_raw_object_to(&mut self, dest: &mut OneWriter) -> Result<()> {
+        self.0.copy_raw_object_to(&mut dest.0)
+    }
+
+    forward! {
+        fn gdb_index(&mut self) -> Result<&GdbIndex>;
+        fn group_gdb_index(&mut self, group: i64) -> Result<&GdbIndex>;
+        fn get_sequence_name(&mut self, seq_id: i64) -> Option<String>;
+        fn get_all_sequence_names(&mut self) -> HashMap<i64, String>;
+        fn get_all_sequence_lengths(&mut self) -> HashMap<i64, i64>;
+        fn get_all_contig_offsets(&mut self) -> HashMap<i64, (i64, i64)>;
+        fn get_all_groups(&mut self) -> Result<Vec<GdbGroup>>;
+    }
+}
+
+/// A ONE file open for writing
+pub struct OneWriter(OneFile);
+
+impl OneWriter {
+    /// Create a ONE file, see `OneFile::open_write_new()`
+    pub fn create(
+        path: &str,
+        schema: &OneSchema,
+        file_type: &str,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        Ok(OneWriter(OneFile::open_write_new(
+            path, schema, file_type, is_binary, nthreads,
+        )?))
+    }
+
+    /// Create a ONE file with the settings of `options`, see `OneFile::create()`
+    pub fn create_with(
+        path: &str,
+        schema: &OneSchema,
+        file_type: &str,
+        options: WriteOptions,
+    ) -> Result<Self> {
+        Ok(OneWriter(OneFile::create(path, schema, file_type, options)?))
+    }
+
+    /// Create a ONE file with the schema and header of `source`, see `OneFile::open_write_from()`
+    pub fn create_from(
+        path: &str,
+        source: &OneFile,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        Ok(OneWriter(OneFile::open_write_from(
+            path, source, is_binary, nthreads,
+        )?))
+    }
+
+    /// Reopen a binary ONE file to write more lines, see `OneFile::open_append()`
+    pub fn append(path: &str) -> Result<Self> {
+        Ok(OneWriter(OneFile::open_append(path)?))
+    }
+
+    /// Reopen a binary ONE file to write more lines, see `OneFile::append()`
+    pub fn append_with(path: &str, options: WriteOptions) -> Result<Self> {
+        Ok(OneWriter(OneFile::append(path, options)?))
+    }
+
+    /// Create a ONE file in memory, see `OneFile::open_write_vec()`
+    pub fn in_memory(
+        schema: &OneSchema,
+        file_type: &str,
+        is_binary: bool,
+        nthreads: i32,
+    ) -> Result<Self> {
+        Ok(OneWriter(OneFile::open_write_vec(
+            schema, file_type, is_binary, nthreads,
+        )?))
+    }
+
+    /// Write a ONE file to standard output, see `OneFile::open_write_stdout()`
+    pub fn stdout(schema: &OneSchema, file_type: &str, is_binary: bool) -> Result<Self> {
+        Ok(OneWriter(OneFile::open_write_stdout(
+            schema, file_type, is_binary,
+        )?))
+    }
+
+    /// The untyped handle, with the methods of both modes
+    pub fn into_inner(self) -> OneFile {
+        self.0
+    }
+
+    forward! {
+        fn set_int(&mut self, field: usize, value: i64);
+        fn set_real(&mut self, field: usize, value: f64);
+        fn set_char(&mut self, field: usize, value: char);
+        fn write_line(&mut self, line_type: char, list_len: i64, list_buf: Option<*mut c_void>);
+        fn write_int_list(&mut self, line_type: char, values: &[i64]) -> Result<()>;
+        fn write_real_list(&mut self, line_type: char, values: &[f64]) -> Result<()>;
+        fn write_string(&mut self, line_type: char, s: &str) -> Result<()>;
+        fn write_string_list(&mut self, line_type: char, strings: &[&str]) -> Result<()>;
+        fn write_dna(&mut self, line_type: char, bases: &[u8]) -> Result<()>;
+        fn write_per_base_int_list(&mut self, line_type: char, values: &[i64]) -> Result<()>;
+        fn write_per_base_real_list(&mut self, line_type: char, values: &[f64]) -> Result<()>;
+        fn write_comment(&mut self, comment: &str) -> Result<()>;
+        fn add_provenance(&mut self, prog: &str, version: &str, command: &str) -> Result<bool>;
+        fn add_reference(&mut self, filename: &str, count: i64) -> Result<bool>;
+        fn inherit_provenance(&mut self, source: &OneFile) -> bool;
+        fn inherit_reference(&mut self, source: &OneFile) -> bool;
+        fn add_header_text(&mut self, text: &str) -> Result<()>;
+        fn set_metadata(&mut self, key: &str, value: &str) -> Result<()>;
+        fn set_ascii_list_limit(&mut self, len: i64);
+        fn set_alphabet(&mut self, line_type: char, alphabet: Alphabet) -> Result<()>;
+        fn set_real_format(&mut self, format: RealFormat);
+        fn split_threads(&mut self) -> Vec<OneFileThread<'_>>;
+        fn close(self);
+        fn finalize(self) -> Result<()>;
+        fn into_vec(self) -> Result<Vec<u8>>;
+    }
+}
+
+/// A writer that checks and counts lines as `OneWriter` does, but discards them
+///
+/// Everything written goes through ONElib as for a real file, so a line the
+/// schema does not allow fails or aborts just the same, and `stats()` counts
+/// what would have been written; only the bytes are dropped. It dereferences
+/// to a `OneWriter`, so it can stand in for one, e.g. in `filter_objects()`.
+/// Writing is single-threaded, see `OneFile::open_write_null()`.
+pub struct NullWriter(OneWriter);
+
+impl NullWriter {
+    /// Discard a ONE file of the given schema and type, see `OneWriter::create()`
+    pub fn new(schema: &OneSchema, file_type: &str, is_binary: bool) -> Result<Self> {
+        Ok(NullWriter(OneWriter(OneFile::open_write_null(
+            schema, file_type, is_binary,
+        )?)))
+    }
+
+    /// Discard a ONE file with the schema and header of `source`, see
+    /// `OneWriter::create_from()`
+    pub fn create_from(source: &OneFile, is_binary: bool) -> Result<Self> {
+        Ok(NullWriter(OneWriter(OneFile::open_write_null_from(
+            source, is_binary,
+        )?)))
+    }
+
+    /// Close the file as `finalize()` would, returning the statistics of
+    /// every line type written
+    pub fn finish(self) -> Result<Vec<LineStats>> {
+        let stats = self.0.all_stats();
+        self.0.finalize()?;
+        Ok(stats)
+    }
+}
+
+impl Deref for NullWriter {
+    type Target = OneWriter;
+
+    fn deref(&self) -> &OneWriter {
+        &self.0
+    }
+}
+
+impl DerefMut for NullWriter {
+    fn deref_mut(&mut self) -> &mut OneWriter {
+        &mut self.0
+    }
+}
+
+// No DerefMut for either: it would hand out the methods of the other mode
+impl Deref for OneReader {
+    type Target = OneFile;
+
+    fn deref(&self) -> &OneFile {
+        &self.0
+    }
+}
+
+impl Deref for OneWriter {
+    type Target = OneFile;
+
+    fn deref(&self) -> &OneFile {
+        &self.0
+    }
+}
+
+impl TryFrom<OneFile> for OneReader {
+    type Error = OneError;
+
+    /// Fails if `file` was opened for writing
+    fn try_from(file: OneFile) -> Result<Self> {
+        if file.is_write() {
+            return Err(OneError::Other(format!(
+                "{} is open for writing, not reading",
+                file.file_name().unwrap_or_default()
+            )));
+        }
+        Ok(OneReader(file))
+    }
+}
+
+impl TryFrom<OneFile> for OneWriter {
+    type Error = OneError;
+
+    /// Fails if `file` was opened for reading
+    fn try_from(file: OneFile) -> Result<Self> {
+        if !file.is_write() {
+            return Err(OneError::Other(format!(
+                "{} is open for reading, not writing",
+                file.file_name().unwrap_or_default()
+            )));
+        }
+        Ok(OneWriter(file))
+    }
+}
+
+impl From<OneReader> for OneFile {
+    fn from(reader: OneReader) -> Self {
+        reader.0
+    }
+}
+
+impl From<OneWriter> for OneFile {
+    fn from(writer: OneWriter) -> Self {
+        writer.0
+    }
+}
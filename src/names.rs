@@ -0,0 +1,104 @@
+//! Sequence name normalization for joining files that name sequences differently
+//!
+//! A `.1aln` made against one build of a reference may call a chromosome
+//! "chr1" where the `.1seq` of another calls it "1" or "NC_000001.11". A
+//! `NameNormalizer` maps both sides to one key: `SeqReader` and `GdbReader`
+//! take one with `with_normalizer()`, normalizing the names they index and
+//! the names they are asked for, and so do their `SequenceStore` lookups.
+//! The names they report stay as in the file.
+//!
+//! ```no_run
+//! use onecode::names::{StripChr, StripPanSn};
+//! use onecode::{SeqReader, SequenceStore};
+//!
+//! let mut reference = SeqReader::open("GRCh38.1seq")?.with_normalizer((StripPanSn, StripChr));
+//! let bases = reference.fetch("HG002#1#chr1", 10_000..10_100)?; // GRCh38 names it "1"
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! Normalizers chain as tuples, applied left to right, and any
+//! `Fn(&str) -> String` closure is one.
+
+use std::borrow::Cow;
+#[cfg(feature = "onelib")]
+use std::collections::HashMap;
+
+/// A mapping of sequence names to the keys they are matched by
+///
+/// Two names refer to the same sequence when they normalize to the same
+/// key.
+pub trait NameNormalizer: Send + Sync {
+    fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str>;
+}
+
+/// Drop a leading "chr", in any case, so "chr1" and "Chr1" match "1"
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripChr;
+
+impl NameNormalizer for StripChr {
+    fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match name.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("chr") && name.len() > 3 => {
+                Cow::Borrowed(&name[3..])
+            }
+            _ => Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Drop a trailing version number, so "NC_000001.11" matches "NC_000001"
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripVersion;
+
+impl NameNormalizer for StripVersion {
+    fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match name.rsplit_once('.') {
+            Some((base, version))
+                if !base.is_empty()
+                    && !version.is_empty()
+                    && version.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                Cow::Borrowed(base)
+            }
+            _ => Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Drop a PanSN "sample#haplotype#" prefix, so "HG002#1#chr1" matches "chr1"
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripPanSn;
+
+impl NameNormalizer for StripPanSn {
+    fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(name.rsplit_once('#').map_or(name, |(_, contig)| contig))
+    }
+}
+
+impl<F: Fn(&str) -> String + Send + Sync> NameNormalizer for F {
+    fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        Cow::Owned(self(name))
+    }
+}
+
+impl<A: NameNormalizer, B: NameNormalizer> NameNormalizer for (A, B) {
+    fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self.0.normalize(name) {
+            Cow::Borrowed(name) => self.1.normalize(name),
+            Cow::Owned(name) => Cow::Owned(self.1.normalize(&name).into_owned()),
+        }
+    }
+}
+
+/// Key `names` by their normalized form, the first of a key winning
+#[cfg(feature = "onelib")]
+pub(crate) fn index_names<'a>(
+    names: impl Iterator<Item = (usize, &'a str)>,
+    normalizer: &dyn NameNormalizer,
+) -> HashMap<String, usize> {
+    let mut index = HashMap::new();
+    for (i, name) in names {
+        index.entry(normalizer.normalize(name).into_owned()).or_insert(i);
+    }
+    index
+}
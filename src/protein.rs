@@ -0,0 +1,151 @@
+//! Reader and writer for `.1pro` protein files
+//!
+//! A `.1pro` file holds one 'S' object per protein, its amino acids as a
+//! STRING in one-letter codes: unlike `.1seq` DNA lists there is no 2-bit
+//! codec, so any residue letter is stored as written. An optional 'I' line
+//! carries the identifier and an 'F' line the source sequence and reading
+//! frame of a translation (see `seq::to_protein()`).
+//!
+//! ```no_run
+//! use onecode::{ProteinReader, ProteinWriter};
+//!
+//! let mut writer = ProteinWriter::create("out.1pro", true)?;
+//! for protein in ProteinReader::open("in.1pro")? {
+//!     if !protein.sequence.contains('*') {
+//!         writer.write_protein(&protein)?;
+//!     }
+//! }
+//! writer.close();
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::linetypes::pro;
+use crate::schema::OneSchema;
+use std::borrow::Cow;
+
+/// One protein with the data lines that follow its 'S' line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Protein {
+    /// Identifier from the 'I' line, if present
+    pub id: Option<String>,
+    /// Amino acids, upper-case one-letter codes with '*' for stop
+    pub sequence: String,
+    /// Source sequence (0-indexed) and reading frame from the 'F' line, if present
+    pub source: Option<(i64, i32)>,
+}
+
+/// Iterator over the proteins of a `.1pro` file
+pub struct ProteinReader {
+    file: OneFile,
+    line_type: char, // read but not yet consumed
+}
+
+impl ProteinReader {
+    /// Open a `.1pro` file, checking it against `PROTEIN_SCHEMA_TEXT`
+    pub fn open(path: &str) -> Result<Self> {
+        let schema = OneSchema::protein()?;
+        Ok(Self::new(OneFile::open_read(path, Some(&schema), Some("pro"), 1)?))
+    }
+
+    /// Read proteins from an open file, starting at its current position
+    pub fn new(mut file: OneFile) -> Self {
+        let line_type = file.read_line();
+        ProteinReader { file, line_type }
+    }
+
+    /// The underlying file, e.g. for reading header statistics
+    pub fn file(&self) -> &OneFile {
+        &self.file
+    }
+}
+
+impl Iterator for ProteinReader {
+    type Item = Protein;
+
+    fn next(&mut self) -> Option<Protein> {
+        while self.line_type != pro::SEQUENCE {
+            if self.line_type == '\0' {
+                return None;
+            }
+            self.line_type = self.file.read_line();
+        }
+
+        let mut protein = Protein {
+            sequence: self.file.string_lossy().unwrap_or_default().into_owned(),
+            ..Protein::default()
+        };
+        loop {
+            self.line_type = self.file.read_line();
+            match self.line_type {
+                pro::IDENTIFIER => protein.id = self.file.string_lossy().map(Cow::into_owned),
+                pro::FRAME => protein.source = Some((self.file.int(0), self.file.int(1) as i32)),
+                '\0' | pro::SEQUENCE => return Some(protein),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Writer of `.1pro` files with the standard `PROTEIN_SCHEMA_TEXT` schema
+pub struct ProteinWriter {
+    file: OneFile,
+}
+
+impl ProteinWriter {
+    /// Create a `.1pro` file, binary or ASCII
+    pub fn create(path: &str, binary: bool) -> Result<Self> {
+        let schema = OneSchema::protein()?;
+        Ok(ProteinWriter {
+            file: OneFile::open_write_new(path, &schema, "pro", binary, 1)?,
+        })
+    }
+
+    /// Write a protein with an optional identifier
+    ///
+    /// The sequence must consist of ASCII letters and '*'.
+    pub fn write(&mut self, id: Option<&str>, sequence: &str) -> Result<()> {
+        if let Some(c) = sequence
+            .chars()
+            .find(|c| !c.is_ascii_alphabetic() && *c != '*')
+        {
+            return Err(OneError::InvalidFormat(format!(
+                "'{}' is not an amino acid code in protein {}",
+                c,
+                id.unwrap_or("without identifier")
+            )));
+        }
+        self.file.write_string(pro::SEQUENCE, sequence)?;
+        if let Some(id) = id {
+            self.file.write_string(pro::IDENTIFIER, id)?;
+        }
+        Ok(())
+    }
+
+    /// Write a protein with its identifier and source, as `ProteinReader` returns them
+    pub fn write_protein(&mut self, protein: &Protein) -> Result<()> {
+        self.write(protein.id.as_deref(), &protein.sequence)?;
+        if let Some((sequence, frame)) = protein.source {
+            self.file.set_int(0, sequence);
+            self.file.set_int(1, frame as i64);
+            self.file.write_line(pro::FRAME, 0, None);
+        }
+        Ok(())
+    }
+
+    /// The underlying file, e.g. to add provenance before the first protein
+    pub fn file(&mut self) -> &mut OneFile {
+        &mut self.file
+    }
+
+    /// Close the file, writing its header counts and index
+    pub fn close(self) {
+        self.file.close();
+    }
+
+    /// Close the file, failing if any of it could not be written, see `OneFile::finalize()`
+    pub fn finalize(self) -> Result<()> {
+        self.file.finalize()
+    }
+}
@@ -0,0 +1,369 @@
+//! Python extension module
+//!
+//! Enabled with the `python` feature and built with maturin from the
+//! repository root (`pyproject.toml` selects the feature):
+//!
+//! ```text
+//! maturin develop --release
+//! ```
+//!
+//! ```python
+//! import onecode
+//!
+//! names = onecode.OneFile("data.1aln").sequence_names()
+//! f = onecode.OneFile("data.1aln")
+//! for t in f:
+//!     if t == "A":
+//!         print(names[f.int(0)], names[f.int(3)])
+//! ```
+//!
+//! Iterating a `OneFile` yields the type of each line, with its fields then
+//! available through the accessors, as with `read_line()` in Rust. Errors are
+//! raised as `OSError` when a file cannot be opened and `ValueError`
+//! otherwise.
+
+// The #[pymethods] expansion converts every PyResult error into PyErr again
+#![allow(clippy::useless_conversion)]
+
+use crate::aln::{Alignment, AlnReader};
+use crate::convert;
+use crate::error::OneError;
+use crate::file::OneFile;
+use crate::gdb::{self, GdbReader};
+use crate::seq::{self, ExportOptions, FastaOptions, SeqReader};
+use pyo3::exceptions::{PyIOError, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+impl From<OneError> for PyErr {
+    fn from(e: OneError) -> PyErr {
+        match e {
+            OneError::OpenFailed(_)
+            | OneError::Io { .. }
+            | OneError::BadMagic { .. }
+            | OneError::Truncated { .. }
+            | OneError::WrongFileType { .. }
+            | OneError::SchemaMismatch { path: Some(_), .. }
+            | OneError::Locked { .. } => PyIOError::new_err(e.to_string()),
+            _ => PyValueError::new_err(e.to_string()),
+        }
+    }
+}
+
+fn io_error(path: &str) -> impl Fn(std::io::Error) -> PyErr + '_ {
+    move |e| PyIOError::new_err(format!("{}: {}", path, e))
+}
+
+fn line_type_arg(line_type: &str) -> PyResult<char> {
+    let mut chars = line_type.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(PyValueError::new_err(format!(
+            "Line type must be a single character, got {:?}",
+            line_type
+        ))),
+    }
+}
+
+/// A ONE file opened for reading
+#[pyclass(name = "OneFile", unsendable)]
+struct PyOneFile {
+    file: OneFile,
+}
+
+#[pymethods]
+impl PyOneFile {
+    #[new]
+    #[pyo3(signature = (path, file_type=None))]
+    fn new(path: &str, file_type: Option<&str>) -> PyResult<Self> {
+        Ok(PyOneFile {
+            file: OneFile::open_read(path, None, file_type, 1)?,
+        })
+    }
+
+    /// Read the next line and return its type, or None at the end of the file
+    fn read_line(&mut self) -> Option<char> {
+        match self.file.read_line() {
+            '\0' => None,
+            t => Some(t),
+        }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<char> {
+        self.read_line()
+    }
+
+    #[getter]
+    fn line_type(&self) -> char {
+        self.file.line_type()
+    }
+
+    #[getter]
+    fn file_type(&self) -> Option<String> {
+        self.file.file_type()
+    }
+
+    #[getter]
+    fn is_binary(&self) -> bool {
+        self.file.is_binary()
+    }
+
+    fn int(&self, field: usize) -> i64 {
+        self.file.int(field)
+    }
+
+    fn real(&self, field: usize) -> f64 {
+        self.file.real(field)
+    }
+
+    fn char(&self, field: usize) -> char {
+        self.file.char(field)
+    }
+
+    /// Length of the list field of the current line
+    fn len(&self) -> i64 {
+        self.file.len()
+    }
+
+    fn string(&self) -> Option<String> {
+        self.file.string_lossy().map(|s| s.into_owned())
+    }
+
+    /// DNA of the current line as lower-case bases
+    fn dna<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.file.dna_char().map(|s| PyBytes::new_bound(py, s))
+    }
+
+    fn int_list(&self) -> Option<Vec<i64>> {
+        self.file.int_list().map(<[i64]>::to_vec)
+    }
+
+    fn real_list(&self) -> Option<Vec<f64>> {
+        self.file.real_list().map(<[f64]>::to_vec)
+    }
+
+    /// (count, max, total) for a line type, from the file header
+    fn stats(&self, line_type: &str) -> PyResult<(i64, i64, i64)> {
+        Ok(self.file.stats(line_type_arg(line_type)?)?)
+    }
+
+    /// Move to object `index` (1-indexed) of type `line_type` in a binary file
+    fn goto(&mut self, line_type: &str, index: i64) -> PyResult<()> {
+        Ok(self.file.goto(line_type_arg(line_type)?, index)?)
+    }
+
+    /// Map of contig id to scaffold name from the embedded GDB of a `.1aln` file
+    ///
+    /// This scans the file and does not reliably restore the read position,
+    /// so call it on a handle of its own.
+    fn sequence_names(&mut self) -> HashMap<i64, String> {
+        self.file.get_all_sequence_names()
+    }
+
+    /// Map of contig id to scaffold length from the embedded GDB of a `.1aln` file
+    ///
+    /// Like `sequence_names()`, call it on a handle of its own.
+    fn sequence_lengths(&mut self) -> HashMap<i64, i64> {
+        self.file.get_all_sequence_lengths()
+    }
+}
+
+/// Indexed random access to the sequences of a `.1seq` file
+#[pyclass(name = "SeqReader", unsendable)]
+struct PySeqReader {
+    reader: SeqReader,
+}
+
+#[pymethods]
+impl PySeqReader {
+    #[new]
+    fn new(path: &str) -> PyResult<Self> {
+        Ok(PySeqReader {
+            reader: SeqReader::open(path)?,
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.reader.num_sequences()
+    }
+
+    fn name(&self, i: usize) -> Option<&str> {
+        self.reader.name(i)
+    }
+
+    fn length(&self, i: usize) -> PyResult<i64> {
+        self.reader
+            .length(i)
+            .ok_or_else(|| PyIndexError::new_err(format!("sequence index {} out of range", i)))
+    }
+
+    /// Index of the sequence with the given identifier, or None
+    fn id(&self, name: &str) -> Option<usize> {
+        self.reader.id(name)
+    }
+
+    /// Sequence `i` as lower-case bases
+    fn sequence<'py>(&mut self, py: Python<'py>, i: usize) -> PyResult<Bound<'py, PyBytes>> {
+        let seq = self.reader.sequence(i)?;
+        Ok(PyBytes::new_bound(py, &seq))
+    }
+}
+
+/// A GDB skeleton: scaffolds with their contigs and gaps
+#[pyclass(name = "GdbReader")]
+struct PyGdbReader {
+    gdb: GdbReader,
+}
+
+#[pymethods]
+impl PyGdbReader {
+    #[new]
+    fn new(path: &str) -> PyResult<Self> {
+        Ok(PyGdbReader {
+            gdb: GdbReader::open(path)?,
+        })
+    }
+
+    /// Read the skeleton of 'g' group `group` (1-indexed) of a `.1aln` file
+    #[staticmethod]
+    #[pyo3(signature = (path, group=1))]
+    fn from_alignment(path: &str, group: i64) -> PyResult<Self> {
+        let mut file = OneFile::open_read(path, None, Some("aln"), 1)?;
+        Ok(PyGdbReader {
+            gdb: GdbReader::from_alignment(&mut file, group)?,
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.gdb.scaffolds().len()
+    }
+
+    /// List of (name, length) for each scaffold
+    fn scaffolds(&self) -> Vec<(String, i64)> {
+        gdb::karyotype(&self.gdb)
+    }
+
+    /// List of (offset, length) of the contigs of the named scaffold
+    fn contigs(&self, name: &str) -> PyResult<Vec<(i64, i64)>> {
+        let scaffold = self
+            .gdb
+            .scaffold(name)
+            .ok_or_else(|| PyValueError::new_err(format!("no scaffold named {}", name)))?;
+        Ok(scaffold.contigs.iter().map(|c| (c.offset, c.len)).collect())
+    }
+
+    /// Write the skeleton as AGP 2.1
+    fn to_agp(&self, path: &str) -> PyResult<()> {
+        let mut out = BufWriter::new(File::create(path).map_err(io_error(path))?);
+        gdb::to_agp(&self.gdb, &mut out)?;
+        out.flush().map_err(io_error(path))
+    }
+}
+
+/// One alignment of a `.1aln` file, as yielded by `AlnReader`
+#[pyclass(name = "Alignment", get_all)]
+struct PyAlignment {
+    query_id: i64,
+    query_start: i64,
+    query_end: i64,
+    target_id: i64,
+    target_start: i64,
+    target_end: i64,
+    reverse: bool,
+    diffs: i64,
+    trace_points: Vec<i64>,
+    trace_diffs: Vec<i64>,
+}
+
+impl From<Alignment> for PyAlignment {
+    fn from(a: Alignment) -> Self {
+        PyAlignment {
+            query_id: a.query_id,
+            query_start: a.query_start,
+            query_end: a.query_end,
+            target_id: a.target_id,
+            target_start: a.target_start,
+            target_end: a.target_end,
+            reverse: a.reverse,
+            diffs: a.diffs,
+            trace_points: a.trace_points,
+            trace_diffs: a.trace_diffs,
+        }
+    }
+}
+
+/// Iterator over the alignments of a `.1aln` file
+#[pyclass(name = "AlnReader", unsendable)]
+struct PyAlnReader {
+    reader: AlnReader,
+}
+
+#[pymethods]
+impl PyAlnReader {
+    #[new]
+    fn new(path: &str) -> PyResult<Self> {
+        Ok(PyAlnReader {
+            reader: AlnReader::open(path)?.with_traces(),
+        })
+    }
+
+    #[getter]
+    fn trace_spacing(&self) -> i64 {
+        self.reader.trace_spacing()
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<PyAlignment> {
+        self.reader.next().map(PyAlignment::from)
+    }
+}
+
+/// Import a FASTA or FASTQ file, optionally gzipped, into a new `.1seq` file,
+/// returning the number of sequences
+#[pyfunction]
+#[pyo3(signature = (fasta_path, out_path, binary=true))]
+fn import_fasta(fasta_path: &str, out_path: &str, binary: bool) -> PyResult<usize> {
+    let input = File::open(fasta_path).map_err(io_error(fasta_path))?;
+    Ok(seq::import_fasta(BufReader::new(input), out_path, binary, &FastaOptions::default())?)
+}
+
+/// Export a `.1seq` file as FASTA (FASTQ for sequences with qualities),
+/// returning the number of sequences
+#[pyfunction]
+#[pyo3(signature = (seq_path, out_path, line_width=0))]
+fn export_fasta(seq_path: &str, out_path: &str, line_width: usize) -> PyResult<usize> {
+    Ok(seq::to_fasta(seq_path, out_path, &ExportOptions { line_width })?)
+}
+
+/// Write the alignments of a binary `.1aln` file as PAF, returning the number of records
+#[pyfunction]
+fn aln_to_paf(aln_path: &str, paf_path: &str) -> PyResult<usize> {
+    let mut file = OneFile::open_read(aln_path, None, Some("aln"), 1)?;
+    let mut out = BufWriter::new(File::create(paf_path).map_err(io_error(paf_path))?);
+    let count = convert::aln_to_paf(&mut file, &mut out)?;
+    out.flush().map_err(io_error(paf_path))?;
+    Ok(count)
+}
+
+#[pymodule]
+fn onecode(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOneFile>()?;
+    m.add_class::<PySeqReader>()?;
+    m.add_class::<PyGdbReader>()?;
+    m.add_class::<PyAlnReader>()?;
+    m.add_class::<PyAlignment>()?;
+    m.add_function(wrap_pyfunction!(import_fasta, m)?)?;
+    m.add_function(wrap_pyfunction!(export_fasta, m)?)?;
+    m.add_function(wrap_pyfunction!(aln_to_paf, m)?)?;
+    Ok(())
+}
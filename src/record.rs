@@ -0,0 +1,104 @@
+//! Typed records
+//!
+//! `OneFile::read_record()` reads the next line and decodes it according to
+//! the file's schema, so callers can match on values instead of knowing
+//! which raw field holds what:
+//!
+//! ```no_run
+//! use onecode::{FieldValue, ListValue, Record};
+//! use onecode::OneFile;
+//!
+//! let mut file = OneFile::open_read("data.1seq", None, None, 1)?;
+//! while let Some(record) = file.read_record() {
+//!     match record {
+//!         Record { line_type: 'S', list: Some(ListValue::Dna(dna)), .. } => println!("{} bp", dna.len()),
+//!         Record { line_type: 'N', fields, .. } => {
+//!             if let [FieldValue::Int(pos), FieldValue::Char(base), FieldValue::Int(n)] = fields[..] {
+//!                 println!("{} x{} at {}", base, n, pos);
+//!             }
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+#[cfg(feature = "onelib")]
+use crate::ffi::OneType;
+#[cfg(feature = "onelib")]
+use crate::lines::{Line, List};
+
+/// A field value
+///
+/// `Record::fields` holds only the scalar variants, the list being in
+/// `Record::list`; `OneFile::fields()` gives every field in schema order,
+/// the list field as `List`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Int(i64),
+    Real(f64),
+    Char(char),
+    List(ListValue),
+}
+
+/// The value of a line's list field
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListValue {
+    String(String),
+    /// Bases as lower-case characters
+    Dna(Vec<u8>),
+    IntList(Vec<i64>),
+    RealList(Vec<f64>),
+    StringList(Vec<String>),
+}
+
+/// A line decoded according to the file's schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub line_type: char,
+    /// Scalar fields in schema order; the list field, if any, is in `list`
+    pub fields: Vec<FieldValue>,
+    pub list: Option<ListValue>,
+}
+
+#[cfg(feature = "onelib")]
+impl ListValue {
+    pub(crate) fn from_list(list_type: OneType, list: List) -> Self {
+        match (list_type, list) {
+            (_, List::Ints(v)) => ListValue::IntList(v),
+            (_, List::Reals(v)) => ListValue::RealList(v),
+            (OneType::oneDNA, List::Bytes(b)) => ListValue::Dna(b),
+            (OneType::oneSTRING_LIST, List::Bytes(b)) => ListValue::StringList(
+                // NUL-terminated strings back to back
+                b.split(|&c| c == 0)
+                    .take(b.iter().filter(|&&c| c == 0).count())
+                    .map(|s| String::from_utf8_lossy(s).into_owned())
+                    .collect(),
+            ),
+            (_, List::Bytes(b)) => ListValue::String(String::from_utf8_lossy(&b).into_owned()),
+            (_, List::None) => ListValue::String(String::new()),
+        }
+    }
+}
+
+#[cfg(feature = "onelib")]
+impl From<Line> for Record {
+    fn from(line: Line) -> Self {
+        let mut fields = Vec::with_capacity(line.fields.len());
+        let mut list_type = None;
+        for (slot, &field_type) in line.fields.iter().zip(&line.field_types) {
+            match field_type {
+                OneType::oneINT => fields.push(FieldValue::Int(slot.as_int())),
+                OneType::oneREAL => fields.push(FieldValue::Real(slot.as_real())),
+                OneType::oneCHAR => fields.push(FieldValue::Char(slot.as_char())),
+                _ => list_type = Some(field_type),
+            }
+        }
+        let list = list_type.map(|list_type| ListValue::from_list(list_type, line.list));
+        Record {
+            line_type: line.line_type,
+            fields,
+            list,
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Region queries over the alignments of a `.1aln` file
+//!
+//! `RegionIndex` reads the 'A' lines once, keeps the target interval of each
+//! alignment in scaffold coordinates, and loads only the alignments that
+//! overlap a query, via the binary object index:
+//!
+//! ```no_run
+//! use onecode::RegionIndex;
+//!
+//! let mut index = RegionIndex::open("data.1aln")?;
+//! for aln in index.query("chr1", 10_000, 20_000)? {
+//!     println!("{} {}..{}", aln.query_id, aln.query_start, aln.query_end);
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use std::collections::HashMap;
+
+use crate::aln::{read_alignment, Alignment};
+use crate::coord::Span;
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::gdb::{GdbIndex, GdbReader};
+use crate::linetypes::aln;
+
+/// Interval index over the target intervals of the alignments in a `.1aln` file
+///
+/// Coordinates are 0-based and half-open, in the scaffold space of the target
+/// skeleton (the second 'g' group, or the first if there is only one), on the
+/// forward strand as `Alignment::normalized()` gives them. Needs a binary
+/// file, since matching alignments are found with `OneFile::goto()`.
+pub struct RegionIndex {
+    file: OneFile,
+    trees: HashMap<String, IntervalTree>, // by target scaffold name
+}
+
+impl RegionIndex {
+    /// Open a binary `.1aln` file and index its alignments
+    pub fn open(path: &str) -> Result<Self> {
+        Self::new(OneFile::open_read(path, None, Some("aln"), 1)?)
+    }
+
+    /// Index the alignments of an open binary `.1aln` file
+    ///
+    /// Reads every 'A' line with its 'R' line once; the file position is
+    /// left undefined.
+    pub fn new(mut file: OneFile) -> Result<Self> {
+        if !file.is_binary() {
+            return Err(OneError::Other(
+                "Region queries need a binary .1aln file with an object index".to_string(),
+            ));
+        }
+        let group = if file.stats(aln::GDB_GROUP)?.0 > 1 {
+            2
+        } else {
+            1
+        };
+        let query = GdbIndex::new(&GdbReader::from_alignment(&mut file, 1)?);
+        let target_gdb = GdbReader::from_alignment(&mut file, group)?;
+        let target = GdbIndex::new(&target_gdb);
+
+        let mut intervals: HashMap<String, Vec<Interval>> = target_gdb
+            .scaffolds()
+            .iter()
+            .map(|s| (s.name.clone(), Vec::new()))
+            .collect();
+        if file.stats(aln::ALIGNMENT)?.0 > 0 {
+            file.goto(aln::ALIGNMENT, 1)?;
+            let mut object = 0;
+            let mut line_type = file.read_line();
+            while line_type != '\0' {
+                if line_type != aln::ALIGNMENT {
+                    line_type = file.read_line();
+                    continue;
+                }
+                let (alignment, next) = read_alignment(&mut file);
+                line_type = next;
+                object += 1;
+
+                let normalized = alignment.normalized(&query, &target)?;
+                let list = intervals.entry(normalized.target_name.to_string()).or_default();
+                list.push(Interval {
+                    start: normalized.target_start,
+                    end: normalized.target_end,
+                    max_end: 0,
+                    object,
+                });
+            }
+        }
+
+        let trees = intervals
+            .into_iter()
+            .map(|(name, intervals)| (name, IntervalTree::new(intervals)))
+            .collect();
+        Ok(RegionIndex { file, trees })
+    }
+
+    /// Number of alignments indexed
+    pub fn len(&self) -> usize {
+        self.trees.values().map(|t| t.intervals.len()).sum()
+    }
+
+    /// Whether the file holds no alignments
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Object numbers (1-indexed, as for `OneFile::goto()`) of the alignments
+    /// whose target interval overlaps `start..end` of scaffold `target_name`
+    ///
+    /// Sorted by target start. Nothing is read from the file. Fails with
+    /// `InvalidFormat` unless `0 <= start <= end`; convert 1-based regions
+    /// with `Span::from_one_based()`.
+    pub fn overlapping(&self, target_name: &str, start: i64, end: i64) -> Result<Vec<i64>> {
+        Span::new(start, end)?;
+        let tree = self
+            .trees
+            .get(target_name)
+            .ok_or_else(|| OneError::SequenceNotFound(target_name.to_string()))?;
+        Ok(tree
+            .overlapping(start, end)
+            .map(|i| tree.intervals[i].object)
+            .collect())
+    }
+
+    /// Alignments whose target interval overlaps `start..end` of scaffold
+    /// `target_name`, sorted by target start
+    ///
+    /// Only the matching 'A' objects are read. The alignments are in contig
+    /// coordinates, as `AlnReader` yields them.
+    pub fn query(&mut self, target_name: &str, start: i64, end: i64) -> Result<Vec<Alignment>> {
+        let objects = self.overlapping(target_name, start, end)?;
+        let mut alignments = Vec::with_capacity(objects.len());
+        for object in objects {
+            self.file.goto(aln::ALIGNMENT, object)?;
+            self.file.read_line();
+            alignments.push(read_alignment(&mut self.file).0);
+        }
+        Ok(alignments)
+    }
+
+    /// The underlying file
+    pub fn file(&self) -> &OneFile {
+        &self.file
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    start: i64,
+    end: i64,
+    max_end: i64, // largest end in the subtree rooted here
+    object: i64,
+}
+
+/// Implicit augmented interval tree over intervals sorted by start
+///
+/// The sorted array is read as a binary search tree in which the node at
+/// index x on level k has children x -/+ 2^(k-1), as in cgranges.
+struct IntervalTree {
+    intervals: Vec<Interval>,
+    root_level: i32, // -1 when empty
+}
+
+impl IntervalTree {
+    fn new(mut intervals: Vec<Interval>) -> Self {
+        intervals.sort_by_key(|i| (i.start, i.end));
+        let root_level = Self::augment(&mut intervals);
+        IntervalTree {
+            intervals,
+            root_level,
+        }
+    }
+
+    /// Fill in `max_end` bottom-up and return the level of the root
+    fn augment(a: &mut [Interval]) -> i32 {
+        let n = a.len();
+        if n == 0 {
+            return -1;
+        }
+        let (mut last_i, mut last) = (0, 0);
+        for i in (0..n).step_by(2) {
+            a[i].max_end = a[i].end;
+            (last_i, last) = (i, a[i].end);
+        }
+        let mut k = 1;
+        while 1 << k <= n {
+            let x = 1 << (k - 1);
+            for i in ((x << 1) - 1..n).step_by(x << 2) {
+                let left = a[i - x].max_end;
+                let right = if i + x < n { a[i + x].max_end } else { last };
+                a[i].max_end = a[i].end.max(left).max(right);
+            }
+            last_i = if (last_i >> k) & 1 == 1 {
+                last_i - x
+            } else {
+                last_i + x
+            };
+            if last_i < n && a[last_i].max_end > last {
+                last = a[last_i].max_end;
+            }
+            k += 1;
+        }
+        k - 1
+    }
+
+    /// Indices of the intervals overlapping `start..end`, in sorted order
+    fn overlapping(&self, start: i64, end: i64) -> impl Iterator<Item = usize> {
+        let a = &self.intervals;
+        let n = a.len();
+        let mut hits = Vec::new();
+        if self.root_level < 0 {
+            return hits.into_iter();
+        }
+        // (node, level, left subtree done)
+        let mut stack = vec![((1usize << self.root_level) - 1, self.root_level, false)];
+        while let Some((x, k, left_done)) = stack.pop() {
+            if k <= 3 {
+                // Small subtree: scan it linearly
+                let i0 = x >> k << k;
+                let i1 = (i0 + (1 << (k + 1)) - 1).min(n);
+                for (i, interval) in a.iter().enumerate().take(i1).skip(i0) {
+                    if interval.start >= end {
+                        break;
+                    }
+                    if start < interval.end {
+                        hits.push(i);
+                    }
+                }
+            } else if !left_done {
+                stack.push((x, k, true));
+                let y = x - (1 << (k - 1));
+                // The left child may lie beyond the array, or may hold an overlap
+                if y >= n || a[y].max_end > start {
+                    stack.push((y, k - 1, false));
+                }
+            } else if x < n && a[x].start < end {
+                if start < a[x].end {
+                    hits.push(x);
+                }
+                stack.push((x + (1 << (k - 1)), k - 1, false));
+            }
+        }
+        hits.into_iter()
+    }
+}
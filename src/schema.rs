@@ -1,14 +1,77 @@
 //! Schema management for ONE files
+//!
+//! Without the `onelib` feature only the schema texts and the helpers that
+//! work on them are built, for `ascii::AsciiReader`.
+
+#![cfg_attr(not(feature = "onelib"), allow(dead_code))]
 
 use crate::error::{OneError, Result};
-use crate::ffi;
+#[cfg(feature = "onelib")]
+use crate::ffi::{self, OneType};
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "onelib")]
 use std::ffi::CString;
+use std::sync::{Mutex, OnceLock};
+
+/// Schema for `.1seq` sequence files, as used by the ONEcode sequence utilities
+pub const SEQ_SCHEMA_TEXT: &str = "\
+P 3 seq                   SEQUENCE
+O S 1 3 DNA               sequence: the DNA string
+D I 1 6 STRING            id: (optional) sequence identifier
+D Q 1 6 STRING            quality: Q values (ascii string = q+33)
+D N 3 3 INT 4 CHAR 3 INT  non-acgt base: pos (0-indexed), base, number
+";
+
+/// Schema for standalone GDB skeleton files (.1gdb), as written by FastGA
+pub const GDB_SCHEMA_TEXT: &str = "\
+P 3 gdb                              GDB
+D f 4 4 REAL 4 REAL 4 REAL 4 REAL    global: base frequency vector
+O S 1 6 STRING                       id for a scaffold
+D G 1 3 INT                          gap of given length
+D C 1 3 INT                          contig of given length
+D M 1 8 INT_LIST                     mask pair list for a contig
+";
+
+/// Schema for alignment files (.1aln), as written by FastGA
+pub const ALN_SCHEMA_TEXT: &str = "\
+P 3 aln                   ALIGNMENT
+D t 1 3 INT               trace point spacing in a - global
+O g 0                     groups scaffolds into a GDB skeleton
+G S 0                     collection of scaffolds constituting a GDB
+O S 1 6 STRING            id for a scaffold
+D G 1 3 INT               gap of given length
+D C 1 3 INT               contig of given length
+D M 1 8 INT_LIST          mask pair list for a contig
+O a 0                     groups A's into a colinear chain
+G A 0                     chains (a) group alignment objects (A)
+D p 2 3 INT 3 INT         spacing in a,b between end of previous alignment and start of next
+O A 6 3 INT 3 INT 3 INT 3 INT 3 INT 3 INT   a_id a_start a_end b_id b_start b_end
+D L 2 3 INT 3 INT         lengths of sequences a and b
+D R 0                     flag: reverse-complement sequence b
+D D 1 3 INT               differences: number of diffs = substitions + indels
+D T 1 8 INT_LIST          trace points in b
+D X 1 8 INT_LIST          number of differences in alignment per trace interval
+D Q 1 3 INT               quality: alignment confidence in phred units
+D E 1 3 INT               match: number of equal bases
+D Z 1 6 STRING            cigar string: encodes precise alignment
+";
+
+/// Schema for `.1pro` protein files, as written by `seq::to_protein()`
+pub const PROTEIN_SCHEMA_TEXT: &str = "\
+P 3 pro                   PROTEIN
+O S 1 6 STRING            sequence: amino acids, one letter each, '*' for stop
+D I 1 6 STRING            id: (optional) sequence identifier
+D F 2 3 INT 3 INT         frame: source sequence (0-indexed) and reading frame, -3..-1 or 1..3
+";
 
+#[cfg(feature = "onelib")]
 /// A ONE file schema
 pub struct OneSchema {
     pub(crate) ptr: *mut ffi::OneSchema,
+    text: String,
 }
 
+#[cfg(feature = "onelib")]
 impl OneSchema {
     /// Create a schema from a file
     pub fn from_file(path: &str) -> Result<Self> {
@@ -22,7 +85,8 @@ impl OneSchema {
                     path
                 )));
             }
-            Ok(OneSchema { ptr })
+            let text = std::fs::read_to_string(path).unwrap_or_default();
+            Ok(OneSchema { ptr, text })
         }
     }
 
@@ -33,6 +97,18 @@ impl OneSchema {
     /// This function is now thread-safe. The C library uses `_Thread_local` for
     /// all global state (errorString, isBootStrap) and mkstemp() for temp files.
     pub fn from_text(text: &str) -> Result<Self> {
+        // ONElib exits the process on a line type it cannot read
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            if let (Some("O" | "D" | "G"), Some(t)) = (words.next(), words.next()) {
+                if !t.is_ascii() {
+                    return Err(OneError::SchemaError(format!(
+                        "Line type '{}' is not an ASCII letter",
+                        t
+                    )));
+                }
+            }
+        }
         let c_text = CString::new(text)?;
 
         unsafe {
@@ -42,16 +118,663 @@ impl OneSchema {
                     "Failed to create schema from text".to_string()
                 ));
             }
-            Ok(OneSchema { ptr })
+            Ok(OneSchema {
+                ptr,
+                text: text.to_string(),
+            })
         }
     }
 
+    /// Standard schema for `.1seq` sequence files
+    pub fn seq() -> Result<Self> {
+        Self::from_text(SEQ_SCHEMA_TEXT)
+    }
+
+    /// Standard schema for `.1gdb` GDB skeleton files
+    pub fn gdb() -> Result<Self> {
+        Self::from_text(GDB_SCHEMA_TEXT)
+    }
+
+    /// Standard schema for `.1aln` alignment files
+    pub fn aln() -> Result<Self> {
+        Self::from_text(ALN_SCHEMA_TEXT)
+    }
+
+    /// Schema for `.1pro` protein files
+    pub fn protein() -> Result<Self> {
+        Self::from_text(PROTEIN_SCHEMA_TEXT)
+    }
+
+    /// Get the schema definition text this schema was created from
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Stable 64-bit fingerprint of the schema definition
+    ///
+    /// Computed over a normalized form of the definition lines: comments,
+    /// spacing and length prefixes are ignored, while primary and sub-types,
+    /// line types, field types and their order all count. Schemas producing
+    /// identical file layouts therefore share a fingerprint across machines
+    /// and builds, so shards can be checked for compatibility before merging.
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint_text(&self.text)
+    }
+
+    /// Readable description of the line types of each primary type
+    ///
+    /// Lists every line type with its kind (object or data), field types and
+    /// the comment from its definition line, followed by the object types
+    /// each group contains. The list field, if any, is shown in brackets. For
+    /// an open file use `OneSchema::from_text(&file.schema_text()?)`.
+    pub fn describe(&self) -> String {
+        describe_text(&self.text)
+    }
+
+    /// Create a new schema extending this one with extra definition lines
+    ///
+    /// The O/D lines in `text` are appended to the last primary type of this
+    /// schema, so tools can add custom line types to a standard file type:
+    ///
+    /// ```no_run
+    /// use onecode::OneSchema;
+    ///
+    /// let schema = OneSchema::seq()?.extend_with_text("D X 1 3 INT")?;
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    ///
+    /// Fails if `text` redefines a line type that already exists, or if it
+    /// starts a new primary type.
+    pub fn extend_with_text(&self, text: &str) -> Result<Self> {
+        let mut defined = defined_line_types(&self.text);
+        for line in text.lines() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("P") => {
+                    return Err(OneError::SchemaError(format!(
+                        "Schema extension cannot declare a primary type: {}",
+                        line.trim()
+                    )));
+                }
+                Some("O") | Some("D") => {
+                    let line_type = tokens.next().and_then(|t| t.chars().next());
+                    if let Some(c) = line_type {
+                        if !defined.insert(c) {
+                            return Err(OneError::SchemaError(format!(
+                                "Schema extension redefines line type '{}'",
+                                c
+                            )));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut combined = self.text.clone();
+        if !combined.is_empty() && !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+        combined.push_str(text);
+        if !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+        Self::from_text(&combined)
+    }
+
     /// Get the internal pointer (for use with FFI functions)
     pub(crate) fn as_ptr(&self) -> *mut ffi::OneSchema {
         self.ptr
     }
 }
 
+/// Maximum number of fields ONElib accepts on one line type
+const MAX_FIELDS: usize = 32;
+
+#[cfg(feature = "onelib")]
+/// Schema type name of a field type, as written in definition lines
+pub(crate) fn type_name(field_type: OneType) -> &'static str {
+    match field_type {
+        OneType::oneINT => "INT",
+        OneType::oneREAL => "REAL",
+        OneType::oneCHAR => "CHAR",
+        OneType::oneSTRING => "STRING",
+        OneType::oneINT_LIST => "INT_LIST",
+        OneType::oneREAL_LIST => "REAL_LIST",
+        OneType::oneSTRING_LIST => "STRING_LIST",
+        OneType::oneDNA => "DNA",
+    }
+}
+
+#[cfg(feature = "onelib")]
+/// One O, D or G definition line
+struct Definition {
+    kind: char,
+    line_type: char,
+    fields: Vec<OneType>,
+    comment: Option<String>,
+}
+
+/// Programmatic construction of a single primary type schema
+///
+/// Definitions are kept in the order they are added, as in schema text, and
+/// checked in `build()` before the text is handed to the C library, which
+/// aborts the process on an invalid schema rather than returning an error:
+///
+/// ```no_run
+/// use onecode::{OneType, SchemaBuilder};
+///
+/// let schema = SchemaBuilder::new("aln")
+///     .object('A', &[OneType::oneINT, OneType::oneINT, OneType::oneINT])
+///     .comment("a_id a_start a_end")
+///     .data('T', &[OneType::oneINT_LIST])
+///     .build()?;
+/// # Ok::<(), onecode::OneError>(())
+/// ```
+#[cfg(feature = "onelib")]
+pub struct SchemaBuilder {
+    primary: String,
+    secondary: Vec<String>,
+    definitions: Vec<Definition>,
+}
+
+#[cfg(feature = "onelib")]
+impl SchemaBuilder {
+    /// Start a schema for the primary file type `primary`
+    pub fn new(primary: &str) -> Self {
+        SchemaBuilder {
+            primary: primary.to_string(),
+            secondary: Vec::new(),
+            definitions: Vec::new(),
+        }
+    }
+
+    /// Add a secondary file type name
+    pub fn secondary(mut self, name: &str) -> Self {
+        self.secondary.push(name.to_string());
+        self
+    }
+
+    /// Define an object line type
+    pub fn object(self, line_type: char, fields: &[OneType]) -> Self {
+        self.define('O', line_type, fields)
+    }
+
+    /// Define a data line type, belonging to the most recent object
+    pub fn data(self, line_type: char, fields: &[OneType]) -> Self {
+        self.define('D', line_type, fields)
+    }
+
+    /// Make the most recent object a group of objects of type `member`
+    pub fn group(self, member: char) -> Self {
+        self.define('G', member, &[])
+    }
+
+    /// Set the comment of the most recent definition
+    pub fn comment(mut self, comment: &str) -> Self {
+        if let Some(definition) = self.definitions.last_mut() {
+            definition.comment = Some(comment.to_string());
+        }
+        self
+    }
+
+    fn define(mut self, kind: char, line_type: char, fields: &[OneType]) -> Self {
+        self.definitions.push(Definition {
+            kind,
+            line_type,
+            fields: fields.to_vec(),
+            comment: None,
+        });
+        self
+    }
+
+    /// Check the definitions and return the schema text
+    pub fn to_text(&self) -> Result<String> {
+        check_name("primary", &self.primary)?;
+        for name in &self.secondary {
+            check_name("secondary", name)?;
+        }
+
+        let objects: HashSet<char> = self
+            .definitions
+            .iter()
+            .filter(|d| d.kind == 'O')
+            .map(|d| d.line_type)
+            .collect();
+        let mut defined = HashSet::new();
+        let mut seen_object = false;
+        for d in &self.definitions {
+            let t = d.line_type;
+            if !t.is_ascii_alphabetic() {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' is not an ASCII letter",
+                    t
+                )));
+            }
+            if d.comment.as_deref().is_some_and(|c| c.contains('\n')) {
+                return Err(OneError::SchemaError(format!(
+                    "Comment of line type '{}' contains a newline",
+                    t
+                )));
+            }
+            match d.kind {
+                'G' => {
+                    if !seen_object {
+                        return Err(OneError::SchemaError(format!(
+                            "Group of '{}' declared before any object",
+                            t
+                        )));
+                    }
+                    if !objects.contains(&t) {
+                        return Err(OneError::SchemaError(format!(
+                            "Group member '{}' is not an object type",
+                            t
+                        )));
+                    }
+                    continue;
+                }
+                'O' => seen_object = true,
+                _ => {}
+            }
+            if !defined.insert(t) {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' is defined more than once",
+                    t
+                )));
+            }
+            if d.fields.len() > MAX_FIELDS {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' has {} fields, at most {} are supported",
+                    t,
+                    d.fields.len(),
+                    MAX_FIELDS
+                )));
+            }
+            let n_list = d
+                .fields
+                .iter()
+                .filter(|f| !matches!(f, OneType::oneINT | OneType::oneREAL | OneType::oneCHAR))
+                .count();
+            if n_list > 1 {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' has {} list fields, at most one is allowed",
+                    t, n_list
+                )));
+            }
+        }
+
+        let mut text = format!("P {} {}\n", self.primary.len(), self.primary);
+        for name in &self.secondary {
+            text.push_str(&format!("S {} {}\n", name.len(), name));
+        }
+        for d in &self.definitions {
+            text.push_str(&format!("{} {} {}", d.kind, d.line_type, d.fields.len()));
+            for &field in &d.fields {
+                let name = type_name(field);
+                text.push_str(&format!(" {} {}", name.len(), name));
+            }
+            if let Some(comment) = &d.comment {
+                text.push_str("   ");
+                text.push_str(comment);
+            }
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Check the definitions and create the schema
+    pub fn build(&self) -> Result<OneSchema> {
+        OneSchema::from_text(&self.to_text()?)
+    }
+}
+
+fn check_name(what: &str, name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '\0') {
+        return Err(OneError::SchemaError(format!(
+            "Invalid {} type name {:?}",
+            what, name
+        )));
+    }
+    Ok(())
+}
+
+/// Schema texts registered with `register_schema()`, by file type name
+fn registry() -> &'static Mutex<HashMap<String, String>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Register `schema` as the schema of file type `name` for the whole process
+///
+/// Afterwards opening a file with `Some(name)` as its file type and no
+/// explicit schema, e.g. `OneFile::open_read(path, None, Some(name), 1)`,
+/// checks the file against `schema`, and headerless files of that type can
+/// be read. `name` must be the primary or a secondary type of `schema`.
+/// Registering a name again replaces its schema.
+///
+/// ```no_run
+/// use onecode::{register_schema, OneFile, OneSchema};
+///
+/// let schema = OneSchema::from_text("P 3 seq\nS 5 reads\nO S 1 3 DNA\nD Q 1 6 STRING\n")?;
+/// register_schema("reads", &schema)?;
+/// let file = OneFile::open_read("sample.1reads", None, Some("reads"), 1)?;
+/// # Ok::<(), onecode::OneError>(())
+/// ```
+#[cfg(feature = "onelib")]
+pub fn register_schema(name: &str, schema: &OneSchema) -> Result<()> {
+    let declares_name = normalize_schema_text(&schema.text)
+        .lines()
+        .any(|line| line.strip_prefix("P ").or_else(|| line.strip_prefix("S ")) == Some(name));
+    if !declares_name {
+        return Err(OneError::SchemaError(format!(
+            "Schema does not declare a file type named {}",
+            name
+        )));
+    }
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), schema.text.clone());
+    Ok(())
+}
+
+/// Remove the schema registered for file type `name`, returning whether there was one
+pub fn unregister_schema(name: &str) -> bool {
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(name)
+        .is_some()
+}
+
+#[cfg(feature = "onelib")]
+/// A new copy of the schema registered for file type `name`, if any
+pub fn registered_schema(name: &str) -> Result<Option<OneSchema>> {
+    let text = registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned();
+    text.map(|text| OneSchema::from_text(&text)).transpose()
+}
+
+/// Line types defined by O/D lines under the last primary type of `text`
+fn defined_line_types(text: &str) -> HashSet<char> {
+    let mut defined = HashSet::new();
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("P") => defined.clear(),
+            Some("O") | Some("D") => {
+                if let Some(c) = tokens.next().and_then(|t| t.chars().next()) {
+                    defined.insert(c);
+                }
+            }
+            _ => {}
+        }
+    }
+    defined
+}
+
+/// Normalize schema definition text into one canonical line per definition
+///
+/// Keeps P/S lines as their type name and O/D/G lines as line type plus field
+/// types, dropping comments. Header lines copied from files ("~ O S ...") are
+/// accepted too.
+pub(crate) fn normalize_schema_text(text: &str) -> String {
+    let mut normalized = String::new();
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace().peekable();
+        if tokens.peek() == Some(&"~") {
+            tokens.next();
+        }
+        let kind = match tokens.next() {
+            Some(k @ ("P" | "S" | "O" | "D" | "G")) => k,
+            _ => continue,
+        };
+        normalized.push_str(kind);
+        if kind == "P" || kind == "S" {
+            if let Some(name) = tokens.nth(1) {
+                normalized.push(' ');
+                normalized.push_str(name);
+            }
+        } else {
+            if let Some(line_type) = tokens.next() {
+                normalized.push(' ');
+                normalized.push_str(line_type);
+            }
+            let n_field: usize = tokens.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+            for _ in 0..n_field {
+                if let Some(field_type) = tokens.nth(1) {
+                    normalized.push(' ');
+                    normalized.push_str(field_type);
+                }
+            }
+        }
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// Primary file types a schema text defines, in order
+pub(crate) fn primary_types(text: &str) -> Vec<String> {
+    normalize_schema_text(text)
+        .lines()
+        .filter_map(|line| line.strip_prefix("P "))
+        .map(str::to_string)
+        .collect()
+}
+
+/// File types a schema text defines, primary and secondary, in order
+pub(crate) fn file_types(text: &str) -> Vec<String> {
+    normalize_schema_text(text)
+        .lines()
+        .filter_map(|line| line.strip_prefix("P ").or_else(|| line.strip_prefix("S ")))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Stands in for the definition of a line type a schema lacks
+pub(crate) const UNDEFINED: &str = "(undefined)";
+
+/// Line types defined differently in two schema texts
+///
+/// Compares the O/D definitions of primary type `file_type` in `expected`
+/// with those in `found` (a file's own schema), returning each line type
+/// with both normalized definitions, "(undefined)" where one side lacks it,
+/// in order of their first definition.
+pub(crate) fn differences(
+    expected: &str,
+    found: &str,
+    file_type: &str,
+) -> Vec<(char, String, String)> {
+    let definitions = |text: &str, primary: Option<&str>| {
+        let mut in_section = primary.is_none();
+        let mut defs: Vec<(char, String)> = Vec::new();
+        for line in normalize_schema_text(text).lines() {
+            match line.split_once(' ') {
+                Some(("P", name)) => in_section = primary.is_none_or(|p| p == name),
+                Some(("O" | "D", rest)) if in_section => {
+                    if let Some(t) = rest.chars().next() {
+                        defs.push((t, line.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        defs
+    };
+    let expected = definitions(expected, Some(file_type));
+    let found = definitions(found, None);
+    let lookup = |defs: &[(char, String)], t: char| {
+        defs.iter()
+            .find(|(d, _)| *d == t)
+            .map_or_else(|| UNDEFINED.to_string(), |(_, def)| def.clone())
+    };
+    let mut seen = Vec::new();
+    expected
+        .iter()
+        .chain(found.iter())
+        .filter(|(t, _)| {
+            let first = !seen.contains(t);
+            seen.push(*t);
+            first
+        })
+        .map(|(t, _)| (*t, lookup(&expected, *t), lookup(&found, *t)))
+        .filter(|(_, e, f)| e != f)
+        .collect()
+}
+
+/// FNV-1a hash of the normalized schema text
+///
+/// Implemented here rather than with `std::hash` so the value is stable across
+/// Rust versions and can be stored in manifests.
+pub(crate) fn fingerprint_text(text: &str) -> u64 {
+    fnv1a(FNV_OFFSET, normalize_schema_text(text).as_bytes())
+}
+
+/// Take the next whitespace-separated token from the front of `rest`
+fn next_token<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    let trimmed = rest.trim_start();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    let (token, tail) = trimmed.split_at(end);
+    *rest = tail;
+    Some(token)
+}
+
+/// Table of line types per primary type, see `OneSchema::describe()`
+fn describe_text(text: &str) -> String {
+    struct Row {
+        line_type: String,
+        kind: &'static str,
+        fields: String,
+        comment: String,
+    }
+    struct Section {
+        title: String,
+        secondary: Vec<String>,
+        rows: Vec<Row>,
+        groups: Vec<(String, Vec<String>)>, // group object type, contained types
+    }
+
+    let mut sections: Vec<Section> = Vec::new();
+    for line in text.lines() {
+        let mut rest = line;
+        if line.trim_start().starts_with('~') {
+            next_token(&mut rest);
+        }
+        let Some(kind) = next_token(&mut rest) else {
+            continue;
+        };
+        if kind == "P" {
+            next_token(&mut rest);
+            let name = next_token(&mut rest).unwrap_or("");
+            let comment = rest.trim();
+            sections.push(Section {
+                title: if comment.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{} - {}", name, comment)
+                },
+                secondary: Vec::new(),
+                rows: Vec::new(),
+                groups: Vec::new(),
+            });
+            continue;
+        }
+        let Some(section) = sections.last_mut() else {
+            continue;
+        };
+        match kind {
+            "S" => {
+                next_token(&mut rest);
+                section.secondary.extend(next_token(&mut rest).map(str::to_string));
+            }
+            "O" | "D" | "G" => {
+                let line_type = next_token(&mut rest).unwrap_or("").to_string();
+                let n_field: usize =
+                    next_token(&mut rest).and_then(|n| n.parse().ok()).unwrap_or(0);
+                let mut fields = Vec::with_capacity(n_field);
+                for _ in 0..n_field {
+                    next_token(&mut rest);
+                    let name = next_token(&mut rest).unwrap_or("?");
+                    let is_list = name.ends_with("LIST") || name == "STRING" || name == "DNA";
+                    fields.push(if is_list { format!("[{}]", name) } else { name.to_string() });
+                }
+                if kind == "G" {
+                    // Groups the given type into the object defined last
+                    let group = section.rows.iter().rev().find(|r| r.kind == "object");
+                    if let Some(group) = group.map(|r| r.line_type.clone()) {
+                        match section.groups.iter_mut().find(|(g, _)| *g == group) {
+                            Some((_, members)) => members.push(line_type),
+                            None => section.groups.push((group, vec![line_type])),
+                        }
+                    }
+                    continue;
+                }
+                section.rows.push(Row {
+                    line_type,
+                    kind: if kind == "O" { "object" } else { "data" },
+                    fields: fields.join(" "),
+                    comment: rest.trim().to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&section.title);
+        out.push('\n');
+        if !section.secondary.is_empty() {
+            out.push_str(&format!("secondary types: {}\n", section.secondary.join(", ")));
+        }
+        let width = section
+            .rows
+            .iter()
+            .map(|r| r.fields.len())
+            .max()
+            .unwrap_or(0)
+            .max("fields".len());
+        out.push_str(&format!("\n{:<6}{:<8}{:<width$}  {}\n", "type", "kind", "fields", "comment"));
+        for row in &section.rows {
+            let line = format!(
+                "{:<6}{:<8}{:<width$}  {}",
+                row.line_type, row.kind, row.fields, row.comment
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        if !section.groups.is_empty() {
+            out.push('\n');
+            for (group, members) in &section.groups {
+                out.push_str(&format!("group {} contains {}\n", group, members.join(", ")));
+            }
+        }
+    }
+    out
+}
+
+/// Initial value for `fnv1a()`
+pub(crate) const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Continue a 64-bit FNV-1a hash over `bytes`
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(feature = "onelib")]
 impl Drop for OneSchema {
     fn drop(&mut self) {
         if !self.ptr.is_null() {
@@ -0,0 +1,874 @@
+//! Reader for `.1seq` sequence files
+//!
+//! A `.1seq` file holds one 'S' object per sequence (the DNA list), optionally
+//! followed by an 'I' line carrying its identifier. `import_fasta()` and
+//! `from_fasta()` create such files from FASTA or FASTQ input, gzipped or not,
+//! and `export_fasta()` and `to_fasta()` write them back out. `translate()`
+//! and `to_protein()` turn them into amino acids.
+
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::linetypes::seq::{DESCRIPTION, IDENTIFIER, NON_ACGT, QUALITY, SEQUENCE};
+use crate::names::{index_names, NameNormalizer};
+use crate::protein::{Protein, ProteinWriter};
+use crate::schema::OneSchema;
+use crate::store::{check_range, SequenceStore};
+use crate::types::DnaEncoding;
+use flate2::bufread::MultiGzDecoder;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Schema extension adding the defline line written by `DeflinePolicy::Line`
+pub const DESCRIPTION_SCHEMA_TEXT: &str =
+    "D D 1 6 STRING            defline: full FASTA header line\n";
+
+/// How FASTA deflines are stored when importing
+///
+/// The identifier ('I' line) is always the defline up to the first
+/// whitespace, as returned by `SeqReader::name()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeflinePolicy {
+    /// Drop everything after the identifier
+    #[default]
+    Discard,
+    /// Keep the full defline as a comment on the 'I' line
+    ///
+    /// Only ASCII output is recommended: the C library currently fails
+    /// reading back comments from binary files.
+    Comment,
+    /// Keep the full defline in a 'D' STRING line after the 'I' line,
+    /// extending the standard schema with `DESCRIPTION_SCHEMA_TEXT`
+    Line,
+}
+
+/// Options for importing FASTA into `.1seq`
+#[derive(Debug, Clone, Default)]
+pub struct FastaOptions {
+    /// Where to keep the full defline
+    pub defline: DeflinePolicy,
+    /// Fail with `OneError::DuplicateName` if two records share an identifier
+    pub unique_ids: bool,
+}
+
+/// A `.1seq` reader with a name index for random access to sequences
+///
+/// Opening the file performs a single pass to record the name and length of
+/// every sequence. Binary files are then accessed through the object index;
+/// ASCII files are re-scanned from the start whenever a fetch moves backwards.
+pub struct SeqReader {
+    file: OneFile,
+    path: String,
+    names: Vec<Option<String>>,
+    lengths: Vec<i64>,
+    index: HashMap<String, usize>,
+    normalizer: Option<Box<dyn NameNormalizer>>,
+    cursor: usize, // number of the next 'S' object the handle will return
+}
+
+impl SeqReader {
+    /// Open a `.1seq` file and index its sequence names and lengths
+    ///
+    /// Identifiers are trimmed at the first whitespace character. If several
+    /// sequences share a name, lookups by name resolve to the first one. An
+    /// identifier that is not UTF-8 fails with `OneError::InvalidUtf8`.
+    pub fn open(path: &str) -> Result<Self> {
+        let mut file = OneFile::open_read(path, None, Some("seq"), 1)?;
+
+        let mut names: Vec<Option<String>> = Vec::new();
+        let mut lengths = Vec::new();
+        loop {
+            match file.read_line() {
+                '\0' => break,
+                SEQUENCE => {
+                    lengths.push(file.len());
+                    names.push(None);
+                }
+                IDENTIFIER => {
+                    if let Some(last) = names.last_mut() {
+                        *last = Some(OneFile::trim_sequence_name(file.string()?));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut index = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            if let Some(name) = name {
+                index.entry(name.clone()).or_insert(i);
+            }
+        }
+
+        let cursor = lengths.len();
+        Ok(SeqReader {
+            file,
+            path: path.to_string(),
+            names,
+            lengths,
+            index,
+            normalizer: None,
+            cursor,
+        })
+    }
+
+    /// Number of sequences in the file
+    pub fn num_sequences(&self) -> usize {
+        self.lengths.len()
+    }
+
+    /// Identifier of sequence `i` (0-indexed), if it has one
+    pub fn name(&self, i: usize) -> Option<&str> {
+        self.names.get(i).and_then(|n| n.as_deref())
+    }
+
+    /// Length of sequence `i` (0-indexed)
+    pub fn length(&self, i: usize) -> Option<i64> {
+        self.lengths.get(i).copied()
+    }
+
+    /// Index of the sequence with the given identifier
+    pub fn id(&self, name: &str) -> Option<usize> {
+        match &self.normalizer {
+            Some(normalizer) => self.index.get(normalizer.normalize(name).as_ref()).copied(),
+            None => self.index.get(name).copied(),
+        }
+    }
+
+    /// Look sequences up by their names as `normalizer` maps them
+    ///
+    /// `id()` and the `SequenceStore` methods then find a sequence by any
+    /// name that normalizes to the same key as its identifier, and if several
+    /// identifiers do, the first sequence. `name()` still gives the
+    /// identifiers as in the file. See `names`.
+    pub fn with_normalizer(mut self, normalizer: impl NameNormalizer + 'static) -> Self {
+        let names = self.names.iter().enumerate();
+        let names = names.filter_map(|(i, name)| Some((i, name.as_deref()?)));
+        self.index = index_names(names, &normalizer);
+        self.normalizer = Some(Box::new(normalizer));
+        self
+    }
+
+    /// Read the full sequence `i` (0-indexed) as lower-case bases
+    pub fn sequence(&mut self, i: usize) -> Result<Vec<u8>> {
+        self.read_sequence_line(i)?;
+        Ok(self.file.dna_char().map(|s| s.to_vec()).unwrap_or_default())
+    }
+
+    /// Iterate over sequence `i` (0-indexed) in chunks of lower-case bases
+    ///
+    /// Chunks are `STREAM_CHUNK_SIZE` bases long, except the last; see
+    /// `SequenceChunks::chunk_size()`. In binary files only the current chunk
+    /// is unpacked, so the sequence is held in its stored 2-bit form, a
+    /// quarter of its length; in ASCII files chunks borrow the line as read.
+    ///
+    /// ```no_run
+    /// use onecode::SeqReader;
+    ///
+    /// let mut reader = SeqReader::open("genome.1seq")?;
+    /// let mut gc = 0;
+    /// for chunk in reader.stream_sequence(0)? {
+    ///     gc += chunk.iter().filter(|&&b| b == b'g' || b == b'c').count();
+    /// }
+    /// # Ok::<(), onecode::OneError>(())
+    /// ```
+    pub fn stream_sequence(&mut self, i: usize) -> Result<SequenceChunks<'_>> {
+        self.read_sequence_line(i)?;
+        let len = self.file.len() as usize;
+        let (data, packed) = match self.file.dna_encoding(SEQUENCE) {
+            Some(DnaEncoding::TwoBit) => (self.file.dna_2bit(), true),
+            _ => (self.file.dna_char(), false),
+        };
+        Ok(SequenceChunks {
+            data: data.unwrap_or_default(),
+            packed,
+            len: if data.is_some() { len } else { 0 },
+            pos: 0,
+            chunk_size: STREAM_CHUNK_SIZE,
+        })
+    }
+
+    /// Read the 'S' line of sequence `i`
+    fn read_sequence_line(&mut self, i: usize) -> Result<()> {
+        if i >= self.lengths.len() {
+            return Err(OneError::Other(format!(
+                "Sequence index {} out of range ({} sequences)",
+                i,
+                self.lengths.len()
+            )));
+        }
+        self.seek(i)?;
+        loop {
+            match self.file.read_line() {
+                SEQUENCE => break,
+                '\0' => return Err(OneError::ReadFailed),
+                _ => {}
+            }
+        }
+        self.cursor = i + 1;
+        Ok(())
+    }
+
+    /// Position the handle so that the next 'S' line read is sequence `i`
+    fn seek(&mut self, i: usize) -> Result<()> {
+        if self.file.is_binary() {
+            return self.file.goto(SEQUENCE, i as i64 + 1);
+        }
+        if i < self.cursor {
+            self.file = OneFile::open_read(&self.path, None, Some("seq"), 1)?;
+            self.cursor = 0;
+        }
+        while self.cursor < i {
+            match self.file.read_line() {
+                SEQUENCE => self.cursor += 1,
+                '\0' => return Err(OneError::ReadFailed),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Default chunk length of `SeqReader::stream_sequence()`, 1 Mb
+pub const STREAM_CHUNK_SIZE: usize = 1 << 20;
+
+/// Chunks of one sequence, from `SeqReader::stream_sequence()`
+///
+/// Chunks unpacked from binary files are owned, those of ASCII files
+/// borrowed; both dereference to `&[u8]`.
+pub struct SequenceChunks<'a> {
+    data: &'a [u8], // as stored: 2-bit packed if `packed`, else characters
+    packed: bool,
+    len: usize,
+    pos: usize,
+    chunk_size: usize,
+}
+
+impl SequenceChunks<'_> {
+    /// Yield chunks of `size` bases (at least 1) instead of `STREAM_CHUNK_SIZE`
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Length of the whole sequence
+    pub fn sequence_len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a> Iterator for SequenceChunks<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.len {
+            return None;
+        }
+        let end = (self.pos + self.chunk_size).min(self.len);
+        let chunk = if self.packed {
+            let base = |k: usize| BASES[(self.data[k / 4] >> (2 * (k % 4))) as usize & 3];
+            Cow::Owned((self.pos..end).map(base).collect())
+        } else {
+            Cow::Borrowed(&self.data[self.pos..end])
+        };
+        self.pos = end;
+        Some(chunk)
+    }
+}
+
+/// Bases of ONElib's 2-bit DNA codec, which packs the first base in the lowest bits
+const BASES: [u8; 4] = [b'a', b'c', b'g', b't'];
+
+/// Iterate over the (w,k)-minimizers of sequence `i` (0-indexed) of `reader`
+///
+/// Each window of `w` consecutive k-mers contributes its k-mer of smallest
+/// hash, the leftmost on ties, and each is yielded once as its 0-based start
+/// position and hash, in order of position. K-mers are canonical, the smaller
+/// of the k-mer and its reverse complement being hashed, so both strands
+/// give the same minimizers; `k` may be at most 32. A sequence of fewer than
+/// `w` k-mers has none.
+///
+/// K-mers are built straight from the 2-bit form of binary files, without
+/// decoding the bases; in ASCII files bases other than acgt count as 'a',
+/// as ONElib packs them.
+pub fn minimizers(
+    reader: &mut SeqReader,
+    i: usize,
+    k: usize,
+    w: usize,
+) -> Result<Minimizers<'_>> {
+    if !(1..=32).contains(&k) || w == 0 {
+        return Err(OneError::Other(format!(
+            "Minimizers need 1 <= k <= 32 and w >= 1, not k = {} and w = {}",
+            k, w
+        )));
+    }
+    let chunks = reader.stream_sequence(i)?;
+    Ok(Minimizers {
+        data: chunks.data,
+        packed: chunks.packed,
+        len: chunks.len,
+        k,
+        w,
+        pos: 0,
+        forward: 0,
+        reverse: 0,
+        window: VecDeque::with_capacity(w),
+        last: None,
+    })
+}
+
+/// Minimizers of one sequence, from `minimizers()`
+pub struct Minimizers<'a> {
+    data: &'a [u8], // as stored: 2-bit packed if `packed`, else characters
+    packed: bool,
+    len: usize,
+    k: usize,
+    w: usize,
+    pos: usize,                     // number of bases consumed
+    forward: u64,                   // last k bases, 2 bits each
+    reverse: u64,                   // their reverse complement
+    window: VecDeque<(usize, u64)>, // candidates by position, hashes increasing
+    last: Option<usize>,            // position of the last minimizer yielded
+}
+
+impl Minimizers<'_> {
+    /// 2-bit code of base `k`, 0 to 3 for acgt
+    fn code(&self, k: usize) -> u64 {
+        if self.packed {
+            (self.data[k / 4] >> (2 * (k % 4))) as u64 & 3
+        } else {
+            match self.data[k] | 0x20 {
+                b'c' => 1,
+                b'g' => 2,
+                b't' => 3,
+                _ => 0,
+            }
+        }
+    }
+}
+
+impl Iterator for Minimizers<'_> {
+    type Item = (usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mask = u64::MAX >> (64 - 2 * self.k);
+        while self.pos < self.len {
+            let code = self.code(self.pos);
+            self.pos += 1;
+            self.forward = (self.forward << 2 | code) & mask;
+            self.reverse = self.reverse >> 2 | (3 - code) << (2 * (self.k - 1));
+            if self.pos < self.k {
+                continue;
+            }
+
+            let start = self.pos - self.k;
+            let hash = kmer_hash(self.forward.min(self.reverse));
+            while self.window.back().is_some_and(|&(_, h)| h > hash) {
+                self.window.pop_back();
+            }
+            self.window.push_back((start, hash));
+            while self.window.front().is_some_and(|&(p, _)| p + self.w <= start) {
+                self.window.pop_front();
+            }
+            if start + 1 < self.w {
+                continue;
+            }
+            let (position, hash) = self.window[0];
+            if self.last != Some(position) {
+                self.last = Some(position);
+                return Some((position, hash));
+            }
+        }
+        None
+    }
+}
+
+/// Invertible mix of a packed k-mer (the SplitMix64 finalizer), so that
+/// minimizers are not biased towards poly-A runs
+fn kmer_hash(kmer: u64) -> u64 {
+    let mut z = kmer;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl SequenceStore for SeqReader {
+    fn len(&self, name: &str) -> Result<usize> {
+        let id = self
+            .id(name)
+            .ok_or_else(|| OneError::SequenceNotFound(name.to_string()))?;
+        Ok(self.lengths[id] as usize)
+    }
+
+    fn fetch(&mut self, name: &str, range: Range<usize>) -> Result<Vec<u8>> {
+        let id = self
+            .id(name)
+            .ok_or_else(|| OneError::SequenceNotFound(name.to_string()))?;
+        check_range(name, &range, self.lengths[id] as usize)?;
+        self.read_sequence_line(id)?;
+        // Only the bases in range are unpacked from the 2-bit form
+        if self.file.dna_encoding(SEQUENCE) == Some(DnaEncoding::TwoBit) {
+            let data = self.file.dna_2bit().unwrap_or_default();
+            let base = |k: usize| BASES[(data[k / 4] >> (2 * (k % 4))) as usize & 3];
+            Ok(range.map(base).collect())
+        } else {
+            Ok(self.file.dna_char().map_or_else(Vec::new, |seq| seq[range].to_vec()))
+        }
+    }
+}
+
+/// Import FASTA or FASTQ records from `reader` into a new `.1seq` file at `out_path`
+///
+/// The format is taken from the first record ('>' or '@'), and gzip input
+/// is decompressed on the fly. Bases are stored lower-case; runs of
+/// characters other than acgt are additionally recorded in 'N' lines
+/// (position, base, count), as ONEcode's own sequence utilities do. FASTQ
+/// qualities are kept as 'Q' lines, already being ASCII q+33. Returns the
+/// number of sequences written.
+pub fn import_fasta<R: BufRead>(
+    mut reader: R,
+    out_path: &str,
+    binary: bool,
+    options: &FastaOptions,
+) -> Result<usize> {
+    let is_gzip = reader
+        .fill_buf()
+        .map_err(read_error)?
+        .starts_with(&[0x1f, 0x8b]);
+    if is_gzip {
+        import_records(
+            BufReader::new(MultiGzDecoder::new(reader)),
+            out_path,
+            binary,
+            options,
+        )
+    } else {
+        import_records(reader, out_path, binary, options)
+    }
+}
+
+/// Import a FASTA or FASTQ file, gzipped or not, into a new `.1seq` file
+///
+/// Like `import_fasta()` with default options, reading from `path`.
+pub fn from_fasta<P: AsRef<Path>>(path: P, out_path: &str, binary: bool) -> Result<usize> {
+    let path = path.as_ref();
+    let input = File::open(path).map_err(|e| OneError::io(path.display(), &e))?;
+    import_fasta(
+        BufReader::new(input),
+        out_path,
+        binary,
+        &FastaOptions::default(),
+    )
+}
+
+fn read_error(e: std::io::Error) -> OneError {
+    OneError::Other(format!("Failed to read FASTA: {}", e))
+}
+
+fn import_records<R: BufRead>(
+    reader: R,
+    out_path: &str,
+    binary: bool,
+    options: &FastaOptions,
+) -> Result<usize> {
+    let schema = match options.defline {
+        DeflinePolicy::Line => OneSchema::seq()?.extend_with_text(DESCRIPTION_SCHEMA_TEXT)?,
+        _ => OneSchema::seq()?,
+    };
+    let mut out = OneFile::open_write_new(out_path, &schema, "seq", binary, 1)?;
+    let mut seen = HashSet::new();
+    let mut lines = reader.lines();
+    let first = loop {
+        match lines.next().transpose().map_err(read_error)? {
+            Some(line) if line.trim().is_empty() => continue,
+            line => break line,
+        }
+    };
+    let count = match first {
+        None => 0,
+        Some(line) if line.starts_with('@') => {
+            import_fastq(line, lines, &mut out, options, &mut seen)?
+        }
+        Some(line) if line.starts_with('>') => {
+            import_fasta_records(line, lines, &mut out, options, &mut seen)?
+        }
+        Some(_) => {
+            return Err(OneError::InvalidFormat(
+                "Input does not start with a '>' (FASTA) or '@' (FASTQ) line".to_string(),
+            ));
+        }
+    };
+    out.finalize()?;
+    Ok(count)
+}
+
+fn import_fasta_records<I: Iterator<Item = std::io::Result<String>>>(
+    first: String,
+    lines: I,
+    out: &mut OneFile,
+    options: &FastaOptions,
+    seen: &mut HashSet<String>,
+) -> Result<usize> {
+    let mut count = 0;
+    let mut defline = first[1..].trim_end().to_string();
+    let mut bases = Vec::new();
+    for line in lines {
+        let line = line.map_err(read_error)?;
+        if let Some(header) = line.strip_prefix('>') {
+            write_record(out, &defline, &bases, None, options, seen)?;
+            count += 1;
+            defline = header.trim_end().to_string();
+            bases.clear();
+        } else {
+            bases.extend(line.bytes().filter(|b| !b.is_ascii_whitespace()));
+        }
+    }
+    write_record(out, &defline, &bases, None, options, seen)?;
+    Ok(count + 1)
+}
+
+/// Import four-line FASTQ records
+fn import_fastq<I: Iterator<Item = std::io::Result<String>>>(
+    first: String,
+    mut lines: I,
+    out: &mut OneFile,
+    options: &FastaOptions,
+    seen: &mut HashSet<String>,
+) -> Result<usize> {
+    let mut count = 0;
+    let mut header = Some(first);
+    while let Some(line) = header.take() {
+        let defline = line[1..].trim_end().to_string();
+        let bad_record =
+            |what: &str| OneError::InvalidFormat(format!("FASTQ record {}: {}", defline, what));
+        let mut next = || lines.next().transpose().map_err(read_error);
+        let bases = next()?.ok_or_else(|| bad_record("missing sequence"))?;
+        let plus = next()?.ok_or_else(|| bad_record("missing '+' line"))?;
+        if !plus.starts_with('+') {
+            return Err(bad_record("missing '+' line"));
+        }
+        let quality = next()?.ok_or_else(|| bad_record("missing quality"))?;
+        let (bases, quality) = (bases.trim_end(), quality.trim_end());
+        if quality.len() != bases.len() {
+            return Err(bad_record("quality and sequence lengths differ"));
+        }
+        write_record(
+            out,
+            &defline,
+            bases.as_bytes(),
+            Some(quality),
+            options,
+            seen,
+        )?;
+        count += 1;
+
+        header = loop {
+            match next()? {
+                Some(line) if line.trim().is_empty() => continue,
+                Some(line) if line.starts_with('@') => break Some(line),
+                Some(line) => {
+                    return Err(OneError::InvalidFormat(format!(
+                        "Expected a FASTQ '@' line, got: {}",
+                        line
+                    )));
+                }
+                None => break None,
+            }
+        };
+    }
+    Ok(count)
+}
+
+fn write_record(
+    out: &mut OneFile,
+    defline: &str,
+    bases: &[u8],
+    quality: Option<&str>,
+    options: &FastaOptions,
+    seen: &mut HashSet<String>,
+) -> Result<()> {
+    let id = OneFile::trim_sequence_name(defline);
+    if options.unique_ids && !seen.insert(id.clone()) {
+        return Err(OneError::DuplicateName(id));
+    }
+
+    let dna = bases.to_ascii_lowercase();
+    out.write_dna(SEQUENCE, &dna)?;
+    out.write_string(IDENTIFIER, &id)?;
+    match options.defline {
+        DeflinePolicy::Discard => {}
+        DeflinePolicy::Comment => out.write_comment(defline)?,
+        DeflinePolicy::Line => out.write_string(DESCRIPTION, defline)?,
+    }
+    if let Some(quality) = quality {
+        out.write_string(QUALITY, quality)?;
+    }
+
+    let mut i = 0;
+    while i < dna.len() {
+        if matches!(dna[i], b'a' | b'c' | b'g' | b't') {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < dna.len() && dna[i] == dna[start] {
+            i += 1;
+        }
+        out.set_int(0, start as i64);
+        out.set_char(1, bases[start] as char);
+        out.set_int(2, (i - start) as i64);
+        out.write_line(NON_ACGT, 0, None);
+    }
+    Ok(())
+}
+
+/// Options for exporting `.1seq` to FASTA or FASTQ
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Wrap FASTA sequence lines after this many bases, 0 for no wrapping
+    ///
+    /// FASTQ sequences are never wrapped.
+    pub line_width: usize,
+}
+
+/// One sequence being gathered for export
+#[derive(Default)]
+struct ExportRecord {
+    bases: Vec<u8>,
+    id: Option<String>,
+    defline: Option<String>,
+    quality: Option<String>,
+}
+
+impl ExportRecord {
+    fn write<W: Write>(&self, out: &mut W, index: usize, options: &ExportOptions) -> Result<()> {
+        let io_error = |e: std::io::Error| OneError::Other(format!("Failed to write FASTA: {}", e));
+        let header = match (&self.defline, &self.id) {
+            (Some(defline), _) => defline.clone(),
+            (None, Some(id)) => id.clone(),
+            (None, None) => index.to_string(),
+        };
+        if let Some(quality) = &self.quality {
+            if quality.len() != self.bases.len() {
+                return Err(OneError::InvalidFormat(format!(
+                    "Sequence {}: quality and sequence lengths differ",
+                    header
+                )));
+            }
+            writeln!(out, "@{}", header)
+                .and_then(|_| out.write_all(&self.bases))
+                .and_then(|_| writeln!(out, "\n+\n{}", quality))
+                .map_err(io_error)?;
+            return Ok(());
+        }
+
+        writeln!(out, ">{}", header).map_err(io_error)?;
+        let width = match options.line_width {
+            0 => self.bases.len().max(1),
+            width => width,
+        };
+        for chunk in self.bases.chunks(width) {
+            out.write_all(chunk)
+                .and_then(|_| out.write_all(b"\n"))
+                .map_err(io_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the sequences of a `.1seq` file as FASTA or FASTQ, returning the number written
+///
+/// Streams one sequence at a time from the current position of `file`.
+/// Sequences with a 'Q' line are written as FASTQ records, the others as
+/// FASTA. The header is the full defline if the import kept one (a 'D' line,
+/// or an 'I' line comment in ASCII files), else the identifier, else the
+/// 0-based sequence number. Bases are written as stored, lower-case, with the
+/// runs recorded in 'N' lines put back. An identifier, defline or quality
+/// string that is not UTF-8 fails with `OneError::InvalidUtf8`.
+pub fn export_fasta<W: Write>(
+    file: &mut OneFile,
+    mut out: W,
+    options: &ExportOptions,
+) -> Result<usize> {
+    let read_comments = !file.is_binary();
+    let mut record: Option<ExportRecord> = None;
+    let mut count = 0;
+    loop {
+        let line_type = file.read_line();
+        match line_type {
+            SEQUENCE | '\0' => {
+                if let Some(record) = record.take() {
+                    record.write(&mut out, count, options)?;
+                    count += 1;
+                }
+                if line_type == '\0' {
+                    break;
+                }
+                record = Some(ExportRecord {
+                    bases: file.dna_char().unwrap_or_default().to_vec(),
+                    ..Default::default()
+                });
+            }
+            IDENTIFIER => {
+                if let Some(record) = record.as_mut() {
+                    record.id = Some(file.string()?.to_string());
+                    if read_comments {
+                        record.defline = file.read_comment();
+                    }
+                }
+            }
+            DESCRIPTION => {
+                if let Some(record) = record.as_mut() {
+                    record.defline = Some(file.string()?.to_string());
+                }
+            }
+            QUALITY => {
+                if let Some(record) = record.as_mut() {
+                    record.quality = Some(file.string()?.to_string());
+                }
+            }
+            NON_ACGT => {
+                if let Some(record) = record.as_mut() {
+                    let len = record.bases.len();
+                    let start = (file.int(0).max(0) as usize).min(len);
+                    let end = (start + file.int(2).max(0) as usize).min(len);
+                    record.bases[start..end].fill(file.char(1) as u8);
+                }
+            }
+            _ => {}
+        }
+    }
+    out.flush()
+        .map_err(|e| OneError::Other(format!("Failed to write FASTA: {}", e)))?;
+    Ok(count)
+}
+
+/// Export a `.1seq` file to a FASTA or FASTQ file
+///
+/// Like `export_fasta()`, writing to `out_path`.
+pub fn to_fasta(seq_path: &str, out_path: &str, options: &ExportOptions) -> Result<usize> {
+    let mut file = OneFile::open_read(seq_path, None, Some("seq"), 1)?;
+    let out = File::create(out_path).map_err(|e| OneError::io(out_path, &e))?;
+    export_fasta(&mut file, BufWriter::new(out), options)
+}
+
+/// Name, offset and length of each sequence in a concatenation, from `concat_all()`
+pub type OffsetTable = Vec<(String, usize, usize)>;
+
+/// Concatenate every sequence of a `.1seq` file, without separators
+///
+/// Returns the bases, lower-case as stored, and for each sequence its name,
+/// offset in the concatenation and length; sequences without an identifier
+/// are named by their 0-based number. This is the input of many suffix
+/// array and FM-index builders. Sequences are streamed into the result, so
+/// no more than one chunk is unpacked at a time besides it.
+pub fn concat_all(reader: &mut SeqReader) -> Result<(Vec<u8>, OffsetTable)> {
+    let total: i64 = reader.lengths.iter().sum();
+    let mut bases = Vec::with_capacity(total as usize);
+    let mut offsets = Vec::with_capacity(reader.num_sequences());
+    for i in 0..reader.num_sequences() {
+        let name = reader.name(i).map_or_else(|| i.to_string(), String::from);
+        let offset = bases.len();
+        for chunk in reader.stream_sequence(i)? {
+            bases.extend_from_slice(&chunk);
+        }
+        offsets.push((name, offset, bases.len() - offset));
+    }
+    Ok((bases, offsets))
+}
+
+/// The standard genetic code, indexed by codon with t, c, a, g as 0 to 3 and
+/// the first base most significant
+const GENETIC_CODE: &[u8; 64] = b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+/// Translate DNA into amino acids in reading frame `frame`
+///
+/// Frames 1 to 3 start at the first, second and third base, -1 to -3 at the
+/// same positions of the reverse complement. Amino acids are upper-case one
+/// letter codes with '*' for stop codons, and 'X' for codons holding a base
+/// other than acgt; a trailing partial codon is dropped.
+pub fn translate(dna: &[u8], frame: i32) -> Result<String> {
+    let offset = match frame {
+        1..=3 => frame - 1,
+        -3..=-1 => -frame - 1,
+        _ => {
+            return Err(OneError::Other(format!(
+                "Reading frame must be 1 to 3 or -1 to -3, not {}",
+                frame
+            )))
+        }
+    } as usize;
+    let strand: Cow<[u8]> = if frame > 0 {
+        Cow::Borrowed(dna)
+    } else {
+        Cow::Owned(dna.iter().rev().map(|&b| complement(b)).collect())
+    };
+    let codons = strand.get(offset..).unwrap_or_default().chunks_exact(3);
+    Ok(codons.map(amino_acid).collect())
+}
+
+pub(crate) fn complement(base: u8) -> u8 {
+    match base | 0x20 {
+        b'a' => b't',
+        b'c' => b'g',
+        b'g' => b'c',
+        b't' => b'a',
+        _ => b'n',
+    }
+}
+
+fn amino_acid(codon: &[u8]) -> char {
+    let index = |b: u8| match b | 0x20 {
+        b't' => Some(0),
+        b'c' => Some(1),
+        b'a' => Some(2),
+        b'g' => Some(3),
+        _ => None,
+    };
+    match (index(codon[0]), index(codon[1]), index(codon[2])) {
+        (Some(first), Some(second), Some(third)) => {
+            GENETIC_CODE[16 * first + 4 * second + third] as char
+        }
+        _ => 'X',
+    }
+}
+
+/// Translate the sequences of a `.1seq` file into a new `.1pro` file,
+/// returning the number of proteins written
+///
+/// Each sequence is translated in each of `frames` in turn, see
+/// `translate()`, with the runs recorded in 'N' lines put back first so
+/// their codons become 'X'. Every protein is an 'S' object of
+/// `PROTEIN_SCHEMA_TEXT` followed by the identifier of its source sequence,
+/// if it has one, and an 'F' line giving the source sequence and frame.
+pub fn to_protein(seq_path: &str, out_path: &str, frames: &[i32], binary: bool) -> Result<usize> {
+    for &frame in frames {
+        translate(&[], frame)?;
+    }
+    let mut file = OneFile::open_read(seq_path, None, Some("seq"), 1)?;
+    let mut out = ProteinWriter::create(out_path, binary)?;
+    let mut count = 0;
+    for (i, sequence) in file.objects(SEQUENCE).enumerate() {
+        let mut bases = sequence.line.dna().unwrap_or_default().to_vec();
+        for run in sequence.lines_of_type(NON_ACGT) {
+            let start = (run.int(0).max(0) as usize).min(bases.len());
+            let end = (start + run.int(2).max(0) as usize).min(bases.len());
+            bases[start..end].fill(run.char(1) as u8);
+        }
+        let id = sequence.lines_of_type(IDENTIFIER).next().and_then(|l| l.string());
+        for &frame in frames {
+            out.write_protein(&Protein {
+                id: id.map(String::from),
+                sequence: translate(&bases, frame)?,
+                source: Some((i as i64, frame)),
+            })?;
+            count += 1;
+        }
+    }
+    out.finalize()?;
+    Ok(count)
+}
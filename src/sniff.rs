@@ -0,0 +1,102 @@
+//! File format detection from content rather than extension
+//!
+//! `sniff()` looks at the first bytes and header lines of a file, so tools
+//! can explain why a file cannot be opened:
+//!
+//! ```no_run
+//! use onecode::{sniff, FileKind};
+//!
+//! match sniff("reads.1seq") {
+//!     Some(FileKind::OneAscii { .. } | FileKind::OneBinary { .. }) => { /* open it */ }
+//!     Some(kind) => eprintln!("reads.1seq: {}, not a ONE file", kind),
+//!     None => eprintln!("reads.1seq: cannot be read"),
+//! }
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Kind of file found by `sniff()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileKind {
+    /// ONE file in ASCII form, with the primary type from its first line
+    OneAscii { file_type: String },
+    /// ONE file in binary form, with the primary type from its first line
+    OneBinary { file_type: String },
+    /// gzip-compressed data, which ONElib cannot read directly
+    Gzip,
+    /// Anything else, including empty files
+    Other,
+}
+
+impl FileKind {
+    /// Primary file type of a ONE file, e.g. "seq"
+    pub fn file_type(&self) -> Option<&str> {
+        match self {
+            FileKind::OneAscii { file_type } | FileKind::OneBinary { file_type } => Some(file_type),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileKind::OneAscii { file_type } => write!(f, "ASCII ONE file of type {}", file_type),
+            FileKind::OneBinary { file_type } => write!(f, "binary ONE file of type {}", file_type),
+            FileKind::Gzip => write!(f, "gzip-compressed file"),
+            FileKind::Other => write!(f, "unrecognised file"),
+        }
+    }
+}
+
+/// Determine the kind of file at `path` from its content
+///
+/// A ONE file is recognised by its first line ("1 <len> <type> <major>
+/// <minor>"); it is binary if a '$' line ends its header. Returns None if
+/// the file cannot be opened or read.
+pub fn sniff<P: AsRef<Path>>(path: P) -> Option<FileKind> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    if reader.fill_buf().ok()?.starts_with(&GZIP_MAGIC) {
+        return Some(FileKind::Gzip);
+    }
+
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).ok()?;
+    let Some(file_type) = primary_type(&line) else {
+        return Some(FileKind::Other);
+    };
+
+    // Header lines start with punctuation or '2' (the subtype line); the
+    // first data line of an ASCII file starts with a letter
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).ok()? == 0 {
+            return Some(FileKind::OneAscii { file_type });
+        }
+        match line[0] {
+            b'$' => return Some(FileKind::OneBinary { file_type }),
+            b'2' | b'.' | b'!' | b'<' | b'>' | b'~' | b'#' | b'@' | b'+' | b'%' | b'/' => {}
+            _ => return Some(FileKind::OneAscii { file_type }),
+        }
+    }
+}
+
+/// Parse the primary file type from the first line of a ONE file
+fn primary_type(line: &[u8]) -> Option<String> {
+    let line = std::str::from_utf8(line).ok()?;
+    let mut fields = line.split_ascii_whitespace();
+    if fields.next()? != "1" {
+        return None;
+    }
+    let len: usize = fields.next()?.parse().ok()?;
+    let file_type = fields.next()?;
+    if file_type.len() != len || fields.next()?.parse::<i64>().is_err() {
+        return None;
+    }
+    Some(file_type.to_string())
+}
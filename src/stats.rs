@@ -0,0 +1,447 @@
+//! Line type statistics of filtered subsets and merged outputs
+//!
+//! A filtering tool can report what it would keep before writing anything:
+//!
+//! ```no_run
+//! use onecode::stats::subset_counts;
+//! use onecode::OneFile;
+//!
+//! let mut file = OneFile::open_read("data.1aln", None, None, 1)?;
+//! // Keep only the alignments of contig 0
+//! for delta in subset_counts(&mut file, |line| line.line_type() != 'A' || line.int(0) == 0) {
+//!     println!("{}: {} -> {}", delta.line_type, delta.header.count, delta.subset.count);
+//! }
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+//!
+//! A merging tool can count what it writes as it goes, or count each input
+//! on its own thread and merge the results, then write correct header counts
+//! into an ASCII output without a second pass:
+//!
+//! ```no_run
+//! use onecode::{OneFile, StatsAccumulator};
+//!
+//! let mut total = StatsAccumulator::new();
+//! for path in ["a.1seq", "b.1seq"] {
+//!     let mut part = StatsAccumulator::new();
+//!     part.add_file(&mut OneFile::open_read(path, None, None, 1)?);
+//!     total.merge(part);
+//! }
+//! println!("{} sequences", total.counts('S').count);
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use crate::error::{OneError, Result};
+use crate::file::OneFile;
+use crate::lines::Line;
+use crate::types::{LineStats, OneCounts};
+use std::collections::BTreeMap;
+
+/// Header counts of a line type next to the counts of a filtered subset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountsDelta {
+    pub line_type: char,
+    /// Counts recorded in the file header
+    pub header: OneCounts,
+    /// Counts a file holding only the kept lines would record
+    pub subset: OneCounts,
+}
+
+impl CountsDelta {
+    /// Number of lines of this type the filter drops
+    pub fn removed(&self) -> i64 {
+        self.header.count - self.subset.count
+    }
+}
+
+/// Compute the header counts of the subset of `file` selected by `keep`
+///
+/// `keep` is called for each object line, and the data lines that follow an
+/// object are kept or dropped with it. Data lines before the first object
+/// are always kept. For list line types `max` and `total` are the largest
+/// and summed list lengths, as ONElib records them on writing.
+///
+/// Reads from the current position to the end of the file, so pass a file
+/// that has just been opened. Returns one entry for each line type with a
+/// non-zero header or subset count, in ASCII order.
+pub fn subset_counts<F>(file: &mut OneFile, mut keep: F) -> Vec<CountsDelta>
+where
+    F: FnMut(&Line) -> bool,
+{
+    let mut subset = [OneCounts::default(); 128];
+    let mut keeping = true;
+    loop {
+        let line_type = file.read_line();
+        if line_type == '\0' {
+            break;
+        }
+        let (is_object, is_list) = unsafe {
+            let info = (*file.ptr).info[line_type as usize];
+            if info.is_null() {
+                continue;
+            }
+            ((*info).isObject, (*info).listEltSize > 0)
+        };
+        if is_object {
+            keeping = keep(&Line::from_current(file, false));
+        }
+        if !keeping {
+            continue;
+        }
+        let counts = &mut subset[line_type as usize];
+        counts.count += 1;
+        if is_list {
+            let len = file.len();
+            counts.total += len;
+            counts.max = counts.max.max(len);
+        }
+    }
+
+    (b'A'..=b'z')
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c as char)
+        .filter_map(|t| {
+            let header = file
+                .stats(t)
+                .map(|(count, max, total)| OneCounts { count, max, total })
+                .unwrap_or_default();
+            let subset = subset[t as usize];
+            (header.count != 0 || subset.count != 0).then_some(CountsDelta {
+                line_type: t,
+                header,
+                subset,
+            })
+        })
+        .collect()
+}
+
+/// Line counts, list length distributions and per-object maxima, gathered
+/// line by line and mergeable
+///
+/// The counts are those ONElib records in a file header: for each line type
+/// the number of lines and the longest and summed list lengths, and for each
+/// object type the most lines and list elements of each contained type any
+/// one object holds. Lines are counted as if they were written out in the
+/// order they are added, and `merge()` appends the lines of another
+/// accumulator, so parts counted separately, e.g. by one thread per slice
+/// of the output, combine to the counts of the whole. An object may run on
+/// from one part into the next, as it would when the parts are concatenated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatsAccumulator {
+    types: BTreeMap<char, TypeStats>,
+    objects: BTreeMap<char, ObjectStats>,
+    /// Objects not yet ended, innermost last, with the lines and list
+    /// elements of their contained types when they started
+    open: Vec<(char, Vec<(i64, i64)>)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct TypeStats {
+    is_list: bool,
+    counts: OneCounts,
+    /// Lists of length 0 in bucket 0, of length in [2^(i-1), 2^i) in bucket i
+    histogram: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ObjectStats {
+    contains: Vec<Contained>,
+    /// Whether a line that ends objects of this type has been added
+    closed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Contained {
+    line_type: char,
+    /// Lines and list elements added before the first line that ends an
+    /// object, which belong to an object left open by a preceding part
+    leading: (i64, i64),
+    /// Most lines and list elements in one object
+    max: (i64, i64),
+}
+
+impl StatsAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the current line of `file`
+    ///
+    /// The line types of the schema are learnt from the first line added;
+    /// every file added should have the same schema.
+    pub fn add_line(&mut self, file: &OneFile) {
+        let t = file.line_type();
+        if !t.is_ascii_alphabetic() {
+            return;
+        }
+        if self.types.is_empty() {
+            self.learn_schema(file);
+        }
+        let Some(is_list) = self.types.get(&t).map(|stats| stats.is_list) else {
+            return;
+        };
+
+        // As ONElib: a line ends the open objects that cannot hold it
+        for object in self.objects.values_mut() {
+            if !object.closed && !object.holds(t) {
+                object.closed = true;
+                for c in &mut object.contains {
+                    c.leading = tally(&self.types, c.line_type);
+                }
+            }
+        }
+        while let Some((object, _)) = self.open.last() {
+            if self.objects[object].holds(t) {
+                break;
+            }
+            self.end_object();
+        }
+
+        let stats = self.types.get_mut(&t).unwrap();
+        stats.counts.count += 1;
+        if is_list {
+            let len = file.len();
+            stats.counts.max = stats.counts.max.max(len);
+            stats.counts.total += len;
+            let bucket = (64 - len.max(0).leading_zeros()) as usize;
+            if stats.histogram.len() <= bucket {
+                stats.histogram.resize(bucket + 1, 0);
+            }
+            stats.histogram[bucket] += 1;
+        }
+        if let Some(object) = self.objects.get(&t) {
+            let start = object.contains.iter().map(|c| tally(&self.types, c.line_type)).collect();
+            self.open.push((t, start));
+        }
+    }
+
+    /// Count the lines of `file` from its current position to the end
+    pub fn add_file(&mut self, file: &mut OneFile) {
+        while file.read_line() != '\0' {
+            self.add_line(file);
+        }
+    }
+
+    /// Append the lines counted by `other`, as if added after those of `self`
+    ///
+    /// Objects left open by `self` take in the lines `other` starts with,
+    /// up to the first line that ends them.
+    pub fn merge(&mut self, other: StatsAccumulator) {
+        if self.types.is_empty() {
+            *self = other;
+            return;
+        }
+        if other.types.is_empty() {
+            return;
+        }
+        let before = self.types.clone();
+
+        // End the objects of self that other closes, with the lines that
+        // other starts with; the others stay open across the whole of other
+        let mut still_open = Vec::new();
+        for (object, start) in std::mem::take(&mut self.open) {
+            match other.objects.get(&object).filter(|o| o.closed) {
+                Some(closing) => {
+                    let stats = self.objects.get_mut(&object).unwrap();
+                    for ((c, &(count, total)), leading) in
+                        stats.contains.iter_mut().zip(&start).zip(&closing.contains)
+                    {
+                        let (now_count, now_total) = tally(&before, c.line_type);
+                        c.max.0 = c.max.0.max(now_count - count + leading.leading.0);
+                        c.max.1 = c.max.1.max(now_total - total + leading.leading.1);
+                    }
+                }
+                None => still_open.push((object, start)),
+            }
+        }
+
+        for (t, theirs) in other.types {
+            let ours = self.types.entry(t).or_default();
+            ours.is_list |= theirs.is_list;
+            ours.counts.count += theirs.counts.count;
+            ours.counts.max = ours.counts.max.max(theirs.counts.max);
+            ours.counts.total += theirs.counts.total;
+            if ours.histogram.len() < theirs.histogram.len() {
+                ours.histogram.resize(theirs.histogram.len(), 0);
+            }
+            for (bucket, n) in ours.histogram.iter_mut().zip(theirs.histogram) {
+                *bucket += n;
+            }
+        }
+        for (t, theirs) in other.objects {
+            let ours = self.objects.entry(t).or_insert_with(|| ObjectStats {
+                contains: theirs
+                    .contains
+                    .iter()
+                    .map(|c| Contained {
+                        line_type: c.line_type,
+                        ..Contained::default()
+                    })
+                    .collect(),
+                closed: false,
+            });
+            for (c, theirs_c) in ours.contains.iter_mut().zip(&theirs.contains) {
+                if !ours.closed && theirs.closed {
+                    let (count, total) = tally(&before, c.line_type);
+                    c.leading = (count + theirs_c.leading.0, total + theirs_c.leading.1);
+                }
+                c.max.0 = c.max.0.max(theirs_c.max.0);
+                c.max.1 = c.max.1.max(theirs_c.max.1);
+            }
+            ours.closed |= theirs.closed;
+        }
+
+        self.open = still_open;
+        for (object, start) in other.open {
+            let start = self.objects[&object]
+                .contains
+                .iter()
+                .zip(start)
+                .map(|(c, (count, total))| {
+                    let (offset_count, offset_total) = tally(&before, c.line_type);
+                    (count + offset_count, total + offset_total)
+                })
+                .collect();
+            self.open.push((object, start));
+        }
+    }
+
+    /// Counts of `line_type` lines, zero if none were added
+    pub fn counts(&self, line_type: char) -> OneCounts {
+        self.types.get(&line_type).map_or(OneCounts::default(), |s| s.counts)
+    }
+
+    /// Counts of every line type of the schema, in ASCII order, as `OneFile::all_stats()`
+    pub fn stats(&self) -> Vec<LineStats> {
+        self.types
+            .iter()
+            .map(|(&line_type, s)| LineStats {
+                line_type,
+                is_object: self.objects.contains_key(&line_type),
+                is_list: s.is_list,
+                counts: s.counts,
+            })
+            .collect()
+    }
+
+    /// Most `line_type` lines and list elements in one `object` object
+    ///
+    /// Objects still open count as ended here, as they would be at the end
+    /// of the file. Zeros if `object` objects cannot hold `line_type` lines.
+    pub fn max_per_object(&self, object: char, line_type: char) -> (i64, i64) {
+        let Some(stats) = self.objects.get(&object) else {
+            return (0, 0);
+        };
+        let Some(i) = stats.contains.iter().position(|c| c.line_type == line_type) else {
+            return (0, 0);
+        };
+        let (now_count, now_total) = tally(&self.types, line_type);
+        self.open
+            .iter()
+            .filter(|(t, _)| *t == object)
+            .fold(stats.contains[i].max, |(count, total), (_, start)| {
+                (count.max(now_count - start[i].0), total.max(now_total - start[i].1))
+            })
+    }
+
+    /// Distribution of the list lengths of `line_type` lines
+    ///
+    /// Entry 0 counts empty lists and entry i > 0 lists of length at least
+    /// 2^(i-1) and less than 2^i; the last entry is non-zero.
+    pub fn length_histogram(&self, line_type: char) -> &[i64] {
+        self.types.get(&line_type).map_or(&[], |s| &s.histogram)
+    }
+
+    /// Record the counts in the header of `file`, being written
+    ///
+    /// Must be called before the first line is written. An ASCII file writes
+    /// these counts in its header, as it cannot go back and write them once
+    /// its lines are out; a binary file records what it writes in its footer
+    /// whatever it is given. Fails if lines were added of a type the schema
+    /// of `file` does not declare.
+    pub fn set_header_counts(&self, file: &mut OneFile) -> Result<()> {
+        unsafe {
+            if !(*file.ptr).isWrite || (*file.ptr).isHeaderOut {
+                return Err(OneError::Other(
+                    "Header counts can only be set on a file being written, before its first line"
+                        .to_string(),
+                ));
+            }
+        }
+        for (&t, stats) in &self.types {
+            let info = unsafe { (*file.ptr).info[t as usize] };
+            if info.is_null() {
+                if stats.counts.count == 0 {
+                    continue;
+                }
+                return Err(OneError::NoSuchLineType(format!(
+                    "'{}' is not a line type of {}",
+                    t,
+                    file.file_name().unwrap_or_default()
+                )));
+            }
+            unsafe {
+                (*info).given.count = stats.counts.count;
+                (*info).given.max = stats.counts.max;
+                (*info).given.total = stats.counts.total;
+                let mut s = (*info).stats;
+                while !s.is_null() && (*s).type_ != 0 {
+                    let (max_count, max_total) = self.max_per_object(t, (*s).type_ as u8 as char);
+                    (*s).maxCount = max_count;
+                    (*s).maxTotal = max_total;
+                    s = s.add(1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn learn_schema(&mut self, file: &OneFile) {
+        let info = |t: u8| unsafe { (*file.ptr).info[t as usize] };
+        for t in (b'A'..=b'z').filter(|t| t.is_ascii_alphabetic()) {
+            let li = info(t);
+            if li.is_null() {
+                continue;
+            }
+            let stats = TypeStats {
+                is_list: unsafe { (*li).listEltSize } > 0,
+                ..TypeStats::default()
+            };
+            self.types.insert(t as char, stats);
+            if unsafe { (*li).isObject } {
+                let contains = (b'A'..=b'z')
+                    .filter(|&u| u.is_ascii_alphabetic() && !info(u).is_null())
+                    .filter(|&u| unsafe { (*li).contains[u as usize] })
+                    .map(|u| Contained {
+                        line_type: u as char,
+                        ..Contained::default()
+                    })
+                    .collect();
+                self.objects.insert(t as char, ObjectStats { contains, closed: false });
+            }
+        }
+    }
+
+    fn end_object(&mut self) {
+        let (object, start) = self.open.pop().unwrap();
+        let stats = self.objects.get_mut(&object).unwrap();
+        for (c, (count, total)) in stats.contains.iter_mut().zip(start) {
+            let (now_count, now_total) = tally(&self.types, c.line_type);
+            c.max.0 = c.max.0.max(now_count - count);
+            c.max.1 = c.max.1.max(now_total - total);
+        }
+    }
+}
+
+impl ObjectStats {
+    fn holds(&self, line_type: char) -> bool {
+        self.contains.iter().any(|c| c.line_type == line_type)
+    }
+}
+
+/// Lines and summed list lengths of `line_type` so far
+fn tally(types: &BTreeMap<char, TypeStats>, line_type: char) -> (i64, i64) {
+    types
+        .get(&line_type)
+        .map_or((0, 0), |s| (s.counts.count, s.counts.total))
+}
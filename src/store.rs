@@ -0,0 +1,132 @@
+//! Random-access sequence stores
+//!
+//! The `SequenceStore` trait abstracts over anything that can hand out
+//! subsequences by name, so tools such as alignment polishers can be written
+//! once and run against ONEcode `.1seq` files, GDB skeletons, or FASTA-backed
+//! sources alike.
+
+use crate::error::{OneError, Result};
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+/// A source of named sequences supporting random access
+///
+/// Coordinates are 0-based and ranges are half-open, as in Rust slicing.
+pub trait SequenceStore {
+    /// Get the length of the named sequence
+    fn len(&self, name: &str) -> Result<usize>;
+
+    /// Fetch the bases of `name` in `range`
+    ///
+    /// Fails if the sequence is unknown or the range extends past its end.
+    fn fetch(&mut self, name: &str, range: Range<usize>) -> Result<Vec<u8>>;
+}
+
+/// A `SequenceStore` decorator caching whole decoded sequences in an LRU
+///
+/// Each miss fetches the complete sequence from the inner store, so repeated
+/// fetches from the same contig decode it only once. Cached sequences are
+/// evicted least-recently-used first once their total size exceeds the byte
+/// budget; sequences larger than the whole budget bypass the cache.
+pub struct CachedSequenceStore<S: SequenceStore> {
+    inner: S,
+    budget: usize,
+    used: usize,
+    entries: HashMap<String, Vec<u8>>,
+    order: VecDeque<String>, // least recently used at the front
+}
+
+impl<S: SequenceStore> CachedSequenceStore<S> {
+    /// Wrap `inner`, caching up to `budget` bytes of decoded sequence
+    pub fn new(inner: S, budget: usize) -> Self {
+        CachedSequenceStore {
+            inner,
+            budget,
+            used: 0,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Number of bytes of sequence currently cached
+    pub fn cached_bytes(&self) -> usize {
+        self.used
+    }
+
+    /// Drop all cached sequences
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.used = 0;
+    }
+
+    /// Get a reference to the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap the cache, returning the inner store
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn touch(&mut self, name: &str) {
+        if let Some(pos) = self.order.iter().position(|n| n == name) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+
+    fn insert(&mut self, name: &str, seq: Vec<u8>) {
+        while self.used + seq.len() > self.budget {
+            match self.order.pop_front() {
+                Some(old) => {
+                    if let Some(evicted) = self.entries.remove(&old) {
+                        self.used -= evicted.len();
+                    }
+                }
+                None => break,
+            }
+        }
+        self.used += seq.len();
+        self.order.push_back(name.to_string());
+        self.entries.insert(name.to_string(), seq);
+    }
+}
+
+impl<S: SequenceStore> SequenceStore for CachedSequenceStore<S> {
+    fn len(&self, name: &str) -> Result<usize> {
+        match self.entries.get(name) {
+            Some(seq) => Ok(seq.len()),
+            None => self.inner.len(name),
+        }
+    }
+
+    fn fetch(&mut self, name: &str, range: Range<usize>) -> Result<Vec<u8>> {
+        if self.entries.contains_key(name) {
+            self.touch(name);
+        } else {
+            let len = self.inner.len(name)?;
+            if len > self.budget {
+                return self.inner.fetch(name, range);
+            }
+            check_range(name, &range, len)?;
+            let seq = self.inner.fetch(name, 0..len)?;
+            self.insert(name, seq);
+        }
+        let seq = &self.entries[name];
+        check_range(name, &range, seq.len())?;
+        Ok(seq[range].to_vec())
+    }
+}
+
+/// Check that `range` lies within a sequence of length `len`
+pub(crate) fn check_range(name: &str, range: &Range<usize>, len: usize) -> Result<()> {
+    if range.start > range.end || range.end > len {
+        return Err(OneError::Other(format!(
+            "Range {}..{} out of bounds for sequence {} of length {}",
+            range.start, range.end, name, len
+        )));
+    }
+    Ok(())
+}
@@ -1,9 +1,12 @@
 //! Type definitions for ONEcode
 
+#[cfg(feature = "onelib")]
 use crate::ffi;
+#[cfg(feature = "onelib")]
 use std::ffi::CStr;
 
 /// Re-export the OneType enum from FFI
+#[cfg(feature = "onelib")]
 pub use ffi::OneType;
 
 /// Provenance information (program, version, command, date)
@@ -15,6 +18,7 @@ pub struct OneProvenance {
     pub date: String,
 }
 
+#[cfg(feature = "onelib")]
 impl From<ffi::OneProvenance> for OneProvenance {
     fn from(prov: ffi::OneProvenance) -> Self {
         unsafe {
@@ -44,6 +48,64 @@ impl From<ffi::OneProvenance> for OneProvenance {
     }
 }
 
+impl OneProvenance {
+    /// The date of the run, if it parses, see `Timestamp::parse()`
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse(&self.date)
+    }
+}
+
+/// Date and time of a provenance line
+///
+/// ONElib records the local time of the machine that ran the program,
+/// without a time zone, so timestamps from different machines may not
+/// compare as the runs happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Timestamp {
+    /// Parse a provenance date as ONElib writes it, "2023-07-18_17:39:10"
+    ///
+    /// A 'T' or a space between date and time is accepted too.
+    pub fn parse(date: &str) -> Option<Self> {
+        let (day, time) = date.trim().split_once(['_', 'T', ' '])?;
+        let mut ymd = day.splitn(3, '-');
+        let mut hms = time.splitn(3, ':');
+        let timestamp = Timestamp {
+            year: ymd.next()?.parse().ok()?,
+            month: ymd.next()?.parse().ok()?,
+            day: ymd.next()?.parse().ok()?,
+            hour: hms.next()?.parse().ok()?,
+            minute: hms.next()?.parse().ok()?,
+            second: hms.next()?.parse().ok()?,
+        };
+        let valid = (1..=12).contains(&timestamp.month)
+            && (1..=31).contains(&timestamp.day)
+            && timestamp.hour < 24
+            && timestamp.minute < 60
+            && timestamp.second < 61;
+        valid.then_some(timestamp)
+    }
+}
+
+/// ISO 8601 without a time zone, as in "2023-07-18T17:39:10"
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
 /// Reference information (filename and count)
 #[derive(Debug, Clone, PartialEq)]
 pub struct OneReference {
@@ -51,6 +113,7 @@ pub struct OneReference {
     pub count: i64,
 }
 
+#[cfg(feature = "onelib")]
 impl From<ffi::OneReference> for OneReference {
     fn from(ref_: ffi::OneReference) -> Self {
         unsafe {
@@ -67,13 +130,14 @@ impl From<ffi::OneReference> for OneReference {
 }
 
 /// Count information
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct OneCounts {
     pub count: i64,
     pub max: i64,
     pub total: i64,
 }
 
+#[cfg(feature = "onelib")]
 impl From<ffi::OneCounts> for OneCounts {
     fn from(counts: ffi::OneCounts) -> Self {
         OneCounts {
@@ -97,6 +161,7 @@ pub struct OneStat {
     pub is_list: bool,
 }
 
+#[cfg(feature = "onelib")]
 impl From<ffi::OneStat> for OneStat {
     fn from(stat: ffi::OneStat) -> Self {
         OneStat {
@@ -111,3 +176,271 @@ impl From<ffi::OneStat> for OneStat {
         }
     }
 }
+
+/// Counts of one declared line type, from `OneFile::all_stats()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStats {
+    pub line_type: char,
+    /// Whether the schema declares it an object ('O') rather than data ('D')
+    pub is_object: bool,
+    /// Whether it has a list field, whose lengths `max` and `total` count
+    pub is_list: bool,
+    /// Number of lines, longest list and summed list lengths, as `stats()`
+    pub counts: OneCounts,
+}
+
+/// How a file stores the DNA list of a line type, see `OneFile::dna_encoding()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnaEncoding {
+    /// Packed two bits per base, as in binary files: `dna_2bit()` returns
+    /// the stored bytes and `dna_char()` unpacks them
+    TwoBit,
+    /// One character per base, as in ASCII files: `dna_char()` returns the
+    /// stored bytes and `dna_2bit()` packs them on each call
+    Chars,
+}
+
+/// How REAL values are written to ASCII files, see `OneFile::set_real_format()`
+///
+/// Binary files store the exact bits whatever the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RealFormat {
+    /// ONElib's default, C's `%f`: six decimals
+    #[default]
+    Default,
+    /// A fixed number of decimals (`%.Nf`), e.g. `Fixed(4)` for identities
+    Fixed(u8),
+    /// Scientific notation with a fixed number of decimals (`%.Ne`)
+    Scientific(u8),
+    /// A number of significant digits without trailing zeros (`%.Ng`);
+    /// `Significant(17)` reads back to the same value
+    Significant(u8),
+}
+
+impl RealFormat {
+    /// `value` as C's printf prints it with this format, e.g. `%.4f` for `Fixed(4)`
+    #[cfg(feature = "onelib")]
+    pub(crate) fn format(self, value: f64) -> String {
+        if !value.is_finite() {
+            return match value {
+                f64::INFINITY => "inf".to_string(),
+                f64::NEG_INFINITY => "-inf".to_string(),
+                _ => "nan".to_string(),
+            };
+        }
+        match self {
+            RealFormat::Default => format!("{:.6}", value),
+            RealFormat::Fixed(n) => format!("{:.*}", n as usize, value),
+            RealFormat::Scientific(n) => c_exponent(&format!("{:.*e}", n as usize, value)),
+            RealFormat::Significant(n) => {
+                // %g: scientific for exponents below -4 or of at least the
+                // precision, else fixed, either without trailing zeros
+                let precision = n.max(1) as i32;
+                let scientific = format!("{:.*e}", precision as usize - 1, value);
+                let (mantissa, exponent) = scientific.split_once('e').unwrap();
+                let exponent: i32 = exponent.parse().unwrap();
+                if exponent < -4 || exponent >= precision {
+                    c_exponent(&format!("{}e{}", trim_zeros(mantissa), exponent))
+                } else {
+                    let decimals = (precision - 1 - exponent) as usize;
+                    trim_zeros(&format!("{:.*}", decimals, value)).to_string()
+                }
+            }
+        }
+    }
+}
+
+/// Rust's exponent ("1.5e-1") written as C's, with a sign and two digits ("1.5e-01")
+#[cfg(feature = "onelib")]
+fn c_exponent(scientific: &str) -> String {
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    format!("{}e{}{:0>2}", mantissa, sign, digits)
+}
+
+/// A decimal without the trailing zeros, and point, that %g leaves off
+#[cfg(feature = "onelib")]
+fn trim_zeros(decimal: &str) -> &str {
+    if decimal.contains('.') {
+        decimal.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        decimal
+    }
+}
+
+/// A non-fatal anomaly met while reading, see `OneFile::warnings()`
+///
+/// Displays as a one-line message, for a QC summary at the end of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Scaffolds of a 'g' group sharing a name, of which lookups by name
+    /// find the first
+    DuplicateName { group: i64, name: String },
+    /// A '#' header line giving another number of lines of a type than the
+    /// file holds
+    CountMismatch { line_type: char, given: i64, found: i64 },
+    /// A line of a type the schema does not declare, skipped by a lenient
+    /// reader
+    SkippedLine { line: i64, line_type: char },
+    /// `get_sequence_name()` called often enough that its rescans of the
+    /// embedded GDB dominate
+    SlowPath { lookups: u64 },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::DuplicateName { group, name } => {
+                write!(f, "scaffold name {} repeated in group {}", name, group)
+            }
+            Warning::CountMismatch { line_type, given, found } => write!(
+                f,
+                "header gives {} '{}' lines but the file holds {}",
+                given, line_type, found
+            ),
+            Warning::SkippedLine { line, line_type } => {
+                write!(f, "line {}: skipped unknown line type '{}'", line, line_type)
+            }
+            Warning::SlowPath { lookups } => write!(
+                f,
+                "get_sequence_name() called {} times; use gdb_index() instead",
+                lookups
+            ),
+        }
+    }
+}
+
+/// Characters allowed in the STRING, STRING_LIST or DNA list of a line type
+///
+/// Set with `OneFile::set_alphabet()` or `OpenOptions::alphabets()`, so
+/// that a corrupted or mis-typed list is caught when it is written or read
+/// rather than deep inside analysis code. Bytes are compared as they are, so
+/// an alphabet that accepts both cases must list both.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Alphabet([u64; 4]);
+
+impl Alphabet {
+    /// Bases acgt in either case
+    pub const DNA: Alphabet = Alphabet::new("acgtACGT");
+    /// Bases and IUPAC ambiguity codes in either case, e.g. for FASTA input
+    pub const IUPAC: Alphabet = Alphabet::new("acgtnrykmswbdhvuACGTNRYKMSWBDHVU");
+    /// Upper-case one-letter amino acid codes and '*' for stop, as in `.1pro` files
+    pub const PROTEIN: Alphabet = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ*");
+
+    /// The alphabet of the bytes of `chars`
+    pub const fn new(chars: &str) -> Self {
+        let bytes = chars.as_bytes();
+        let mut bits = [0u64; 4];
+        let mut i = 0;
+        while i < bytes.len() {
+            bits[(bytes[i] / 64) as usize] |= 1 << (bytes[i] % 64);
+            i += 1;
+        }
+        Alphabet(bits)
+    }
+
+    /// Whether `byte` belongs to the alphabet
+    pub fn contains(&self, byte: u8) -> bool {
+        self.0[(byte / 64) as usize] & (1 << (byte % 64)) != 0
+    }
+
+    /// Position and value of the first byte of `bytes` outside the alphabet
+    pub fn first_invalid(&self, bytes: &[u8]) -> Option<(usize, u8)> {
+        bytes.iter().position(|&b| !self.contains(b)).map(|i| (i, bytes[i]))
+    }
+}
+
+impl std::fmt::Debug for Alphabet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let chars: String = (0..=255u8).filter(|&b| self.contains(b)).map(char::from).collect();
+        f.debug_tuple("Alphabet").field(&chars).finish()
+    }
+}
+
+/// One field of a ONE line
+///
+/// A transparent wrapper around the C library's `OneField` union. The union
+/// carries no tag, so the checked getters take the field's type from the
+/// schema (see `OneFile::field_type()`) and return None rather than
+/// reinterpreting the bits as something the field is not.
+#[cfg(feature = "onelib")]
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct FieldSlot(ffi::OneField);
+
+#[cfg(feature = "onelib")]
+impl FieldSlot {
+    /// A field holding an integer
+    pub fn from_int(value: i64) -> Self {
+        FieldSlot(ffi::OneField { i: value })
+    }
+
+    /// A field holding a real
+    pub fn from_real(value: f64) -> Self {
+        FieldSlot(ffi::OneField { r: value })
+    }
+
+    /// A field holding a character
+    pub fn from_char(value: char) -> Self {
+        // Zero the whole word first so that the unused bytes are defined
+        let mut field = ffi::OneField { i: 0 };
+        field.c = value as i8;
+        FieldSlot(field)
+    }
+
+    /// The integer value, if the field is an INT
+    pub fn int(&self, field_type: OneType) -> Option<i64> {
+        (field_type == OneType::oneINT).then(|| self.as_int())
+    }
+
+    /// The real value, if the field is a REAL
+    pub fn real(&self, field_type: OneType) -> Option<f64> {
+        (field_type == OneType::oneREAL).then(|| self.as_real())
+    }
+
+    /// The character value, if the field is a CHAR
+    pub fn char(&self, field_type: OneType) -> Option<char> {
+        (field_type == OneType::oneCHAR).then(|| self.as_char())
+    }
+
+    /// The list length, if the field is a list type (STRING, DNA or any *_LIST)
+    pub fn list_len(&self, field_type: OneType) -> Option<i64> {
+        let is_list = !matches!(field_type, OneType::oneINT | OneType::oneREAL | OneType::oneCHAR);
+        is_list.then(|| self.as_list_len())
+    }
+
+    pub(crate) fn as_int(&self) -> i64 {
+        // Every variant is plain data and the word is always fully initialised
+        unsafe { self.0.i }
+    }
+
+    pub(crate) fn as_real(&self) -> f64 {
+        unsafe { self.0.r }
+    }
+
+    pub(crate) fn as_char(&self) -> char {
+        unsafe { self.0.c as u8 as char }
+    }
+
+    pub(crate) fn as_list_len(&self) -> i64 {
+        // As oneLen(): the top byte of a list field is used internally by ONElib
+        unsafe { self.0.len & 0xffffffffffffff }
+    }
+}
+
+#[cfg(feature = "onelib")]
+impl Default for FieldSlot {
+    fn default() -> Self {
+        Self::from_int(0)
+    }
+}
+
+#[cfg(feature = "onelib")]
+impl std::fmt::Debug for FieldSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FieldSlot({:#018x})", self.as_int())
+    }
+}
@@ -0,0 +1,502 @@
+//! Buffered line writer
+//!
+//! `BufferedWriter` accumulates lines on the Rust side and hands them to the C
+//! library in batches. Until the first batch is flushed no data has reached
+//! the file, so provenance and references can still be added to the header
+//! in any order relative to the lines written so far.
+//!
+//! Throughput, measured with `cargo run --release --example buffered_write`
+//! (ten million small binary lines): crossing the FFI boundary costs next to
+//! nothing compared with encoding in the C library, so buffering brings no
+//! speedup and its bookkeeping adds roughly 20-30 ns per line, about a third
+//! on top of direct `OneFile` writes for such tiny lines. Use it for the
+//! header ordering guarantee, not for speed.
+//!
+//! With an `ObjectOrder` the writer also puts objects into a canonical order
+//! before writing them, so that the same content always gives the same bytes
+//! whatever order it was produced in:
+//!
+//! ```no_run
+//! use onecode::{BufferedWriter, ObjectOrder, OneFile, OneSchema};
+//!
+//! let schema = OneSchema::seq()?;
+//! let file = OneFile::open_write_new("out.1seq", &schema, "seq", true, 1)?;
+//! let mut writer = BufferedWriter::with_order(file, ObjectOrder::sequences_by_name())?;
+//! // ... queue 'S' objects with their 'I' lines in any order
+//! writer.close()?;
+//! # Ok::<(), onecode::OneError>(())
+//! ```
+
+use crate::error::{OneError, Result};
+use crate::ffi;
+use crate::file::OneFile;
+use crate::linetypes::{aln, seq};
+use crate::types::{FieldSlot, OneType};
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::ptr;
+
+/// Default number of lines buffered before flushing
+pub const DEFAULT_BUFFER_LINES: usize = 4096;
+
+/// Order in which a `BufferedWriter` writes objects
+///
+/// Sorting applies to each run of consecutive objects of one type (with the
+/// data lines that follow each object); any other object, such as a group
+/// line, ends the run, and everything else keeps its place. Objects with
+/// equal keys are ordered by their whole content, so the output does not
+/// depend on the order they were queued in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ObjectOrder {
+    /// As queued
+    #[default]
+    Written,
+    /// By the bytes of the first `line` line of each `object` object, which
+    /// must have a STRING or DNA list; objects without one sort first
+    ByString { object: char, line: char },
+    /// By the INT fields of the `object` line, compared in the order given
+    ByInts { object: char, fields: Vec<usize> },
+}
+
+impl ObjectOrder {
+    /// `.1seq` sequences by the name on their 'I' line
+    pub fn sequences_by_name() -> Self {
+        ObjectOrder::ByString {
+            object: seq::SEQUENCE,
+            line: seq::IDENTIFIER,
+        }
+    }
+
+    /// `.1aln` alignments by target contig, start and end, then by query
+    ///
+    /// Target coordinates are compared as stored, i.e. on the reverse
+    /// complement for reverse alignments.
+    pub fn alignments_by_target() -> Self {
+        ObjectOrder::ByInts {
+            object: aln::ALIGNMENT,
+            fields: vec![3, 4, 5, 0, 1, 2],
+        }
+    }
+
+    fn object(&self) -> Option<char> {
+        match self {
+            ObjectOrder::Written => None,
+            ObjectOrder::ByString { object, .. } | ObjectOrder::ByInts { object, .. } => {
+                Some(*object)
+            }
+        }
+    }
+}
+
+struct BufferedLine {
+    line_type: char,
+    is_object: bool,
+    field_start: usize, // offset into BufferedWriter::field_data
+    n_field: usize,
+    list_start: usize, // offset in words into BufferedWriter::list_data
+    list_len: i64,
+    list_bytes: usize,
+    comment: Option<String>,
+}
+
+/// A writer that batches lines before passing them to the C library
+///
+/// Fields are set with `set_int()`, `set_real()` and `set_char()` as on
+/// `OneFile`, then the line is queued with one of the `write_*()` methods.
+/// Lines are written in order when the buffer is full, on `flush()`, and
+/// when the writer is closed or dropped; see `with_order()` for writing
+/// objects in a canonical order instead.
+pub struct BufferedWriter {
+    file: Option<OneFile>, // only None once taken by into_inner()
+    capacity: usize,
+    fields: Vec<FieldSlot>,
+    lines: Vec<BufferedLine>,
+    field_data: Vec<FieldSlot>, // fields of all buffered lines, back to back
+    list_data: Vec<u64>,            // list elements of all buffered lines, each list word-aligned
+    is_started: bool, // true once any line has reached the C library
+    order: ObjectOrder,
+    run_start: Option<usize>, // first line of the run of objects still being sorted
+}
+
+impl BufferedWriter {
+    /// Wrap a file opened for writing, buffering up to `DEFAULT_BUFFER_LINES` lines
+    pub fn new(file: OneFile) -> Self {
+        Self::with_capacity(file, DEFAULT_BUFFER_LINES)
+    }
+
+    /// Wrap a file opened for writing, buffering up to `capacity` lines
+    pub fn with_capacity(file: OneFile, capacity: usize) -> Self {
+        let n_field = unsafe { (*file.ptr).nFieldMax.max(1) as usize };
+        BufferedWriter {
+            file: Some(file),
+            capacity: capacity.max(1),
+            fields: vec![FieldSlot::default(); n_field],
+            lines: Vec::with_capacity(capacity.max(1)),
+            field_data: Vec::new(),
+            list_data: Vec::new(),
+            is_started: false,
+            order: ObjectOrder::Written,
+            run_start: None,
+        }
+    }
+
+    /// Wrap a file opened for writing, writing its objects in `order`
+    ///
+    /// A run of objects to sort is held in memory until it ends or the writer
+    /// is closed, whatever the buffer capacity. Fails if `order` does not fit
+    /// the file schema.
+    pub fn with_order(file: OneFile, order: ObjectOrder) -> Result<Self> {
+        let mut writer = Self::new(file);
+        if let Some(object) = order.object() {
+            let info = writer.info(object)?;
+            if !unsafe { (*info).isObject } {
+                return Err(OneError::SchemaError(format!(
+                    "Line type '{}' is not an object type",
+                    object
+                )));
+            }
+        }
+        match &order {
+            ObjectOrder::Written => {}
+            ObjectOrder::ByString { line, .. } => {
+                let info = writer.info(*line)?;
+                let list_type = unsafe {
+                    let field = (*info).listField as usize;
+                    ((*info).listEltSize > 0).then(|| *(*info).fieldType.add(field))
+                };
+                if !matches!(list_type, Some(OneType::oneSTRING | OneType::oneDNA)) {
+                    return Err(OneError::SchemaError(format!(
+                        "Line type '{}' has no STRING or DNA list to sort by",
+                        line
+                    )));
+                }
+            }
+            ObjectOrder::ByInts { object, fields } => {
+                let info = writer.info(*object)?;
+                for &field in fields {
+                    let field_type = unsafe {
+                        (field < (*info).nField as usize).then(|| *(*info).fieldType.add(field))
+                    };
+                    if field_type != Some(OneType::oneINT) {
+                        return Err(OneError::SchemaError(format!(
+                            "Field {} of line type '{}' is not an INT",
+                            field, object
+                        )));
+                    }
+                }
+            }
+        }
+        writer.order = order;
+        Ok(writer)
+    }
+
+    /// Number of lines waiting to be flushed
+    pub fn buffered(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Add provenance information to the file header
+    ///
+    /// Fails once lines have been flushed, since the header is written with
+    /// the first line.
+    pub fn add_provenance(&mut self, prog: &str, version: &str, command: &str) -> Result<bool> {
+        self.check_header_open()?;
+        self.file().add_provenance(prog, version, command)
+    }
+
+    /// Add a reference to the file header
+    ///
+    /// Fails once lines have been flushed, since the header is written with
+    /// the first line.
+    pub fn add_reference(&mut self, filename: &str, count: i64) -> Result<bool> {
+        self.check_header_open()?;
+        self.file().add_reference(filename, count)
+    }
+
+    /// Set an integer field of the next line
+    pub fn set_int(&mut self, field: usize, value: i64) {
+        self.fields[field] = FieldSlot::from_int(value);
+    }
+
+    /// Set a real field of the next line
+    pub fn set_real(&mut self, field: usize, value: f64) {
+        self.fields[field] = FieldSlot::from_real(value);
+    }
+
+    /// Set a character field of the next line
+    pub fn set_char(&mut self, field: usize, value: char) {
+        self.fields[field] = FieldSlot::from_char(value);
+    }
+
+    /// Queue a line without list data
+    pub fn write_line(&mut self, line_type: char) -> Result<()> {
+        self.push(line_type, 0, &[])
+    }
+
+    /// Queue a line whose list field is a string or DNA
+    pub fn write_bytes(&mut self, line_type: char, bytes: &[u8]) -> Result<()> {
+        self.push(line_type, bytes.len() as i64, bytes)
+    }
+
+    /// Queue a line whose list field is an integer list
+    pub fn write_ints(&mut self, line_type: char, values: &[i64]) -> Result<()> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+        };
+        self.push(line_type, values.len() as i64, bytes)
+    }
+
+    /// Queue a line whose list field is a real list
+    pub fn write_reals(&mut self, line_type: char, values: &[f64]) -> Result<()> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+        };
+        self.push(line_type, values.len() as i64, bytes)
+    }
+
+    /// Attach a comment to the most recently queued line
+    pub fn write_comment(&mut self, comment: &str) -> Result<()> {
+        match self.lines.last_mut() {
+            Some(line) => {
+                line.comment = Some(comment.to_string());
+                Ok(())
+            }
+            None => Err(OneError::Other("No buffered line to attach a comment to".to_string())),
+        }
+    }
+
+    /// Pass all buffered lines to the C library
+    ///
+    /// With an `ObjectOrder`, the run of objects still being queued stays
+    /// buffered, since objects queued later may sort before it.
+    pub fn flush(&mut self) -> Result<()> {
+        let end = self.run_start.unwrap_or(self.lines.len());
+        self.write_lines(end)
+    }
+
+    /// Write the first `end` buffered lines, sorting complete runs of objects
+    fn write_lines(&mut self, end: usize) -> Result<()> {
+        let order = self.write_order(end);
+        let file = self.file.as_mut().unwrap();
+        for i in order {
+            let line = &self.lines[i];
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    self.field_data.as_ptr().add(line.field_start),
+                    (*file.ptr).field as *mut FieldSlot,
+                    line.n_field,
+                );
+                // The C library copies list data into its own buffer when writing
+                let list_buf = if line.list_len > 0 {
+                    self.list_data.as_mut_ptr().add(line.list_start) as *mut std::ffi::c_void
+                } else {
+                    ptr::null_mut()
+                };
+                file.write_c_line(line.line_type, line.list_len, list_buf);
+            }
+            if let Some(comment) = &line.comment {
+                file.write_comment(comment)?;
+            }
+            self.is_started = true;
+        }
+
+        if end == self.lines.len() {
+            self.lines.clear();
+            self.field_data.clear();
+            self.list_data.clear();
+        } else {
+            // Move the lines still buffered to the front of the data buffers
+            let kept: Vec<BufferedLine> = self.lines.drain(end..).collect();
+            self.lines.clear();
+            let field_data = std::mem::take(&mut self.field_data);
+            let list_data = std::mem::take(&mut self.list_data);
+            for mut line in kept {
+                let fields = &field_data[line.field_start..line.field_start + line.n_field];
+                line.field_start = self.field_data.len();
+                self.field_data.extend_from_slice(fields);
+                let words = line.list_bytes.div_ceil(8);
+                let list = &list_data[line.list_start..line.list_start + words];
+                line.list_start = self.list_data.len();
+                self.list_data.extend_from_slice(list);
+                self.lines.push(line);
+            }
+            self.run_start = self.run_start.map(|_| 0);
+        }
+        if self.lines.is_empty() {
+            self.run_start = None;
+        }
+        Ok(())
+    }
+
+    /// Indices of the first `end` buffered lines in the order to write them
+    fn write_order(&self, end: usize) -> Vec<usize> {
+        let Some(object) = self.order.object() else {
+            return (0..end).collect();
+        };
+        let is_member = |i: usize| self.lines[i].is_object && self.lines[i].line_type == object;
+        let mut order = Vec::with_capacity(end);
+        let mut i = 0;
+        while i < end {
+            if !is_member(i) {
+                order.push(i);
+                i += 1;
+                continue;
+            }
+            let mut objects = Vec::new();
+            while i < end && is_member(i) {
+                let start = i;
+                i += 1;
+                while i < end && !self.lines[i].is_object {
+                    i += 1;
+                }
+                objects.push(start..i);
+            }
+            objects.sort_by(|a, b| self.compare_objects(a, b));
+            order.extend(objects.into_iter().flatten());
+        }
+        order
+    }
+
+    fn compare_objects(&self, a: &Range<usize>, b: &Range<usize>) -> Ordering {
+        let by_key = match &self.order {
+            ObjectOrder::Written => Ordering::Equal,
+            ObjectOrder::ByString { line, .. } => {
+                let string = |r: &Range<usize>| {
+                    self.lines[r.clone()]
+                        .iter()
+                        .find(|l| l.line_type == *line)
+                        .map(|l| self.list_bytes(l))
+                };
+                string(a).cmp(&string(b))
+            }
+            ObjectOrder::ByInts { fields, .. } => {
+                let ints = |r: &Range<usize>| {
+                    let start = self.lines[r.start].field_start;
+                    fields.iter().map(move |&f| self.field_data[start + f].as_int())
+                };
+                ints(a).cmp(ints(b))
+            }
+        };
+        by_key.then_with(|| {
+            for (x, y) in self.lines[a.clone()].iter().zip(&self.lines[b.clone()]) {
+                let ordering = self.compare_lines(x, y);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            a.len().cmp(&b.len())
+        })
+    }
+
+    fn compare_lines(&self, a: &BufferedLine, b: &BufferedLine) -> Ordering {
+        let words = |l: &BufferedLine| {
+            self.field_data[l.field_start..l.field_start + l.n_field]
+                .iter()
+                .map(FieldSlot::as_int)
+        };
+        a.line_type
+            .cmp(&b.line_type)
+            .then_with(|| words(a).cmp(words(b)))
+            .then_with(|| self.list_bytes(a).cmp(self.list_bytes(b)))
+            .then_with(|| a.comment.cmp(&b.comment))
+    }
+
+    fn list_bytes(&self, line: &BufferedLine) -> &[u8] {
+        let words = &self.list_data[line.list_start..line.list_start + line.list_bytes.div_ceil(8)];
+        unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, line.list_bytes) }
+    }
+
+    /// Flush and return the underlying file
+    pub fn into_inner(mut self) -> Result<OneFile> {
+        self.write_lines(self.lines.len())?;
+        Ok(self.file.take().unwrap())
+    }
+
+    /// Flush and close the underlying file
+    pub fn close(self) -> Result<()> {
+        self.into_inner()?.finalize()
+    }
+
+    fn push(&mut self, line_type: char, list_len: i64, list: &[u8]) -> Result<()> {
+        let info = self.info(line_type)?;
+        let (n_field, is_object, list_field) = unsafe {
+            if list.len() as i64 != list_len * (*info).listEltSize as i64 {
+                return Err(OneError::SchemaError(format!(
+                    "List data does not match the list field of line type '{}'",
+                    line_type
+                )));
+            }
+            let list_field = ((*info).listEltSize > 0).then_some((*info).listField as usize);
+            ((*info).nField as usize, (*info).isObject, list_field)
+        };
+        if is_object {
+            self.run_start = match self.order.object() {
+                Some(object) if object == line_type => self.run_start.or(Some(self.lines.len())),
+                _ => None,
+            };
+        }
+        self.lines.push(BufferedLine {
+            line_type,
+            is_object,
+            field_start: self.field_data.len(),
+            n_field,
+            list_start: self.list_data.len(),
+            list_len,
+            list_bytes: list.len(),
+            comment: None,
+        });
+        self.field_data.extend_from_slice(&self.fields[..n_field]);
+        if let Some(field) = list_field {
+            // As the C library will, so that stale values do not affect sorting
+            let len = self.field_data.len() - n_field + field;
+            self.field_data[len] = FieldSlot::from_int(list_len);
+        }
+        let start = self.list_data.len();
+        self.list_data.resize(start + list.len().div_ceil(8), 0);
+        unsafe {
+            let dest = self.list_data.as_mut_ptr().add(start) as *mut u8;
+            ptr::copy_nonoverlapping(list.as_ptr(), dest, list.len());
+        }
+        if self.lines.len() >= self.capacity && self.run_start.is_none() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn info(&mut self, line_type: char) -> Result<*mut ffi::OneInfo> {
+        let info = if line_type.is_ascii() {
+            unsafe { (*self.file().ptr).info[line_type as usize] }
+        } else {
+            ptr::null_mut()
+        };
+        if info.is_null() {
+            return Err(OneError::SchemaError(format!(
+                "Line type '{}' is not defined in the file schema",
+                line_type
+            )));
+        }
+        Ok(info)
+    }
+
+    fn file(&mut self) -> &mut OneFile {
+        self.file.as_mut().unwrap()
+    }
+
+    fn check_header_open(&self) -> Result<()> {
+        if self.is_started {
+            return Err(OneError::Other(
+                "Header can no longer be changed: lines have already been written".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BufferedWriter {
+    fn drop(&mut self) {
+        if self.file.is_some() {
+            // Errors only arise from comments containing NUL bytes; there is no one to report to here
+            self.write_lines(self.lines.len()).ok();
+        }
+    }
+}
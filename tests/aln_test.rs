@@ -0,0 +1,197 @@
+//! Tests for the .1aln alignment reader
+
+use onecode::convert::aln_to_paf;
+use onecode::{Alignment, AlnReader, OneError, OneFile, Result};
+
+#[test]
+fn test_aln_reader() -> Result<()> {
+    let reader = AlnReader::open("data/test.1aln")?.with_traces();
+    assert_eq!(reader.trace_spacing(), 100);
+    let alignments: Vec<_> = reader.collect();
+    assert_eq!(alignments.len(), 72);
+    assert_eq!(alignments.iter().filter(|a| a.reverse).count(), 16);
+
+    let first = &alignments[0];
+    assert_eq!(
+        (first.query_id, first.query_start, first.query_end),
+        (0, 0, 3341)
+    );
+    assert_eq!(
+        (first.target_id, first.target_start, first.target_end),
+        (1, 0, 3340)
+    );
+    assert_eq!(first.diffs, 45);
+
+    for aln in &alignments {
+        // One trace interval per spacing step in the query
+        assert_eq!(aln.trace_points.len(), aln.trace_diffs.len());
+        assert_eq!(
+            aln.trace_points.iter().sum::<i64>(),
+            aln.target_end - aln.target_start
+        );
+        assert_eq!(aln.trace_diffs.iter().sum::<i64>(), aln.diffs);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_deferred_traces() -> Result<()> {
+    let eager: Vec<_> = AlnReader::open("data/test.1aln")?.with_traces().collect();
+    assert!(eager.iter().all(|a| a.trace_loaded && !a.trace_points.is_empty()));
+
+    // Loading a trace mid-iteration leaves the reader where it was
+    let mut reader = AlnReader::open("data/test.1aln")?;
+    let mut alignments = Vec::new();
+    while let Some(mut aln) = reader.next() {
+        assert!(!aln.trace_loaded && aln.trace_points.is_empty());
+        if aln.object % 3 == 0 {
+            aln.load_trace(&mut reader)?;
+        }
+        alignments.push(aln);
+    }
+    assert_eq!(alignments.len(), eager.len());
+
+    // The rest are fetched by object id once the scan is over
+    for aln in alignments.iter_mut().rev() {
+        aln.load_trace(&mut reader)?;
+    }
+    assert_eq!(alignments, eager);
+    assert!(reader.next().is_none());
+
+    let mut detached = Alignment::default();
+    assert!(detached.load_trace(&mut reader).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_aln_reader_from_open_file() -> Result<()> {
+    // Starting past the header still finds every alignment
+    let mut file = OneFile::open_read("data/test.1aln", None, Some("aln"), 1)?;
+    file.goto('A', 1)?;
+    let reader = AlnReader::new(file);
+    assert_eq!(reader.file().stats('A')?.0, 72);
+    assert_eq!(reader.count(), 72);
+    Ok(())
+}
+
+#[test]
+fn test_normalized_matches_paf() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, Some("aln"), 1)?;
+    let mut paf = Vec::new();
+    aln_to_paf(&mut file, &mut paf)?;
+    let paf = String::from_utf8(paf).unwrap();
+    let lines: Vec<&str> = paf.lines().collect();
+
+    // Records as FastGA's ALNtoPAF writes them, by alignment number: reverse
+    // alignments report the target on its forward strand, which moves
+    // intervals that do not cover the whole contig
+    let expected = [
+        (0, "gi|568815592:31353871-31357211\t3341\t0\t3341\t+\t\
+             gi|568815529:2834231-2837570\t3340\t0\t3340"),
+        (31, "gi|568815564:2695843-2699207\t3365\t2\t3356\t-\t\
+              gi|299782605:5000-8340\t3341\t0\t3341"),
+        (48, "gi|299782605:5000-8340\t3341\t0\t3341\t-\t\
+              gi|568815564:2695843-2699207\t3365\t2\t3356"),
+        (55, "gi|299782605:5000-8340\t3341\t25\t3341\t-\t\
+              gi|528476637:31323556-31326919\t3364\t2\t3327"),
+        (63, "gi|528476637:31323556-31326919\t3364\t2\t3327\t-\t\
+              gi|299782605:5000-8340\t3341\t25\t3341"),
+    ];
+    let index = file.gdb_index()?.clone();
+    let alignments: Vec<_> = AlnReader::open("data/test.1aln")?.collect();
+    assert_eq!(lines.len(), alignments.len());
+    for (i, record) in expected {
+        let fields: Vec<&str> = lines[i].split('\t').collect();
+        assert_eq!(fields[..9].join("\t"), record, "alignment {}", i);
+
+        // A single skeleton serves as both query and target
+        let n = alignments[i].normalized(&index, &index)?;
+        let strand = if n.reverse { "-" } else { "+" };
+        let normalized = format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            n.query_name, n.query_len, n.query_start, n.query_end, strand,
+            n.target_name, n.target_len, n.target_start, n.target_end
+        );
+        assert_eq!(normalized, record, "alignment {}", i);
+    }
+
+    for aln in &alignments {
+        let n = aln.normalized(&index, &index)?;
+        assert!(0 <= n.target_start && n.target_start <= n.target_end && n.target_end <= n.target_len);
+        assert_eq!(n.target_end - n.target_start, aln.target_end - aln.target_start);
+        assert_eq!(n.query_end - n.query_start, aln.query_end - aln.query_start);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_normalized_errors() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, Some("aln"), 1)?;
+    let index = file.gdb_index()?.clone();
+    let len = index.contig_length(0).unwrap();
+    let aln = Alignment {
+        query_end: 10,
+        target_end: 10,
+        ..Alignment::default()
+    };
+    assert!(aln.normalized(&index, &index).is_ok());
+
+    let unknown = Alignment { target_id: 1000, ..aln.clone() };
+    assert!(matches!(unknown.normalized(&index, &index), Err(OneError::InvalidFormat(_))));
+
+    let beyond = Alignment { query_end: len + 1, ..aln };
+    assert!(matches!(beyond.normalized(&index, &index), Err(OneError::InvalidFormat(_))));
+    Ok(())
+}
+
+#[test]
+fn test_trace_intervals() -> Result<()> {
+    let reader = AlnReader::open("data/test.1aln")?.with_traces();
+    let spacing = reader.trace_spacing();
+    for aln in reader {
+        let intervals = aln.trace_intervals(spacing)?;
+        assert_eq!(intervals[0].query_start, aln.query_start);
+        assert_eq!(intervals[0].target_start, aln.target_start);
+        let last = intervals.last().unwrap();
+        assert_eq!((last.query_end, last.target_end), (aln.query_end, aln.target_end));
+        for pair in intervals.windows(2) {
+            assert_eq!(pair[0].query_end % spacing, 0);
+            assert_eq!(pair[0].query_end, pair[1].query_start);
+            assert_eq!(pair[0].target_end, pair[1].target_start);
+        }
+        assert_eq!(intervals.iter().map(|i| i.diffs).sum::<i64>(), aln.diffs);
+    }
+
+    // The spacing is the file's, not FastGA's default
+    let first = AlnReader::open("data/test.1aln")?.with_traces().next().unwrap();
+    assert!(matches!(first.trace_intervals(50), Err(OneError::InvalidFormat(_))));
+    assert!(matches!(first.trace_intervals(0), Err(OneError::InvalidFormat(_))));
+    Ok(())
+}
+
+#[test]
+fn test_cigar() -> Result<()> {
+    // Intervals of spacing 4 from query 2: an insertion, a mismatch and a deletion
+    let query = b"ggACGTTCGAACGT";
+    let target = b"cacgtcgatcgta";
+    let aln = Alignment {
+        query_start: 2,
+        query_end: 14,
+        target_start: 1,
+        target_end: 13,
+        diffs: 3,
+        trace_points: vec![2, 3, 4, 3],
+        trace_diffs: vec![0, 1, 1, 1],
+        ..Alignment::default()
+    };
+    assert_eq!(aln.cigar(4, query, target)?, "3=1I4=1X3=1D");
+
+    // Reverse target coordinates are on the reverse complement of the contig
+    let forward = b"tacgatcgacgtg";
+    let reverse = Alignment { reverse: true, ..aln.clone() };
+    assert_eq!(reverse.cigar(4, query, forward)?, "3=1I4=1X3=1D");
+
+    assert!(matches!(aln.cigar(100, query, target), Err(OneError::InvalidFormat(_))));
+    assert!(matches!(aln.cigar(4, &query[..10], target), Err(OneError::InvalidFormat(_))));
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Tests for restricting list fields to an alphabet
+
+use onecode::{Alphabet, OneError, OneFile, OneSchema, Result};
+
+const SCHEMA: &str =
+    "P 3 tst\nO S 1 3 DNA\nD N 1 6 STRING\nD W 1 11 STRING_LIST\nD L 1 8 INT_LIST\n";
+
+#[test]
+fn test_alphabet() {
+    let digits = Alphabet::new("0123456789");
+    assert!(digits.contains(b'7'));
+    assert!(!digits.contains(b'a'));
+    assert_eq!(digits.first_invalid(b"12x4y"), Some((2, b'x')));
+    assert_eq!(digits.first_invalid(b""), None);
+    assert_eq!(format!("{:?}", digits), "Alphabet(\"0123456789\")");
+
+    assert!(Alphabet::IUPAC.contains(b'N'));
+    assert!(!Alphabet::DNA.contains(b'n'));
+    assert!(Alphabet::PROTEIN.contains(b'*'));
+}
+
+#[test]
+fn test_alphabet_on_write() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", true, 1)?;
+    file.set_alphabet('S', Alphabet::DNA)?;
+    file.set_alphabet('N', Alphabet::new("chr0123456789"))?;
+    file.set_alphabet('W', Alphabet::new("ab"))?;
+    assert_eq!(file.alphabet('S'), Some(Alphabet::DNA));
+    assert_eq!(file.alphabet('L'), None);
+
+    file.write_dna('S', b"acgtACGT")?;
+    let err = file.write_dna('S', b"acgtn").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid character: 'n' at position 4 of a 'S' list"
+    );
+    assert!(err.detailed_report().help.is_some());
+    file.write_string('N', "chr12")?;
+    assert!(matches!(
+        file.write_string('N', "chr 1"),
+        Err(OneError::InvalidCharacter(_))
+    ));
+
+    // Positions count across the strings of a list, including terminators
+    let err = file.write_string_list('W', &["ab", "b\n"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid character: '\\n' at position 4 of a 'W' list"
+    );
+
+    // Only lists of characters take an alphabet
+    assert!(matches!(
+        file.set_alphabet('L', Alphabet::DNA),
+        Err(OneError::SchemaError(_))
+    ));
+    assert!(file.set_alphabet('Q', Alphabet::DNA).is_err());
+
+    // Rejected lines are not written
+    let bytes = file.into_vec()?;
+    let file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+    assert_eq!(
+        (file.stats('S')?.0, file.stats('N')?.0, file.stats('W')?.0),
+        (1, 1, 0)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_alphabet_on_read() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", false, 1)?;
+    file.write_dna('S', b"acgt")?;
+    file.write_string('N', "chr1")?;
+    file.write_dna('S', b"ggcc")?;
+    file.write_string('N', "chr2 second")?;
+    let bytes = file.into_vec()?;
+
+    let alphabets = [('N', Alphabet::new("chr0123456789"))];
+    let mut file = OneFile::options()
+        .alphabets(&alphabets)
+        .open_read_bytes(&bytes)?;
+    assert_eq!(file.read_line_checked()?, 'S');
+    assert_eq!(file.read_line_checked()?, 'N');
+    assert_eq!(file.read_line_checked()?, 'S');
+    let err = file.read_line_checked().unwrap_err();
+    assert!(err.to_string().contains("' ' at position 4 of a 'N' list"));
+    assert_eq!(err.detailed_report().line, Some(file.line_number()));
+    assert_eq!(file.read_line_checked()?, '\0');
+
+    // Setting an alphabet for a line type without characters fails the open
+    let alphabets = [('L', Alphabet::DNA)];
+    assert!(OneFile::options()
+        .alphabets(&alphabets)
+        .open_read_bytes(&bytes)
+        .is_err());
+    Ok(())
+}
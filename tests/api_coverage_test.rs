@@ -1,10 +1,11 @@
-/// Test coverage for all ONE file API methods
-///
-/// This test demonstrates all the macros from ONElib.h exposed as Rust methods
+//! Test coverage for all ONE file API methods
+//!
+//! This test demonstrates all the macros from ONElib.h exposed as Rust methods
 
 use onecode::{OneFile, OneSchema};
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn test_all_field_accessors() {
     // Create a simple schema with different field types
     let schema_text = "P 3 tst\nO T 3 3 INT 4 REAL 4 CHAR\n";
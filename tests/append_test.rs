@@ -0,0 +1,154 @@
+//! Tests for appending to existing binary files
+
+use onecode::{OneError, OneFile, OneSchema, OneWriter, Result, WriteOptions};
+
+fn write_reads(path: &str, names: &[&str]) -> Result<()> {
+    let mut file = OneFile::open_write_new(path, &OneSchema::seq()?, "seq", true, 1)?;
+    for name in names {
+        file.write_dna('S', name.to_lowercase().as_bytes())?;
+        file.write_string('I', name)?;
+    }
+    file.finalize()
+}
+
+fn read_reads(path: &str) -> Result<Vec<(String, String)>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let mut reads = Vec::new();
+    while file.read_line() != '\0' {
+        match file.line_type() {
+            'S' => reads.push((dna(&file), String::new())),
+            'I' => reads.last_mut().unwrap().1 = file.string()?.to_string(),
+            _ => {}
+        }
+    }
+    Ok(reads)
+}
+
+fn dna(file: &OneFile) -> String {
+    String::from_utf8_lossy(file.dna_char().unwrap()).into_owned()
+}
+
+#[test]
+fn test_append_objects() -> Result<()> {
+    let path = format!("/tmp/test_append_{}.1seq", std::process::id());
+    write_reads(&path, &["ACGT", "GG"])?;
+
+    let mut file = OneFile::open_append(&path)?;
+    file.write_dna('S', b"acgtacgtac")?;
+    file.write_string('I', "third")?;
+    file.finalize()?;
+
+    let reads = read_reads(&path)?;
+    assert_eq!(reads.len(), 3);
+    assert_eq!(reads[0], ("acgt".to_string(), "ACGT".to_string()));
+    assert_eq!(reads[2], ("acgtacgtac".to_string(), "third".to_string()));
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(file.stats('S')?, (3, 10, 16));
+    file.goto('S', 2)?;
+    assert_eq!(file.read_line(), 'S');
+    assert_eq!(dna(&file), "gg");
+    drop(file);
+
+    // The file itself is written, with no copy beside it
+    let mut file = OneFile::open_append(&path)?;
+    assert_eq!(file.file_name().as_deref(), Some(path.as_str()));
+    file.write_dna('S', b"tttt")?;
+    file.finalize()?;
+    assert_eq!(read_reads(&path)?.len(), 4);
+
+    // Appending atomically, readers see the file as it was until it is closed
+    let mut file = OneFile::append(&path, WriteOptions::default().atomic(true))?;
+    file.write_dna('S', b"cccc")?;
+    assert_ne!(file.file_name().as_deref(), Some(path.as_str()));
+    assert_eq!(read_reads(&path)?.len(), 4);
+    file.finalize()?;
+    assert_eq!(read_reads(&path)?.len(), 5);
+
+    // Dropped unfinished while panicking, the copy is discarded
+    let result = std::panic::catch_unwind(|| {
+        let mut file = OneWriter::append_with(&path, WriteOptions::default().atomic(true)).unwrap();
+        file.write_dna('S', b"gggg").unwrap();
+        panic!("worker failed");
+    });
+    assert!(result.is_err());
+    assert_eq!(read_reads(&path)?.len(), 5);
+
+    // Appending nothing leaves the file as it was
+    let before = std::fs::read(&path).unwrap();
+    OneWriter::append(&path)?.finalize()?;
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_append_compressed_lists() -> Result<()> {
+    // Enough identifiers for ONElib to train a codec for 'I' lists
+    let path = format!("/tmp/test_append_codec_{}.1seq", std::process::id());
+    let names: Vec<String> = (0..20000).map(|i| format!("read_{:06}", i)).collect();
+    let first: Vec<&str> = names[..10000].iter().map(String::as_str).collect();
+    write_reads(&path, &first)?;
+
+    let mut file = OneFile::open_append(&path)?;
+    for name in &names[10000..] {
+        file.write_dna('S', b"acgt")?;
+        file.write_string('I', name)?;
+    }
+    file.finalize()?;
+
+    let reads = read_reads(&path)?;
+    assert_eq!(reads.len(), 20000);
+    assert!(reads.iter().zip(&names).all(|(read, name)| &read.1 == name));
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_append_to_empty_file() -> Result<()> {
+    let path = format!("/tmp/test_append_empty_{}.1seq", std::process::id());
+    write_reads(&path, &[])?;
+
+    let mut file = OneFile::options().open_append(&path)?;
+    file.write_dna('S', b"tt")?;
+    file.finalize()?;
+    assert_eq!(read_reads(&path)?, [("tt".to_string(), String::new())]);
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    file.goto('S', 1)?;
+    assert_eq!(file.read_line(), 'S');
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_append_needs_binary_file() -> Result<()> {
+    let path = format!("/tmp/test_append_ascii_{}.1seq", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &OneSchema::seq()?, "seq", false, 1)?;
+    file.write_dna('S', b"acgt")?;
+    file.finalize()?;
+    let before = std::fs::read(&path).unwrap();
+
+    let err = OneFile::open_append(&path).err().unwrap();
+    assert!(matches!(err, OneError::OpenFailed(ref msg) if msg.contains("binary")));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    assert!(matches!(
+        OneFile::open_append("/nonexistent/x.1seq"),
+        Err(OneError::Io { .. })
+    ));
+
+    // ONElib's errors name the file given, not the copy appended to
+    std::fs::write(&path, "not a ONE file\n").unwrap();
+    let err = OneFile::append(&path, WriteOptions::default().atomic(true)).err().unwrap();
+    assert!(err.to_string().contains(&path), "{}", err);
+    assert!(!err.to_string().contains(".tmp"), "{}", err);
+    let dir = std::path::Path::new(&path).parent().unwrap();
+    let name = std::path::Path::new(&path).file_name().unwrap().to_string_lossy().into_owned();
+    assert!(std::fs::read_dir(dir)
+        .unwrap()
+        .all(|e| !e.unwrap().file_name().to_string_lossy().contains(&format!("{}.", name))));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
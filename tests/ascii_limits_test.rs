@@ -0,0 +1,77 @@
+//! Tests for list length limits on ASCII lines
+
+use onecode::file::MAX_ASCII_LIST_LEN;
+use onecode::{OneError, OneFile, OneSchema, Result};
+
+fn write_seq(path: &str, binary: bool, len: usize) -> Result<()> {
+    let schema = OneSchema::seq()?;
+    let mut writer = OneFile::open_write_new(path, &schema, "seq", binary, 1)?;
+    writer.write_dna('S', &vec![b'a'; len])?;
+    writer.close();
+    Ok(())
+}
+
+#[test]
+fn test_ascii_writer_limit() -> Result<()> {
+    let schema = OneSchema::seq()?;
+    let path = format!("/tmp/test_ascii_limit_write_{}.1seq", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &schema, "seq", false, 1)?;
+        writer.set_ascii_list_limit(10);
+        writer.write_dna('S', b"acgtacgtac")?;
+        let err = writer.write_dna('S', b"acgtacgtacg").unwrap_err();
+        assert!(matches!(err, OneError::ListTooLong(_)), "{}", err);
+        assert!(writer.write_string('I', "a_rather_long_name").is_err());
+        writer.close();
+    }
+    // Nothing was written for the rejected lines
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(file.read_line(), 'S');
+    assert_eq!(file.len(), 10);
+    assert_eq!(file.read_line(), '\0');
+
+    // The limit cannot be raised past what ONElib can write
+    let mut writer = OneFile::open_write_new(&path, &schema, "seq", false, 1)?;
+    writer.set_ascii_list_limit(i64::MAX);
+    assert!(writer.write_dna('S', b"acgt").is_ok());
+    writer.close();
+
+    // Binary files are not limited
+    let mut writer = OneFile::open_write_new(&path, &schema, "seq", true, 1)?;
+    writer.set_ascii_list_limit(2);
+    writer.write_dna('S', b"acgt")?;
+    writer.close();
+
+    std::fs::remove_file(&path).ok();
+    assert_eq!(MAX_ASCII_LIST_LEN, i32::MAX as i64);
+    Ok(())
+}
+
+#[test]
+fn test_binary_above() -> Result<()> {
+    let source_path = format!("/tmp/test_binary_above_src_{}.1seq", std::process::id());
+    let out_path = format!("/tmp/test_binary_above_out_{}.1seq", std::process::id());
+    // Binary files record their longest lists in the header
+    write_seq(&source_path, true, 100)?;
+    let source = OneFile::open_read(&source_path, None, None, 1)?;
+
+    let ascii = OneFile::options().binary(false);
+    let out = ascii.binary_above(500).open_write_from(&out_path, &source)?;
+    assert!(!out.is_binary());
+    out.close();
+    let out = ascii.binary_above(50).open_write_from(&out_path, &source)?;
+    assert!(out.is_binary());
+    out.close();
+
+
+    // Without a source there are no list lengths to go by
+    let schema = OneSchema::seq()?;
+    let ascii = ascii.schema(&schema).file_type("seq").binary_above(50);
+    assert!(matches!(ascii.open_write(&out_path), Err(OneError::Other(_))));
+    assert!(matches!(ascii.open_write_vec(), Err(OneError::Other(_))));
+    assert!(ascii.binary(true).open_write(&out_path)?.is_binary());
+
+    std::fs::remove_file(&source_path).ok();
+    std::fs::remove_file(&out_path).ok();
+    Ok(())
+}
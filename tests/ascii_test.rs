@@ -0,0 +1,160 @@
+//! Tests of the pure-Rust ASCII reader against the C library
+
+#![cfg(feature = "pure-rust")]
+
+use onecode::ascii::AsciiReader;
+use onecode::{FieldValue, ListValue, OneError, OneFile, OneSchema, Record, Result, Warning};
+
+fn c_records(path: &str) -> Result<Vec<Record>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    Ok(std::iter::from_fn(|| file.read_record()).collect())
+}
+
+#[test]
+fn test_small_seq_matches_c() -> Result<()> {
+    let path = "ONEcode/TEST/small.seq";
+    let mut reader = AsciiReader::open(path)?;
+
+    let header = reader.header();
+    assert_eq!(header.file_type, "seq");
+    assert_eq!((header.major, header.minor), (2, 1));
+    assert_eq!(header.provenance.len(), 2);
+    assert_eq!(header.provenance[0].program, "seqio");
+    assert_eq!(header.provenance[1].command, "ONEview small.1seq");
+    assert_eq!(header.counts[&'S'].count, 10);
+    assert_eq!(header.counts[&'S'].max, 72);
+    assert_eq!(header.counts[&'I'].total, 41);
+    assert!(header.text[0].contains("provenance lines"));
+    assert_eq!(reader.object_types(), ['S']);
+
+    let records = reader.by_ref().collect::<Result<Vec<_>>>()?;
+    assert_eq!(records, c_records(path)?);
+    assert!(reader.read_record()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_all_field_types_match_c() -> Result<()> {
+    let schema = OneSchema::from_text(
+        "P 3 tst\nO A 3 3 INT 4 REAL 4 CHAR\nD L 1 8 INT_LIST\nD R 1 9 REAL_LIST\n\
+         D W 1 11 STRING_LIST\nD N 2 3 INT 6 STRING\nD S 1 3 DNA\n",
+    )?;
+    let path = format!("/tmp/test_ascii_reader_{}.tst", std::process::id());
+    {
+        let mut file = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+        file.set_int(0, -7);
+        file.set_real(1, 0.5);
+        file.set_char(2, 'x');
+        file.write_line('A', 0, None);
+        file.write_comment("first object")?;
+        file.write_int_list('L', &[1, -2, 300])?;
+        file.write_real_list('R', &[1.5, -2.25])?;
+        file.write_string_list('W', &["ab", "", "c d"])?;
+        file.set_int(0, 3);
+        file.write_string('N', "name with spaces")?;
+        file.write_dna('S', b"acgtn")?;
+        file.write_int_list('L', &[])?;
+        file.close();
+    }
+
+    let mut reader = AsciiReader::open(&path)?;
+    let first = reader.read_record()?.unwrap();
+    assert_eq!(
+        first.fields,
+        [
+            FieldValue::Int(-7),
+            FieldValue::Real(0.5),
+            FieldValue::Char('x')
+        ]
+    );
+    assert_eq!(reader.comment(), Some("first object"));
+    let rest = reader.collect::<Result<Vec<_>>>()?;
+    assert_eq!(
+        rest[2].list,
+        Some(ListValue::StringList(vec![
+            "ab".to_string(),
+            String::new(),
+            "c d".to_string()
+        ]))
+    );
+    assert_eq!(
+        rest[3].list,
+        Some(ListValue::String("name with spaces".to_string()))
+    );
+
+    let mut records = vec![first];
+    records.extend(rest);
+    assert_eq!(records, c_records(&path)?);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_schema_for_bare_file() -> Result<()> {
+    let body = "B 5\nB 7 a comment\n";
+    let schema = "P 3 foo\nO B 1 3 INT\n";
+    let records = AsciiReader::with_schema(body.as_bytes(), schema)?.collect::<Result<Vec<_>>>()?;
+    let values: Vec<_> = records.iter().map(|r| r.fields[0].clone()).collect();
+    assert_eq!(values, [FieldValue::Int(5), FieldValue::Int(7)]);
+
+    // A header without '~' lines takes the section for its type
+    let text = "1 3 foo 1 0\n# B 1\nB 9\n";
+    let schema = "P 3 bar\nO B 1 4 REAL\nP 3 foo\nO B 1 3 INT\n";
+    let mut reader = AsciiReader::with_schema(text.as_bytes(), schema)?;
+    assert_eq!(reader.read_record()?.unwrap().fields, [FieldValue::Int(9)]);
+
+    let err = AsciiReader::new(text.as_bytes()).err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(_)));
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors() -> Result<()> {
+    let header = "1 3 seq 1 0\n~ O S 1 3 DNA\n";
+    let error = |body: &str| {
+        let text = format!("{}{}", header, body);
+        let reader = AsciiReader::new(text.as_bytes()).unwrap();
+        reader
+            .collect::<Result<Vec<_>>>()
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        error("S 4 acgt\nS 10 acgt\n"),
+        "Invalid file format: line 4: line too short"
+    );
+    assert_eq!(
+        error("S x acgt\n"),
+        "Invalid file format: line 3: bad int 'x'"
+    );
+    assert_eq!(
+        error("S 2 acgt\n"),
+        "Invalid file format: line 3: comment not separated by a space"
+    );
+    // A huge length runs out of data rather than allocating up front
+    assert_eq!(
+        error("S 999999999999999 acgt\n"),
+        "Invalid file format: line 3: line too short"
+    );
+    assert_eq!(
+        error("Q 1\n"),
+        "Invalid file format: line 3: unknown line type 'Q'"
+    );
+
+    assert!(AsciiReader::open("data/test.1aln").is_err());
+    assert!(AsciiReader::new("~ O S 1 3 DNA\n".as_bytes()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_lenient_skips_unknown_lines() -> Result<()> {
+    let text = "1 3 seq 1 0\n~ O S 1 3 DNA\nS 4 acgt\nQ 1\nS 2 gg\n";
+    let mut reader = AsciiReader::new(text.as_bytes())?.lenient(true);
+    let records = reader.by_ref().collect::<Result<Vec<_>>>()?;
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].list, Some(ListValue::Dna(b"gg".to_vec())));
+    assert_eq!(reader.warnings(), [Warning::SkippedLine { line: 4, line_type: 'Q' }]);
+    Ok(())
+}
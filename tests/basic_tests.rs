@@ -1,4 +1,4 @@
-use onecode::{OneFile, OneSchema, Result};
+use onecode::{DnaEncoding, FieldSlot, OneError, OneFile, OneSchema, OneType, Result};
 
 #[test]
 fn test_open_read_simple_seq() -> Result<()> {
@@ -72,6 +72,19 @@ fn test_stats() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_stats_of_absent_line_types() -> Result<()> {
+    let file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+
+    // Declared mask lines with no occurrences
+    assert_eq!(file.stats('M')?, (0, 0, 0));
+
+    // Undeclared line type, e.g. a typo
+    assert!(file.stats('W').is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_schema_from_text() -> Result<()> {
     let schema_text = "P 3 seq\nO S 1 3 DNA\nD I 1 6 STRING\n";
@@ -91,6 +104,111 @@ fn test_read_with_type_check() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_open_options() -> Result<()> {
+    let file = OneFile::options().file_type("seq").open_read("ONEcode/TEST/small.seq")?;
+    assert_eq!(file.file_type(), Some("seq".to_string()));
+
+    let file = OneFile::options().nthreads(4).open_read("data/test.1aln")?;
+    assert_eq!(file.nthreads(), 4);
+
+    let schema = OneSchema::seq()?;
+    let path = format!("/tmp/test_open_options_{}.1seq", std::process::id());
+    assert!(OneFile::options().schema(&schema).open_write(&path).is_err());
+    {
+        let mut writer = OneFile::options()
+            .schema(&schema)
+            .file_type("seq")
+            .binary(false)
+            .open_write(&path)?;
+        assert!(!writer.is_binary());
+        writer.write_dna('S', b"acgt")?;
+        writer.close();
+    }
+    let mut reader = OneFile::options().schema(&schema).open_read(&path)?;
+    assert_eq!(reader.read_line(), 'S');
+    assert_eq!(reader.dna_char(), Some(&b"acgt"[..]));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_field_slots() -> Result<()> {
+    let mut file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    assert_eq!(file.read_line(), 'S');
+
+    // The DNA list field only reads back as a list length
+    let field_type = file.field_type(0).unwrap();
+    assert_eq!(field_type, OneType::oneDNA);
+    assert_eq!(file.field(0).list_len(field_type), Some(file.len()));
+    assert_eq!(file.field(0).int(field_type), None);
+    assert_eq!(file.field_type(1), None);
+
+    let slot = FieldSlot::from_real(2.5);
+    assert_eq!(slot.real(OneType::oneREAL), Some(2.5));
+    assert_eq!(slot.int(OneType::oneREAL), None);
+    assert_eq!(FieldSlot::from_char('x').char(OneType::oneCHAR), Some('x'));
+
+    Ok(())
+}
+
+#[test]
+fn test_checked_field_access() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 3 3 INT 4 REAL 4 CHAR\nD N 1 6 STRING\n")?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", true, 1)?;
+    file.set_int(0, -3);
+    file.set_real(1, 0.25);
+    file.set_char(2, 'q');
+    file.write_line('T', 0, None);
+    file.write_string('N', "name")?;
+    let bytes = file.into_vec()?;
+
+    let mut file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+    assert!(matches!(file.try_int(0), Err(OneError::FieldOutOfRange(_))));
+    assert_eq!(file.read_line(), 'T');
+    assert_eq!(file.try_int(0)?, -3);
+    assert_eq!(file.try_real(1)?, 0.25);
+    assert_eq!(file.try_char(2)?, 'q');
+
+    let err = file.try_int(1).unwrap_err();
+    assert!(matches!(err, OneError::FieldTypeMismatch(_)));
+    assert_eq!(err.to_string(), "Field type mismatch: field 1 of a 'T' line is REAL, not INT");
+    let err = file.try_char(3).unwrap_err();
+    assert_eq!(err.to_string(), "Field out of range: field 3 of a 'T' line, which has 3 fields");
+
+    // The list length is not an INT field
+    assert_eq!(file.read_line(), 'N');
+    assert!(matches!(file.try_int(0), Err(OneError::FieldTypeMismatch(_))));
+    assert!(matches!(file.try_int(1), Err(OneError::FieldOutOfRange(_))));
+    Ok(())
+}
+
+#[cfg(feature = "checked")]
+#[test]
+#[should_panic(expected = "field 1 of a 'T' line is REAL, not INT")]
+fn test_checked_feature_panics() {
+    let schema = OneSchema::from_text("P 3 tst\nO T 2 3 INT 4 REAL\n").unwrap();
+    let mut file = OneFile::open_write_vec(&schema, "tst", true, 1).unwrap();
+    file.write_line('T', 0, None);
+    let bytes = file.into_vec().unwrap();
+    let mut file = OneFile::open_read_bytes(&bytes, None, None, 1).unwrap();
+    file.read_line();
+    file.int(1);
+}
+
+#[cfg(feature = "debug-ffi")]
+#[test]
+#[should_panic(expected = "overruns its")]
+fn test_debug_ffi_catches_bad_list_length() {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1).unwrap();
+    while file.read_line() != 'T' {}
+    assert!(file.int_list().is_some());
+    // As if the file gave a length far beyond the buffer the list was read into
+    file.set_int(0, 1 << 40);
+    file.int_list();
+}
+
 #[test]
 fn test_file_properties() -> Result<()> {
     let file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
@@ -102,6 +220,32 @@ fn test_file_properties() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_thread_slices() -> Result<()> {
+    let single = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    assert_eq!(single.nthreads(), 1);
+    assert!(!single.is_threaded());
+    assert_eq!(single.slice_ranges('A')?, vec![1..73]);
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 4)?;
+    assert_eq!(file.nthreads(), 4);
+    assert!(file.is_threaded());
+    let ranges = file.slice_ranges('A')?;
+    assert_eq!(ranges, vec![1..19, 19..37, 37..55, 55..73]);
+    assert!(file.slice_range('A', 4).is_err());
+
+    // The first object of a slice is reachable via goto()
+    file.goto('A', ranges[2].start)?;
+    assert_eq!(file.read_line(), 'A');
+    assert_eq!(file.object('A'), ranges[2].start);
+
+    // More threads than objects leaves some slices empty
+    let groups = file.slice_ranges('g')?;
+    assert_eq!(groups.iter().map(|r| r.end - r.start).sum::<i64>(), 1);
+
+    Ok(())
+}
+
 #[test]
 fn test_open_nonexistent_file() {
     // This should fail
@@ -162,6 +306,13 @@ fn test_write_and_read_roundtrip() -> Result<()> {
 
         assert_eq!(reader.file_type(), Some("tst".to_string()));
 
+        let provenance = reader.provenance();
+        assert_eq!(provenance.len(), 1);
+        assert_eq!(provenance[0].program, "test");
+        assert_eq!(provenance[0].version, "1.0");
+        assert_eq!(provenance[0].command, "test command");
+        assert!(!provenance[0].date.is_empty());
+
         let line_type = reader.read_line();
         assert_eq!(line_type, 'T');
         assert_eq!(reader.int(0), 42);
@@ -179,3 +330,277 @@ fn test_write_and_read_roundtrip() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_header_metadata() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    for binary in [true, false] {
+        let path = format!("/tmp/test_metadata_{}_{}.1tst", std::process::id(), binary);
+        let mut writer = OneFile::open_write_new(&path, &schema, "tst", binary, 1)?;
+        writer.add_header_text("free text, not metadata")?;
+        writer.set_metadata("sample", "HG001")?;
+        writer.set_metadata("aligner_params", "-k 19 -w 10")?;
+        writer.set_metadata("sample", "HG002")?;
+        assert!(writer.set_metadata("two words", "x").is_err());
+        assert!(writer.set_metadata("", "x").is_err());
+        assert!(writer.set_metadata("note", "a\nb").is_err());
+        writer.write_line('T', 0, None);
+        assert!(writer.set_metadata("late", "x").is_err());
+        writer.close();
+
+        let reader = OneFile::open_read(&path, None, None, 1)?;
+        assert_eq!(reader.header_text()[0], "free text, not metadata");
+        assert_eq!(
+            reader.metadata(),
+            [
+                ("sample".to_string(), "HG002".to_string()),
+                ("aligner_params".to_string(), "-k 19 -w 10".to_string())
+            ]
+        );
+        assert_eq!(reader.metadata_value("aligner_params").as_deref(), Some("-k 19 -w 10"));
+        assert_eq!(reader.metadata_value("missing"), None);
+
+        // Files written from this one carry the metadata over
+        let copy_path = format!("{}.copy", path);
+        let copy = OneFile::open_write_from(&copy_path, &reader, true, 1)?;
+        assert_eq!(copy.metadata_value("sample").as_deref(), Some("HG002"));
+        copy.close();
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&copy_path).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_typed_list_writers() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO S 1 3 DNA\nD I 1 6 STRING\nD L 1 8 INT_LIST\n")?;
+    let path = format!("/tmp/test_typed_writers_{}.1tst", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+    file.write_dna('S', b"acgtacgt")?;
+    file.write_string('I', "first")?;
+    file.write_int_list('L', &[5, 3, 1_000_000])?;
+    file.write_dna('S', b"")?;
+
+    // The list type must match the schema, and the line type must exist
+    assert!(file.write_string('L', "oops").is_err());
+    assert!(file.write_int_list('S', &[1]).is_err());
+    assert!(file.write_dna('Z', b"a").is_err());
+    assert!(file.write_string('I', "nul\0byte").is_err());
+    file.close();
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert!(file.write_dna('S', b"a").is_err());
+    assert_eq!(file.read_line(), 'S');
+    assert_eq!(file.dna_char(), Some(&b"acgtacgt"[..]));
+    assert_eq!(file.read_line(), 'I');
+    assert_eq!(file.string()?, "first");
+    assert_eq!(file.read_line(), 'L');
+    assert_eq!(file.int_list(), Some(&[5, 3, 1_000_000][..]));
+    assert_eq!(file.read_line(), 'S');
+    assert_eq!(file.len(), 0);
+    assert_eq!(file.read_line(), '\0');
+    std::fs::remove_file(&path).ok();
+
+    Ok(())
+}
+
+#[test]
+fn test_raw_string_bytes() -> Result<()> {
+    let schema =
+        OneSchema::from_text("P 3 seq\nO S 1 3 DNA\nD I 1 6 STRING\nD W 1 11 STRING_LIST\n")?;
+    for binary in [true, false] {
+        let mut file = OneFile::open_write_vec(&schema, "seq", binary, 1)?;
+        file.write_dna('S', b"acgt")?;
+        // Names are not always UTF-8; write_line() takes any bytes
+        let mut name = b"chr\xff1".to_vec();
+        file.write_line('I', name.len() as i64, Some(name.as_mut_ptr() as *mut _));
+        file.write_string_list('W', &["ab", "c"])?;
+        let bytes = file.into_vec()?;
+
+        let mut file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+        assert_eq!(file.read_line(), 'S');
+        assert!(matches!(file.string(), Err(OneError::FieldTypeMismatch(_))));
+        assert_eq!(file.string_lossy(), None);
+        assert_eq!(file.bytes(), Some(&b"acgt"[..]));
+
+        assert_eq!(file.read_line(), 'I');
+        assert!(matches!(file.string(), Err(OneError::InvalidUtf8(_))));
+        assert_eq!(file.string_lossy().as_deref(), Some("chr\u{fffd}1"));
+        assert_eq!(file.bytes(), Some(&b"chr\xff1"[..]));
+
+        // A STRING_LIST reads as its first string, or all its bytes
+        assert_eq!(file.read_line(), 'W');
+        assert_eq!(file.string()?, "ab");
+        assert_eq!(file.bytes(), Some(&b"ab\0c\0"[..]));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dna_encoding() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO S 1 3 DNA\nD I 1 6 STRING\n")?;
+    let path = format!("/tmp/test_dna_encoding_{}.1tst", std::process::id());
+    for (binary, encoding) in [(true, DnaEncoding::TwoBit), (false, DnaEncoding::Chars)] {
+        let mut file = OneFile::open_write_new(&path, &schema, "tst", binary, 1)?;
+        assert_eq!(file.dna_encoding('S'), Some(encoding));
+        file.write_dna('S', b"acgtac")?;
+        file.close();
+
+        let mut file = OneFile::open_read(&path, None, None, 1)?;
+        assert_eq!(file.dna_encoding('S'), Some(encoding));
+        assert_eq!(file.dna_encoding('I'), None);
+        assert_eq!(file.dna_encoding('W'), None);
+        assert_eq!(file.read_line(), 'S');
+        assert_eq!(file.dna_2bit().map(|b| b.len()), Some(2));
+        assert_eq!(file.dna_char(), Some(&b"acgtac"[..]));
+    }
+
+    // Packing an ASCII line leaves its characters as read
+    let mut file = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+    file.write_dna('S', b"ACGTN")?;
+    file.close();
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(file.read_line(), 'S');
+    assert_eq!(file.dna_2bit(), Some(&[0b1110_0100, 0][..]));
+    assert_eq!(file.dna_char(), Some(&b"ACGTN"[..]));
+    assert_eq!(file.dna_2bit().map(|b| b.len()), Some(2));
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_per_base_lists() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO S 1 3 DNA\nD C 1 8 INT_LIST\nD M 1 9 REAL_LIST\n")?;
+    let path = format!("/tmp/test_per_base_{}.1tst", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+    let too_early = file.write_per_base_int_list('C', &[]);
+    assert!(matches!(too_early, Err(OneError::LengthMismatch(_))));
+
+    file.write_dna('S', b"acgt")?;
+    file.write_per_base_int_list('C', &[3, 4, 4, 2])?;
+    file.write_per_base_real_list('M', &[0.0, 0.5, 0.5, 1.0])?;
+    let off_by_one = file.write_per_base_int_list('C', &[3, 4, 4]);
+    assert!(matches!(off_by_one, Err(OneError::LengthMismatch(_))));
+    assert!(matches!(file.write_per_base_real_list('C', &[0.0; 4]), Err(OneError::SchemaError(_))));
+
+    file.write_dna('S', b"ac")?;
+    assert!(file.write_per_base_int_list('C', &[1, 2, 3, 4]).is_err());
+    file.write_per_base_int_list('C', &[1, 2])?;
+    file.close();
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let coverage: Vec<Vec<i64>> = file
+        .lines()
+        .of_type('C')
+        .map(|l| l.int_list().unwrap().to_vec())
+        .collect();
+    assert_eq!(coverage, vec![vec![3, 4, 4, 2], vec![1, 2]]);
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_per_base_lists_need_a_sequence_in_the_object() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO R 0\nD S 1 3 DNA\nD C 1 8 INT_LIST\n")?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", true, 1)?;
+    file.write_line('R', 0, None);
+    file.write_dna('S', b"acgt")?;
+    file.write_per_base_int_list('C', &[3, 4, 4, 2])?;
+
+    // A new object without a sequence of its own
+    file.write_line('R', 0, None);
+    let stale = file.write_per_base_int_list('C', &[3, 4, 4, 2]);
+    assert!(matches!(stale, Err(OneError::LengthMismatch(_))));
+    file.write_dna('S', b"ac")?;
+    file.write_per_base_int_list('C', &[1, 2])?;
+    Ok(())
+}
+
+#[test]
+fn test_object_byte_sizes() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let offsets = file.get_all_alignment_byte_offsets();
+    let sizes = file.object_byte_sizes('A')?;
+    assert_eq!(sizes.len(), 72);
+    // Alignments follow each other, so all but the last end where the next starts
+    for i in 0..71 {
+        assert_eq!(sizes[i], offsets[i + 2] - offsets[i + 1]);
+    }
+    // The last runs up to the end-of-data marker
+    let bytes = std::fs::read("data/test.1aln").unwrap();
+    assert_eq!(bytes[(offsets[72] + sizes[71]) as usize], b'\n');
+    assert_eq!(file.object_byte_size('A', 72)?, sizes[71]);
+
+    // The group spans its scaffolds, up to the first alignment
+    file.goto('g', 1)?;
+    let group_start = file.get_current_byte_position();
+    assert_eq!(group_start + file.object_byte_size('g', 1)?, offsets[1]);
+
+    assert!(file.object_byte_size('A', 0).is_err());
+    assert!(file.object_byte_size('A', 73).is_err());
+    assert!(file.object_byte_size('D', 1).is_err());
+
+    let ascii = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    assert!(ascii.object_byte_sizes('S').is_err());
+    Ok(())
+}
+
+#[test]
+fn test_object_byte_size_finds_large_object() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\nD L 1 8 INT_LIST\n")?;
+    let path = format!("/tmp/test_object_sizes_{}.1tst", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+        for i in 0..5 {
+            writer.set_int(0, i);
+            writer.write_line('T', 0, None);
+            let len = if i == 3 { 100_000 } else { 10 };
+            let values: Vec<i64> = (0..len).map(|x| x * 7919 % 100_003).collect();
+            writer.write_int_list('L', &values)?;
+        }
+        writer.close();
+    }
+    let file = OneFile::open_read(&path, None, None, 1)?;
+    let sizes = file.object_byte_sizes('T')?;
+    let largest = (1..=5).max_by_key(|&i| sizes[i as usize - 1]).unwrap();
+    assert_eq!(largest, 4);
+    assert!(sizes[3] > 100 * sizes[0]);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_finalize_reports_write_errors() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    for binary in [true, false] {
+        let path = format!("/tmp/test_finalize_{}_{}.1tst", std::process::id(), binary);
+        let mut file = OneFile::open_write_new(&path, &schema, "tst", binary, 1)?;
+        file.write_line('T', 0, None);
+        file.finalize()?;
+        assert_eq!(OneFile::open_read(&path, None, None, 1)?.read_line(), 'T');
+        std::fs::remove_file(&path).ok();
+
+        // Writes to /dev/full fail with ENOSPC once the C buffers are flushed
+        let mut file = OneFile::open_write_new("/dev/full", &schema, "tst", binary, 1)?;
+        file.write_line('T', 0, None);
+        let err = file.finalize().unwrap_err();
+        assert!(matches!(err, OneError::CloseFailed(_)));
+        assert!(err.to_string().contains("/dev/full"), "{}", err);
+        assert!(err.detailed_report().help.is_some());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_close_reports_write_errors_without_panicking() -> Result<()> {
+    // Both only print the failure, in debug and release builds alike
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let mut file = OneFile::open_write_new("/dev/full", &schema, "tst", true, 1)?;
+    file.write_line('T', 0, None);
+    file.close();
+    let mut file = OneFile::open_write_new("/dev/full", &schema, "tst", true, 1)?;
+    file.write_line('T', 0, None);
+    drop(file);
+    Ok(())
+}
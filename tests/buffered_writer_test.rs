@@ -0,0 +1,203 @@
+//! Tests for the buffered line writer
+
+use onecode::{BufferedWriter, ObjectOrder, OneError, OneFile, OneSchema, Result};
+
+const SCHEMA: &str = "P 3 tst\nO P 2 3 INT 4 CHAR\nD L 1 8 INT_LIST\nD N 1 6 STRING\n";
+
+#[test]
+fn test_buffered_round_trip() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let path = format!("/tmp/test_buffered_{}.1tst", std::process::id());
+    {
+        let file = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+        let mut writer = BufferedWriter::with_capacity(file, 4);
+        for i in 0..10 {
+            writer.set_int(0, i);
+            writer.set_char(1, 'x');
+            writer.write_line('P')?;
+            writer.write_ints('L', &[i, i * 2, i * 3])?;
+            writer.write_bytes('N', format!("name{}", i).as_bytes())?;
+            if i == 0 {
+                writer.write_comment("first")?;
+                // Lines are still buffered, so the header can be extended
+                assert_eq!(writer.buffered(), 3);
+                writer.add_provenance("buffered_test", "1.0", "test")?;
+            }
+        }
+        // Ten objects of three lines each have overflowed the buffer by now
+        assert!(writer.add_reference("other.1tst", 1).is_err());
+        assert!(writer.write_line('Z').is_err());
+        assert!(matches!(writer.write_ints('P', &[1]), Err(OneError::SchemaError(_))));
+        writer.close()?;
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let provenance = text.lines().position(|l| l.starts_with("! "));
+    let first_data = text.lines().position(|l| l.starts_with("P "));
+    assert!(provenance.unwrap() < first_data.unwrap());
+    assert!(text.contains("first"));
+
+    let mut reader = OneFile::open_read(&path, None, None, 1)?;
+    for i in 0..10 {
+        assert_eq!(reader.read_line(), 'P');
+        assert_eq!(reader.int(0), i);
+        assert_eq!(reader.char(1), 'x');
+        assert_eq!(reader.read_line(), 'L');
+        assert_eq!(reader.int_list().unwrap(), &[i, i * 2, i * 3]);
+        assert_eq!(reader.read_line(), 'N');
+        assert_eq!(reader.string()?, format!("name{}", i));
+    }
+    assert_eq!(reader.read_line(), '\0');
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_buffered_flush_on_drop() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let path = format!("/tmp/test_buffered_drop_{}.1tst", std::process::id());
+    {
+        let file = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+        let mut writer = BufferedWriter::new(file);
+        writer.set_int(0, 42);
+        writer.set_char(1, 'y');
+        writer.write_line('P')?;
+    }
+
+    let mut reader = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(reader.read_line(), 'P');
+    assert_eq!(reader.int(0), 42);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+/// Write sequences in the given order, sorted by name, and return the file bytes
+fn write_sorted_seq(path: &str, records: &[(&str, &[u8])], capacity: usize) -> Result<Vec<u8>> {
+    let schema = OneSchema::seq()?;
+    let file = OneFile::open_write_new(path, &schema, "seq", true, 1)?;
+    let mut writer = BufferedWriter::with_order(file, ObjectOrder::sequences_by_name())?;
+    for (i, (name, dna)) in records.iter().enumerate() {
+        writer.write_bytes('S', dna)?;
+        writer.write_bytes('I', name.as_bytes())?;
+        if i == capacity {
+            // The run of sequences is not complete, so nothing is written yet
+            writer.flush()?;
+            assert_eq!(writer.buffered(), 2 * (i + 1));
+        }
+    }
+    writer.close()?;
+    Ok(std::fs::read(path).unwrap())
+}
+
+#[test]
+fn test_canonical_sequence_order() -> Result<()> {
+    let path = format!("/tmp/test_buffered_sorted_{}.1seq", std::process::id());
+    let records: [(&str, &[u8]); 5] = [
+        ("chr2", b"acgt"),
+        ("chr10", b"ggg"),
+        ("chr1", b"tttt"),
+        ("chr1", b"aaaa"),
+        ("chr3", b"c"),
+    ];
+    let forward = write_sorted_seq(&path, &records, 1)?;
+    let mut reversed = records;
+    reversed.reverse();
+    assert_eq!(write_sorted_seq(&path, &reversed, 3)?, forward);
+
+    // Sorted by name bytes, with the duplicate name ordered by content
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let mut found = Vec::new();
+    while file.read_line() != '\0' {
+        match file.line_type() {
+            'S' => found.push((String::new(), file.dna_char().unwrap().to_vec())),
+            'I' => found.last_mut().unwrap().0 = file.string().unwrap().to_string(),
+            _ => {}
+        }
+    }
+    let names: Vec<_> = found.iter().map(|(n, d)| (n.as_str(), d.as_slice())).collect();
+    assert_eq!(
+        names,
+        [
+            ("chr1", &b"aaaa"[..]),
+            ("chr1", b"tttt"),
+            ("chr10", b"ggg"),
+            ("chr2", b"acgt"),
+            ("chr3", b"c")
+        ]
+    );
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_canonical_alignment_order() -> Result<()> {
+    let schema = OneSchema::aln()?;
+    let path = format!("/tmp/test_buffered_sorted_{}.1aln", std::process::id());
+    let alignments = [(0, 5, 1, 100), (1, 0, 0, 50), (2, 7, 1, 10), (3, 9, 0, 50)];
+    {
+        let file = OneFile::open_write_new(&path, &schema, "aln", false, 1)?;
+        let mut writer = BufferedWriter::with_capacity(file, 2);
+        writer.set_int(0, 100);
+        writer.write_line('t')?;
+        let mut writer = {
+            let file = writer.into_inner()?;
+            BufferedWriter::with_order(file, ObjectOrder::alignments_by_target())?
+        };
+        // The group and its skeleton keep their place before the alignments
+        writer.write_line('g')?;
+        writer.write_bytes('S', b"ctg")?;
+        writer.set_int(0, 1000);
+        writer.write_line('C')?;
+        for (query, start, target, target_start) in alignments {
+            for (f, v) in [query, start, start + 10, target, target_start, target_start + 10]
+                .into_iter()
+                .enumerate()
+            {
+                writer.set_int(f, v);
+            }
+            writer.write_line('A')?;
+            writer.set_int(0, query);
+            writer.write_line('D')?;
+        }
+        writer.close()?;
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let data: Vec<&str> = text
+        .lines()
+        .filter(|l| l.as_bytes()[0].is_ascii_alphabetic())
+        .collect();
+    assert_eq!(
+        data,
+        [
+            "t 100", "g", "S 3 ctg", "C 1000", "A 1 0 10 0 50 60", "D 1", "A 3 9 19 0 50 60", "D 3",
+            "A 2 7 17 1 10 20", "D 2", "A 0 5 15 1 100 110", "D 0"
+        ]
+    );
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_object_order_checked_against_schema() -> Result<()> {
+    let schema = OneSchema::aln()?;
+    let path = format!("/tmp/test_buffered_order_schema_{}.1aln", std::process::id());
+    let open = || OneFile::open_write_new(&path, &schema, "aln", true, 1);
+
+    let not_object = ObjectOrder::ByInts { object: 'D', fields: vec![0] };
+    let not_int = ObjectOrder::ByInts { object: 'A', fields: vec![6] };
+    let not_string = ObjectOrder::ByString { object: 'A', line: 'D' };
+    for order in [not_object, not_int, not_string] {
+        let err = BufferedWriter::with_order(open()?, order).err().unwrap();
+        assert!(matches!(err, OneError::SchemaError(_)), "{}", err);
+    }
+    let by_cigar = ObjectOrder::ByString { object: 'A', line: 'Z' };
+    assert!(BufferedWriter::with_order(open()?, by_cigar).is_ok());
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
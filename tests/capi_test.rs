@@ -0,0 +1,87 @@
+#![cfg(feature = "capi")]
+
+use onecode::capi::*;
+use std::ffi::{CStr, CString};
+
+fn take_string(s: *mut std::ffi::c_char) -> String {
+    assert!(!s.is_null());
+    let owned = unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned();
+    unsafe { onecode_string_free(s) };
+    owned
+}
+
+#[test]
+fn test_capi_seq_reader() {
+    let path = CString::new("ONEcode/TEST/small.seq").unwrap();
+    unsafe {
+        let reader = onecode_seq_open(path.as_ptr());
+        assert!(!reader.is_null());
+        assert_eq!(onecode_seq_count(reader), 10);
+        assert_eq!(take_string(onecode_seq_name(reader, 0)), "seq1");
+
+        let name = CString::new("seq1").unwrap();
+        assert_eq!(onecode_seq_id(reader, name.as_ptr()), 0);
+
+        let mut len = 0usize;
+        let data = onecode_seq_fetch(reader, 0, &mut len);
+        assert!(!data.is_null());
+        assert_eq!(len as i64, onecode_seq_length(reader, 0));
+        onecode_bytes_free(data, len);
+
+        assert!(onecode_seq_fetch(reader, 100, &mut len).is_null());
+        assert!(!onecode_last_error().is_null());
+        onecode_seq_free(reader);
+    }
+}
+
+#[test]
+fn test_capi_gdb_and_errors() {
+    let path = CString::new("data/test.1aln").unwrap();
+    unsafe {
+        let gdb = onecode_gdb_from_alignment(path.as_ptr(), 1);
+        assert!(!gdb.is_null());
+        assert_eq!(onecode_gdb_num_scaffolds(gdb), 9);
+        assert!(take_string(onecode_gdb_scaffold_name(gdb, 0)).contains("gi|568815592"));
+        assert_eq!(onecode_gdb_num_contigs(gdb, 0), 1);
+
+        let (mut offset, mut len) = (-1i64, -1i64);
+        assert!(onecode_gdb_contig(gdb, 0, 0, &mut offset, &mut len));
+        assert_eq!(len, onecode_gdb_scaffold_length(gdb, 0));
+        assert!(!onecode_gdb_contig(gdb, 0, 1, &mut offset, &mut len));
+        onecode_gdb_free(gdb);
+
+        let missing = CString::new("/nonexistent.1gdb").unwrap();
+        assert!(onecode_gdb_open(missing.as_ptr()).is_null());
+        assert!(!onecode_last_error().is_null());
+        assert_eq!(onecode_gdb_num_scaffolds(std::ptr::null()), -1);
+    }
+}
+
+#[test]
+fn test_capi_aln_to_paf() {
+    let aln = CString::new("data/test.1aln").unwrap();
+    let out_path = format!("/tmp/test_capi_paf_{}.paf", std::process::id());
+    let out = CString::new(out_path.clone()).unwrap();
+    unsafe {
+        assert_eq!(onecode_aln_to_paf(aln.as_ptr(), out.as_ptr()), 72);
+        assert_eq!(onecode_aln_to_paf(std::ptr::null(), out.as_ptr()), -1);
+    }
+    let paf = std::fs::read_to_string(&out_path).unwrap();
+    assert_eq!(paf.lines().count(), 72);
+    std::fs::remove_file(&out_path).ok();
+}
+
+#[test]
+fn test_capi_export_fasta() {
+    let seq = CString::new("ONEcode/TEST/small.seq").unwrap();
+    let out_path = format!("/tmp/test_capi_fasta_{}.fa", std::process::id());
+    let out = CString::new(out_path.clone()).unwrap();
+    unsafe {
+        assert_eq!(onecode_export_fasta(seq.as_ptr(), out.as_ptr(), 60), 10);
+        assert_eq!(onecode_export_fasta(std::ptr::null(), out.as_ptr(), 60), -1);
+    }
+    let fasta = std::fs::read_to_string(&out_path).unwrap();
+    assert!(fasta.starts_with(">seq1\n"));
+    assert!(fasta.lines().all(|l| l.len() <= 60));
+    std::fs::remove_file(&out_path).ok();
+}
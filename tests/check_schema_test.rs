@@ -0,0 +1,73 @@
+//! Tests for checking an open file against a schema
+
+use onecode::{LineTypeMismatch, OneError, OneFile, OneSchema, Result};
+
+/// Write a binary file with 'T' and 'L' lines but no 'X' lines
+fn write_file(path: &str) -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\nD L 1 8 INT_LIST\nD X 1 4 REAL\n")?;
+    let mut file = OneFile::open_write_new(path, &schema, "tst", true, 1)?;
+    file.set_int(0, 1);
+    file.write_line('T', 0, None);
+    file.write_int_list('L', &[1, 2])?;
+    file.close();
+    Ok(())
+}
+
+fn mismatched_types(err: OneError) -> Vec<char> {
+    match err {
+        OneError::SchemaMismatch { mismatches, .. } => mismatches.iter().map(|m| m.line_type).collect(),
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn test_check_schema() -> Result<()> {
+    let path = format!("/tmp/test_check_schema_{}.1tst", std::process::id());
+    write_file(&path)?;
+    let file = OneFile::open_read(&path, None, None, 1)?;
+
+    let same = OneSchema::from_text("P 3 tst\nO T 1 3 INT\nD L 1 8 INT_LIST\nD X 1 4 REAL\n")?;
+    file.check_schema(&same, false)?;
+    file.check_schema(&same, true)?;
+
+    // 'X' has no lines and 'Y' is not in the file, so only strict checks see them
+    let other = OneSchema::from_text(
+        "P 3 tst\nO T 1 4 REAL\nD L 1 8 INT_LIST\nD X 1 3 INT\nD Y 1 3 INT\n",
+    )?;
+    let err = file.check_schema(&other, false).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Schema mismatch: line type 'T': expected `O T REAL`, found `O T INT`"
+    );
+    let report = err.detailed_report();
+    assert_eq!(report.expected.as_deref(), Some("O T REAL"));
+    assert_eq!(report.found.as_deref(), Some("O T INT"));
+    assert!(report.help.is_some());
+
+    let err = file.check_schema(&other, true).err().unwrap();
+    assert_eq!(mismatched_types(err.clone()), ['T', 'X', 'Y']);
+    if let OneError::SchemaMismatch { path: None, mismatches } = err {
+        assert_eq!(
+            mismatches[2],
+            LineTypeMismatch {
+                line_type: 'Y',
+                expected: "D Y INT".to_string(),
+                found: "(undefined)".to_string(),
+            }
+        );
+    }
+
+    // Line types only the file defines are fine
+    file.check_schema(&OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?, true)?;
+
+    // Line types are ASCII, and ONElib would exit on any other
+    let err = OneSchema::from_text("P 3 tst\nO T 1 3 INT\nD é 1 3 INT\n").err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(ref msg) if msg.contains("'é'")));
+
+    let foreign = OneSchema::from_text("P 3 foo\nO F 1 3 INT\n")?;
+    let err = file.check_schema(&foreign, false).err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(ref msg) if msg.contains("tst not found")));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
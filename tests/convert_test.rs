@@ -0,0 +1,165 @@
+//! Tests for converting alignment files
+
+use onecode::convert::{aln_to_paf, aln_to_paf_with_cigar, transcode};
+use onecode::{AlnReader, OneError, OneFile, OneSchema, Record, Result, SequenceStore};
+
+#[test]
+fn test_aln_to_paf() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, Some("aln"), 1)?;
+    let mut paf = Vec::new();
+    assert_eq!(aln_to_paf(&mut file, &mut paf)?, 72);
+    let paf = String::from_utf8(paf).unwrap();
+    let records: Vec<Vec<&str>> = paf.lines().map(|l| l.split('\t').collect()).collect();
+    assert_eq!(records.len(), 72);
+
+    // A 0 0 3341 1 0 3340 with 45 differences
+    let first = &records[0];
+    assert_eq!(first.len(), 14);
+    assert_eq!(first[0], "gi|568815592:31353871-31357211");
+    assert_eq!(first[1..5], ["3341", "0", "3341", "+"]);
+    assert_eq!(first[5], "gi|568815529:2834231-2837570");
+    assert_eq!(first[6..12], ["3340", "0", "3340", "3318", "3340", "255"]);
+    assert_eq!(first[12..], ["dv:f:0.0067", "id:f:0.9933"]);
+
+    // Reverse alignments are reported on the forward strand of the target
+    let alignments: Vec<_> = AlnReader::open("data/test.1aln")?.collect();
+    for (record, aln) in records.iter().zip(&alignments) {
+        assert_eq!(record[4], if aln.reverse { "-" } else { "+" });
+        let target_len: i64 = record[6].parse().unwrap();
+        let (start, end): (i64, i64) = (record[7].parse().unwrap(), record[8].parse().unwrap());
+        assert!(0 <= start && start <= end && end <= target_len);
+        assert_eq!(end - start, aln.target_end - aln.target_start);
+    }
+    Ok(())
+}
+
+/// Sequences held in memory, by scaffold name
+struct Scaffolds(Vec<(&'static str, Vec<u8>)>);
+
+impl SequenceStore for Scaffolds {
+    fn len(&self, name: &str) -> Result<usize> {
+        self.0
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, s)| s.len())
+            .ok_or_else(|| OneError::SequenceNotFound(name.to_string()))
+    }
+
+    fn fetch(&mut self, name: &str, range: std::ops::Range<usize>) -> Result<Vec<u8>> {
+        self.len(name)?;
+        let (_, seq) = self.0.iter().find(|(n, _)| *n == name).unwrap();
+        Ok(seq[range].to_vec())
+    }
+}
+
+#[test]
+fn test_aln_to_paf_with_cigar() -> Result<()> {
+    // Query "q", target "t" after a 3-base gap, and "r" holding the reverse
+    // complement of the target, aligned with trace points every 4 bases
+    let path = format!("/tmp/test_paf_cigar_{}.1aln", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &OneSchema::aln()?, "aln", true, 1)?;
+    file.set_int(0, 4);
+    file.write_line('t', 0, None);
+    file.write_line('g', 0, None);
+    for (name, gap, len) in [("q", 0, 14), ("t", 3, 13), ("r", 0, 13)] {
+        file.write_string('S', name)?;
+        if gap > 0 {
+            file.set_int(0, gap);
+            file.write_line('G', 0, None);
+        }
+        file.set_int(0, len);
+        file.write_line('C', 0, None);
+    }
+    for (target, reverse) in [(1, false), (2, true)] {
+        for (field, value) in [0, 2, 14, target, 1, 13].into_iter().enumerate() {
+            file.set_int(field, value);
+        }
+        file.write_line('A', 0, None);
+        if reverse {
+            file.write_line('R', 0, None);
+        }
+        file.set_int(0, 3);
+        file.write_line('D', 0, None);
+        file.write_int_list('T', &[2, 3, 4, 3])?;
+        file.write_int_list('X', &[0, 1, 1, 1])?;
+    }
+    file.finalize()?;
+
+    let scaffolds = || {
+        Scaffolds(vec![
+            ("q", b"ggACGTTCGAACGT".to_vec()),
+            ("t", b"nnncacgtcgatcgta".to_vec()),
+            ("r", b"tacgatcgacgtg".to_vec()),
+        ])
+    };
+    let mut file = OneFile::open_read(&path, None, Some("aln"), 1)?;
+    let mut paf = Vec::new();
+    assert_eq!(
+        aln_to_paf_with_cigar(&mut file, &mut scaffolds(), &mut scaffolds(), &mut paf)?,
+        2
+    );
+    let paf = String::from_utf8(paf).unwrap();
+    let tags = "dv:f:0.2308\tid:f:0.7692\tNM:i:3\tcg:Z:3=1I4=1X3=1D";
+    assert_eq!(
+        paf.lines().collect::<Vec<_>>(),
+        [
+            format!("q\t14\t2\t14\t+\tt\t16\t4\t16\t10\t13\t255\t{}", tags),
+            format!("q\t14\t2\t14\t-\tr\t13\t0\t12\t10\t13\t255\t{}", tags),
+        ]
+    );
+
+    // The sequences must cover the skeletons
+    let mut short = Scaffolds(vec![("q", b"ggACGTTCGAACGT".to_vec())]);
+    let result = aln_to_paf_with_cigar(&mut file, &mut scaffolds(), &mut short, Vec::new());
+    assert!(matches!(result, Err(OneError::SequenceNotFound(_))));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+fn records(path: &str) -> Result<Vec<(Record, Option<String>)>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let mut records = Vec::new();
+    while let Some(record) = file.read_record() {
+        records.push((record, file.read_comment()));
+    }
+    Ok(records)
+}
+
+#[test]
+fn test_transcode_round_trip() -> Result<()> {
+    let pid = std::process::id();
+    let ascii = format!("/tmp/test_transcode_{}.aln", pid);
+    let binary = format!("/tmp/test_transcode_{}.1aln", pid);
+    let expected = records("data/test.1aln")?;
+
+    for nthreads in [1, 3] {
+        let n = transcode("data/test.1aln", &ascii, false, nthreads)?;
+        assert_eq!(n as usize, expected.len());
+        assert_eq!(records(&ascii)?, expected);
+
+        let header = OneFile::open_read(&ascii, None, None, 1)?;
+        let source = OneFile::open_read("data/test.1aln", None, None, 1)?;
+        let provenance = header.provenance();
+        assert_eq!(provenance[..provenance.len() - 1], source.provenance()[..]);
+        assert_eq!(provenance.last().unwrap().program, "onecode");
+        assert_eq!(header.get_references().len(), source.get_references().len());
+        assert_eq!(header.stats('A')?, source.stats('A')?);
+        drop(header);
+
+        // Back to binary, in slices of the 72 'A' objects when threaded
+        transcode(&ascii, &binary, true, 1)?;
+        assert_eq!(records(&binary)?, expected);
+        transcode("data/test.1aln", &binary, true, nthreads)?;
+        assert_eq!(records(&binary)?, expected);
+        let mut file = OneFile::open_read(&binary, None, None, 1)?;
+        assert_eq!(file.stats('A')?, source.stats('A')?);
+        file.goto('A', 72)?;
+        assert_eq!(file.read_line(), 'A');
+    }
+
+    assert!(transcode("/nonexistent.1aln", &binary, true, 1).is_err());
+    std::fs::remove_file(&ascii).ok();
+    std::fs::remove_file(&binary).ok();
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Tests for the coordinate convention and its conversions
+
+use onecode::convert::aln_to_paf;
+use onecode::gdb::{from_agp, to_agp};
+use onecode::{
+    AlnReader, Coord, CoordinateMapper, GdbIndex, GdbReader, OneError, OneFile, RegionIndex,
+    Result, Span,
+};
+
+#[test]
+fn test_conversions() -> Result<()> {
+    assert_eq!(Coord::from_one_based(1)?.to_zero_based(), 0);
+    assert_eq!(Coord::from_zero_based(41)?.to_one_based(), 42);
+    assert_eq!(Coord::from_one_based(42)?, Coord::from_zero_based(41)?);
+    assert!(Coord::from_one_based(0).is_err());
+    assert!(Coord::from_zero_based(-1).is_err());
+
+    // samtools chr1:101-200 is BED 100-200
+    let span = Span::from_one_based(101, 200)?;
+    assert_eq!(span, Span::new(100, 200)?);
+    assert_eq!(span.len(), 100);
+    assert_eq!(span.to_one_based(), (101, 200));
+    assert_eq!(span.to_zero_based(), (100, 200));
+
+    // A single base, and the empty interval before it
+    assert_eq!(Span::from_one_based(5, 5)?.len(), 1);
+    assert!(Span::from_one_based(5, 4)?.is_empty());
+    assert!(Span::from_one_based(0, 10).is_err());
+    assert!(Span::from_one_based(5, 3).is_err());
+    assert!(Span::new(10, 5).is_err());
+    assert!(Span::new(-1, 5).is_err());
+
+    assert!(Span::new(0, 10)?.overlaps(&Span::new(9, 20)?));
+    assert!(!Span::new(0, 10)?.overlaps(&Span::new(10, 20)?));
+    Ok(())
+}
+
+#[test]
+fn test_agp_positions() -> Result<()> {
+    let agp = "chr1\t1\t10\t1\tW\tc1\t1\t10\t+\n\
+               chr1\t11\t15\t2\tN\t5\tscaffold\tyes\tunspecified\n\
+               chr1\t16\t20\t3\tW\tc2\t1\t5\t+\n";
+    let gdb = from_agp(agp.as_bytes())?;
+    let scaffold = &gdb.scaffolds()[0];
+    assert_eq!(
+        (scaffold.contigs[1].offset, scaffold.contigs[1].len),
+        (15, 5)
+    );
+    assert_eq!((scaffold.gaps[0].offset, scaffold.gaps[0].len), (10, 5));
+
+    let mut out = Vec::new();
+    to_agp(&gdb, &mut out)?;
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("chr1\t16\t20\t3\tW"));
+
+    // Position 0 does not exist in 1-based coordinates
+    assert!(from_agp("chr1\t0\t10\t1\tW\tc1\t1\t11\t+\n".as_bytes()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_region_queries_are_checked() -> Result<()> {
+    let path = "data/test.1aln";
+    let index = RegionIndex::open(path)?;
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let name = GdbReader::from_alignment(&mut file, 1)?.scaffolds()[0]
+        .name
+        .clone();
+    assert!(index.overlapping(&name, 0, 1000).is_ok());
+    assert!(matches!(
+        index.overlapping(&name, 1000, 10),
+        Err(OneError::InvalidFormat(_))
+    ));
+    assert!(matches!(
+        index.overlapping(&name, -1, 10),
+        Err(OneError::InvalidFormat(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_mapper_matches_paf() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut paf = Vec::new();
+    aln_to_paf(&mut file, &mut paf)?;
+    let paf = String::from_utf8(paf).unwrap();
+    let index = file.gdb_index()?.clone();
+    let mapper = CoordinateMapper::new(&index);
+
+    // Both ends of every alignment as ALNtoPAF places them, and back
+    let alignments: Vec<_> = AlnReader::open("data/test.1aln")?.collect();
+    assert!(alignments.iter().any(|aln| aln.reverse));
+    for (line, aln) in paf.lines().zip(&alignments) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let query = Span::new(aln.query_start, aln.query_end)?;
+        let (name, span) = mapper.span_to_scaffold(aln.query_id, query, false)?;
+        assert_eq!(
+            [name, &span.start.to_string(), &span.end.to_string()],
+            [0, 2, 3].map(|i| fields[i])
+        );
+        let target = Span::new(aln.target_start, aln.target_end)?;
+        let (name, span) = mapper.span_to_scaffold(aln.target_id, target, aln.reverse)?;
+        assert_eq!(
+            [name, &span.start.to_string(), &span.end.to_string()],
+            [5, 7, 8].map(|i| fields[i])
+        );
+
+        let (contig, pos) = mapper.to_contig(name, span.start)?.unwrap();
+        assert_eq!(contig, aln.target_id);
+        let expected = if aln.reverse {
+            index.contig_length(contig).unwrap() - aln.target_end
+        } else {
+            aln.target_start
+        };
+        assert_eq!(pos, expected);
+    }
+
+    assert!(matches!(
+        mapper.to_scaffold(-1, 0, false),
+        Err(OneError::InvalidFormat(_))
+    ));
+    assert!(matches!(
+        mapper.to_scaffold(0, 1 << 40, false),
+        Err(OneError::InvalidFormat(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_mapper_gaps() -> Result<()> {
+    // s1 is a 5-base gap, contig 0 of 10 bases and a 2-base gap
+    let agp = "s1\t1\t5\t1\tN\t5\tscaffold\tyes\tunspecified\n\
+               s1\t6\t15\t2\tW\tctgA\t11\t20\t-\n\
+               s1\t16\t17\t3\tN\t2\tscaffold\tyes\tunspecified\n\
+               s2\t1\t3\t1\tW\tctgB\t1\t3\t+\n";
+    let index = GdbIndex::new(&from_agp(agp.as_bytes())?);
+    let mapper = CoordinateMapper::new(&index);
+    assert_eq!(mapper.to_scaffold(0, 0, false)?, ("s1", 5));
+    assert_eq!(mapper.to_scaffold(0, 10, false)?, ("s1", 15));
+    assert_eq!(mapper.to_scaffold(0, 3, true)?, ("s1", 12));
+    assert_eq!(mapper.to_scaffold(1, 3, false)?, ("s2", 3));
+
+    assert_eq!(mapper.to_contig("s1", 4)?, None);
+    assert_eq!(mapper.to_contig("s1", 5)?, Some((0, 0)));
+    assert_eq!(mapper.to_contig("s1", 14)?, Some((0, 9)));
+    assert_eq!(mapper.to_contig("s1", 15)?, None);
+    assert_eq!(mapper.to_contig("s2", 0)?, Some((1, 0)));
+    assert!(matches!(
+        mapper.to_contig("s1", 17),
+        Err(OneError::InvalidFormat(_))
+    ));
+    assert!(matches!(
+        mapper.to_contig("s3", 0),
+        Err(OneError::SequenceNotFound(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,307 @@
+//! Pass-through copying with copy_line_to() across ASCII/binary formats
+
+use onecode::{OneError, OneFile, OneSchema, Result, WriteOptions};
+
+/// Render every data line of a file so two files can be compared
+fn dump(path: &str) -> Result<Vec<String>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let mut lines = Vec::new();
+    loop {
+        let line_type = file.read_line();
+        let text = match line_type {
+            '\0' => break,
+            'A' => format!("A {:?}", (0..6).map(|i| file.int(i)).collect::<Vec<_>>()),
+            'T' | 'X' | 'M' => format!("{} {:?}", line_type, file.int_list().unwrap_or(&[])),
+            'S' if file.file_type().as_deref() == Some("seq") => {
+                format!("S {}", String::from_utf8_lossy(file.dna_char().unwrap_or(&[])))
+            }
+            'S' | 'I' => format!("{} {}", line_type, file.string().unwrap_or("")),
+            'C' | 'G' | 'D' | 't' | 'B' => format!("{} {}", line_type, file.int(0)),
+            other => other.to_string(),
+        };
+        lines.push(text);
+    }
+    Ok(lines)
+}
+
+fn copy(input: &str, output: &str, binary: bool) -> Result<()> {
+    let mut reader = OneFile::open_read(input, None, None, 1)?;
+    let mut writer = OneFile::open_write_from(output, &reader, binary, 1)?;
+    while reader.read_line() != '\0' {
+        reader.copy_line_to(&mut writer)?;
+    }
+    writer.close();
+    Ok(())
+}
+
+#[test]
+fn test_copy_matrix() -> Result<()> {
+    let inputs = ["ONEcode/TEST/small.seq", "ONEcode/TEST/t2.seq", "data/test.1aln"];
+    for (n, input) in inputs.iter().enumerate() {
+        let expected = dump(input)?;
+        for first_binary in [false, true] {
+            for second_binary in [false, true] {
+                let first = format!("/tmp/test_copy_{}_{}_a", std::process::id(), n);
+                let second = format!("/tmp/test_copy_{}_{}_b", std::process::id(), n);
+
+                copy(input, &first, first_binary)?;
+                copy(&first, &second, second_binary)?;
+                assert_eq!(dump(&first)?, expected, "{} -> binary={}", input, first_binary);
+                assert_eq!(
+                    dump(&second)?,
+                    expected,
+                    "{} -> binary={} -> binary={}",
+                    input,
+                    first_binary,
+                    second_binary
+                );
+
+                std::fs::remove_file(&first).ok();
+                std::fs::remove_file(&second).ok();
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_copy_preserves_comments() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let input = format!("/tmp/test_copy_comment_{}.1tst", std::process::id());
+    let output = format!("/tmp/test_copy_comment_{}_out.1tst", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&input, &schema, "tst", false, 1)?;
+        writer.set_int(0, 3);
+        writer.write_line('T', 0, None);
+        writer.write_comment("hello")?;
+        writer.close();
+    }
+    // ASCII output: ONElib cannot yet read back comment lines in binary files
+    copy(&input, &output, false)?;
+
+    let mut reader = OneFile::open_read(&output, None, None, 1)?;
+    assert_eq!(reader.read_line(), 'T');
+    assert_eq!(reader.int(0), 3);
+    assert_eq!(reader.read_comment().as_deref(), Some("hello"));
+
+    std::fs::remove_file(&input).ok();
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+/// A filter step that only needs to look at the reader
+fn copy_if_even(reader: &OneFile, writer: &mut OneFile) -> Result<()> {
+    if reader.int(0) % 2 == 0 {
+        reader.copy_line_to(writer)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_copy_line_to_filter() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let input = format!("/tmp/test_copy_filter_{}.1tst", std::process::id());
+    let output = format!("/tmp/test_copy_filter_{}_out.1tst", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&input, &schema, "tst", false, 1)?;
+        for i in 1..=6 {
+            writer.set_int(0, i);
+            writer.write_line('T', 0, None);
+            writer.write_comment(&format!("line {}", i))?;
+        }
+        writer.close();
+    }
+    {
+        let mut reader = OneFile::open_read(&input, None, None, 1)?;
+        let mut writer = OneFile::open_write_from(&output, &reader, false, 1)?;
+        while reader.read_line() != '\0' {
+            copy_if_even(&reader, &mut writer)?;
+        }
+        writer.close();
+    }
+
+    let mut reader = OneFile::open_read(&output, None, None, 1)?;
+    let mut kept = Vec::new();
+    while reader.read_line() != '\0' {
+        kept.push((reader.int(0), reader.read_comment().unwrap_or_default()));
+    }
+    assert_eq!(
+        kept,
+        [(2, "line 2".to_string()), (4, "line 4".to_string()), (6, "line 6".to_string())]
+    );
+
+    std::fs::remove_file(&input).ok();
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_copy_rejects_undeclared_type() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO U 1 3 INT\n")?;
+    let output = format!("/tmp/test_copy_reject_{}.1tst", std::process::id());
+
+    let mut reader = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let mut writer = OneFile::open_write_new(&output, &schema, "tst", false, 1)?;
+    assert_eq!(reader.read_line(), 'S');
+    assert!(reader.copy_line_to(&mut writer).is_err());
+
+    drop(writer);
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_copy_rejects_mismatched_field_types() -> Result<()> {
+    // Same number of fields, but lists of 8-byte elements rather than bases
+    let schema = OneSchema::from_text("P 3 seq\nO S 1 8 INT_LIST\nD I 1 4 REAL\n")?;
+    let output = format!("/tmp/test_copy_mismatch_{}.1seq", std::process::id());
+
+    let mut reader = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let mut writer = OneFile::open_write_new(&output, &schema, "seq", false, 1)?;
+    assert_eq!(reader.read_line(), 'S');
+    let err = reader.copy_line_to(&mut writer).err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(_)));
+    while reader.read_line() != 'I' {}
+    assert!(matches!(reader.copy_line_to(&mut writer), Err(OneError::SchemaError(_))));
+
+    drop(writer);
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_copy_raw_objects_subset() -> Result<()> {
+    let binary = format!("/tmp/test_raw_{}_in.1seq", std::process::id());
+    let subset = format!("/tmp/test_raw_{}_out.1seq", std::process::id());
+    copy("ONEcode/TEST/small.seq", &binary, true)?;
+
+    // Keep every other sequence, copying whole objects
+    {
+        let mut reader = OneFile::open_read(&binary, None, None, 1)?;
+        let mut writer = OneFile::open_write_from(&subset, &reader, true, 1)?;
+        let mut n = 0;
+        let mut line_type = reader.read_line();
+        while line_type != '\0' {
+            if line_type == 'S' {
+                n += 1;
+                if n % 2 == 1 {
+                    reader.copy_raw_object_to(&mut writer)?;
+                    line_type = reader.read_line();
+                    continue;
+                }
+            }
+            line_type = reader.read_line();
+        }
+        writer.close();
+    }
+
+    let expected: Vec<String> = dump("ONEcode/TEST/small.seq")?
+        .chunks(2)
+        .step_by(2)
+        .flatten()
+        .cloned()
+        .collect();
+    assert_eq!(dump(&subset)?, expected);
+
+    let reader = OneFile::open_read(&subset, None, None, 1)?;
+    assert_eq!(reader.stats('S')?.0, 5);
+
+    std::fs::remove_file(&binary).ok();
+    std::fs::remove_file(&subset).ok();
+    Ok(())
+}
+
+#[test]
+fn test_copy_raw_objects_nested() -> Result<()> {
+    // t2.seq groups S objects into 's' scaffolds; a raw S copy must stop at the next 's'
+    let binary = format!("/tmp/test_raw_nested_{}.1seq", std::process::id());
+    let output = format!("/tmp/test_raw_nested_{}_out.1seq", std::process::id());
+    copy("ONEcode/TEST/t2.seq", &binary, true)?;
+
+    {
+        // The destination's codecs need not match, as lists are encoded afresh
+        let mut reader = OneFile::open_read(&binary, None, None, 1)?;
+        let mut writer = OneFile::open_write_from(&output, &reader, false, 1)?;
+        let mut line_type = reader.read_line();
+        while line_type != '\0' {
+            if line_type == 'S' {
+                reader.copy_raw_object_to(&mut writer)?;
+                line_type = reader.read_line();
+            } else {
+                reader.copy_line_to(&mut writer)?;
+                line_type = reader.read_line();
+            }
+        }
+        writer.close();
+    }
+    assert_eq!(dump(&output)?, dump("ONEcode/TEST/t2.seq")?);
+
+    // ASCII files have no index to delimit objects
+    let mut ascii = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let mut writer = OneFile::open_write_from(&output, &ascii, true, 1)?;
+    ascii.read_line();
+    assert!(ascii.copy_raw_object_to(&mut writer).is_err());
+
+    std::fs::remove_file(&binary).ok();
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+/// Copy the reads of `input` from the `first`th on with copy_raw_object_to()
+/// into a file created with `options`, returning the source's bytes copied
+fn copy_raw_from(input: &str, output: &str, first: i64, options: WriteOptions) -> Result<Vec<u8>> {
+    let mut reader = OneFile::open_read(input, None, None, 1)?;
+    let schema = OneSchema::seq()?;
+    let mut writer = OneFile::create(output, &schema, "seq", options)?;
+    reader.goto('S', first)?;
+    let start = reader.get_current_byte_position() as usize;
+    let mut line_type = reader.read_line();
+    while line_type != '\0' {
+        if line_type == 'S' {
+            reader.copy_raw_object_to(&mut writer)?;
+        }
+        line_type = reader.read_line();
+    }
+    // Past the '\n' that ends the data
+    let end = reader.get_current_byte_position() as usize - 1;
+    writer.finalize()?;
+    Ok(std::fs::read(input).unwrap()[start..end].to_vec())
+}
+
+#[test]
+fn test_copy_raw_objects_bytes() -> Result<()> {
+    // Enough identifiers for ONElib to train a codec for 'I' lists
+    let input = format!("/tmp/test_raw_bytes_{}.1seq", std::process::id());
+    let output = format!("/tmp/test_raw_bytes_{}_out.1seq", std::process::id());
+    let names: Vec<String> = (0..20000).map(|i| format!("read_{:06}", i)).collect();
+    let mut file = OneFile::open_write_new(&input, &OneSchema::seq()?, "seq", true, 1)?;
+    for name in &names {
+        file.write_dna('S', b"acgtacgt")?;
+        file.write_string('I', name)?;
+    }
+    file.finalize()?;
+
+    // The second half goes across as it is, compressed with the source's codec
+    let copied = copy_raw_from(&input, &output, 10001, WriteOptions::default())?;
+    let bytes = std::fs::read(&output).unwrap();
+    let mut file = OneFile::open_read(&output, None, None, 1)?;
+    file.goto('S', 1)?;
+    let start = file.get_current_byte_position() as usize;
+    assert_eq!(&bytes[start..start + copied.len()], &copied[..]);
+    let expected: Vec<String> = names[10000..]
+        .iter()
+        .flat_map(|name| ["S acgtacgt".to_string(), format!("I {}", name)])
+        .collect();
+    assert_eq!(dump(&output)?, expected);
+    assert_eq!(file.stats('I')?, (10000, 11, 110000));
+
+    // Lists the destination won't compress are decoded and written plain
+    let options = WriteOptions::default().compress('I', false);
+    let copied = copy_raw_from(&input, &output, 10001, options)?;
+    assert!(std::fs::read(&output).unwrap().len() > copied.len());
+    assert_eq!(dump(&output)?, expected);
+
+    std::fs::remove_file(&input).ok();
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
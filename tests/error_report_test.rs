@@ -0,0 +1,118 @@
+//! Tests for multi-line error reports
+
+use onecode::gdb::from_agp;
+use onecode::{OneError, OneFile, OneSchema, Result};
+use std::io::Write;
+
+#[test]
+fn test_report_schema_mismatch() -> Result<()> {
+    let path = format!("/tmp/test_report_{}.1seq", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &OneSchema::seq()?, "seq", true, 1)?;
+    file.write_dna('S', b"acgt")?;
+    file.close();
+
+    let wrong = OneSchema::from_text("P 3 seq\nO S 1 6 STRING\nD I 1 6 STRING\n")?;
+    let err = OneFile::open_read(&path, Some(&wrong), Some("seq"), 1).err().unwrap();
+    let report = err.detailed_report();
+    assert_eq!(report.file.as_deref(), Some(path.as_str()));
+    assert_eq!(report.expected.as_deref(), Some("O S STRING"));
+    assert_eq!(report.found.as_deref(), Some("O S DNA"));
+    assert!(report.help.unwrap().contains("different schema"));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_report_open_failures() {
+    let missing = OneFile::open_read("/nonexistent/x.1seq", None, None, 1).err().unwrap();
+    let report = missing.detailed_report();
+    assert!(report.help.unwrap().contains("does not exist"));
+    assert!(missing.detailed_report().to_string().contains("\n  --> /nonexistent/x.1seq"));
+
+    let path = format!("/tmp/test_report_{}.fa.gz", std::process::id());
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b">chr1\nacgt\n").unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+    let err = OneFile::open_read(&path, None, None, 1).err().unwrap();
+    assert!(err.detailed_report().help.unwrap().contains("gzip"));
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_open_error_kinds() -> Result<()> {
+    let dir = format!("/tmp/test_error_kinds_{}", std::process::id());
+    std::fs::create_dir_all(&dir).unwrap();
+    let open = |name: &str, schema: Option<&OneSchema>| {
+        OneFile::open_read(&format!("{}/{}", dir, name), schema, None, 1)
+            .err()
+            .unwrap()
+    };
+
+    // A file type the schema lacks fails before the file is created
+    let path = format!("{}/new.1xyz", dir);
+    let err = OneFile::open_write_new(&path, &OneSchema::seq()?, "xyz", true, 1).err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(ref msg) if msg.contains("xyz not found")));
+    assert!(!std::path::Path::new(&path).exists());
+
+    let err = open("missing.1seq", None);
+    assert!(matches!(err, OneError::Io { kind: std::io::ErrorKind::NotFound, .. }));
+    assert_eq!(err.path(), Some(format!("{}/missing.1seq", dir).as_str()));
+
+    std::fs::write(format!("{}/text.1seq", dir), "hello\n").unwrap();
+    std::fs::write(format!("{}/empty.1seq", dir), "").unwrap();
+    assert!(matches!(open("text.1seq", None), OneError::BadMagic { .. }));
+    assert!(matches!(open("empty.1seq", None), OneError::BadMagic { .. }));
+
+    std::fs::write(format!("{}/header.1seq", dir), "1 3 seq 1 1").unwrap();
+    let err = open("header.1seq", None);
+    assert!(matches!(err, OneError::Truncated { line: Some(1), .. }));
+    assert_eq!(err.detailed_report().line, Some(1));
+
+    // A binary file cut short loses the footer ONElib looks for first
+    let path = format!("{}/cut.1seq", dir);
+    let mut file = OneFile::open_write_new(&path, &OneSchema::seq()?, "seq", true, 1)?;
+    file.write_dna('S', b"acgtacgt")?;
+    file.finalize()?;
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 20]).unwrap();
+    assert!(matches!(open("cut.1seq", None), OneError::Truncated { line: None, .. }));
+    std::fs::write(&path, &bytes).unwrap();
+    OneFile::open_read(&path, None, None, 1)?;
+
+    let err = open("cut.1seq", Some(&OneSchema::aln()?));
+    assert!(matches!(err, OneError::WrongFileType { ref found, .. } if found == "seq"));
+    let wrong = OneSchema::from_text("P 3 seq\nO S 1 6 STRING\n")?;
+    assert!(matches!(open("cut.1seq", Some(&wrong)), OneError::SchemaMismatch { path: Some(_), .. }));
+
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
+
+#[test]
+fn test_report_line_number() {
+    let err = from_agp("chr1\t1\t100\t1\tW\tctg1\t1\t100\t+\nchr1\tx\n".as_bytes())
+        .err()
+        .unwrap();
+    let report = err.detailed_report();
+    assert_eq!(report.line, Some(2));
+    assert_eq!(report.file, None);
+    assert_eq!(report.help, None);
+    assert_eq!(
+        report.to_string(),
+        "error: Invalid file format: AGP line 2: chr1\tx\n  --> line 2"
+    );
+
+    assert!(OneError::ListTooLong("I".to_string())
+        .detailed_report()
+        .help
+        .is_some());
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_miette_help() {
+    use miette::Diagnostic;
+    let err = OneError::DuplicateName("chr1".to_string());
+    assert!(err.help().is_some());
+}
@@ -0,0 +1,241 @@
+//! Tests for FASTA/FASTQ import into and export from .1seq
+
+use onecode::seq::{export_fasta, from_fasta, import_fasta, to_fasta, to_protein, translate};
+use onecode::{
+    DeflinePolicy, ExportOptions, FastaOptions, OneError, OneFile, OneSchema, Result, SeqReader,
+};
+use std::io::Write;
+
+const FASTA: &str = ">chr1 first chromosome\nACGTNN\nacgt\n>chr2\nGGGG\n>chr3 third\nttRa\n";
+
+fn import(name: &str, input: &str, binary: bool, options: &FastaOptions) -> Result<(String, usize)> {
+    let path = format!("/tmp/test_fasta_{}_{}.1seq", std::process::id(), name);
+    let n = import_fasta(input.as_bytes(), &path, binary, options)?;
+    Ok((path, n))
+}
+
+#[test]
+fn test_import_discards_description() -> Result<()> {
+    let (path, n) = import("discard", FASTA, true, &FastaOptions::default())?;
+    assert_eq!(n, 3);
+
+    let mut reader = SeqReader::open(&path)?;
+    assert_eq!(reader.name(0), Some("chr1"));
+    assert_eq!(reader.name(2), Some("chr3"));
+    assert_eq!(reader.sequence(1)?, b"gggg");
+    assert_eq!(reader.length(0), Some(10));
+
+    // Non-acgt runs are recorded as N lines
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let runs: Vec<(i64, char, i64)> = file
+        .lines()
+        .of_type('N')
+        .map(|l| (l.int(0), l.char(1), l.int(2)))
+        .collect();
+    assert_eq!(runs, vec![(4, 'N', 2), (2, 'R', 1)]);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_import_keeps_defline() -> Result<()> {
+    let comment = FastaOptions {
+        defline: DeflinePolicy::Comment,
+        ..Default::default()
+    };
+    let (path, _) = import("comment", FASTA, false, &comment)?;
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let comments: Vec<Option<String>> = file
+        .lines()
+        .of_type('I')
+        .with_comments()
+        .map(|l| l.comment().map(String::from))
+        .collect();
+    assert_eq!(comments[0].as_deref(), Some("chr1 first chromosome"));
+    assert_eq!(comments[1].as_deref(), Some("chr2"));
+    std::fs::remove_file(&path).ok();
+
+    let line = FastaOptions {
+        defline: DeflinePolicy::Line,
+        ..Default::default()
+    };
+    let (path, _) = import("line", FASTA, true, &line)?;
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let deflines: Vec<String> = file
+        .lines()
+        .of_type('D')
+        .map(|l| l.string().unwrap().to_string())
+        .collect();
+    assert_eq!(deflines, vec!["chr1 first chromosome", "chr2", "chr3 third"]);
+    assert_eq!(SeqReader::open(&path)?.name(0), Some("chr1"));
+    std::fs::remove_file(&path).ok();
+
+    Ok(())
+}
+
+#[test]
+fn test_import_unique_ids() -> Result<()> {
+    let input = ">a x\nacgt\n>b\nacgt\n>a y\nacgt\n";
+
+    // Duplicates pass unless uniqueness is requested
+    let (path, n) = import("dups", input, true, &FastaOptions::default())?;
+    assert_eq!(n, 3);
+    std::fs::remove_file(&path).ok();
+
+    let unique = FastaOptions {
+        unique_ids: true,
+        ..Default::default()
+    };
+    let err = import("unique", input, true, &unique).err().unwrap();
+    assert_eq!(err, OneError::DuplicateName("a".to_string()));
+
+    assert!(import("bad", "acgt\n", true, &unique).is_err());
+
+    for name in ["unique", "bad"] {
+        std::fs::remove_file(format!("/tmp/test_fasta_{}_{}.1seq", std::process::id(), name)).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_import_fastq() -> Result<()> {
+    let input = "@r1 first read\nACGTN\n+\nIIII#\n@r2\nggc\n+r2\n!!5\n";
+    let (path, n) = import("fastq", input, false, &FastaOptions::default())?;
+    assert_eq!(n, 2);
+
+    let mut reader = SeqReader::open(&path)?;
+    assert_eq!(reader.name(1), Some("r2"));
+    assert_eq!(reader.sequence(0)?, b"acgtn");
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let quals: Vec<String> = file
+        .lines()
+        .of_type('Q')
+        .map(|l| l.string().unwrap().to_string())
+        .collect();
+    assert_eq!(quals, vec!["IIII#", "!!5"]);
+
+    // Quality must match the sequence length
+    assert!(import("fastq_bad", "@r1\nACGT\n+\nIII\n", true, &FastaOptions::default()).is_err());
+
+    for name in ["fastq", "fastq_bad"] {
+        std::fs::remove_file(format!("/tmp/test_fasta_{}_{}.1seq", std::process::id(), name)).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_from_fasta_gzip() -> Result<()> {
+    let gz_path = format!("/tmp/test_fasta_{}_gz.fa.gz", std::process::id());
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(FASTA.as_bytes()).unwrap();
+    std::fs::write(&gz_path, encoder.finish().unwrap()).unwrap();
+
+    let path = format!("/tmp/test_fasta_{}_gz.1seq", std::process::id());
+    assert_eq!(from_fasta(&gz_path, &path, true)?, 3);
+    let mut reader = SeqReader::open(&path)?;
+    assert_eq!(reader.name(2), Some("chr3"));
+    assert_eq!(reader.sequence(1)?, b"gggg");
+
+    assert!(matches!(from_fasta("/nonexistent.fa", &path, true), Err(OneError::Io { .. })));
+
+    std::fs::remove_file(&gz_path).ok();
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_export_roundtrip() -> Result<()> {
+    let line = FastaOptions {
+        defline: DeflinePolicy::Line,
+        ..Default::default()
+    };
+    let (path, _) = import("export", FASTA, true, &line)?;
+    let mut out = Vec::new();
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(export_fasta(&mut file, &mut out, &ExportOptions { line_width: 4 })?, 3);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        ">chr1 first chromosome\nacgt\nNNac\ngt\n>chr2\ngggg\n>chr3 third\nttRa\n"
+    );
+    std::fs::remove_file(&path).ok();
+
+    // FASTQ records come back with their qualities, named by identifier
+    let fastq = "@r1 first read\nACGTN\n+\nIIII#\n@r2\nggc\n+r2\n!!5\n";
+    let (path, _) = import("export_fastq", fastq, false, &FastaOptions::default())?;
+    let out_path = format!("/tmp/test_fasta_{}_export.fq", std::process::id());
+    assert_eq!(to_fasta(&path, &out_path, &ExportOptions::default())?, 2);
+    assert_eq!(
+        std::fs::read_to_string(&out_path).unwrap(),
+        "@r1\nacgtN\n+\nIIII#\n@r2\nggc\n+\n!!5\n"
+    );
+
+    std::fs::remove_file(&path).ok();
+    std::fs::remove_file(&out_path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_non_utf8_identifier() -> Result<()> {
+    let path = format!("/tmp/test_fasta_{}_utf8.1seq", std::process::id());
+    {
+        let mut file = OneFile::open_write_new(&path, &OneSchema::seq()?, "seq", true, 1)?;
+        file.write_dna('S', b"acgt")?;
+        let mut name = b"chr\xff1".to_vec();
+        file.write_line('I', 5, Some(name.as_mut_ptr() as *mut std::ffi::c_void));
+        file.finalize()?;
+    }
+
+    // Names are neither replaced nor dropped, but reported
+    let err = SeqReader::open(&path).err().unwrap();
+    assert!(matches!(err, OneError::InvalidUtf8(_)), "{}", err);
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let err = export_fasta(&mut file, Vec::new(), &ExportOptions::default()).err().unwrap();
+    assert!(matches!(err, OneError::InvalidUtf8(_)), "{}", err);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_translate() -> Result<()> {
+    assert_eq!(translate(b"atgaaatagc", 1)?, "MK*");
+    assert_eq!(translate(b"atgaaatagc", 2)?, "*NS");
+    assert_eq!(translate(b"ATGNAA", 1)?, "MX");
+    // The reverse complement of gctatttcat is atgaaatagc
+    assert_eq!(translate(b"gctatttcat", -1)?, "MK*");
+    assert_eq!(translate(b"gctatttcat", -3)?, "EI");
+    assert_eq!(translate(b"gg", 3)?, "");
+    assert!(translate(b"acgt", 0).is_err());
+    assert!(translate(b"acgt", 4).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_to_protein() -> Result<()> {
+    let (path, _) = import("protein", FASTA, true, &FastaOptions::default())?;
+    let out_path = format!("/tmp/test_fasta_{}_protein.1pro", std::process::id());
+    assert_eq!(to_protein(&path, &out_path, &[1, -2], false)?, 6);
+
+    let mut file = OneFile::open_read(&out_path, None, Some("pro"), 1)?;
+    let proteins: Vec<(String, String, i64, i64)> = file
+        .objects('S')
+        .map(|p| {
+            let frame = p.lines_of_type('F').next().unwrap();
+            let id = p.lines_of_type('I').next().unwrap();
+            let protein = p.line.string().unwrap().to_string();
+            (protein, id.string().unwrap().to_string(), frame.int(0), frame.int(1))
+        })
+        .collect();
+    // acgtNNacgt: the N run is put back, so its codon translates to X
+    assert_eq!(proteins[0], ("TXT".to_string(), "chr1".to_string(), 0, 1));
+    assert_eq!(proteins[1], ("RXR".to_string(), "chr1".to_string(), 0, -2));
+    assert_eq!(proteins[2].0, "G");
+    assert_eq!((proteins[5].0.as_str(), proteins[5].2, proteins[5].3), ("X", 2, -2));
+
+    assert!(to_protein(&path, &out_path, &[7], false).is_err());
+    std::fs::remove_file(&path).ok();
+    std::fs::remove_file(&out_path).ok();
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Differential tests of the Rust list decoders against the C library
+
+#![cfg(feature = "fastpath")]
+
+use onecode::fastpath::{decompact_int_list, unpack_2bit};
+use onecode::{OneFile, OneSchema, Result};
+use std::ffi::{c_char, c_int};
+
+extern "C" {
+    fn Uncompress_DNA(s: *mut c_char, len: c_int, t: *mut c_char) -> c_int;
+}
+
+/// Deterministic pseudo-random numbers
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 11
+    }
+}
+
+#[test]
+fn test_unpack_2bit_matches_c() {
+    let mut rng = Lcg(1);
+    for len in (0..40).chain([1000, 4097]) {
+        let mut packed: Vec<u8> = (0..len / 4 + 1).map(|_| rng.next() as u8).collect();
+        let mut expected = vec![0u8; len];
+        unsafe {
+            Uncompress_DNA(packed.as_mut_ptr() as *mut c_char, len as c_int, expected.as_mut_ptr() as *mut c_char);
+        }
+        let mut actual = vec![0u8; len];
+        unpack_2bit(&packed, &mut actual);
+        assert_eq!(actual, expected, "length {}", len);
+    }
+}
+
+#[test]
+fn test_decompact_int_list() {
+    // First value in full, then differences 3, -1, 200 in two bytes each
+    let mut list = vec![100i64, 0, 0, 0];
+    let diffs: Vec<u8> = [3i16, -1, 200].iter().flat_map(|d| d.to_ne_bytes()).collect();
+    unsafe {
+        let bytes = std::slice::from_raw_parts_mut(list.as_mut_ptr() as *mut u8, 32);
+        bytes[8..8 + diffs.len()].copy_from_slice(&diffs);
+    }
+    decompact_int_list(&mut list, 2);
+    assert_eq!(list, vec![100, 103, 102, 302]);
+}
+
+#[test]
+fn test_binary_lists_round_trip() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO S 1 3 DNA\nD L 1 8 INT_LIST\n")?;
+    let path = format!("/tmp/test_fastpath_{}.1tst", std::process::id());
+    let mut rng = Lcg(7);
+
+    // Enough lines for the integer list codec to be trained part way through
+    let mut seqs = Vec::new();
+    let mut lists = Vec::new();
+    for i in 0..3000 {
+        let len = (rng.next() % 300) as usize;
+        seqs.push((0..len).map(|_| b"acgt"[(rng.next() % 4) as usize]).collect::<Vec<u8>>());
+        let scale = [10, 1000, 1 << 40][i % 3];
+        let n = 1 + (rng.next() % 50) as usize;
+        lists.push((0..n).map(|_| (rng.next() % scale) as i64 - scale as i64 / 2).collect::<Vec<i64>>());
+    }
+
+    let mut file = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+    for (seq, list) in seqs.iter().zip(&lists) {
+        file.write_dna('S', seq)?;
+        file.write_int_list('L', list)?;
+    }
+    file.close();
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    for (seq, list) in seqs.iter().zip(&lists) {
+        assert_eq!(file.read_line(), 'S');
+        assert_eq!(file.dna_char().unwrap(), &seq[..]);
+        assert_eq!(file.read_line(), 'L');
+        assert_eq!(file.int_list().unwrap(), &list[..]);
+    }
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
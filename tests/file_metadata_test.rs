@@ -0,0 +1,40 @@
+//! Tests for the size, modification time and descriptor of an open file
+
+use onecode::{OneFile, OneSchema, Result};
+
+#[test]
+fn test_file_metadata_of_reader() -> Result<()> {
+    let path = "data/test.1aln";
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let metadata = file.file_metadata()?;
+    let expected = std::fs::metadata(path).unwrap();
+    assert_eq!(metadata.len, expected.len());
+    assert_eq!(metadata.modified, expected.modified().ok());
+    assert!(metadata.is_file);
+
+    // The descriptor is the open file, and still belongs to it afterwards
+    #[cfg(target_os = "linux")]
+    {
+        let link = std::fs::read_link(format!("/proc/self/fd/{}", metadata.fd)).unwrap();
+        assert_eq!(link, std::fs::canonicalize(path).unwrap());
+    }
+    assert_ne!(file.read_line(), '\0');
+    assert_eq!(file.file_metadata()?, metadata);
+    Ok(())
+}
+
+#[test]
+fn test_file_metadata_of_memory_and_written_files() -> Result<()> {
+    let bytes = std::fs::read("ONEcode/TEST/small.seq").unwrap();
+    let file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+    let metadata = file.file_metadata()?;
+    assert_eq!(metadata.len, bytes.len() as u64);
+    assert!(metadata.is_file);
+
+    let path = format!("/tmp/test_file_metadata_{}.1seq", std::process::id());
+    let writer = OneFile::open_write_new(&path, &OneSchema::seq()?, "seq", true, 1)?;
+    assert!(writer.file_metadata()?.is_file);
+    writer.finalize()?;
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! Tests for copying a file with a predicate over its objects
+
+use onecode::{Alignment, AlnReader, OneError, OneFile, OneReader, OneSchema, OneWriter, Result};
+
+#[test]
+fn test_filter_alignments() -> Result<()> {
+    let input = "data/test.1aln";
+    let output = format!("/tmp/test_filter_aln_{}.1aln", std::process::id());
+    let mut source = OneFile::open_read(input, None, None, 1)?;
+    let mut out = OneFile::open_write_from(&output, &source, true, 1)?;
+    let kept = source.filter_objects(&mut out, 'A', |aln| aln.number % 3 == 1)?;
+    out.finalize()?;
+
+    // Alignments keep their content but are numbered afresh
+    let renumbered = |(i, aln): (usize, Alignment)| Alignment { object: i as i64 + 1, ..aln };
+    let expected: Vec<_> = AlnReader::open(input)?
+        .with_traces()
+        .enumerate()
+        .filter(|(i, _)| i % 3 == 0)
+        .map(|(_, aln)| aln)
+        .enumerate()
+        .map(renumbered)
+        .collect();
+    let found: Vec<_> = AlnReader::open(&output)?.with_traces().collect();
+    assert_eq!(kept, expected.len() as i64);
+    assert_eq!(found, expected);
+
+    // The skeletons before the alignments are copied whole
+    let file = OneFile::open_read(&output, None, None, 1)?;
+    for line_type in ['g', 'S', 'C'] {
+        assert_eq!(file.stats(line_type)?, source.stats(line_type)?);
+    }
+    assert_eq!(file.stats('A')?.0, kept);
+
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_filter_sequences_with_data_lines() -> Result<()> {
+    let output = format!("/tmp/test_filter_seq_{}.seq", std::process::id());
+    let mut reader = OneReader::open("ONEcode/TEST/small.seq", None, None, 1)?;
+    let mut writer = OneWriter::create_from(&output, &reader, false, 1)?;
+    let kept = reader.filter_objects(&mut writer, 'S', |seq| {
+        seq.line.dna().is_some_and(|dna| dna.len() >= 60)
+    })?;
+    writer.finalize()?;
+
+    let mut file = OneFile::open_read(&output, None, None, 1)?;
+    let mut names = Vec::new();
+    while file.read_line() != '\0' {
+        if file.line_type() == 'I' {
+            names.push(file.string()?.to_string());
+        }
+    }
+    assert_eq!(kept, names.len() as i64);
+    assert_eq!(names[..2], ["seq2", "seq6"]);
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_filter_groups_with_members() -> Result<()> {
+    let input = "data/test.1aln";
+    let output = format!("/tmp/test_filter_groups_{}.1aln", std::process::id());
+    let mut source = OneFile::open_read(input, None, None, 1)?;
+    let mut out = OneFile::open_write_from(&output, &source, true, 1)?;
+    let mut members = 0;
+    let kept = source.filter_objects(&mut out, 'g', |group| {
+        members = group.lines.iter().filter(|l| l.line_type() == 'S').count();
+        false
+    })?;
+    out.finalize()?;
+
+    let file = OneFile::open_read(&output, None, None, 1)?;
+    assert_eq!(kept, 0);
+    assert!(members > 0);
+    assert_eq!(file.stats('g')?.0, 0);
+    assert_eq!(file.stats('S')?.0, 0);
+    assert_eq!(file.stats('A')?, source.stats('A')?);
+
+    let mut out = OneFile::open_write_from(&output, &source, true, 1)?;
+    let err = source.filter_objects(&mut out, 'T', |_| true).err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(_)));
+    drop(out);
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_filter_rejects_mismatched_field_types() -> Result<()> {
+    let output = format!("/tmp/test_filter_mismatch_{}.1seq", std::process::id());
+    let schema = OneSchema::from_text("P 3 seq\nO S 1 8 INT_LIST\nD I 1 6 STRING\n")?;
+    let mut source = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let mut out = OneFile::open_write_new(&output, &schema, "seq", false, 1)?;
+    let err = source.filter_objects(&mut out, 'S', |_| true).err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(_)));
+    drop(out);
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
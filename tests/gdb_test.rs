@@ -0,0 +1,229 @@
+//! Tests for GDB skeleton reading
+
+use onecode::gdb::{from_agp, karyotype, to_agp, to_circos_karyotype, Contig, Gap, Segment};
+use onecode::{DuplicatePolicy, GdbReader, OneError, OneFile, OneSchema, Result};
+
+/// Write a standalone .1gdb with one contig per scaffold
+fn write_gdb(path: &str, scaffolds: &[(&str, i64)]) -> Result<()> {
+    let schema = OneSchema::gdb()?;
+    let mut file = OneFile::open_write_new(path, &schema, "gdb", true, 1)?;
+    for (name, len) in scaffolds {
+        let mut bytes = name.as_bytes().to_vec();
+        file.write_line('S', bytes.len() as i64, Some(bytes.as_mut_ptr() as *mut std::ffi::c_void));
+        file.set_int(0, *len);
+        file.write_line('C', 0, None);
+    }
+    file.close();
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_scaffold_names() -> Result<()> {
+    let path = format!("/tmp/test_gdb_dups_{}.1gdb", std::process::id());
+    write_gdb(&path, &[("chr1", 10), ("chr2", 20), ("chr1", 30), ("chr1", 40)])?;
+
+    let keep = GdbReader::open(&path)?;
+    assert_eq!(keep.duplicates(), &[2, 3]);
+    assert_eq!(keep.scaffolds().len(), 4);
+    assert_eq!(keep.scaffold("chr1").unwrap().length, 10);
+
+    let suffixed = GdbReader::open_with(&path, DuplicatePolicy::Suffix)?;
+    assert_eq!(suffixed.scaffold("chr1").unwrap().length, 10);
+    assert_eq!(suffixed.scaffold("chr1.2").unwrap().length, 30);
+    assert_eq!(suffixed.scaffold("chr1.3").unwrap().length, 40);
+    assert_eq!(suffixed.scaffolds()[3].name, "chr1.3");
+
+    let err = GdbReader::open_with(&path, DuplicatePolicy::Error).err().unwrap();
+    assert_eq!(err, OneError::DuplicateName("chr1".to_string()));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_non_utf8_scaffold_name() -> Result<()> {
+    let path = format!("/tmp/test_gdb_utf8_{}.1gdb", std::process::id());
+    {
+        let schema = OneSchema::gdb()?;
+        let mut file = OneFile::open_write_new(&path, &schema, "gdb", true, 1)?;
+        let mut name = b"chr\xff1".to_vec();
+        file.write_line('S', 5, Some(name.as_mut_ptr() as *mut std::ffi::c_void));
+        file.set_int(0, 10);
+        file.write_line('C', 0, None);
+        file.close();
+    }
+
+    let err = GdbReader::open(&path).err().unwrap();
+    assert!(matches!(err, OneError::InvalidUtf8(_)));
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_unique_names_pass_every_policy() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let gdb = GdbReader::from_alignment_with(&mut file, 1, DuplicatePolicy::Error)?;
+    assert_eq!(gdb.scaffolds().len(), 9);
+    assert!(gdb.duplicates().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_scaffold_segments_and_masks() -> Result<()> {
+    let path = format!("/tmp/test_gdb_segments_{}.1gdb", std::process::id());
+    {
+        let schema = OneSchema::gdb()?;
+        let mut file = OneFile::open_write_new(&path, &schema, "gdb", true, 1)?;
+        let mut name = b"scaf".to_vec();
+        file.write_line('S', 4, Some(name.as_mut_ptr() as *mut std::ffi::c_void));
+        file.set_int(0, 5);
+        file.write_line('G', 0, None);
+        file.set_int(0, 10);
+        file.write_line('C', 0, None);
+        let mut masks = vec![1i64, 3, 6, 8];
+        file.write_line('M', 4, Some(masks.as_mut_ptr() as *mut std::ffi::c_void));
+        file.set_int(0, 2);
+        file.write_line('G', 0, None);
+        file.set_int(0, 4);
+        file.write_line('C', 0, None);
+        file.close();
+    }
+
+    let gdb = GdbReader::open(&path)?;
+    let scaffold = gdb.scaffold("scaf").unwrap();
+    assert_eq!(scaffold.length, 21);
+    assert_eq!(scaffold.contigs[0].masks, vec![(1, 3), (6, 8)]);
+    assert!(scaffold.contigs[1].masks.is_empty());
+
+    let gap = |offset, len| Gap { offset, len };
+    let contig = |id, offset, len, masks: Vec<(i64, i64)>| Contig { id, offset, len, masks };
+    let (g0, g1) = (gap(0, 5), gap(15, 2));
+    let (c0, c1) = (contig(0, 5, 10, vec![(1, 3), (6, 8)]), contig(1, 17, 4, vec![]));
+    assert_eq!(
+        scaffold.segments(),
+        vec![Segment::Gap(&g0), Segment::Contig(&c0), Segment::Gap(&g1), Segment::Contig(&c1)]
+    );
+
+    let (names, lengths, offsets) = OneFile::read_gdb_metadata(&path)?;
+    assert_eq!((names[&0].as_str(), names[&1].as_str()), ("scaf", "scaf"));
+    assert_eq!((lengths[&0], lengths[&1]), (21, 21));
+    assert_eq!((offsets[&0], offsets[&1]), ((5, 10), (17, 4)));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_agp_round_trip() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let gdb = GdbReader::from_alignment(&mut file, 1)?;
+
+    let mut agp = Vec::new();
+    to_agp(&gdb, &mut agp)?;
+    let text = String::from_utf8(agp.clone()).unwrap();
+    assert!(text.starts_with("##agp-version\t2.1\n"));
+    assert_eq!(text.lines().count(), 1 + 9);
+
+    let back = from_agp(agp.as_slice())?;
+    assert_eq!(back.scaffolds(), gdb.scaffolds());
+    Ok(())
+}
+
+#[test]
+fn test_agp_gaps() -> Result<()> {
+    let agp = "##agp-version\t2.1\n\
+               # comment\n\
+               s1\t1\t5\t1\tN\t5\tscaffold\tyes\tunspecified\n\
+               s1\t6\t15\t2\tW\tctgA\t11\t20\t-\n\
+               s2\t1\t3\t1\tW\tctgB\t1\t3\t+\n";
+    let gdb = from_agp(agp.as_bytes())?;
+    let s1 = gdb.scaffold("s1").unwrap();
+    assert_eq!(s1.length, 15);
+    assert_eq!(s1.gaps, vec![Gap { offset: 0, len: 5 }]);
+    assert_eq!(s1.contigs[0].offset, 5);
+    assert_eq!(gdb.scaffold("s2").unwrap().contigs[0].id, 1);
+
+    let mut out = Vec::new();
+    to_agp(&gdb, &mut out)?;
+    let lines: Vec<String> = String::from_utf8(out).unwrap().lines().map(String::from).collect();
+    assert_eq!(lines[1], "s1\t1\t5\t1\tN\t5\tscaffold\tyes\tunspecified");
+    assert_eq!(lines[2], "s1\t6\t15\t2\tW\ts1_1\t1\t10\t+");
+
+    // Coordinates that do not tile the object are rejected
+    assert!(from_agp("s1\t2\t5\t1\tW\tc\t1\t4\t+\n".as_bytes()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_karyotype() -> Result<()> {
+    let path = format!("/tmp/test_gdb_karyotype_{}.1gdb", std::process::id());
+    write_gdb(&path, &[("chr2", 200), ("chr1", 100)])?;
+    let gdb = GdbReader::open(&path)?;
+
+    assert_eq!(karyotype(&gdb), vec![("chr2".to_string(), 200), ("chr1".to_string(), 100)]);
+
+    let mut out = Vec::new();
+    to_circos_karyotype(&gdb, &mut out, "grey")?;
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "chr - chr2 chr2 0 200 grey\nchr - chr1 chr1 0 100 grey\n"
+    );
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_all_groups() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let groups = file.get_all_groups()?;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].scaffolds.len(), 9);
+    assert_eq!(groups[0].scaffolds, GdbReader::from_alignment(&mut file, 1)?.scaffolds());
+
+    // Same content as the tuple form it replaces
+    let (names, lengths, offsets) = file.get_all_groups_metadata().remove(0);
+    assert_eq!(groups[0].num_contigs(), names.len());
+    for scaffold in &groups[0].scaffolds {
+        for contig in &scaffold.contigs {
+            assert_eq!(names[&contig.id], scaffold.name);
+            assert_eq!(lengths[&contig.id], scaffold.length);
+            assert_eq!(offsets[&contig.id], (contig.offset, contig.len));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_missing_skeletons() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    assert!(file.has_objects('g') && file.has_objects('A'));
+    assert!(!file.has_objects('J'));
+    let err = file.goto('J', 1).unwrap_err();
+    assert!(matches!(err, OneError::NoSuchLineType(_)));
+    assert!(err.detailed_report().help.is_some());
+
+    // An alignment file written without GDB skeletons
+    let schema = OneSchema::aln()?;
+    let mut writer = OneFile::open_write_vec(&schema, "aln", true, 1)?;
+    for field in 0..6 {
+        writer.set_int(field, 0);
+    }
+    writer.write_line('A', 0, None);
+    let bytes = writer.into_vec()?;
+    let mut file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+    assert!(!file.has_objects('g'));
+    assert!(matches!(
+        GdbReader::from_alignment(&mut file, 1),
+        Err(OneError::NoObjects(_))
+    ));
+    assert!(file.get_all_groups()?.is_empty());
+    assert!(matches!(file.goto('g', 0), Err(OneError::NoObjects(_))));
+    file.goto('A', 0)?;
+    assert_eq!(file.read_line(), 'A');
+
+    let mut seq = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    assert!(matches!(seq.goto('g', 1), Err(OneError::NoSuchLineType(_))));
+    Ok(())
+}
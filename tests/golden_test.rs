@@ -0,0 +1,185 @@
+//! Golden tests comparing the crate against the ONEcode C tools
+//!
+//! These run only when `ONECODE_TOOLS_DIR` names a directory containing the
+//! `ONEview` and `ONEstat` binaries (built from `ONEcode/`), e.g.
+//!
+//! ```text
+//! ONECODE_TOOLS_DIR=/path/to/ONEcode cargo test --test golden_test
+//! ```
+//!
+//! Without it every test returns early, so the suite stays green on machines
+//! without the C tools.
+
+use onecode::{GdbReader, OneFile, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+const FILES: [&str; 3] = ["ONEcode/TEST/small.seq", "ONEcode/TEST/t2.seq", "data/test.1aln"];
+
+/// Path to a C tool, or None if the golden tests are not enabled
+fn tool(name: &str) -> Option<PathBuf> {
+    let dir = std::env::var_os("ONECODE_TOOLS_DIR")?;
+    let path = PathBuf::from(dir).join(name);
+    assert!(path.exists(), "ONECODE_TOOLS_DIR is set but {} is missing", path.display());
+    Some(path)
+}
+
+/// Run a tool and return its stdout
+fn run(tool: &PathBuf, args: &[&str]) -> String {
+    let output = Command::new(tool).args(args).output().unwrap();
+    assert!(output.status.success(), "{} {:?} failed", tool.display(), args);
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Data lines of an ASCII ONE file: everything except the header
+fn data_lines(text: &str) -> Vec<&str> {
+    text.lines()
+        .filter(|l| l.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .collect()
+}
+
+fn convert(input: &str, output: &str, binary: bool) -> Result<()> {
+    let mut reader = OneFile::open_read(input, None, None, 1)?;
+    let mut writer = OneFile::open_write_from(output, &reader, binary, 1)?;
+    while reader.read_line() != '\0' {
+        reader.copy_line_to(&mut writer)?;
+    }
+    writer.close();
+    Ok(())
+}
+
+fn temp(name: &str) -> String {
+    format!("/tmp/test_golden_{}_{}", std::process::id(), name)
+}
+
+#[test]
+fn test_golden_ascii_rendering() -> Result<()> {
+    let Some(oneview) = tool("ONEview") else { return Ok(()) };
+
+    for (n, input) in FILES.iter().enumerate() {
+        let expected = run(&oneview, &["-h", input]);
+
+        // Binary written by the C tool, rendered to ASCII by the crate
+        let binary = temp(&format!("{}_c.bin", n));
+        run(&oneview, &["-b", "-o", &binary, input]);
+        let ascii = temp(&format!("{}_rs.txt", n));
+        convert(&binary, &ascii, false)?;
+        let rendered = std::fs::read_to_string(&ascii).unwrap();
+        assert_eq!(data_lines(&rendered), data_lines(&expected), "{}", input);
+
+        std::fs::remove_file(&binary).ok();
+        std::fs::remove_file(&ascii).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_golden_binary_conversion() -> Result<()> {
+    let Some(oneview) = tool("ONEview") else { return Ok(()) };
+
+    for (n, input) in FILES.iter().enumerate() {
+        // Binary written by the crate must read back identically in the C tool
+        let binary = temp(&format!("{}_rs.bin", n));
+        convert(input, &binary, true)?;
+        assert_eq!(
+            run(&oneview, &["-h", &binary]),
+            run(&oneview, &["-h", input]),
+            "{}",
+            input
+        );
+
+        // The schema the crate reports is the one the C tool extracts
+        let schema = temp(&format!("{}_c.schema", n));
+        run(&oneview, &["-s", "-o", &schema, input]);
+        let file = OneFile::open_read(input, None, None, 1)?;
+        assert_eq!(file.schema_text()?, std::fs::read_to_string(&schema).unwrap());
+
+        std::fs::remove_file(&binary).ok();
+        std::fs::remove_file(&schema).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_golden_stats() -> Result<()> {
+    let Some(onestat) = tool("ONEstat") else { return Ok(()) };
+    let Some(oneview) = tool("ONEview") else { return Ok(()) };
+
+    for (n, input) in FILES.iter().enumerate() {
+        // stats() reports the counts recorded in the header, which ASCII files
+        // such as t2.seq may omit, while ONEstat counts the data. Binary files
+        // always carry counts, so compare on a binary conversion.
+        let binary = temp(&format!("{}_stats.bin", n));
+        run(&oneview, &["-b", "-o", &binary, input]);
+        let input = binary.as_str();
+
+        // ONEstat -H writes '#' count, '@' max and '+' total lines for each line type
+        let mut expected: HashMap<char, (i64, i64, i64)> = HashMap::new();
+        for line in run(&onestat, &["-H", input]).lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 3 || !["#", "@", "+"].contains(&tokens[0]) {
+                continue;
+            }
+            let entry = expected.entry(tokens[1].chars().next().unwrap()).or_default();
+            let value: i64 = tokens[2].parse().unwrap();
+            match tokens[0] {
+                "#" => entry.0 = value,
+                "@" => entry.1 = value,
+                _ => entry.2 = value,
+            }
+        }
+        assert!(!expected.is_empty());
+
+        let file = OneFile::open_read(input, None, None, 1)?;
+        for (line_type, counts) in expected {
+            assert_eq!(file.stats(line_type)?, counts, "{} '{}'", FILES[n], line_type);
+        }
+        std::fs::remove_file(&binary).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_golden_gdb_scan() -> Result<()> {
+    let Some(oneview) = tool("ONEview") else { return Ok(()) };
+
+    // Rebuild the skeletons from the C tool's rendering of the 'g' groups
+    let mut groups: Vec<Vec<(String, i64)>> = Vec::new();
+    for line in data_lines(&run(&oneview, &["-h", "data/test.1aln"])) {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("g") => groups.push(Vec::new()),
+            Some("S") => {
+                let name = tokens.nth(1).unwrap_or("").to_string();
+                groups.last_mut().unwrap().push((name, 0));
+            }
+            Some("G") | Some("C") => {
+                let len: i64 = tokens.next().unwrap().parse().unwrap();
+                groups.last_mut().unwrap().last_mut().unwrap().1 += len;
+            }
+            Some("A") | Some("a") => break,
+            _ => {}
+        }
+    }
+    assert!(!groups.is_empty());
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    for (i, expected) in groups.iter().enumerate() {
+        let gdb = GdbReader::from_alignment(&mut file, i as i64 + 1)?;
+        let scanned: Vec<(String, i64)> = gdb
+            .scaffolds()
+            .iter()
+            .map(|s| (s.name.clone(), s.length))
+            .collect();
+        assert_eq!(&scanned, expected);
+    }
+
+    let names = file.get_all_sequence_names();
+    let lengths = file.get_all_sequence_lengths();
+    for (id, (name, length)) in groups[0].iter().enumerate() {
+        assert_eq!(names.get(&(id as i64)), Some(name));
+        assert_eq!(lengths.get(&(id as i64)), Some(length));
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Tests for the inventory of the ONE files under a directory
+
+use onecode::{line_type_usage, scan_dir, FileKind, OneFile, OneSchema, Result};
+
+#[test]
+fn test_scan_dir() -> Result<()> {
+    let dir = format!("/tmp/test_inventory_{}", std::process::id());
+    std::fs::create_dir_all(format!("{}/nested", dir)).unwrap();
+    std::fs::copy("data/test.1aln", format!("{}/a.1aln", dir)).unwrap();
+    std::fs::copy("data/test.1aln", format!("{}/nested/copy.data", dir)).unwrap();
+    std::fs::copy("ONEcode/TEST/small.seq", format!("{}/reads.1seq", dir)).unwrap();
+    std::fs::write(format!("{}/notes.txt", dir), "not a ONE file\n").unwrap();
+    std::fs::write(format!("{}/broken.1seq", dir), "1 3 seq 9 1\n").unwrap();
+
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let mut file = OneFile::open_write_new(&format!("{}/t.1tst", dir), &schema, "tst", true, 1)?;
+    file.write_line('T', 0, None);
+    file.write_line('T', 0, None);
+    file.finalize()?;
+
+    let files = scan_dir(&dir)?;
+    let names: Vec<_> = files
+        .iter()
+        .map(|f| {
+            f.path
+                .strip_prefix(&dir)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "a.1aln",
+            "broken.1seq",
+            "nested/copy.data",
+            "reads.1seq",
+            "t.1tst"
+        ]
+    );
+
+    let aln = &files[0];
+    assert_eq!(aln.file_type(), "aln");
+    assert!(matches!(aln.kind, FileKind::OneBinary { .. }));
+    assert_eq!(aln.size, std::fs::metadata("data/test.1aln").unwrap().len());
+    let reference = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    assert_eq!(aln.fingerprint, Some(reference.schema_fingerprint()?));
+    assert_eq!(aln.count('A'), reference.stats('A')?.0);
+    assert_eq!(aln.count('J'), 0);
+    assert_eq!(files[2].stats, aln.stats);
+
+    assert!(files[1].error.is_some() && files[1].stats.is_empty());
+    assert!(matches!(files[3].kind, FileKind::OneAscii { .. }));
+    assert!(files[3].error.is_none());
+    assert_eq!(files[4].count('T'), 2);
+
+    let usage = line_type_usage(&files);
+    let a = usage
+        .iter()
+        .find(|u| u.file_type == "aln" && u.line_type == 'A')
+        .unwrap();
+    assert_eq!((a.files, a.counts.count), (2, 2 * aln.count('A')));
+    // ASCII files have usage only if their header carries counts
+    let sequences = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?
+        .stats('S')?
+        .0;
+    assert!(usage
+        .iter()
+        .any(|u| u.file_type == "seq" && u.counts.count == sequences));
+    assert!(usage
+        .iter()
+        .any(|u| u.file_type == "tst" && u.counts.count == 2));
+
+    assert!(scan_dir(format!("{}/missing", dir)).is_err());
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Tests for creation times and provenance graphs
+
+use onecode::{OneFile, OneSchema, ProvenanceGraph, Result, Timestamp};
+
+#[test]
+fn test_timestamps() -> Result<()> {
+    let file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let created = file.created().unwrap();
+    assert_eq!(created.to_string(), "2023-07-21T11:00:56");
+    assert_eq!(file.provenance()[0].timestamp().unwrap().day, 18);
+    assert!(file.provenance()[0].timestamp().unwrap() < created);
+
+    assert_eq!(Timestamp::parse("2024-02-29T00:00:00").unwrap().month, 2);
+    assert_eq!(Timestamp::parse("2024-13-01_00:00:00"), None);
+    assert_eq!(Timestamp::parse("yesterday"), None);
+    Ok(())
+}
+
+#[test]
+fn test_provenance_graph() -> Result<()> {
+    let dir = format!("/tmp/test_lineage_{}", std::process::id());
+    std::fs::create_dir_all(&dir).unwrap();
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let write = |name: &str, program: &str, references: &[&str]| -> Result<()> {
+        let path = format!("{}/{}", dir, name);
+        let mut file = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+        file.add_provenance(program, "1.0", &format!("{} \"quoted\"", program))?;
+        for reference in references {
+            file.add_reference(reference, 1)?;
+        }
+        file.write_line('T', 0, None);
+        file.close();
+        Ok(())
+    };
+    // reads and out reference each other, and missing does not exist
+    write("reads.1tst", "import", &["out.1tst"])?;
+    write("out.1tst", "align", &["reads.1tst", "missing.1tst"])?;
+
+    let graph = ProvenanceGraph::from_file(&format!("{}/out.1tst", dir))?;
+    assert_eq!(graph.files.len(), 3);
+    assert!(graph.files[0].path.ends_with("out.1tst"));
+    assert!(graph.files[1].path.ends_with("reads.1tst"));
+    assert_eq!(graph.files[2].path, "missing.1tst");
+    assert!(graph.files[1].resolved && !graph.files[2].resolved);
+    assert_eq!(graph.files[1].file_type.as_deref(), Some("tst"));
+    assert_eq!(graph.files[1].generated_by().unwrap().program, "import");
+    assert!(graph.files[0].created().is_some());
+    assert_eq!(graph.derived_from, [(0, 1), (0, 2), (1, 0)]);
+    assert_eq!(graph.sources(0).collect::<Vec<_>>(), [1, 2]);
+
+    let prov = graph.to_prov_n();
+    assert!(prov.starts_with("document\n"));
+    assert!(prov.ends_with("endDocument\n"));
+    assert!(prov.contains("wasDerivedFrom(one:file0, one:file2)"));
+    assert!(prov.contains("used(one:run1, one:file0, -)"));
+    assert!(prov.contains("one:command=\"align \\\"quoted\\\"\""));
+    assert!(!prov.contains("one:run2"));
+
+    assert!(ProvenanceGraph::from_file(&format!("{}/missing.1tst", dir)).is_err());
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
@@ -0,0 +1,234 @@
+//! Tests for the line iterator and its filters
+
+use onecode::{GdbReader, OneFile, OneSchema, OneType, Result};
+
+#[test]
+fn test_lines_all_and_of_type() -> Result<()> {
+    let mut file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    assert_eq!(file.lines().count(), 20);
+
+    let mut file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let names: Vec<String> = file
+        .lines()
+        .of_type('I')
+        .map(|l| l.string().unwrap().to_string())
+        .collect();
+    assert_eq!(names.len(), 10);
+    assert_eq!(names[0], "seq1");
+
+    let mut file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let first = file.lines().of_type('S').next().unwrap();
+    assert_eq!(first.dna().unwrap().len() as i64, file.len());
+
+    Ok(())
+}
+
+#[test]
+fn test_lines_in_group() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let skeleton: Vec<char> = file.lines().in_group('g', 1).map(|l| l.line_type()).collect();
+    assert_eq!(skeleton[0], 'g');
+    assert_eq!(skeleton.iter().filter(|&&t| t == 'S').count(), 9);
+    assert!(!skeleton.contains(&'A'));
+    assert_eq!(file.lines().in_group('é', 1).count(), 0);
+    assert_eq!(file.object('é'), -1);
+
+    // Typed list access matches the direct accessors
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let traces: Vec<Vec<i64>> = file
+        .lines()
+        .of_type('T')
+        .map(|l| l.int_list().unwrap().to_vec())
+        .collect();
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut expected = Vec::new();
+    loop {
+        match file.read_line() {
+            '\0' => break,
+            'T' => expected.push(file.int_list().unwrap().to_vec()),
+            _ => {}
+        }
+    }
+    assert_eq!(traces, expected);
+
+    // Several types can be accepted at once
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    assert_eq!(file.lines().of_type('A').of_type('R').count(), 72 + 16);
+
+    Ok(())
+}
+
+#[test]
+fn test_lines_with_comments() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO P 1 3 INT\n")?;
+    let path = format!("/tmp/test_lines_comments_{}.1tst", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+        for i in 0..3 {
+            writer.set_int(0, i);
+            writer.write_line('P', 0, None);
+            if i == 1 {
+                writer.write_comment("middle")?;
+            }
+        }
+        writer.close();
+    }
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let comments: Vec<Option<String>> = file
+        .lines()
+        .with_comments()
+        .map(|l| l.comment().map(String::from))
+        .collect();
+    assert_eq!(comments, vec![None, Some("middle".to_string()), None]);
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert!(file.lines().all(|l| l.comment().is_none()));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_lines_for_loop_and_checked_fields() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut alignments = 0;
+    for line in &mut file {
+        if line.line_type() != 'A' {
+            continue;
+        }
+        alignments += 1;
+        assert_eq!(line.num_fields(), 6);
+        assert_eq!(line.field_type(0), Some(OneType::oneINT));
+        assert_eq!(line.get_int(0), Some(line.int(0)));
+        assert_eq!(line.get_real(0), None);
+        assert_eq!(line.get_int(6), None);
+    }
+    assert_eq!(alignments, 72);
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let skeleton = file.lines().take_while(|l| l.line_type() != 'A').count();
+    assert!(skeleton > 9);
+
+    Ok(())
+}
+
+#[test]
+fn test_sample_and_last_objects() -> Result<()> {
+    // Lines of each alignment object, by a sequential scan
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut objects: Vec<Vec<char>> = Vec::new();
+    for line in file.lines() {
+        match line.line_type() {
+            'A' => objects.push(vec!['A']),
+            t if !objects.is_empty() => objects.last_mut().unwrap().push(t),
+            _ => {}
+        }
+    }
+    assert_eq!(objects.len(), 72);
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let sample = file.sample_objects('A', 10, 42)?;
+    assert_eq!(sample.remaining(), 10);
+    let sample: Vec<(i64, Vec<onecode::Line>)> = sample.collect::<Result<_>>()?;
+    let numbers: Vec<i64> = sample.iter().map(|(i, _)| *i).collect();
+    assert!(numbers.windows(2).all(|w| w[0] < w[1]));
+    assert!(numbers.iter().all(|i| (1..=72).contains(i)));
+    for (i, lines) in &sample {
+        let types: Vec<char> = lines.iter().map(|l| l.line_type()).collect();
+        assert_eq!(types, objects[*i as usize - 1], "object {}", i);
+    }
+
+    // Reproducible for a seed, and all objects once n reaches the count
+    let again: Vec<i64> = file.sample_objects('A', 10, 42)?.map(|o| o.unwrap().0).collect();
+    assert_eq!(again, numbers);
+    let other: Vec<i64> = file.sample_objects('A', 10, 7)?.map(|o| o.unwrap().0).collect();
+    assert_ne!(other, numbers);
+    let all: Vec<i64> = file.sample_objects('A', 100, 1)?.map(|o| o.unwrap().0).collect();
+    assert_eq!(all, (1..=72).collect::<Vec<_>>());
+    assert_eq!(file.sample_objects('A', 0, 1)?.count(), 0);
+
+    let mut ascii = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    assert!(ascii.sample_objects('S', 3, 1).is_err());
+
+    // The tail of the file, up to the end of the data
+    let last: Vec<(i64, Vec<onecode::Line>)> =
+        file.last_objects('A', 3)?.collect::<Result<_>>()?;
+    assert_eq!(last.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [70, 71, 72]);
+    for (i, lines) in &last {
+        let types: Vec<char> = lines.iter().map(|l| l.line_type()).collect();
+        assert_eq!(types, objects[*i as usize - 1], "object {}", i);
+    }
+    assert_eq!(last[2].1[0].int(0), objects_first_field(72)?);
+    assert_eq!(file.last_objects('A', 1000)?.count(), 72);
+    assert_eq!(file.last_objects('A', 0)?.count(), 0);
+    assert!(ascii.last_objects('S', 1).is_err());
+    Ok(())
+}
+
+/// First field of 'A' object `i`, by a sequential scan
+fn objects_first_field(i: usize) -> Result<i64> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    Ok(file.lines().of_type('A').nth(i - 1).unwrap().int(0))
+}
+
+#[test]
+fn test_objects() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let alignments: Vec<_> = file.objects('A').collect();
+    assert_eq!(alignments.len(), 72);
+    for (i, object) in alignments.iter().enumerate() {
+        assert_eq!(object.number, i as i64 + 1);
+        assert_eq!(object.line.line_type(), 'A');
+        assert_eq!(object.lines_of_type('T').count(), 1);
+        assert!(object.lines.iter().all(|l| !"gSaA".contains(l.line_type())));
+    }
+
+    // Scaffolds with their gaps and contigs, skipping the 'g' group lines
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut scaffolds = file.objects('S');
+    let first = scaffolds.next().unwrap();
+    assert_eq!(first.number, 1);
+    assert!(first.lines_of_type('C').count() > 0);
+    assert_eq!(scaffolds.count() as i64 + 1, file.stats('S')?.0);
+
+    let mut file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let names: Vec<_> = file
+        .objects('S')
+        .map(|s| s.lines_of_type('I').next().unwrap().string().unwrap().to_string())
+        .collect();
+    assert_eq!(names.len(), 10);
+    assert_eq!(names[0], "seq1");
+    Ok(())
+}
+
+#[test]
+fn test_groups() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let n_groups = file.stats('g')?.0;
+    let skeletons: Vec<_> = file.groups('g').collect();
+    assert_eq!(skeletons.len() as i64, n_groups);
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    for group in &skeletons {
+        let gdb = GdbReader::from_alignment(&mut file, group.number)?;
+        let names: Vec<&str> = group
+            .objects_of_type('S')
+            .map(|s| s.line.string().unwrap().split_whitespace().next().unwrap())
+            .collect();
+        let expected: Vec<&str> = gdb.scaffolds().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, expected);
+        let contigs: usize = group.objects.iter().map(|s| s.lines_of_type('C').count()).sum();
+        assert_eq!(contigs, gdb.scaffolds().iter().map(|s| s.contigs.len()).sum::<usize>());
+        assert!(group.lines.is_empty());
+    }
+
+    // The last skeleton stops at the first alignment
+    let last = skeletons.last().unwrap();
+    assert!(last.objects.iter().all(|o| o.line.line_type() == 'S'));
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let chains = file.groups('a').count() as i64;
+    assert_eq!(chains, file.stats('a')?.0);
+    Ok(())
+}
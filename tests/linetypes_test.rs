@@ -0,0 +1,85 @@
+//! Tests that the named line types match the standard schemas
+
+use onecode::linetypes::{aln, gdb, pro, seq};
+use onecode::schema::{ALN_SCHEMA_TEXT, GDB_SCHEMA_TEXT, PROTEIN_SCHEMA_TEXT, SEQ_SCHEMA_TEXT};
+
+/// (kind, line type) of each O/D/G definition line
+fn definitions(text: &str) -> Vec<(char, char)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let kind = tokens.next()?.chars().next()?;
+            let line_type = tokens.next()?.chars().next()?;
+            matches!(kind, 'O' | 'D').then_some((kind, line_type))
+        })
+        .collect()
+}
+
+fn assert_defined(text: &str, expected: &[(char, char)]) {
+    let defined = definitions(text);
+    for entry in expected {
+        assert!(defined.contains(entry), "{:?} not defined", entry);
+    }
+    assert_eq!(defined.len(), expected.len(), "schema has unnamed line types");
+}
+
+#[test]
+fn test_seq_line_types() {
+    assert_defined(
+        SEQ_SCHEMA_TEXT,
+        &[
+            ('O', seq::SEQUENCE),
+            ('D', seq::IDENTIFIER),
+            ('D', seq::QUALITY),
+            ('D', seq::NON_ACGT),
+        ],
+    );
+}
+
+#[test]
+fn test_gdb_line_types() {
+    assert_defined(
+        GDB_SCHEMA_TEXT,
+        &[
+            ('D', gdb::FREQUENCY),
+            ('O', gdb::SCAFFOLD),
+            ('D', gdb::GAP),
+            ('D', gdb::CONTIG),
+            ('D', gdb::MASK),
+        ],
+    );
+}
+
+#[test]
+fn test_aln_line_types() {
+    assert_defined(
+        ALN_SCHEMA_TEXT,
+        &[
+            ('D', aln::TRACE_SPACING),
+            ('O', aln::GDB_GROUP),
+            ('O', aln::SCAFFOLD),
+            ('D', aln::GAP),
+            ('D', aln::CONTIG),
+            ('D', aln::MASK),
+            ('O', aln::CHAIN),
+            ('D', aln::CHAIN_SPACING),
+            ('O', aln::ALIGNMENT),
+            ('D', aln::LENGTHS),
+            ('D', aln::REVERSE),
+            ('D', aln::DIFFERENCES),
+            ('D', aln::TRACE),
+            ('D', aln::TRACE_DIFFS),
+            ('D', aln::QUALITY),
+            ('D', aln::MATCHES),
+            ('D', aln::CIGAR),
+        ],
+    );
+}
+
+#[test]
+fn test_protein_line_types() {
+    assert_defined(
+        PROTEIN_SCHEMA_TEXT,
+        &[('O', pro::SEQUENCE), ('D', pro::IDENTIFIER), ('D', pro::FRAME)],
+    );
+}
@@ -0,0 +1,56 @@
+//! Tests for dataset manifests
+
+use onecode::{Manifest, OneError, OneFile, Result};
+
+fn copy(input: &str, output: &str) -> Result<()> {
+    let mut reader = OneFile::open_read(input, None, None, 1)?;
+    let mut writer = OneFile::open_write_from(output, &reader, true, 1)?;
+    while reader.read_line() != '\0' {
+        reader.copy_line_to(&mut writer)?;
+    }
+    writer.close();
+    Ok(())
+}
+
+#[test]
+fn test_manifest_round_trip_and_verify() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("test_manifest_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir_str = dir.to_str().unwrap();
+    copy("ONEcode/TEST/small.seq", &format!("{}/a.1seq", dir_str))?;
+    copy("ONEcode/TEST/t2.seq", &format!("{}/b.1seq", dir_str))?;
+    copy("data/test.1aln", &format!("{}/c.1aln", dir_str))?;
+
+    let manifest_path = format!("{}/dataset.1manifest", dir_str);
+    {
+        let mut manifest = Manifest::with_dir(dir_str);
+        let entry = manifest.add_file("a.1seq")?;
+        assert_eq!(entry.file_type, "seq");
+        assert_eq!(entry.counts, vec![('S', 10)]);
+        manifest.add_file("c.1aln")?;
+        assert!(manifest.add_file("a.1seq").is_err());
+        manifest.write(&manifest_path)?;
+    }
+
+    let manifest = Manifest::read(&manifest_path)?;
+    assert_eq!(manifest.entries().len(), 2);
+    let aln = manifest.entry("c.1aln").unwrap();
+    assert_eq!(aln.file_type, "aln");
+    assert!(aln.counts.contains(&('A', 72)));
+    manifest.verify()?;
+    manifest.check_schemas()?;
+
+    // Shards of the same type with different schemas are flagged
+    let mut mixed = Manifest::with_dir(dir_str);
+    mixed.add_file("a.1seq")?;
+    mixed.add_file("b.1seq")?;
+    assert!(matches!(mixed.check_schemas(), Err(OneError::ManifestMismatch(_))));
+
+    // Replacing a shard with different content is detected
+    std::fs::copy(dir.join("b.1seq"), dir.join("a.1seq")).unwrap();
+    let err = manifest.verify().err().unwrap();
+    assert!(matches!(err, OneError::ManifestMismatch(_)));
+
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Tests for ONE files read from and written to memory
+
+use onecode::{OneFile, OneSchema, Result};
+
+const SCHEMA: &str = "P 3 tst\nO T 1 3 INT\nD L 1 8 INT_LIST\n";
+
+/// Write three 'T' objects with 'L' lists into memory
+fn write_vec(binary: bool) -> Result<Vec<u8>> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", binary, 1)?;
+    for i in 0..3 {
+        file.set_int(0, i);
+        file.write_line('T', 0, None);
+        file.write_int_list('L', &[i, i * 10, i * 100])?;
+    }
+    file.into_vec()
+}
+
+#[test]
+fn test_read_bytes() -> Result<()> {
+    let bytes = std::fs::read("data/test.1aln").unwrap();
+    for nthreads in [1, 2] {
+        let mut file = OneFile::open_read_bytes(&bytes, None, Some("aln"), nthreads)?;
+        let backing = file.file_name().unwrap();
+        assert!(std::path::Path::new(&backing).exists());
+
+        assert_eq!(file.stats('A')?.0, 72);
+        file.goto('A', 72)?;
+        assert_eq!(file.read_line(), 'A');
+        assert_eq!(file.lines().of_type('A').count(), 0);
+
+        drop(file);
+        assert!(!std::path::Path::new(&backing).exists());
+    }
+
+    assert!(OneFile::open_read_bytes(b"not a ONE file\n", None, None, 1).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_write_vec_round_trip() -> Result<()> {
+    for binary in [true, false] {
+        let bytes = write_vec(binary)?;
+        assert!(bytes.starts_with(b"1 3 tst"));
+        assert_eq!(bytes.contains(&b'$'), binary);
+
+        let mut file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+        let mut lists = Vec::new();
+        while file.read_line() != '\0' {
+            if file.line_type() == 'L' {
+                lists.push(file.int_list().unwrap().to_vec());
+            }
+        }
+        assert_eq!(lists, [[0, 0, 0], [1, 10, 100], [2, 20, 200]]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_memory_options() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let mut file = OneFile::options()
+        .schema(&schema)
+        .file_type("tst")
+        .binary(false)
+        .open_write_vec()?;
+    file.set_int(0, 42);
+    file.write_line('T', 0, None);
+    let text = String::from_utf8(file.into_vec()?).unwrap();
+    assert!(text.contains("\nT 42\n"));
+
+    let mut file = OneFile::options()
+        .file_type("tst")
+        .open_read_bytes(text.as_bytes())?;
+    assert_eq!(file.read_line(), 'T');
+    assert_eq!(file.int(0), 42);
+
+    // into_vec() needs an in-memory file being written
+    let path = format!("/tmp/test_memory_{}.1tst", std::process::id());
+    let on_disk = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+    assert!(on_disk.into_vec().is_err());
+    assert!(file.into_vec().is_err());
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
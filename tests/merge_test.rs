@@ -0,0 +1,211 @@
+//! Tests for merging shards into one file and splitting one into parts
+
+use onecode::convert::transcode;
+use onecode::{
+    merge, split, split_by_objects, AlnReader, GdbIndex, GdbReader, OneError, OneFile, OneSchema,
+    Result,
+};
+
+/// Copy `input` up to its first alignment, then the alignments `keep` selects
+fn write_shard(input: &str, output: &str, keep: impl Fn(i64) -> bool) -> Result<()> {
+    let mut source = OneFile::open_read(input, None, None, 1)?;
+    let mut out = OneFile::open_write_from(output, &source, true, 1)?;
+    let mut in_alignments = false;
+    while source.read_line() != '\0' {
+        in_alignments |= source.line_type() == 'A';
+        if !in_alignments || keep(source.object('A')) {
+            source.copy_line_to(&mut out)?;
+        }
+    }
+    out.finalize()
+}
+
+/// A single-skeleton alignment file of `contigs` with one alignment per
+/// (query contig, target contig) pair in `alignments`
+fn write_aln(path: &str, contigs: &[(&str, i64)], alignments: &[(i64, i64)]) -> Result<()> {
+    let mut file = OneFile::open_write_new(path, &OneSchema::aln()?, "aln", true, 1)?;
+    file.set_int(0, 100);
+    file.write_line('t', 0, None);
+    file.write_line('g', 0, None);
+    for (name, len) in contigs {
+        file.write_string('S', name)?;
+        file.set_int(0, *len);
+        file.write_line('C', 0, None);
+    }
+    for &(query, target) in alignments {
+        for (i, value) in [query, 0, 10, target, 0, 10].into_iter().enumerate() {
+            file.set_int(i, value);
+        }
+        file.write_line('A', 0, None);
+    }
+    file.finalize()
+}
+
+#[test]
+fn test_merge_shards() -> Result<()> {
+    let input = "data/test.1aln";
+    let tmp = |name: &str| format!("/tmp/test_merge_{}_{}", std::process::id(), name);
+    let (first, second, merged) = (tmp("1.1aln"), tmp("2.1aln"), tmp("all.1aln"));
+    let source = OneFile::open_read(input, None, None, 1)?;
+    let n = source.stats('A')?.0;
+    write_shard(input, &first, |a| a <= n / 2)?;
+    write_shard(input, &second, |a| a > n / 2)?;
+
+    merge(&[&first, &second], &merged)?;
+    let expected: Vec<_> = AlnReader::open(input)?.with_traces().collect();
+    let found: Vec<_> = AlnReader::open(&merged)?.with_traces().collect();
+    assert_eq!(found, expected);
+
+    // The shared skeletons and history are written once
+    let file = OneFile::open_read(&merged, None, None, 1)?;
+    assert_eq!(file.stats('g')?, source.stats('g')?);
+    assert_eq!(file.stats('S')?, source.stats('S')?);
+    let provenance = file.provenance();
+    assert_eq!(provenance.len(), source.provenance().len() + 1);
+    assert!(provenance.last().unwrap().command.starts_with("merge"));
+
+    for path in [&first, &second, &merged] {
+        std::fs::remove_file(path).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_merge_renumbers_contigs() -> Result<()> {
+    let tmp = |name: &str| format!("/tmp/test_merge_ids_{}_{}", std::process::id(), name);
+    let (first, second, merged) = (tmp("1.1aln"), tmp("2.1aln"), tmp("all.1aln"));
+    write_aln(&first, &[("chr1", 100)], &[(0, 0)])?;
+    write_aln(&second, &[("chr2", 50), ("chr3", 60)], &[(1, 0), (0, 1)])?;
+
+    assert_eq!(merge(&[&first, &second], &merged)?, 1 + 1 + 6 + 3);
+    let mut file = OneFile::open_read(&merged, None, None, 1)?;
+    let index = GdbIndex::new(&GdbReader::from_alignment(&mut file, 1)?);
+    let names: Vec<_> = AlnReader::open(&merged)?
+        .map(|aln| {
+            let aln = aln.normalized(&index, &index).unwrap();
+            (aln.query_name.to_string(), aln.target_name.to_string())
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [("chr1", "chr1"), ("chr3", "chr2"), ("chr2", "chr3")]
+            .map(|(q, t)| (q.to_string(), t.to_string()))
+    );
+
+    for path in [&first, &second, &merged] {
+        std::fs::remove_file(path).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_merge_sequences() -> Result<()> {
+    let merged = format!("/tmp/test_merge_seq_{}.1seq", std::process::id());
+    let input = "ONEcode/TEST/small.seq";
+    assert_eq!(merge(&[input, input], &merged)?, 40);
+
+    let file = OneFile::open_read(&merged, None, None, 1)?;
+    let source = OneFile::open_read(input, None, None, 1)?;
+    assert!(file.is_binary());
+    assert_eq!(file.stats('S')?.0, 20);
+    assert_eq!(file.provenance().len(), source.provenance().len() + 1);
+    std::fs::remove_file(&merged).ok();
+    Ok(())
+}
+
+#[test]
+fn test_merge_errors() -> Result<()> {
+    let tmp = |name: &str| format!("/tmp/test_merge_err_{}_{}", std::process::id(), name);
+    let output = tmp("out");
+    assert!(merge(&[], &output).is_err());
+
+    let err = merge(&["ONEcode/TEST/small.seq", "data/test.1aln"], &output)
+        .err()
+        .unwrap();
+    assert!(matches!(err, OneError::WrongFileType { .. }));
+
+    // Shards written with another trace spacing cannot share one 't' line
+    let spacing = tmp("spacing.1aln");
+    let mut file = OneFile::open_write_new(&spacing, &OneSchema::aln()?, "aln", true, 1)?;
+    file.set_int(0, 50);
+    file.write_line('t', 0, None);
+    file.finalize()?;
+    let plain = tmp("plain.1aln");
+    write_aln(&plain, &[("chr1", 100)], &[(0, 0)])?;
+    assert!(merge(&[&plain, &spacing], &output).is_err());
+
+    for path in [&output, &spacing, &plain] {
+        std::fs::remove_file(path).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_split_round_trip() -> Result<()> {
+    let input = "data/test.1aln";
+    let dir = format!("/tmp/test_split_{}", std::process::id());
+    std::fs::create_dir_all(&dir).unwrap();
+    let whole = format!("{}/all.1aln", dir);
+    std::fs::copy(input, &whole).unwrap();
+
+    let parts = split(&whole, 3)?;
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[0], format!("{}/all.1.1aln", dir));
+    let source = OneFile::open_read(input, None, None, 1)?;
+    let mut total = 0;
+    for part in &parts {
+        let file = OneFile::open_read(part, None, None, 1)?;
+        assert_eq!(file.stats('g')?, source.stats('g')?);
+        assert!(file
+            .provenance()
+            .last()
+            .unwrap()
+            .command
+            .starts_with("split"));
+        total += file.stats('A')?.0;
+    }
+    assert_eq!(total, source.stats('A')?.0);
+
+    // Merging the parts gives back the alignments
+    let merged = format!("{}/merged.1aln", dir);
+    let parts: Vec<&str> = parts.iter().map(String::as_str).collect();
+    merge(&parts, &merged)?;
+    let expected: Vec<_> = AlnReader::open(input)?.with_traces().collect();
+    let found: Vec<_> = AlnReader::open(&merged)?.with_traces().collect();
+    assert_eq!(found, expected);
+
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
+
+#[test]
+fn test_split_by_objects() -> Result<()> {
+    let dir = format!("/tmp/test_split_objects_{}", std::process::id());
+    std::fs::create_dir_all(&dir).unwrap();
+    let reads = format!("{}/reads.1seq", dir);
+    transcode("ONEcode/TEST/small.seq", &reads, true, 1)?;
+
+    let parts = split_by_objects(&reads, 'S', &[1..4, 4..4, 8..11])?;
+    let counts: Vec<i64> = parts
+        .iter()
+        .map(|part| Ok(OneFile::open_read(part, None, None, 1)?.stats('S')?.0))
+        .collect::<Result<_>>()?;
+    assert_eq!(counts, [3, 0, 3]);
+    let mut last = OneFile::open_read(&parts[2], None, None, 1)?;
+    last.read_line();
+    assert_eq!(last.read_line(), 'I');
+    assert_eq!(last.string()?, "seq8");
+
+    assert_eq!(split(&reads, 4)?.len(), 4);
+    assert_eq!(split(&reads, 20)?.len(), 10);
+    assert!(matches!(
+        split_by_objects(&reads, 'S', &[1..2, 0..3]),
+        Err(OneError::InvalidFormat(_))
+    ));
+    assert!(split_by_objects(&reads, 'S', &[1..2, 5..12]).is_err());
+    assert!(split(&reads, 0).is_err());
+    assert!(split("ONEcode/TEST/small.seq", 2).is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
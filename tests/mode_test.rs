@@ -0,0 +1,55 @@
+//! Tests for the read-only and write-only handles
+
+use onecode::{OneFile, OneReader, OneSchema, OneWriter, Result};
+
+#[test]
+fn test_reader_and_writer() -> Result<()> {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\nD N 1 6 STRING\n")?;
+    let mut writer = OneWriter::in_memory(&schema, "tst", true, 1)?;
+    assert!(writer.is_write());
+    writer.add_provenance("mode_test", "1.0", "test")?;
+    for i in 0..3 {
+        writer.set_int(0, i);
+        writer.write_line('T', 0, None);
+        writer.write_string('N', &format!("name{}", i))?;
+    }
+    let bytes = writer.into_vec()?;
+
+    let mut reader = OneReader::open_bytes(&bytes, None, None, 1)?;
+    assert!(!reader.is_write());
+    assert_eq!(reader.stats('T')?.0, 3);
+    let mut names = Vec::new();
+    while reader.read_line() != '\0' {
+        if reader.line_type() == 'N' {
+            names.push(reader.string()?.to_string());
+        }
+    }
+    assert_eq!(names, ["name0", "name1", "name2"]);
+
+    reader.goto('T', 2)?;
+    assert_eq!(reader.read_line(), 'T');
+    assert_eq!(reader.int(0), 1);
+    assert_eq!(reader.into_inner().provenance()[0].program, "mode_test");
+    Ok(())
+}
+
+#[test]
+fn test_conversions() -> Result<()> {
+    let file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let err = OneWriter::try_from(file).err().unwrap();
+    assert!(err.to_string().contains("open for reading, not writing"));
+
+    let file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let reader = OneReader::try_from(file)?;
+    assert_eq!(reader.file_type().as_deref(), Some("aln"));
+    let file: OneFile = reader.into();
+    assert_eq!(file.file_type().as_deref(), Some("aln"));
+
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let writer = OneFile::options()
+        .schema(&schema)
+        .file_type("tst")
+        .open_write_vec()?;
+    assert!(OneReader::try_from(writer).is_err());
+    Ok(())
+}
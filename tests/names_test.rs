@@ -0,0 +1,63 @@
+//! Tests for name normalization in lookups across files
+
+use onecode::gdb::from_agp;
+use onecode::names::{StripChr, StripPanSn, StripVersion};
+use onecode::{NameNormalizer, OneFile, OneSchema, Result, SeqReader, SequenceStore};
+
+#[test]
+fn test_normalizers() {
+    assert_eq!(StripChr.normalize("chr1"), "1");
+    assert_eq!(StripChr.normalize("Chr2"), "2");
+    assert_eq!(StripChr.normalize("chr"), "chr");
+    assert_eq!(StripChr.normalize("1"), "1");
+    assert_eq!(StripVersion.normalize("NC_000001.11"), "NC_000001");
+    assert_eq!(StripVersion.normalize("contig.a"), "contig.a");
+    assert_eq!(StripVersion.normalize(".1"), ".1");
+    assert_eq!(StripPanSn.normalize("HG002#1#chr1"), "chr1");
+    assert_eq!(StripPanSn.normalize("chr1"), "chr1");
+
+    let chained = (StripPanSn, StripChr);
+    assert_eq!(chained.normalize("HG002#2#chrX"), "X");
+    let upper = |name: &str| name.to_uppercase();
+    assert_eq!((StripChr, upper).normalize("chrx"), "X");
+}
+
+#[test]
+fn test_seq_reader_lookups() -> Result<()> {
+    let path = format!("/tmp/test_names_{}.1seq", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &OneSchema::seq()?, "seq", true, 1)?;
+    for (name, dna) in [("1", "acgtacgt"), ("chrX", "ggcc"), ("chr1", "tttt")] {
+        file.write_dna('S', dna.as_bytes())?;
+        file.write_string('I', name)?;
+    }
+    file.finalize()?;
+
+    let reader = SeqReader::open(&path)?;
+    assert_eq!(reader.id("chr1"), Some(2));
+    assert_eq!(reader.id("X"), None);
+
+    let mut reader = SeqReader::open(&path)?.with_normalizer(StripChr);
+    assert_eq!(reader.id("chr1"), Some(0)); // "1" comes first
+    assert_eq!(reader.id("X"), Some(1));
+    assert_eq!(reader.name(1), Some("chrX"));
+    assert!(reader.fetch("HG002#1#chrX", 0..2).is_err());
+
+    let mut reader = SeqReader::open(&path)?.with_normalizer((StripPanSn, StripChr));
+    assert_eq!(reader.fetch("HG002#1#chrX", 1..3)?, b"gc");
+    assert_eq!(reader.len("chr1")?, 8);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_gdb_reader_lookups() -> Result<()> {
+    let agp = "NC_000001.11\t1\t10\t1\tW\tc1\t1\t10\t+\n";
+    let gdb = from_agp(agp.as_bytes())?;
+    assert!(gdb.scaffold("NC_000001").is_none());
+
+    let gdb = gdb.with_normalizer(StripVersion);
+    assert_eq!(gdb.scaffold("NC_000001.10").unwrap().name, "NC_000001.11");
+    assert_eq!(gdb.len("NC_000001")?, 10);
+    Ok(())
+}
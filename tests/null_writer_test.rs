@@ -0,0 +1,47 @@
+//! Tests for the writer that checks and counts lines but discards them
+
+use onecode::{NullWriter, OneError, OneFile, OneReader, OneSchema, OneWriter, Result};
+
+#[test]
+fn test_null_writer_counts_as_real_file() -> Result<()> {
+    let output = format!("/tmp/test_null_writer_{}.1aln", std::process::id());
+    let mut reader = OneReader::open("data/test.1aln", None, None, 1)?;
+    let mut writer = OneWriter::create_from(&output, &reader, true, 1)?;
+    reader.filter_objects(&mut writer, 'A', |aln| aln.number % 2 == 0)?;
+    let expected = writer.all_stats();
+    writer.finalize()?;
+
+    let mut reader = OneReader::open("data/test.1aln", None, None, 1)?;
+    let mut null = NullWriter::create_from(&reader, true)?;
+    let kept = reader.filter_objects(&mut null, 'A', |aln| aln.number % 2 == 0)?;
+    assert_eq!(null.stats('A')?.0, kept);
+    assert_eq!(null.file_name().as_deref(), Some("/dev/null"));
+    assert_eq!(null.finish()?, expected);
+
+    // The real file holds what the dry run counted
+    let file = OneFile::open_read(&output, None, None, 1)?;
+    assert_eq!(file.stats('A')?.0, kept);
+    std::fs::remove_file(&output).ok();
+    Ok(())
+}
+
+#[test]
+fn test_null_writer_checks_lines() -> Result<()> {
+    for is_binary in [false, true] {
+        let mut null = NullWriter::new(&OneSchema::seq()?, "seq", is_binary)?;
+        for i in 0..1000 {
+            null.write_dna('S', b"acgtacgtac")?;
+            null.write_string('I', &format!("read_{}", i))?;
+        }
+        let err = null.write_string('S', "acgt").err().unwrap();
+        assert!(matches!(err, OneError::SchemaError(_)));
+        assert!(null.write_dna('Q', b"acgt").is_err());
+
+        let stats = null.finish()?;
+        let seqs = stats.iter().find(|s| s.line_type == 'S').unwrap();
+        assert_eq!((seqs.counts.count, seqs.counts.total), (1000, 10_000));
+        let names = stats.iter().find(|s| s.line_type == 'I').unwrap();
+        assert_eq!(names.counts.count, 1000);
+    }
+    Ok(())
+}
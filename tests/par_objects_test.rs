@@ -0,0 +1,68 @@
+//! Tests for parallel processing of objects over the per-thread handles
+
+#![cfg(feature = "rayon")]
+
+use onecode::{AlnReader, OneError, OneFile, Result};
+
+const ALN: &str = "data/test.1aln";
+
+fn expected() -> Vec<(i64, i64, i64, bool, i64)> {
+    AlnReader::open(ALN)
+        .unwrap()
+        .map(|a| (a.query_id, a.query_start, a.target_id, a.reverse, a.diffs))
+        .collect()
+}
+
+#[test]
+fn test_par_objects_matches_sequential() -> Result<()> {
+    for nthreads in [1, 3, 4, 100] {
+        let mut file = OneFile::open_read(ALN, None, None, nthreads)?;
+
+        // Reading the data lines runs on into the next 'A' line
+        let found = file.par_objects('A', |handle, _| {
+            let (query_id, query_start, target_id) = (handle.int(0), handle.int(1), handle.int(3));
+            let (mut reverse, mut diffs) = (false, 0);
+            loop {
+                match handle.read_line() {
+                    'R' => reverse = true,
+                    'D' => diffs = handle.int(0),
+                    'A' | '\0' => break,
+                    _ => {}
+                }
+            }
+            Ok((query_id, query_start, target_id, reverse, diffs))
+        })?;
+        assert_eq!(found, expected(), "{} threads", nthreads);
+
+        // Leaving the handle on the object line, or moving it elsewhere
+        let objects = file.par_objects('A', |handle, object| {
+            let first = handle.int(0);
+            if object % 5 == 0 {
+                handle.goto('A', 1)?;
+            }
+            Ok((object, first))
+        })?;
+        let numbers: Vec<i64> = objects.iter().map(|&(object, _)| object).collect();
+        assert_eq!(numbers, (1..=72).collect::<Vec<_>>());
+        let firsts: Vec<i64> = objects.iter().map(|&(_, first)| first).collect();
+        assert_eq!(firsts, expected().iter().map(|e| e.0).collect::<Vec<_>>());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_par_objects_errors() -> Result<()> {
+    let mut file = OneFile::open_read(ALN, None, None, 4)?;
+    let result: Result<Vec<()>> = file.par_objects('A', |_, object| {
+        if object == 40 {
+            Err(OneError::Other("stop".to_string()))
+        } else {
+            Ok(())
+        }
+    });
+    assert_eq!(result.err().unwrap().to_string(), "stop");
+
+    let mut ascii = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 2)?;
+    assert!(ascii.par_objects('S', |_, _| Ok(())).is_err());
+    Ok(())
+}
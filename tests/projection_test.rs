@@ -0,0 +1,60 @@
+//! Tests for reading a projection of a file onto some of its line types
+
+use onecode::{OneError, OneFile, OneSchema, Result};
+
+#[test]
+fn test_projection() -> Result<()> {
+    let mut all = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut counts = std::collections::HashMap::new();
+    loop {
+        match all.read_line() {
+            '\0' => break,
+            t => *counts.entry(t).or_insert(0) += 1,
+        }
+    }
+
+    let mut file = OneFile::options()
+        .project(&['A', 'T'])
+        .open_read("data/test.1aln")?;
+    assert_eq!(file.projection(), Some(vec!['A', 'T']));
+    let (mut a, mut t) = (0, 0);
+    loop {
+        match file.read_line() {
+            '\0' => break,
+            'A' => a += 1,
+            'T' => {
+                t += 1;
+                assert!(file.int_list().is_some());
+            }
+            other => panic!("read a '{}' line outside the projection", other),
+        }
+    }
+    assert_eq!((a, t), (counts[&'A'], counts[&'T']));
+    // Skipped lines still count
+    assert_eq!(file.line_number(), all.line_number());
+
+    // Iterators go through read_line() too
+    let mut file = OneFile::options()
+        .project(&['A'])
+        .open_read("data/test.1aln")?;
+    assert!(file.lines().all(|line| line.line_type() == 'A'));
+    file.set_projection(None)?;
+    assert_eq!(file.projection(), None);
+    Ok(())
+}
+
+#[test]
+fn test_projection_errors() -> Result<()> {
+    let err = OneFile::options()
+        .project(&['A', 'J'])
+        .open_read("data/test.1aln")
+        .err()
+        .unwrap();
+    assert!(matches!(err, OneError::SchemaError(_)));
+    assert!(err.to_string().contains("'J'"));
+
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", true, 1)?;
+    assert!(file.set_projection(Some(&['T'])).is_err());
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Tests for reading and writing .1pro protein files
+
+use onecode::{OneError, OneFile, Protein, ProteinReader, ProteinWriter, Result};
+
+fn proteins() -> Vec<Protein> {
+    vec![
+        Protein {
+            id: Some("sp|P69905|HBA_HUMAN".to_string()),
+            sequence: "MVLSPADKTNVKAAWGKVGAHAGEYGAEALERMFLSFPTTKTYFPHF".to_string(),
+            source: None,
+        },
+        Protein {
+            id: None,
+            sequence: "MKWVTFISLLFLFSSAYS*".to_string(),
+            source: Some((3, -2)),
+        },
+        Protein {
+            id: Some("empty".to_string()),
+            sequence: String::new(),
+            source: None,
+        },
+    ]
+}
+
+#[test]
+fn test_protein_round_trip() -> Result<()> {
+    for binary in [true, false] {
+        let path = format!("/tmp/test_protein_{}_{}.1pro", std::process::id(), binary);
+        let mut writer = ProteinWriter::create(&path, binary)?;
+        for protein in proteins() {
+            writer.write_protein(&protein)?;
+        }
+        writer.close();
+
+        let reader = ProteinReader::open(&path)?;
+        // ASCII files are written without header counts
+        let count = if binary { 3 } else { 0 };
+        assert_eq!(reader.file().stats('S')?.0, count);
+        assert_eq!(reader.collect::<Vec<_>>(), proteins());
+
+        // Residues are stored as written, not 2-bit encoded
+        let mut file = OneFile::open_read(&path, None, Some("pro"), 1)?;
+        assert_eq!(file.read_line(), 'S');
+        assert_eq!(file.string()?, proteins()[0].sequence);
+        std::fs::remove_file(&path).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_protein_errors() -> Result<()> {
+    let path = format!("/tmp/test_protein_errors_{}.1pro", std::process::id());
+    let mut writer = ProteinWriter::create(&path, true)?;
+    let err = writer.write(Some("p1"), "MKW-VT").unwrap_err();
+    assert!(matches!(err, OneError::InvalidFormat(_)));
+    assert!(err
+        .to_string()
+        .contains("'-' is not an amino acid code in protein p1"));
+    writer.write(None, "MKWVT")?;
+    writer.close();
+
+    assert_eq!(ProteinReader::open(&path)?.count(), 1);
+    assert!(ProteinReader::open("data/test.1aln").is_err());
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
@@ -0,0 +1,156 @@
+//! Tests for the formatting of REAL values in ASCII output
+
+use onecode::{OneFile, OneSchema, RealFormat, Result};
+
+const SCHEMA: &str = "P 3 tst\nO R 2 4 REAL 9 REAL_LIST\n";
+
+/// Write one 'R' line and return it as it appears in the ASCII file
+fn write_r_line(path: &str, format: Option<RealFormat>) -> Result<String> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    {
+        let mut options = OneFile::options()
+            .schema(&schema)
+            .file_type("tst")
+            .binary(false);
+        if let Some(format) = format {
+            options = options.real_format(format);
+        }
+        let mut writer = options.open_write(path)?;
+        writer.set_real(0, 0.99331234);
+        writer.write_real_list('R', &[1.5, 1.0 / 3.0])?;
+        writer.close();
+    }
+    let text = std::fs::read_to_string(path).unwrap();
+    Ok(text
+        .lines()
+        .find(|l| l.starts_with('R'))
+        .unwrap()
+        .to_string())
+}
+
+#[test]
+fn test_real_format() -> Result<()> {
+    let path = format!("/tmp/test_real_format_{}.1tst", std::process::id());
+
+    assert_eq!(write_r_line(&path, None)?, "R 0.993312 2 1.500000 0.333333");
+    assert_eq!(
+        write_r_line(&path, Some(RealFormat::Fixed(4)))?,
+        "R 0.9933 2 1.5000 0.3333"
+    );
+    assert_eq!(
+        write_r_line(&path, Some(RealFormat::Scientific(2)))?,
+        "R 9.93e-01 2 1.50e+00 3.33e-01"
+    );
+    assert_eq!(
+        write_r_line(&path, Some(RealFormat::Significant(17)))?,
+        "R 0.99331234000000002 2 1.5 0.33333333333333331"
+    );
+
+    // Significant(17) reads back exactly
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(file.read_line(), 'R');
+    assert_eq!(file.real(0), 0.99331234);
+    assert_eq!(file.real_list(), Some(&[1.5, 1.0 / 3.0][..]));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_set_real_format_midway() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let path = format!("/tmp/test_real_format_midway_{}.1tst", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+        writer.set_real(0, 0.25);
+        writer.write_real_list('R', &[])?;
+        writer.set_real_format(RealFormat::Fixed(1));
+        writer.set_real(0, 0.25);
+        writer.write_real_list('R', &[])?;
+        writer.close();
+    }
+    let text = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().filter(|l| l.starts_with('R')).collect();
+    assert_eq!(lines, ["R 0.250000 0", "R 0.2 0"]);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_real_format_matches_printf() -> Result<()> {
+    let schema = OneSchema::from_text(SCHEMA)?;
+    let path = format!("/tmp/test_real_format_printf_{}.1tst", std::process::id());
+    let values = [
+        0.0, -0.0, 0.5, 2.5, 0.125, 1.0 / 3.0, -2.0 / 3.0, 9.9999999, 99999.5, 1e-5, 0.000123456,
+        123456789.0, 1e21, -1.5e-300, f64::INFINITY, f64::NEG_INFINITY,
+    ];
+    let formats = [
+        (RealFormat::Default, "%f"),
+        (RealFormat::Fixed(0), "%.0f"),
+        (RealFormat::Fixed(3), "%.3f"),
+        (RealFormat::Scientific(0), "%.0e"),
+        (RealFormat::Scientific(4), "%.4e"),
+        (RealFormat::Significant(1), "%.1g"),
+        (RealFormat::Significant(3), "%.3g"),
+        (RealFormat::Significant(17), "%.17g"),
+    ];
+    for (format, printf) in formats {
+        {
+            let mut writer = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+            writer.set_real_format(format);
+            writer.set_real(0, 0.0);
+            writer.write_real_list('R', &values)?;
+            writer.close();
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        let line = text.lines().find(|l| l.starts_with('R')).unwrap();
+        let expected: Vec<String> = values.iter().map(|&v| c_printf(printf, v)).collect();
+        let written: Vec<&str> = line.split(' ').skip(3).collect();
+        assert_eq!(written, expected, "{:?}", format);
+    }
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+/// `value` as C's printf formats it
+fn c_printf(format: &str, value: f64) -> String {
+    let format = std::ffi::CString::new(format).unwrap();
+    let mut buf = [0u8; 512];
+    let n = unsafe {
+        libc::snprintf(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), format.as_ptr(), value)
+    };
+    String::from_utf8(buf[..n as usize].to_vec()).unwrap()
+}
+
+#[test]
+fn test_formatted_lines_match_onelib() -> Result<()> {
+    // Fixed(6) prints as ONElib's own %f, so both files must be the same
+    let schema = OneSchema::from_text(
+        "P 3 tst\nO A 4 3 INT 4 CHAR 4 REAL 6 STRING\nD B 2 4 REAL 11 STRING_LIST\nD C 2 4 REAL 8 INT_LIST\n",
+    )?;
+    let pid = std::process::id();
+    let mut texts = Vec::new();
+    for format in [RealFormat::Default, RealFormat::Fixed(6)] {
+        let path = format!("/tmp/test_real_format_fields_{}.1tst", pid);
+        {
+            let mut writer = OneFile::open_write_new(&path, &schema, "tst", false, 1)?;
+            writer.set_real_format(format);
+            for i in 0..3 {
+                writer.set_int(0, -i);
+                writer.set_char(1, 'x');
+                writer.set_real(2, i as f64 / 7.0);
+                writer.write_string('A', if i == 1 { "" } else { "name" })?;
+                writer.set_real(0, 0.5);
+                writer.write_string_list('B', &["ab", "", "cde"])?;
+                writer.set_real(0, -0.25);
+                writer.write_int_list('C', &[1, -2, 300])?;
+            }
+            writer.close();
+        }
+        texts.push(std::fs::read_to_string(&path).unwrap());
+        std::fs::remove_file(&path).ok();
+    }
+    assert_eq!(texts[0], texts[1]);
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Tests for typed records
+
+use onecode::{FieldValue, ListValue, OneFile, OneSchema, Record, Result};
+
+#[test]
+fn test_read_record_seq() -> Result<()> {
+    let mut file = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let first = file.read_record().unwrap();
+    assert_eq!(first.line_type, 'S');
+    assert!(first.fields.is_empty());
+    assert!(matches!(&first.list, Some(ListValue::Dna(dna)) if dna.len() as i64 == file.len()));
+
+    let id = file.read_record().unwrap();
+    assert_eq!(id.list, Some(ListValue::String("seq1".to_string())));
+
+    let mut count = 2;
+    while file.read_record().is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 20);
+    Ok(())
+}
+
+#[test]
+fn test_read_record_all_types() -> Result<()> {
+    let schema = OneSchema::from_text(
+        "P 3 tst\nO A 3 3 INT 4 REAL 4 CHAR\nD L 1 8 INT_LIST\nD R 1 9 REAL_LIST\nD W 1 11 STRING_LIST\n",
+    )?;
+    let path = format!("/tmp/test_record_{}.1tst", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+    file.set_int(0, 7);
+    file.set_real(1, 0.5);
+    file.set_char(2, 'x');
+    file.write_line('A', 0, None);
+    file.write_int_list('L', &[1, -2, 300])?;
+    file.write_real_list('R', &[1.5, 2.5])?;
+    file.write_string_list('W', &["ab", "", "cde"])?;
+    file.close();
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    let records: Vec<Record> = std::iter::from_fn(|| file.read_record()).collect();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(records.len(), 4);
+    assert_eq!(
+        records[0].fields,
+        vec![FieldValue::Int(7), FieldValue::Real(0.5), FieldValue::Char('x')]
+    );
+    assert_eq!(records[0].list, None);
+    assert_eq!(records[1].list, Some(ListValue::IntList(vec![1, -2, 300])));
+    assert_eq!(records[2].list, Some(ListValue::RealList(vec![1.5, 2.5])));
+    assert_eq!(
+        records[3].list,
+        Some(ListValue::StringList(vec!["ab".to_string(), String::new(), "cde".to_string()]))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_fields() -> Result<()> {
+    let schema =
+        OneSchema::from_text("P 3 tst\nO A 3 3 INT 6 STRING 4 REAL\nD W 1 11 STRING_LIST\n")?;
+    let mut file = OneFile::open_write_vec(&schema, "tst", true, 1)?;
+    file.set_int(0, 7);
+    file.set_real(2, 0.5);
+    file.write_string('A', "name")?;
+    file.write_string_list('W', &["ab", "c"])?;
+    let bytes = file.into_vec()?;
+
+    let mut file = OneFile::open_read_bytes(&bytes, None, None, 1)?;
+    assert!(file.fields().is_empty());
+    file.read_line();
+    // The list field keeps its place between the scalars
+    assert_eq!(
+        file.fields(),
+        vec![
+            FieldValue::Int(7),
+            FieldValue::List(ListValue::String("name".to_string())),
+            FieldValue::Real(0.5)
+        ]
+    );
+    file.read_line();
+    let words = ListValue::StringList(vec!["ab".to_string(), "c".to_string()]);
+    assert_eq!(file.fields(), vec![FieldValue::List(words)]);
+
+    // Records keep the scalars and the list apart
+    file.goto('A', 1)?;
+    let record = file.read_record().unwrap();
+    assert_eq!(record.fields, vec![FieldValue::Int(7), FieldValue::Real(0.5)]);
+    Ok(())
+}
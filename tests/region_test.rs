@@ -0,0 +1,87 @@
+//! Tests for region queries over indexed alignments
+
+use onecode::{AlnReader, GdbIndex, GdbReader, OneError, OneFile, RegionIndex, Result};
+
+/// Target intervals in scaffold coordinates, by brute force over all alignments
+fn scan(path: &str, name: &str, start: i64, end: i64) -> Result<Vec<(i64, i64, i64, i64)>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let group = if file.stats('g')?.0 > 1 { 2 } else { 1 };
+    let index = GdbIndex::new(&GdbReader::from_alignment(&mut file, group)?);
+    let mut hits = Vec::new();
+    for a in AlnReader::open(path)? {
+        let id = a.target_id;
+        let (offset, len) = (index.offset(id).unwrap(), index.contig_length(id).unwrap());
+        let (s, e) = if a.reverse {
+            (len - a.target_end, len - a.target_start)
+        } else {
+            (a.target_start, a.target_end)
+        };
+        if index.name(id) == Some(name) && offset + s < end && offset + e > start {
+            hits.push((a.query_id, a.query_start, a.target_id, a.target_start));
+        }
+    }
+    hits.sort();
+    Ok(hits)
+}
+
+#[test]
+fn test_query_matches_scan() -> Result<()> {
+    let path = "data/test.1aln";
+    let mut index = RegionIndex::open(path)?;
+    assert_eq!(index.len(), AlnReader::open(path)?.count());
+
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let scaffolds = GdbReader::from_alignment(&mut file, 1)?
+        .scaffolds()
+        .to_vec();
+    let mut total = 0;
+    for scaffold in &scaffolds {
+        let len = scaffold.length;
+        for (start, end) in [
+            (0, len),
+            (0, len / 3),
+            (len / 3, 2 * len / 3),
+            (len / 2, len / 2 + 1),
+            (len, len + 10),
+        ] {
+            let objects = index.overlapping(&scaffold.name, start, end)?;
+            let alignments = index.query(&scaffold.name, start, end)?;
+            assert_eq!(objects.len(), alignments.len());
+
+            let mut found: Vec<_> = alignments
+                .iter()
+                .map(|a| (a.query_id, a.query_start, a.target_id, a.target_start))
+                .collect();
+            found.sort();
+            assert_eq!(
+                found,
+                scan(path, &scaffold.name, start, end)?,
+                "{}:{}-{}",
+                scaffold.name,
+                start,
+                end
+            );
+            if (start, end) == (0, len) {
+                total += found.len();
+            }
+        }
+    }
+    // Every alignment overlaps its whole target scaffold
+    assert_eq!(total, index.len());
+    Ok(())
+}
+
+#[test]
+fn test_query_errors() -> Result<()> {
+    let mut index = RegionIndex::open("data/test.1aln")?;
+    let err = index.query("no_such_scaffold", 0, 100).err().unwrap();
+    assert_eq!(
+        err,
+        OneError::SequenceNotFound("no_such_scaffold".to_string())
+    );
+
+    // ASCII files have no object index to load alignments from
+    let ascii = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    assert!(RegionIndex::new(ascii).is_err());
+    Ok(())
+}
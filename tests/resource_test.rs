@@ -0,0 +1,115 @@
+//! Leak checks for the C file handles behind `OneFile`, by counting the
+//! process's open file descriptors
+//!
+//! Descriptors are counted process-wide, so the tests in this file take a
+//! lock to run one at a time.
+
+#![cfg(target_os = "linux")]
+
+use onecode::{AlnReader, GdbReader, OneFile, OneSchema, RegionIndex, Result};
+use std::sync::Mutex;
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd").unwrap().count()
+}
+
+/// Run `f` with panic messages silenced, returning whether it panicked
+fn panics(f: impl FnOnce() + std::panic::UnwindSafe) -> bool {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let panicked = std::panic::catch_unwind(f).is_err();
+    std::panic::set_hook(hook);
+    panicked
+}
+
+#[test]
+fn test_thread_handles_closed() -> Result<()> {
+    let _guard = LOCK.lock().unwrap();
+    let base = open_fds();
+
+    for nthreads in [1, 4] {
+        let file = OneFile::open_read("data/test.1aln", None, None, nthreads)?;
+        assert_eq!(open_fds(), base + nthreads as usize);
+        drop(file);
+        assert_eq!(open_fds(), base);
+
+        OneFile::open_read("data/test.1aln", None, None, nthreads)?.close();
+        assert_eq!(open_fds(), base);
+    }
+
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\n")?;
+    let path = format!("/tmp/test_resource_{}.1tst", std::process::id());
+    for binary in [true, false] {
+        let mut file = OneFile::open_write_new(&path, &schema, "tst", binary, 4)?;
+        std::thread::scope(|scope| {
+            for mut handle in file.split_threads() {
+                scope.spawn(move || {
+                    handle.set_int(0, handle.index() as i64);
+                    handle.write_line('T', 0, None);
+                });
+            }
+        });
+        file.close();
+        assert_eq!(open_fds(), base);
+        assert_eq!(OneFile::open_read(&path, None, None, 1)?.lines().count(), 4);
+        assert_eq!(open_fds(), base);
+    }
+    std::fs::remove_file(&path).ok();
+
+    // Failed opens, including a schema mismatch found after the thread
+    // handles were made
+    let seq = OneSchema::seq()?;
+    assert!(OneFile::open_read("/nonexistent.1aln", None, None, 4).is_err());
+    assert!(OneFile::open_read("data/test.1aln", Some(&seq), Some("seq"), 4).is_err());
+    assert!(OneFile::open_read("-", None, None, 4).is_err());
+    assert_eq!(open_fds(), base);
+
+    // Readers that open second handles
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 4)?;
+    GdbReader::from_alignment(&mut file, 1)?;
+    file.gdb_index()?;
+    drop(file);
+    assert_eq!(AlnReader::open("data/test.1aln")?.count(), 72);
+    RegionIndex::open("data/test.1aln")?
+        .query("no_such_scaffold", 0, 1)
+        .ok();
+    assert_eq!(open_fds(), base);
+    Ok(())
+}
+
+#[test]
+fn test_thread_handles_closed_after_worker_panic() -> Result<()> {
+    let _guard = LOCK.lock().unwrap();
+    let base = open_fds();
+
+    assert!(panics(|| {
+        let mut file = OneFile::open_read("data/test.1aln", None, None, 4).unwrap();
+        let ranges = file.slice_ranges('A').unwrap();
+        std::thread::scope(|scope| {
+            for (mut handle, range) in file.split_threads().into_iter().zip(ranges) {
+                scope.spawn(move || {
+                    handle.goto('A', range.start).unwrap();
+                    handle.read_line();
+                    assert!(handle.index() != 2, "worker failed");
+                });
+            }
+        });
+    }));
+    assert_eq!(open_fds(), base);
+
+    #[cfg(feature = "rayon")]
+    {
+        assert!(panics(|| {
+            let mut file = OneFile::open_read("data/test.1aln", None, None, 4).unwrap();
+            file.par_objects('A', |_, object| {
+                assert!(object != 50, "closure failed");
+                Ok(())
+            })
+            .unwrap();
+        }));
+        assert_eq!(open_fds(), base);
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Tests for building schemas in code
+
+use onecode::{OneError, OneFile, OneSchema, OneType, Result, SchemaBuilder};
+use OneType::*;
+
+#[test]
+fn test_builder_matches_aln_schema() -> Result<()> {
+    let schema = SchemaBuilder::new("aln")
+        .data('t', &[oneINT])
+        .object('g', &[])
+        .group('S')
+        .object('S', &[oneSTRING])
+        .data('G', &[oneINT])
+        .data('C', &[oneINT])
+        .data('M', &[oneINT_LIST])
+        .object('a', &[])
+        .group('A')
+        .data('p', &[oneINT, oneINT])
+        .object('A', &[oneINT; 6])
+        .comment("a_id a_start a_end b_id b_start b_end")
+        .data('L', &[oneINT, oneINT])
+        .data('R', &[])
+        .data('D', &[oneINT])
+        .data('T', &[oneINT_LIST])
+        .data('X', &[oneINT_LIST])
+        .data('Q', &[oneINT])
+        .data('E', &[oneINT])
+        .data('Z', &[oneSTRING])
+        .build()?;
+    assert_eq!(schema.fingerprint(), OneSchema::aln()?.fingerprint());
+    Ok(())
+}
+
+#[test]
+fn test_built_schema_writes_files() -> Result<()> {
+    let schema = SchemaBuilder::new("tst")
+        .secondary("sub")
+        .object('P', &[oneINT, oneREAL])
+        .data('N', &[oneCHAR, oneSTRING])
+        .build()?;
+    assert!(schema.text().starts_with("P 3 tst\nS 3 sub\nO P 2 3 INT 4 REAL\n"));
+
+    let path = format!("/tmp/test_schema_builder_{}.1tst", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &schema, "tst", true, 1)?;
+        writer.set_int(0, 3);
+        writer.set_real(1, 0.5);
+        writer.write_line('P', 0, None);
+        writer.set_char(0, 'x');
+        writer.write_string('N', "name")?;
+        writer.close();
+    }
+
+    let mut reader = OneFile::open_read(&path, None, Some("tst"), 1)?;
+    assert_eq!(reader.read_line(), 'P');
+    assert_eq!((reader.int(0), reader.real(1)), (3, 0.5));
+    assert_eq!(reader.read_line(), 'N');
+    assert_eq!((reader.char(0), reader.string()?), ('x', "name"));
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_builder_rejects_invalid_schemas() {
+    let rejected = |builder: SchemaBuilder| match builder.build() {
+        Err(OneError::SchemaError(msg)) => msg,
+        _ => panic!("schema should have been rejected"),
+    };
+
+    let msg = rejected(SchemaBuilder::new("tst").object('1', &[oneINT]));
+    assert!(msg.contains("not an ASCII letter"), "{}", msg);
+
+    let msg = rejected(SchemaBuilder::new("tst").object('A', &[]).data('A', &[oneINT]));
+    assert!(msg.contains("more than once"), "{}", msg);
+
+    let msg = rejected(SchemaBuilder::new("tst").data('L', &[oneSTRING, oneINT_LIST]));
+    assert!(msg.contains("at most one"), "{}", msg);
+
+    let msg = rejected(SchemaBuilder::new("tst").data('W', &[oneINT; 33]));
+    assert!(msg.contains("33 fields"), "{}", msg);
+
+    let msg = rejected(SchemaBuilder::new("tst").group('A').object('A', &[]));
+    assert!(msg.contains("before any object"), "{}", msg);
+
+    let msg = rejected(SchemaBuilder::new("tst").object('g', &[]).group('A').data('A', &[]));
+    assert!(msg.contains("not an object"), "{}", msg);
+
+    let msg = rejected(SchemaBuilder::new("").object('A', &[]));
+    assert!(msg.contains("primary"), "{}", msg);
+}
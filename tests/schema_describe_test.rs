@@ -0,0 +1,54 @@
+//! Tests for the readable schema description
+
+use onecode::{OneFile, OneSchema, Result};
+
+#[test]
+fn test_describe_aln() -> Result<()> {
+    let text = OneSchema::aln()?.describe();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "aln - ALIGNMENT");
+    assert!(lines.contains(&"type  kind    fields                   comment"));
+    assert!(lines
+        .contains(&"A     object  INT INT INT INT INT INT  a_id a_start a_end b_id b_start b_end"));
+    assert!(lines.contains(&"T     data    [INT_LIST]               trace points in b"));
+    assert!(lines
+        .contains(&"R     data                             flag: reverse-complement sequence b"));
+    assert_eq!(
+        &lines[lines.len() - 2..],
+        ["group g contains S", "group a contains A"]
+    );
+
+    // A file's own schema describes the same line types
+    let file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let from_file = OneSchema::from_text(&file.schema_text()?)?.describe();
+    let types = |text: &str| -> Vec<String> {
+        text.lines()
+            .filter(|l| l.contains(" object ") || l.contains(" data "))
+            .map(|l| l[..14].to_string())
+            .collect()
+    };
+    assert_eq!(types(&from_file), types(&text));
+    Ok(())
+}
+
+#[test]
+fn test_describe_sections() -> Result<()> {
+    let schema = OneSchema::from_text(
+        "P 3 seq  SEQUENCE\nS 4 fseq\nO S 1 3 DNA   bases\nD N 3 3 INT 4 CHAR 3 INT\nP 3 foo\nO B 1 3 INT\n",
+    )?;
+    assert_eq!(
+        schema.describe(),
+        "seq - SEQUENCE\n\
+         secondary types: fseq\n\
+         \n\
+         type  kind    fields        comment\n\
+         S     object  [DNA]         bases\n\
+         N     data    INT CHAR INT\n\
+         \n\
+         foo\n\
+         \n\
+         type  kind    fields  comment\n\
+         B     object  INT\n"
+    );
+    Ok(())
+}
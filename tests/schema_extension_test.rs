@@ -0,0 +1,81 @@
+//! Tests for standard schemas and user schema extension
+
+use onecode::{OneError, OneFile, OneSchema, Result};
+
+#[test]
+fn test_standard_schemas() -> Result<()> {
+    OneSchema::seq()?;
+    OneSchema::gdb()?;
+    OneSchema::aln()?;
+    Ok(())
+}
+
+#[test]
+fn test_extend_seq_schema() -> Result<()> {
+    let schema = OneSchema::seq()?.extend_with_text("D X 1 3 INT  custom annotation")?;
+
+    let path = format!("/tmp/test_extend_{}.1seq", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &schema, "seq", true, 1)?;
+        let dna = b"acgt";
+        writer.write_line('S', dna.len() as i64, Some(dna.as_ptr() as *mut std::ffi::c_void));
+        writer.set_int(0, 7);
+        writer.write_line('X', 0, None);
+        writer.close();
+    }
+
+    let mut reader = OneFile::open_read(&path, None, Some("seq"), 1)?;
+    assert_eq!(reader.read_line(), 'S');
+    assert_eq!(reader.read_line(), 'X');
+    assert_eq!(reader.int(0), 7);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_extend_rejects_collisions() -> Result<()> {
+    let seq = OneSchema::seq()?;
+
+    let err = seq.extend_with_text("D I 1 3 INT").err().unwrap();
+    assert!(matches!(err, OneError::SchemaError(_)));
+
+    // Duplicate definitions within the extension itself are also rejected
+    assert!(seq.extend_with_text("D X 1 3 INT\nD X 1 4 REAL").is_err());
+
+    // A new primary type is not an extension
+    assert!(seq.extend_with_text("P 3 foo\nO F 1 3 INT").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_schema_fingerprint() -> Result<()> {
+    let seq = OneSchema::seq()?;
+    assert_eq!(seq.fingerprint(), OneSchema::seq()?.fingerprint());
+    assert_ne!(seq.fingerprint(), OneSchema::gdb()?.fingerprint());
+    assert_ne!(seq.fingerprint(), OneSchema::aln()?.fingerprint());
+
+    // Comments and spacing do not matter, field types do
+    let plain = OneSchema::from_text(
+        "P 3 seq\nO S 1 3 DNA\nD I 1 6 STRING\nD Q 1 6 STRING\nD N 3 3 INT 4 CHAR 3 INT\n",
+    )?;
+    assert_eq!(plain.fingerprint(), seq.fingerprint());
+    let extended = seq.extend_with_text("D X 1 3 INT")?;
+    assert_ne!(extended.fingerprint(), seq.fingerprint());
+
+    // A file written with a schema reports the same fingerprint
+    let path = format!("/tmp/test_fingerprint_{}.1seq", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&path, &seq, "seq", true, 1)?;
+        assert_eq!(writer.schema_fingerprint()?, seq.fingerprint());
+        let dna = b"acgt";
+        writer.write_line('S', dna.len() as i64, Some(dna.as_ptr() as *mut std::ffi::c_void));
+        writer.close();
+    }
+    let reader = OneFile::open_read(&path, None, Some("seq"), 1)?;
+    assert_eq!(reader.schema_fingerprint()?, seq.fingerprint());
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
@@ -1,7 +1,7 @@
-/// Test to reproduce schema temporary file cleanup bug
-///
-/// Run with: cargo test --test schema_parallel_test
-/// Expected: Both tests should pass when run together
+//! Test to reproduce schema temporary file cleanup bug
+//!
+//! Run with: cargo test --test schema_parallel_test
+//! Expected: Both tests should pass when run together
 
 use onecode::{OneSchema, OneFile};
 
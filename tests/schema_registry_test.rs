@@ -0,0 +1,63 @@
+//! Tests for process-wide registration of custom file type schemas
+
+use onecode::schema::{registered_schema, unregister_schema};
+use onecode::{register_schema, OneError, OneFile, OneSchema, Result};
+
+const READS_SCHEMA: &str = "P 3 seq\nS 5 reads\nO S 1 3 DNA\nD Q 1 6 STRING\n";
+
+#[test]
+fn test_registered_type_checks_files() -> Result<()> {
+    let schema = OneSchema::from_text(READS_SCHEMA)?;
+    register_schema("reads", &schema)?;
+
+    let good = format!("/tmp/test_registry_good_{}.1seq", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&good, &schema, "reads", true, 1)?;
+        writer.write_dna('S', b"acgt")?;
+        writer.write_string('Q', "IIII")?;
+        writer.close();
+    }
+    let mut reader = OneFile::open_read(&good, None, Some("reads"), 1)?;
+    assert_eq!(reader.read_line(), 'S');
+
+    // A seq file whose 'Q' lines hold something else does not open as "reads"
+    let other = OneSchema::from_text("P 3 seq\nO S 1 3 DNA\nD Q 1 3 INT\n")?;
+    let bad = format!("/tmp/test_registry_bad_{}.1seq", std::process::id());
+    {
+        let mut writer = OneFile::open_write_new(&bad, &other, "seq", true, 1)?;
+        writer.write_dna('S', b"acgt")?;
+        writer.set_int(0, 40);
+        writer.write_line('Q', 0, None);
+        writer.close();
+    }
+    assert!(matches!(
+        OneFile::open_read(&bad, None, Some("reads"), 1),
+        Err(OneError::SchemaMismatch { path: Some(_), .. })
+    ));
+    // ...but does without the registered type
+    OneFile::open_read(&bad, None, None, 1)?;
+
+    assert!(unregister_schema("reads"));
+    assert!(!unregister_schema("reads"));
+    OneFile::open_read(&bad, None, Some("reads"), 1)?;
+
+    std::fs::remove_file(&good).ok();
+    std::fs::remove_file(&bad).ok();
+    Ok(())
+}
+
+#[test]
+fn test_register_requires_declared_name() -> Result<()> {
+    let schema = OneSchema::from_text(READS_SCHEMA)?;
+    assert!(matches!(
+        register_schema("contigs", &schema),
+        Err(OneError::SchemaError(_))
+    ));
+    assert!(registered_schema("contigs")?.is_none());
+
+    register_schema("seq", &schema)?;
+    let copy = registered_schema("seq")?.unwrap();
+    assert_eq!(copy.fingerprint(), schema.fingerprint());
+    unregister_schema("seq");
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use onecode::OneFile;
+use onecode::file::SLOW_PATH_WARN_THRESHOLD;
+use onecode::{AccessStats, OneFile};
 
 #[test]
 fn test_get_all_sequence_names() {
@@ -99,3 +100,79 @@ fn test_alignment_with_sequence_names() {
 
     assert!(alignment_count > 0, "Should have found some alignments");
 }
+
+#[test]
+fn test_access_stats() {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)
+        .expect("Failed to open test.1aln");
+    file.set_slow_path_warnings(false);
+    assert_eq!(file.access_stats(), AccessStats::default());
+
+    file.get_all_sequence_names();
+    for _ in 0..SLOW_PATH_WARN_THRESHOLD {
+        file.get_sequence_name(0);
+    }
+
+    let stats = file.access_stats();
+    assert_eq!(stats.sequence_name_lookups, SLOW_PATH_WARN_THRESHOLD);
+    assert_eq!(stats.gdb_scans, SLOW_PATH_WARN_THRESHOLD + 1);
+}
+
+#[test]
+fn test_gdb_index() {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)
+        .expect("Failed to open test.1aln");
+    file.set_slow_path_warnings(false);
+    let names = file.get_all_sequence_names();
+    let lengths = file.get_all_sequence_lengths();
+    let offsets = file.get_all_contig_offsets();
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)
+        .expect("Failed to open test.1aln");
+    let index = file.gdb_index().expect("Should index group 1").clone();
+    assert_eq!(index.num_contigs(), names.len());
+    for (id, name) in &names {
+        assert_eq!(index.name(*id), Some(name.as_str()));
+        assert_eq!(index.length(*id), lengths.get(id).copied());
+        assert_eq!(index.offset(*id), offsets.get(id).map(|o| o.0));
+        assert_eq!(index.contig_length(*id), offsets.get(id).map(|o| o.1));
+    }
+    assert_eq!(index.name(999), None);
+    assert_eq!(index.name(-1), None);
+
+    // Lookups while reading neither move the read position nor rescan
+    let mut alignments = 0;
+    while file.read_line() != '\0' {
+        if file.line_type() == 'A' {
+            let (a_id, b_id) = (file.int(0), file.int(3));
+            assert!(file.gdb_index().unwrap().name(a_id).is_some());
+            assert!(file.gdb_index().unwrap().name(b_id).is_some());
+            alignments += 1;
+        }
+    }
+    assert_eq!(alignments, 72);
+    assert_eq!(file.access_stats().gdb_scans, 1);
+
+    // The test file embeds a single skeleton
+    assert!(file.group_gdb_index(2).is_err());
+}
+
+#[test]
+fn test_contig_ids_by_name() {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)
+        .expect("Failed to open test.1aln");
+    let names = file.get_all_sequence_names();
+    let ids = file.get_all_contig_ids_by_name();
+    assert_eq!(ids.values().map(Vec::len).sum::<usize>(), names.len());
+    for (name, contigs) in &ids {
+        assert!(contigs.windows(2).all(|w| w[0] < w[1]));
+        assert!(contigs.iter().all(|id| names[id] == *name));
+    }
+
+    let name0 = names[&0].clone();
+    let contigs = file.get_contig_ids_by_name(&name0);
+    assert_eq!(contigs.first(), Some(&0));
+    assert_eq!(file.gdb_index().unwrap().contig_ids(&name0), contigs.as_slice());
+    assert!(file.get_contig_ids_by_name("no_such_scaffold").is_empty());
+    assert!(file.gdb_index().unwrap().contig_ids("no_such_scaffold").is_empty());
+}
@@ -0,0 +1,265 @@
+//! Tests for the SequenceStore trait over .1seq and GDB readers
+
+use onecode::seq::{concat_all, minimizers};
+use onecode::{CachedSequenceStore, GdbReader, OneError, OneFile, Result, SeqReader, SequenceStore};
+
+/// Generic helper, as a polishing tool would write it
+fn fetch_prefix<S: SequenceStore>(store: &mut S, name: &str, n: usize) -> Result<Vec<u8>> {
+    let len = store.len(name)?;
+    store.fetch(name, 0..n.min(len))
+}
+
+/// Copy small.seq to a binary file so the object index is available
+fn write_binary_copy(path: &str) -> Result<()> {
+    let mut reader = OneFile::open_read("ONEcode/TEST/small.seq", None, None, 1)?;
+    let mut writer = OneFile::open_write_from(path, &reader, true, 1)?;
+    loop {
+        match reader.read_line() {
+            '\0' => break,
+            'S' => {
+                let seq = reader.dna_char().unwrap().to_vec();
+                writer.write_line('S', seq.len() as i64, Some(seq.as_ptr() as *mut std::ffi::c_void));
+            }
+            'I' => {
+                let id = reader.string().unwrap().to_string();
+                writer.write_line('I', id.len() as i64, Some(id.as_ptr() as *mut std::ffi::c_void));
+            }
+            _ => {}
+        }
+    }
+    writer.close();
+    Ok(())
+}
+
+#[test]
+fn test_seq_reader_ascii() -> Result<()> {
+    let mut reader = SeqReader::open("ONEcode/TEST/small.seq")?;
+
+    assert_eq!(reader.num_sequences(), 10);
+    assert_eq!(reader.len("seq2")?, 72);
+    assert_eq!(fetch_prefix(&mut reader, "seq1", 5)?, b"cttag");
+
+    // Fetch backwards to force a rescan of the ASCII file
+    assert_eq!(reader.fetch("seq4", 37..42)?, b"ttgaa");
+    assert_eq!(reader.fetch("seq2", 0..3)?, b"ctt");
+
+    Ok(())
+}
+
+#[test]
+fn test_seq_reader_binary() -> Result<()> {
+    let path = format!("/tmp/test_store_{}.1seq", std::process::id());
+    write_binary_copy(&path)?;
+
+    let mut reader = SeqReader::open(&path)?;
+    assert_eq!(reader.fetch("seq4", 37..42)?, b"ttgaa");
+    assert_eq!(reader.fetch("seq1", 0..5)?, b"cttag");
+    assert_eq!(reader.name(1), Some("seq2"));
+
+    // Ranges not starting or ending on a byte of the 2-bit form
+    let seq2 = reader.sequence(1)?;
+    for range in [5..70, 3..4, 13..13, 0..72] {
+        assert_eq!(reader.fetch("seq2", range.clone())?, &seq2[range]);
+    }
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_store_errors() -> Result<()> {
+    let mut reader = SeqReader::open("ONEcode/TEST/small.seq")?;
+
+    assert_eq!(
+        reader.len("missing"),
+        Err(OneError::SequenceNotFound("missing".to_string()))
+    );
+    assert!(reader.fetch("seq1", 40..60).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_gdb_reader_embedded() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut gdb = GdbReader::from_alignment(&mut file, 1)?;
+
+    assert_eq!(gdb.scaffolds().len(), 9);
+    assert_eq!(gdb.len("gi|568815592:31353871-31357211")?, 3341);
+
+    // The skeleton has lengths but no bases
+    assert!(gdb.fetch("gi|568815592:31353871-31357211", 0..10).is_err());
+
+    Ok(())
+}
+
+/// In-memory store counting how often the backing data is decoded
+struct CountingStore {
+    seqs: Vec<(String, Vec<u8>)>,
+    fetches: usize,
+}
+
+impl SequenceStore for CountingStore {
+    fn len(&self, name: &str) -> Result<usize> {
+        self.seqs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, s)| s.len())
+            .ok_or_else(|| OneError::SequenceNotFound(name.to_string()))
+    }
+
+    fn fetch(&mut self, name: &str, range: std::ops::Range<usize>) -> Result<Vec<u8>> {
+        self.fetches += 1;
+        let len = self.len(name)?;
+        assert!(range.end <= len);
+        let (_, seq) = self.seqs.iter().find(|(n, _)| n == name).unwrap();
+        Ok(seq[range].to_vec())
+    }
+}
+
+#[test]
+fn test_cached_store_lru() -> Result<()> {
+    let inner = CountingStore {
+        seqs: vec![
+            ("a".to_string(), b"acgtacgtac".to_vec()),
+            ("b".to_string(), b"ggggcccc".to_vec()),
+            ("c".to_string(), b"tttttttttttttttttttt".to_vec()),
+        ],
+        fetches: 0,
+    };
+    let mut cache = CachedSequenceStore::new(inner, 20);
+
+    assert_eq!(cache.fetch("a", 0..4)?, b"acgt");
+    assert_eq!(cache.fetch("a", 4..8)?, b"acgt");
+    assert_eq!(cache.fetch("b", 0..2)?, b"gg");
+    assert_eq!(cache.inner().fetches, 2);
+    assert_eq!(cache.cached_bytes(), 18);
+
+    // Touch "a" so "b" is least recently used, then load "c" (20 bytes):
+    // both must go to fit it
+    cache.fetch("a", 0..1)?;
+    cache.fetch("c", 0..1)?;
+    assert_eq!(cache.cached_bytes(), 20);
+    cache.fetch("c", 5..6)?;
+    assert_eq!(cache.inner().fetches, 3);
+
+    // Out-of-range requests fail even on a cache hit
+    assert!(cache.fetch("c", 10..30).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cached_store_over_seq_reader() -> Result<()> {
+    let reader = SeqReader::open("ONEcode/TEST/small.seq")?;
+    let mut cache = CachedSequenceStore::new(reader, 1000);
+
+    assert_eq!(cache.fetch("seq4", 37..42)?, b"ttgaa");
+    assert_eq!(cache.fetch("seq4", 0..4)?, b"tttt");
+    assert_eq!(cache.cached_bytes(), 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_stream_sequence() -> Result<()> {
+    let path = format!("/tmp/test_store_{}_stream.1seq", std::process::id());
+    write_binary_copy(&path)?;
+
+    for file in ["ONEcode/TEST/small.seq", path.as_str()] {
+        let mut reader = SeqReader::open(file)?;
+        for i in [3, 0, 9] {
+            let whole = reader.sequence(i)?;
+            let chunks: Vec<Vec<u8>> = reader
+                .stream_sequence(i)?
+                .chunk_size(7)
+                .map(|c| c.into_owned())
+                .collect();
+            assert_eq!(chunks.concat(), whole);
+            assert!(chunks.iter().rev().skip(1).all(|c| c.len() == 7));
+        }
+        assert_eq!(reader.stream_sequence(1)?.count(), 1);
+        assert!(reader.stream_sequence(10).is_err());
+    }
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_concat_all() -> Result<()> {
+    let path = format!("/tmp/test_store_{}_concat.1seq", std::process::id());
+    write_binary_copy(&path)?;
+
+    for file in ["ONEcode/TEST/small.seq", path.as_str()] {
+        let mut reader = SeqReader::open(file)?;
+        let (bases, offsets) = concat_all(&mut reader)?;
+        assert_eq!(offsets.len(), 10);
+        assert_eq!(bases.len() as i64, (0..10).map(|i| reader.length(i).unwrap()).sum::<i64>());
+        let mut end = 0;
+        for (i, (name, offset, len)) in offsets.iter().enumerate() {
+            assert_eq!(Some(name.as_str()), reader.name(i).or(Some(&i.to_string())));
+            assert_eq!(*offset, end);
+            assert_eq!(bases[*offset..offset + len], reader.sequence(i)?);
+            end = offset + len;
+        }
+    }
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+/// (w,k)-minimizers of `seq` by brute force over every window
+fn naive_minimizers(seq: &[u8], k: usize, w: usize) -> Vec<(usize, u64)> {
+    let code = |b: u8| match b {
+        b'c' => 1u64,
+        b'g' => 2,
+        b't' => 3,
+        _ => 0,
+    };
+    let hash = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    };
+    let hashes: Vec<u64> = seq
+        .windows(k)
+        .map(|kmer| {
+            let forward = kmer.iter().fold(0, |h, &b| h << 2 | code(b));
+            let reverse = kmer.iter().rev().fold(0, |h, &b| h << 2 | (3 - code(b)));
+            hash(forward.min(reverse))
+        })
+        .collect();
+    let mut found: Vec<(usize, u64)> = Vec::new();
+    for start in 0..(hashes.len() + 1).saturating_sub(w) {
+        let window = &hashes[start..start + w];
+        let min = *window.iter().min().unwrap();
+        let position = start + window.iter().position(|&h| h == min).unwrap();
+        if found.last().map(|&(p, _)| p) != Some(position) {
+            found.push((position, min));
+        }
+    }
+    found
+}
+
+#[test]
+fn test_minimizers() -> Result<()> {
+    let path = format!("/tmp/test_store_{}_minimizers.1seq", std::process::id());
+    write_binary_copy(&path)?;
+
+    let mut ascii = SeqReader::open("ONEcode/TEST/small.seq")?;
+    let mut binary = SeqReader::open(&path)?;
+    for (k, w) in [(5, 4), (15, 10), (32, 1)] {
+        for i in 0..10 {
+            let expected = naive_minimizers(&ascii.sequence(i)?, k, w);
+            assert!(!expected.is_empty());
+            assert_eq!(minimizers(&mut ascii, i, k, w)?.collect::<Vec<_>>(), expected);
+            assert_eq!(minimizers(&mut binary, i, k, w)?.collect::<Vec<_>>(), expected);
+        }
+    }
+    assert!(minimizers(&mut ascii, 0, 33, 1).is_err());
+    assert!(minimizers(&mut ascii, 0, 5, 0).is_err());
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! Tests for content-based file format detection
+
+use onecode::{sniff, FileKind, OneFile, OneSchema, Result};
+use std::io::Write;
+
+#[test]
+fn test_sniff_one_files() -> Result<()> {
+    assert_eq!(
+        sniff("data/test.1aln"),
+        Some(FileKind::OneBinary {
+            file_type: "aln".to_string()
+        })
+    );
+
+    let schema = OneSchema::from_text("P 3 seq\nO S 1 3 DNA\nD I 1 6 STRING\n")?;
+    let path = format!("/tmp/test_sniff_{}.1seq", std::process::id());
+    for binary in [false, true] {
+        let mut file = OneFile::open_write_new(&path, &schema, "seq", binary, 1)?;
+        file.write_dna('S', b"acgt")?;
+        file.close();
+
+        let kind = sniff(&path).unwrap();
+        assert_eq!(kind.file_type(), Some("seq"));
+        assert_eq!(matches!(kind, FileKind::OneBinary { .. }), binary);
+    }
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_sniff_other_files() {
+    let path = format!("/tmp/test_sniff_{}.fa", std::process::id());
+    std::fs::write(&path, ">chr1\nacgt\n").unwrap();
+    assert_eq!(sniff(&path), Some(FileKind::Other));
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b">chr1\nacgt\n").unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+    assert_eq!(sniff(&path), Some(FileKind::Gzip));
+    assert_eq!(FileKind::Gzip.to_string(), "gzip-compressed file");
+
+    std::fs::write(&path, "").unwrap();
+    assert_eq!(sniff(&path), Some(FileKind::Other));
+    assert_eq!(sniff("/nonexistent/file.1seq"), None);
+
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,186 @@
+//! Tests for subset line type statistics and the statistics accumulator
+
+use onecode::convert::transcode;
+use onecode::stats::subset_counts;
+use onecode::{OneFile, Result, StatsAccumulator};
+
+#[test]
+fn test_subset_counts_keep_all_matches_header() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let deltas = subset_counts(&mut file, |_| true);
+    assert!(!deltas.is_empty());
+    for delta in &deltas {
+        assert_eq!(delta.subset, delta.header, "line type {}", delta.line_type);
+        assert_eq!(delta.removed(), 0);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_subset_counts_drops_data_with_objects() -> Result<()> {
+    // Count the 'R' flags of odd-numbered alignments by hand
+    let mut expected_r = 0;
+    {
+        let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+        let mut n = 0;
+        loop {
+            match file.read_line() {
+                '\0' => break,
+                'A' => n += 1,
+                'R' if n % 2 == 1 => expected_r += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut n = 0;
+    let deltas = subset_counts(&mut file, |line| {
+        if line.line_type() != 'A' {
+            return true;
+        }
+        n += 1;
+        n % 2 == 1
+    });
+
+    let a = deltas.iter().find(|d| d.line_type == 'A').unwrap();
+    assert_eq!((a.header.count, a.subset.count, a.removed()), (72, 36, 36));
+    let r = deltas.iter().find(|d| d.line_type == 'R').unwrap();
+    assert_eq!(r.header.count, 16);
+    assert_eq!(r.subset.count, expected_r);
+
+    // Scaffold objects are untouched, and so are their contigs
+    for t in ['S', 'C'] {
+        let d = deltas.iter().find(|d| d.line_type == t).unwrap();
+        assert_eq!(d.subset, d.header);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_all_stats() -> Result<()> {
+    let file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let stats = file.all_stats();
+    let types: String = stats.iter().map(|s| s.line_type).collect();
+    assert_eq!(types, "ACDEGLMQRSTXZagpt");
+
+    for s in &stats {
+        let (count, max, total) = file.stats(s.line_type)?;
+        assert_eq!((s.counts.count, s.counts.max, s.counts.total), (count, max, total));
+    }
+    let alignments = stats.iter().find(|s| s.line_type == 'A').unwrap();
+    assert!(alignments.is_object && !alignments.is_list);
+    assert_eq!(alignments.counts.count, 72);
+    let trace = stats.iter().find(|s| s.line_type == 'T').unwrap();
+    assert!(!trace.is_object && trace.is_list);
+    assert!(trace.counts.total > 0);
+    let unused = stats.iter().find(|s| s.line_type == 'Z').unwrap();
+    assert_eq!(unused.counts.count, 0);
+    Ok(())
+}
+
+#[test]
+fn test_accumulator_matches_header() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut acc = StatsAccumulator::new();
+    acc.add_file(&mut file);
+    assert_eq!(acc.stats(), file.all_stats());
+
+    let trace = acc.counts('T');
+    let histogram = acc.length_histogram('T');
+    assert_eq!(histogram.iter().sum::<i64>(), trace.count);
+    assert_eq!(histogram.len(), 64 - trace.max.leading_zeros() as usize + 1);
+    assert!(acc.length_histogram('A').is_empty());
+    assert!(acc.max_per_object('A', 'T').0 >= 1);
+    assert_eq!(acc.max_per_object('T', 'A'), (0, 0));
+    Ok(())
+}
+
+#[test]
+fn test_accumulator_merges_parts() -> Result<()> {
+    let mut whole = StatsAccumulator::new();
+    whole.add_file(&mut OneFile::open_read("data/test.1aln", None, None, 1)?);
+    let lines = whole.stats().iter().map(|s| s.counts.count).sum::<i64>();
+
+    // Split inside objects as well as between them, and at either end
+    for split in [0, 1, 2, 7, lines / 3, lines / 2 + 1, lines - 1, lines] {
+        let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+        let (mut first, mut second) = (StatsAccumulator::new(), StatsAccumulator::new());
+        let mut n = 0;
+        while file.read_line() != '\0' {
+            if file.line_type().is_ascii_alphabetic() {
+                n += 1;
+            }
+            if n <= split {
+                first.add_line(&file);
+            } else {
+                second.add_line(&file);
+            }
+        }
+        first.merge(second);
+        assert_eq!(first, whole, "split after {} lines", split);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_accumulator_sets_ascii_header_counts() -> Result<()> {
+    // Keep the odd-numbered alignments, counted before the output header is written
+    let pid = std::process::id();
+    let ascii = format!("/tmp/test_stats_subset_{}.1aln", pid);
+    let mut source = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    let mut out = OneFile::open_write_from(&ascii, &source, false, 1)?;
+    let mut acc = StatsAccumulator::new();
+    let mut kept = Vec::new();
+    let mut n = 0;
+    while source.read_line() != '\0' {
+        if source.line_type() == 'A' {
+            n += 1;
+        }
+        if n % 2 == 1 || n == 0 {
+            acc.add_line(&source);
+            kept.push(source.line_number());
+        }
+    }
+    acc.set_header_counts(&mut out)?;
+    let mut source = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    while source.read_line() != '\0' {
+        if kept.contains(&source.line_number()) {
+            source.copy_line_to(&mut out)?;
+        }
+    }
+    out.finalize()?;
+
+    // A binary copy counts its own lines, and writes them back out to ASCII
+    let binary = format!("/tmp/test_stats_binary_{}.1aln", pid);
+    let expected = format!("/tmp/test_stats_expected_{}.1aln", pid);
+    transcode(&ascii, &binary, true, 1)?;
+    transcode(&binary, &expected, false, 1)?;
+    assert_eq!(OneFile::open_read(&ascii, None, None, 1)?.all_stats(), acc.stats());
+    assert_eq!(OneFile::open_read(&binary, None, None, 1)?.all_stats(), acc.stats());
+    let counts = |path: &str| -> Vec<String> {
+        let text = std::fs::read_to_string(path).unwrap();
+        let mut lines: Vec<String> = text
+            .lines()
+            .filter(|l| ["# ", "@ ", "+ ", "% "].iter().any(|p| l.starts_with(p)))
+            .map(String::from)
+            .collect();
+        lines.sort();
+        lines
+    };
+    assert!(counts(&ascii).iter().any(|l| l.starts_with("% A")));
+    assert_eq!(counts(&ascii), counts(&expected));
+
+    // Too late once a line is out
+    let mut file = OneFile::open_write_from(&ascii, &source, false, 1)?;
+    source.goto('A', 1)?;
+    source.read_line();
+    source.copy_line_to(&mut file)?;
+    assert!(acc.set_header_counts(&mut file).is_err());
+    drop(file);
+
+    for path in [ascii, binary, expected] {
+        std::fs::remove_file(path).ok();
+    }
+    Ok(())
+}
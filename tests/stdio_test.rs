@@ -0,0 +1,110 @@
+//! Tests for reading standard input and writing standard output
+//!
+//! The standard streams are redirected with dup2(), so everything runs in a
+//! single test that cannot interleave with output from others.
+
+use onecode::{OneFile, OneSchema, Result};
+use std::io::{Read, Write};
+use std::os::fd::FromRawFd;
+
+const SCHEMA: &str = "P 3 tst\nO T 1 3 INT\nD L 1 8 INT_LIST\n";
+
+/// Run `f` with file descriptor `target` replaced by `fd`
+fn redirected<T>(target: i32, fd: i32, f: impl FnOnce() -> T) -> T {
+    unsafe {
+        let saved = libc::dup(target);
+        libc::dup2(fd, target);
+        let result = f();
+        libc::dup2(saved, target);
+        libc::close(saved);
+        result
+    }
+}
+
+fn pipe() -> (std::fs::File, std::fs::File) {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    unsafe {
+        (
+            std::fs::File::from_raw_fd(fds[0]),
+            std::fs::File::from_raw_fd(fds[1]),
+        )
+    }
+}
+
+fn write_objects(file: &mut OneFile) -> Result<()> {
+    for i in 1..=3 {
+        file.set_int(0, i);
+        file.write_line('T', 0, None);
+        file.write_int_list('L', &[i, 2 * i])?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_stdin_and_stdout() -> Result<()> {
+    use std::os::fd::AsRawFd;
+    let schema = OneSchema::from_text(SCHEMA)?;
+
+    // Binary output to a redirected file, which can seek
+    let path = format!("/tmp/test_stdio_{}.1tst", std::process::id());
+    let out = std::fs::File::create(&path).unwrap();
+    redirected(libc::STDOUT_FILENO, out.as_raw_fd(), || -> Result<()> {
+        let mut file = OneFile::open_write_stdout(&schema, "tst", true)?;
+        write_objects(&mut file)?;
+        file.close();
+        Ok(())
+    })?;
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert!(file.is_seekable());
+    file.goto('T', 3)?;
+    assert_eq!(file.read_line(), 'T');
+    assert_eq!(file.int(0), 3);
+
+    // ASCII output to a pipe; binary is refused up front
+    let (mut reader, writer) = pipe();
+    let drain = std::thread::spawn(move || {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        text
+    });
+    redirected(libc::STDOUT_FILENO, writer.as_raw_fd(), || -> Result<()> {
+        assert!(OneFile::open_write_stdout(&schema, "tst", true).is_err());
+        let mut file = OneFile::open_write_stdout(&schema, "tst", false)?;
+        write_objects(&mut file)?;
+        file.close();
+        Ok(())
+    })?;
+    drop(writer);
+    let text = drain.join().unwrap();
+    assert!(text.starts_with("1 3 tst"));
+    assert!(text.contains("\nT 2\nL 2 2 4\n"));
+
+    // ASCII input from a pipe, read in order only
+    let (reader, mut writer) = pipe();
+    let feed = std::thread::spawn(move || writer.write_all(text.as_bytes()).unwrap());
+    let values = redirected(
+        libc::STDIN_FILENO,
+        reader.as_raw_fd(),
+        || -> Result<Vec<i64>> {
+            let mut file = OneFile::open_read_stdin(None, Some("tst"))?;
+            assert!(!file.is_seekable());
+            let err = file.goto('T', 1).err().unwrap();
+            assert!(err.to_string().contains("not seekable"));
+            let mut values = Vec::new();
+            while file.read_line() != '\0' {
+                if file.line_type() == 'T' {
+                    values.push(file.int(0));
+                }
+            }
+            Ok(values)
+        },
+    )?;
+    feed.join().unwrap();
+    assert_eq!(values, [1, 2, 3]);
+
+    assert!(OneFile::open_read("-", None, None, 2).is_err());
+    assert!(OneFile::open_write_new("-", &schema, "tst", false, 2).is_err());
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
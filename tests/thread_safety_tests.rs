@@ -1,9 +1,9 @@
-/// Thread safety tests for ONEcode Rust wrapper
-///
-/// These tests verify that the mutexes protecting C library global state work correctly.
+//! Thread safety tests for ONEcode Rust wrapper
+//!
+//! These tests verify that the mutexes protecting C library global state work correctly.
 
-use onecode::{OneFile, OneSchema};
-use std::sync::Arc;
+use onecode::{AlnReader, OneFile, OneSchema, RegionIndex, SeqReader};
+use std::sync::{Arc, Mutex};
 use std::thread;
 
 #[test]
@@ -135,3 +135,157 @@ fn test_error_message_correctness() {
         handle.join().unwrap();
     }
 }
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn test_readers_are_send() {
+    assert_send::<OneFile>();
+    assert_send::<AlnReader>();
+    assert_send::<SeqReader>();
+    assert_send::<RegionIndex>();
+    assert_send::<onecode::OneFileThread<'static>>();
+}
+
+#[test]
+fn test_readers_moved_to_workers() {
+    // Each worker reads its own slice of the alignments with a reader
+    // opened on the main thread
+    let readers: Vec<OneFile> = (0..4)
+        .map(|_| OneFile::open_read("data/test.1aln", None, None, 1).unwrap())
+        .collect();
+    let ranges = OneFile::open_read("data/test.1aln", None, None, 4)
+        .unwrap()
+        .slice_ranges('A')
+        .unwrap();
+
+    let handles: Vec<_> = readers
+        .into_iter()
+        .zip(ranges)
+        .map(|(mut file, range)| {
+            thread::spawn(move || {
+                file.goto('A', range.start).unwrap();
+                let mut count = 0;
+                while file.read_line() != '\0' {
+                    if file.line_type() == 'A' {
+                        if file.object('A') >= range.end {
+                            break;
+                        }
+                        count += 1;
+                    }
+                }
+                count
+            })
+        })
+        .collect();
+
+    let total: i64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(total, 72);
+}
+
+#[test]
+fn test_reader_shared_behind_mutex() {
+    // ONElib must not be asked to read past the end, so the first thread to
+    // reach it closes the file for all
+    let file = Arc::new(Mutex::new(Some(
+        OneFile::open_read("data/test.1aln", None, None, 1).unwrap(),
+    )));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let file = Arc::clone(&file);
+            thread::spawn(move || {
+                let mut count = 0;
+                loop {
+                    let mut guard = file.lock().unwrap();
+                    let Some(file) = guard.as_mut() else {
+                        return count;
+                    };
+                    match file.read_line() {
+                        '\0' => *guard = None,
+                        'A' => count += 1,
+                        _ => {}
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let total: i64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(total, 72);
+}
+
+#[test]
+fn test_split_threads_parallel_read() {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 4).unwrap();
+    let ranges = file.slice_ranges('A').unwrap();
+    let handles = file.split_threads();
+    assert_eq!(handles.len(), 4);
+    assert_eq!(handles.iter().map(|h| h.index()).collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+    // Each handle reads the first fields of the alignments in its slice
+    let slices: Vec<Vec<(i64, i64)>> = thread::scope(|scope| {
+        let workers: Vec<_> = handles
+            .into_iter()
+            .zip(ranges)
+            .map(|(mut handle, range)| {
+                scope.spawn(move || {
+                    let mut found = Vec::new();
+                    handle.goto('A', range.start).unwrap();
+                    let mut line_type = handle.read_line();
+                    while line_type != '\0' && (found.len() as i64) < range.end - range.start {
+                        if line_type == 'A' {
+                            found.push((handle.int(0), handle.int(1)));
+                        }
+                        line_type = handle.read_line();
+                    }
+                    found
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let expected: Vec<(i64, i64)> = AlnReader::open("data/test.1aln")
+        .unwrap()
+        .map(|a| (a.query_id, a.query_start))
+        .collect();
+    assert_eq!(slices.concat(), expected);
+}
+
+#[test]
+fn test_split_threads_parallel_write() {
+    let schema = OneSchema::from_text("P 3 tst\nO T 1 3 INT\nD L 1 8 INT_LIST\n").unwrap();
+    let path = format!("/tmp/test_split_threads_{}.1tst", std::process::id());
+    {
+        let mut file = OneFile::open_write_new(&path, &schema, "tst", true, 3).unwrap();
+        thread::scope(|scope| {
+            for mut handle in file.split_threads() {
+                scope.spawn(move || {
+                    for j in 0..10 {
+                        let value = handle.index() as i64 * 10 + j;
+                        handle.set_int(0, value);
+                        handle.write_line('T', 0, None);
+                        handle.write_int_list('L', &[value; 3]).unwrap();
+                    }
+                });
+            }
+        });
+        file.close();
+    }
+
+    // Closing appends the thread files in handle order
+    let mut file = OneFile::open_read(&path, None, None, 1).unwrap();
+    assert_eq!(file.stats('T').unwrap().0, 30);
+    let mut values = Vec::new();
+    while file.read_line() != '\0' {
+        if file.line_type() == 'T' {
+            values.push(file.int(0));
+        }
+    }
+    assert_eq!(values, (0..30).collect::<Vec<_>>());
+    file.goto('T', 25).unwrap();
+    assert_eq!(file.read_line(), 'T');
+    assert_eq!(file.int(0), 24);
+
+    std::fs::remove_file(&path).ok();
+}
@@ -0,0 +1,70 @@
+//! Tests for the non-fatal anomalies collected while reading
+
+use onecode::file::SLOW_PATH_WARN_THRESHOLD;
+use onecode::{OneFile, OneReader, OneSchema, Result, Warning};
+
+#[test]
+fn test_header_count_mismatch() -> Result<()> {
+    let path = format!("/tmp/test_warnings_counts_{}.seq", std::process::id());
+    let text = "1 3 seq 2 1\n~ O S 1 3 DNA\n~ D I 1 6 STRING\n# S 3\n# I 2\n\
+                S 4 acgt\nI 1 a\nS 2 gg\nI 1 b\n";
+    std::fs::write(&path, text).unwrap();
+
+    let mut reader = OneReader::open(&path, None, None, 1)?;
+    assert_eq!(reader.read_line(), 'S');
+    assert!(reader.warnings().is_empty());
+    while reader.read_line() != '\0' {}
+    reader.read_line();
+    let expected = Warning::CountMismatch { line_type: 'S', given: 3, found: 2 };
+    assert_eq!(reader.warnings(), [expected]);
+    assert_eq!(
+        reader.warnings()[0].to_string(),
+        "header gives 3 'S' lines but the file holds 2"
+    );
+    reader.clear_warnings();
+    assert!(reader.warnings().is_empty());
+
+    // Files without counts, and binary files, have nothing to disagree with
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    while file.read_line() != '\0' {}
+    assert!(file.warnings().is_empty());
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_name_warning() -> Result<()> {
+    let path = format!("/tmp/test_warnings_dups_{}.1aln", std::process::id());
+    let mut file = OneFile::open_write_new(&path, &OneSchema::aln()?, "aln", true, 1)?;
+    file.set_int(0, 100);
+    file.write_line('t', 0, None);
+    file.write_line('g', 0, None);
+    for (name, len) in [("chr1", 10), ("chr2", 20), ("chr1", 30)] {
+        file.write_string('S', name)?;
+        file.set_int(0, len);
+        file.write_line('C', 0, None);
+    }
+    file.finalize()?;
+
+    let mut file = OneFile::open_read(&path, None, None, 1)?;
+    assert_eq!(file.gdb_index()?.name(2), Some("chr1"));
+    file.gdb_index()?;
+    let expected = Warning::DuplicateName { group: 1, name: "chr1".to_string() };
+    assert_eq!(file.warnings(), [expected]);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[test]
+fn test_slow_path_warning() -> Result<()> {
+    let mut file = OneFile::open_read("data/test.1aln", None, None, 1)?;
+    file.set_slow_path_warnings(false);
+    for _ in 0..SLOW_PATH_WARN_THRESHOLD + 3 {
+        file.get_sequence_name(0);
+    }
+    let lookups = SLOW_PATH_WARN_THRESHOLD;
+    assert_eq!(file.warnings(), [Warning::SlowPath { lookups }]);
+    Ok(())
+}
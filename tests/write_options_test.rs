@@ -0,0 +1,141 @@
+//! Tests for creating files with WriteOptions
+
+use onecode::{OneError, OneFile, OneSchema, OneWriter, Result, WriteOptions};
+use std::path::Path;
+
+fn write_reads(path: &str, options: WriteOptions, n: usize) -> Result<()> {
+    let mut file = OneFile::create(path, &OneSchema::seq()?, "seq", options)?;
+    for i in 0..n {
+        file.write_dna('S', b"acgtacgt")?;
+        file.write_string('I', &format!("read_{:06}", i))?;
+    }
+    file.finalize()
+}
+
+fn read_names(path: &str) -> Result<Vec<String>> {
+    let mut file = OneFile::open_read(path, None, None, 1)?;
+    let mut names = Vec::new();
+    while file.read_line() != '\0' {
+        if file.line_type() == 'I' {
+            names.push(file.string()?.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[test]
+fn test_create_matches_open_write_new() -> Result<()> {
+    let pid = std::process::id();
+    let (created, positional) = (
+        format!("/tmp/test_write_options_{}.1seq", pid),
+        format!("/tmp/test_write_positional_{}.1seq", pid),
+    );
+    write_reads(&created, WriteOptions::default().binary(false), 3)?;
+    let mut file = OneFile::open_write_new(&positional, &OneSchema::seq()?, "seq", false, 1)?;
+    for i in 0..3 {
+        file.write_dna('S', b"acgtacgt")?;
+        file.write_string('I', &format!("read_{:06}", i))?;
+    }
+    file.finalize()?;
+    assert_eq!(std::fs::read(&created).unwrap(), std::fs::read(&positional).unwrap());
+
+    // The buffer size changes how the file is written, not what is in it
+    let options = WriteOptions::default().binary(false).buffer_size(1 << 16);
+    write_reads(&created, options, 3)?;
+    assert_eq!(std::fs::read(&created).unwrap(), std::fs::read(&positional).unwrap());
+
+    std::fs::remove_file(&created).ok();
+    std::fs::remove_file(&positional).ok();
+    Ok(())
+}
+
+#[test]
+fn test_compression_per_line_type() -> Result<()> {
+    let pid = std::process::id();
+    let (packed, plain) = (
+        format!("/tmp/test_write_packed_{}.1seq", pid),
+        format!("/tmp/test_write_plain_{}.1seq", pid),
+    );
+    write_reads(&packed, WriteOptions::default(), 20000)?;
+    write_reads(&plain, WriteOptions::default().nthreads(2).compress('I', false), 20000)?;
+    let size = |path: &str| std::fs::metadata(path).unwrap().len();
+    assert!(size(&plain) > size(&packed) + 20000);
+    assert_eq!(read_names(&plain)?, read_names(&packed)?);
+
+    // DNA is always packed, 'Z' is not a seq line type, and 'é' is no line type
+    for line_type in ['S', 'Z', 'é'] {
+        let options = WriteOptions::default().compress(line_type, false);
+        let err = OneFile::create(&plain, &OneSchema::seq()?, "seq", options).err().unwrap();
+        assert!(matches!(err, OneError::SchemaError(_)), "{:?}", err);
+        assert!(!Path::new(&plain).exists());
+    }
+    std::fs::remove_file(&packed).ok();
+    Ok(())
+}
+
+#[test]
+fn test_atomic_write() -> Result<()> {
+    let dir = format!("/tmp/test_write_atomic_{}", std::process::id());
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = format!("{}/reads.1seq", dir);
+    write_reads(&path, WriteOptions::default(), 2)?;
+
+    // The old file stays in place until the new one is complete
+    let options = WriteOptions::default().atomic(true).nthreads(2);
+    let mut writer = OneWriter::create_with(&path, &OneSchema::seq()?, "seq", options)?;
+    writer.write_dna('S', b"ttt")?;
+    writer.write_string('I', "new")?;
+    assert_ne!(writer.file_name().as_deref(), Some(path.as_str()));
+    assert_eq!(read_names(&path)?.len(), 2);
+    writer.finalize()?;
+    assert_eq!(read_names(&path)?, ["new"]);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    // A panic while writing leaves the old file, and nothing else
+    let result = std::panic::catch_unwind(|| {
+        let options = WriteOptions::default().atomic(true);
+        let mut file = OneFile::create(&path, &OneSchema::seq().unwrap(), "seq", options).unwrap();
+        file.write_dna('S', b"gg").unwrap();
+        panic!("interrupted");
+    });
+    assert!(result.is_err());
+    assert_eq!(read_names(&path)?, ["new"]);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    assert!(matches!(
+        OneFile::create("-", &OneSchema::seq()?, "seq", WriteOptions::default().atomic(true)),
+        Err(OneError::OpenFailed(_))
+    ));
+    std::fs::remove_dir_all(&dir).ok();
+    Ok(())
+}
+
+#[test]
+fn test_locked_writers() -> Result<()> {
+    let path = format!("/tmp/test_write_lock_{}.1seq", std::process::id());
+    write_reads(&path, WriteOptions::default(), 3)?;
+
+    // A second writer is turned away without touching the first's file
+    let options = WriteOptions::default().lock(true);
+    let mut first = OneFile::create(&path, &OneSchema::seq()?, "seq", options)?;
+    first.write_dna('S', b"acgt")?;
+    first.write_string('I', "first")?;
+    let err = OneFile::create(&path, &OneSchema::seq()?, "seq", options).err().unwrap();
+    assert_eq!(err, OneError::Locked { path: path.clone() });
+    assert_eq!(err.path(), Some(path.as_str()));
+    let err = OneFile::options().lock(true).open_append(&path).err().unwrap();
+    assert!(matches!(err, OneError::Locked { .. }));
+    first.finalize()?;
+    assert_eq!(read_names(&path)?, ["first"]);
+
+    // The lock goes with the file, and appending takes it too
+    let mut append = OneFile::options().lock(true).open_append(&path)?;
+    append.write_dna('S', b"gg")?;
+    append.write_string('I', "second")?;
+    assert!(OneFile::create(&path, &OneSchema::seq()?, "seq", options).is_err());
+    append.finalize()?;
+    assert_eq!(read_names(&path)?, ["first", "second"]);
+
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}